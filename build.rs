@@ -1,16 +1,27 @@
 use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-fn main() {
-    let version = Command::new("git")
-        .args(["describe", "--tags", "--always"])
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
         .output()
         .ok()
         .filter(|o| o.status.success())
-        .map(|o| {
-            let s = String::from_utf8_lossy(&o.stdout).trim().to_string();
-            s.strip_prefix('v').unwrap_or(&s).to_string()
-        })
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+fn main() {
+    let version = git(&["describe", "--tags", "--always"])
+        .map(|s| s.strip_prefix('v').unwrap_or(&s).to_string())
         .unwrap_or_else(|| env!("CARGO_PKG_VERSION").into());
+    let commit = git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".into());
+    // Honour SOURCE_DATE_EPOCH so reproducible builds get a fixed timestamp
+    let build_epoch = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0));
 
     println!("cargo:rustc-env=GIT_VERSION={version}");
+    println!("cargo:rustc-env=GIT_COMMIT={commit}");
+    println!("cargo:rustc-env=BUILD_EPOCH={build_epoch}");
 }
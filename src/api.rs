@@ -0,0 +1,383 @@
+//! Library entry points for embedding taskai. Each call works on a caller-provided
+//! `Connection` and returns a typed outcome instead of printing; the CLI handlers are
+//! thin wrappers that resolve references, call these and format the result.
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::db::{dependency_repo, document_repo, migrations, plan_repo, task_repo, task_tags_repo};
+use crate::error::TaskaiError;
+use crate::graph::next_tasks;
+use crate::models::{Plan, Task, TaskStatus};
+use crate::plan_input::{self, PlanLoadInput};
+
+pub use crate::db::task_repo::TaskProgress;
+
+/// Prepare a connection (e.g. `Connection::open_in_memory()`) for use with this module:
+/// enforce foreign keys and bring the schema up to date.
+pub fn migrate(conn: &Connection) -> Result<(), TaskaiError> {
+    conn.execute_batch("PRAGMA foreign_keys=ON;")?;
+    migrations::run_migrations(conn)
+}
+
+// --- plan load ---
+
+/// Result of `load_plan`.
+#[derive(Debug, Clone)]
+pub struct LoadOutcome {
+    pub plan: Plan,
+    /// Document task id → created task id
+    pub id_mapping: HashMap<String, String>,
+    pub ready_now: Vec<Task>,
+    /// Non-fatal surprises in the document
+    pub warnings: Vec<String>,
+    /// The document's idempotency key matched an earlier load; nothing was created
+    pub idempotent_replay: bool,
+}
+
+/// Validate a `plan load` document (JSON or YAML) and create its plan, tasks and
+/// dependencies in one transaction. Does not activate the plan.
+pub fn load_plan(conn: &Connection, document: &str) -> Result<LoadOutcome, TaskaiError> {
+    let plan_input = plan_input::parse_load_input(document, None)?;
+    plan_input::validate_load_input(&plan_input, false)?;
+    create_loaded_plan(conn, &plan_input)
+}
+
+/// Create the plan described by a validated document, or replay an earlier load with the
+/// same idempotency key.
+pub(crate) fn create_loaded_plan(conn: &Connection, plan_input: &PlanLoadInput) -> Result<LoadOutcome, TaskaiError> {
+    if let Some(key) = &plan_input.idempotency_key {
+        if let Some(existing) = plan_repo::find_plan_by_idempotency_key(conn, key)? {
+            return load_replay(conn, existing, plan_input);
+        }
+    }
+    if plan_repo::find_plan_by_name(conn, &plan_input.name)?.is_some() {
+        return Err(TaskaiError::plan_name_conflict(&plan_input.name));
+    }
+
+    let plan_id = ulid::Ulid::new().to_string();
+    let mut id_mapping: HashMap<String, String> = HashMap::new();
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        conn.execute(
+            "INSERT INTO plans (id, name, title, description, idempotency_key) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                plan_id, plan_input.name, plan_input.title, plan_input.description, plan_input.idempotency_key
+            ],
+        )?;
+
+        for doc in &plan_input.documents {
+            let doc_id = ulid::Ulid::new().to_string();
+            document_repo::create_plan_document(conn, &doc_id, &plan_id, &doc.title, &doc.content)?;
+        }
+
+        for (i, task_input) in plan_input.tasks.iter().enumerate() {
+            let task_id = ulid::Ulid::new().to_string();
+            id_mapping.insert(task_input.id.clone(), task_id.clone());
+
+            let status = if task_input.after.is_empty() {
+                TaskStatus::Ready
+            } else {
+                TaskStatus::Blocked
+            };
+
+            task_repo::create_task(
+                conn, &task_id, &plan_id, &task_input.title,
+                task_input.description.as_deref(), task_input.priority,
+                i as i32, &status, task_input.agent.as_deref(), task_input.estimated_minutes,
+            )?;
+            task_repo::set_external_id(conn, &task_id, &task_input.id)?;
+
+            for tag in &task_input.tags {
+                task_tags_repo::add_tag(conn, &task_id, tag)?;
+            }
+            for doc in &task_input.documents {
+                let doc_id = ulid::Ulid::new().to_string();
+                document_repo::create_task_document(conn, &doc_id, &task_id, &doc.title, &doc.content)?;
+            }
+        }
+
+        for task_input in &plan_input.tasks {
+            let task_id = &id_mapping[&task_input.id];
+            for dep_temp_id in &task_input.after {
+                dependency_repo::add_dependency(conn, task_id, &id_mapping[dep_temp_id])?;
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = conn.execute_batch("ROLLBACK");
+        // A concurrent load with the same key got there first
+        if let Some(key) = &plan_input.idempotency_key {
+            if let Some(existing) = plan_repo::find_plan_by_idempotency_key(conn, key)? {
+                return load_replay(conn, existing, plan_input);
+            }
+        }
+        return Err(e);
+    }
+    conn.execute_batch("COMMIT")?;
+
+    Ok(LoadOutcome {
+        plan: plan_repo::get_plan_by_id(conn, &plan_id)?,
+        id_mapping,
+        ready_now: ready_tasks(conn, &plan_id)?,
+        warnings: plan_input::load_warnings(plan_input),
+        idempotent_replay: false,
+    })
+}
+
+/// Answer a load whose idempotency key matches `existing` with that plan's current state.
+fn load_replay(conn: &Connection, existing: Plan, plan_input: &PlanLoadInput) -> Result<LoadOutcome, TaskaiError> {
+    let mut id_mapping = HashMap::new();
+    for task_input in &plan_input.tasks {
+        if let Some(id) = task_repo::find_task_id_by_external_id(conn, &existing.id, &task_input.id)? {
+            id_mapping.insert(task_input.id.clone(), id);
+        }
+    }
+    Ok(LoadOutcome {
+        ready_now: ready_tasks(conn, &existing.id)?,
+        plan: existing,
+        id_mapping,
+        warnings: plan_input::load_warnings(plan_input),
+        idempotent_replay: true,
+    })
+}
+
+fn ready_tasks(conn: &Connection, plan_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    let tasks = task_repo::list_tasks_by_plan(conn, plan_id)?;
+    Ok(tasks.into_iter().filter(|t| t.status == TaskStatus::Ready).collect())
+}
+
+// --- next ---
+
+/// Result of `next_task`.
+#[derive(Debug, Clone)]
+pub struct NextOutcome {
+    /// The next ready task (claimed when `claim` was set), if any
+    pub task: Option<Task>,
+    /// Claims whose timeout lapsed and went back to the queue first
+    pub expired: Vec<Task>,
+    pub progress: TaskProgress,
+    pub plan_completed: bool,
+}
+
+/// Pick the plan's next ready task, claiming it for `agent` when `claim` is set.
+pub fn next_task(conn: &Connection, plan_id: &str, claim: bool, agent: Option<&str>) -> Result<NextOutcome, TaskaiError> {
+    let expired = release_expired_claims(conn, plan_id)?;
+    let filter = next_tasks::NextFilter::default();
+    let task = if claim {
+        claim_next(conn, plan_id, agent, &filter, None)?
+    } else {
+        next_tasks::pick_next_task(conn, plan_id, &filter)?
+    };
+    let progress = task_repo::task_progress(conn, plan_id)?;
+    Ok(NextOutcome { task, expired, plan_completed: is_completed(&progress), progress })
+}
+
+/// Claim the next ready task matching `filter` in its own transaction, stamping
+/// `claim_expires_at` when a `timeout` in minutes is given.
+pub(crate) fn claim_next(
+    conn: &Connection,
+    plan_id: &str,
+    agent: Option<&str>,
+    filter: &next_tasks::NextFilter,
+    timeout: Option<i64>,
+) -> Result<Option<Task>, TaskaiError> {
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = next_tasks::claim_next_task(conn, plan_id, agent, filter)
+        .and_then(|task| Ok(with_claim_timeout(conn, task.into_iter().collect(), timeout)?.pop()));
+    match result {
+        Ok(task) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(task)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+/// Stamp `claim_expires_at` on freshly claimed tasks (within the claiming transaction).
+pub(crate) fn with_claim_timeout(conn: &Connection, tasks: Vec<Task>, timeout: Option<i64>) -> Result<Vec<Task>, TaskaiError> {
+    let Some(minutes) = timeout else { return Ok(tasks) };
+    tasks
+        .iter()
+        .map(|t| {
+            task_repo::set_claim_expiry(conn, &t.id, minutes)?;
+            task_repo::get_task_by_id(conn, &t.id)
+        })
+        .collect()
+}
+
+/// Return the plan's expired claims to the queue. Only opens a write transaction when
+/// something actually expired, so a plain `next`/`status` stays read-only.
+pub fn release_expired_claims(conn: &Connection, plan_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    if !task_repo::has_expired_claims(conn, plan_id)? {
+        return Ok(Vec::new());
+    }
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    match task_repo::release_expired_claims(conn, plan_id) {
+        Ok(tasks) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(tasks)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+// --- transitions ---
+
+/// A status change requested for one task.
+#[derive(Debug, Clone, Copy)]
+pub enum TransitionAction<'a> {
+    /// Claim the task, optionally for a named agent
+    Start { agent: Option<&'a str> },
+    Done { note: Option<&'a str> },
+    /// Send the task back to the queue; past `max_attempts` failures it is cancelled instead
+    Fail { reason: Option<&'a str>, max_attempts: Option<i64> },
+    Skip,
+    Cancel,
+    Reset,
+}
+
+impl TransitionAction<'_> {
+    /// Action name as used by the CLI subcommands and `INVALID_STATUS_TRANSITION` errors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransitionAction::Start { .. } => "start",
+            TransitionAction::Done { .. } => "done",
+            TransitionAction::Fail { .. } => "fail",
+            TransitionAction::Skip => "skip",
+            TransitionAction::Cancel => "cancel",
+            TransitionAction::Reset => "reset",
+        }
+    }
+}
+
+/// Result of `transition`.
+#[derive(Debug, Clone)]
+pub struct TransitionOutcome {
+    /// The task after the transition
+    pub task: Task,
+    /// Dependents that became ready
+    pub newly_ready: Vec<Task>,
+    /// Dependents sent back to blocked by resetting a done task
+    pub reblocked: Vec<Task>,
+    /// Dependents left in_progress although resetting a done task un-met their dependencies
+    pub in_progress_on_unmet_deps: Vec<Task>,
+    pub progress: TaskProgress,
+    pub plan_completed: bool,
+}
+
+/// Apply `action` to the task with id `task_id` in one transaction, including the
+/// unblock/reblock cascades. The task is read and checked inside the transaction, so of
+/// two concurrent calls only the first sees the status it expects.
+pub fn transition(conn: &Connection, task_id: &str, action: TransitionAction) -> Result<TransitionOutcome, TaskaiError> {
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        // Read under the write lock so the transition is checked against the current status
+        let task = task_repo::get_task_by_id(conn, task_id)?;
+        let (updated, newly_ready, reblocked) = apply_transition(conn, &task, action)?;
+        let in_progress_on_unmet_deps = if matches!(action, TransitionAction::Reset) && task.status == TaskStatus::Done {
+            next_tasks::in_progress_dependents(conn, &task.id)?
+        } else {
+            Vec::new()
+        };
+        let progress = task_repo::task_progress(conn, &task.plan_id)?;
+        Ok(TransitionOutcome {
+            task: updated,
+            newly_ready,
+            reblocked,
+            in_progress_on_unmet_deps,
+            plan_completed: is_completed(&progress),
+            progress,
+        })
+    })();
+    match result {
+        Ok(outcome) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(outcome)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+/// Validate and apply `action` to `task` (within an existing transaction),
+/// including the unblock/reblock cascades.
+/// Returns the updated task, newly ready dependents and re-blocked dependents.
+pub(crate) fn apply_transition(
+    conn: &Connection,
+    task: &Task,
+    action: TransitionAction,
+) -> Result<(Task, Vec<Task>, Vec<Task>), TaskaiError> {
+    let mut new_status = validate_transition(&task.status, action.name())?;
+    // A task that keeps failing is given up on instead of going back into the queue
+    if let TransitionAction::Fail { max_attempts: Some(max), .. } = action {
+        if task.fail_count + 1 > max {
+            new_status = TaskStatus::Cancelled;
+        }
+    }
+
+    // For fail/reset: check if deps are still met before going back to ready
+    let actual_status = if new_status == TaskStatus::Ready
+        && matches!(action, TransitionAction::Fail { .. } | TransitionAction::Reset)
+        && !dependency_repo::all_dependencies_done(conn, &task.id)?
+    {
+        TaskStatus::Blocked
+    } else {
+        new_status
+    };
+
+    match action {
+        TransitionAction::Reset => task_repo::reopen_task(conn, &task.id, &actual_status)?,
+        TransitionAction::Fail { reason, .. } => task_repo::fail_task(conn, &task.id, &actual_status, reason)?,
+        TransitionAction::Done { note } => {
+            task_repo::update_task_status_with_note(conn, &task.id, &actual_status, None, note)?
+        }
+        TransitionAction::Start { agent } => task_repo::update_task_status(conn, &task.id, &actual_status, agent)?,
+        TransitionAction::Skip | TransitionAction::Cancel => {
+            task_repo::update_task_status(conn, &task.id, &actual_status, None)?
+        }
+    }
+
+    let mut newly_ready = Vec::new();
+    if actual_status == TaskStatus::Done {
+        newly_ready = next_tasks::cascade_unblock(conn, &task.id)?;
+    }
+    let mut reblocked = Vec::new();
+    if matches!(action, TransitionAction::Reset) && task.status == TaskStatus::Done {
+        reblocked = next_tasks::cascade_reblock(conn, &task.id)?;
+    }
+
+    let updated_task = task_repo::get_task_by_id(conn, &task.id)?;
+    Ok((updated_task, newly_ready, reblocked))
+}
+
+pub(crate) fn validate_transition(current: &TaskStatus, action: &str) -> Result<TaskStatus, TaskaiError> {
+    match (current, action) {
+        (TaskStatus::Ready, "start") => Ok(TaskStatus::InProgress),
+        (TaskStatus::Ready, "done") => Ok(TaskStatus::Done),
+        (TaskStatus::InProgress, "done") => Ok(TaskStatus::Done),
+        (TaskStatus::InProgress, "fail") => Ok(TaskStatus::Ready),
+        (TaskStatus::Ready | TaskStatus::Blocked, "skip") => Ok(TaskStatus::Skipped),
+        (TaskStatus::Ready | TaskStatus::Blocked | TaskStatus::InProgress, "cancel") => {
+            Ok(TaskStatus::Cancelled)
+        }
+        (TaskStatus::Done | TaskStatus::Cancelled | TaskStatus::Skipped, "reset") => Ok(TaskStatus::Ready),
+        _ => Err(TaskaiError::invalid_transition(current.as_str(), action)),
+    }
+}
+
+fn is_completed(progress: &TaskProgress) -> bool {
+    progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
 const VERSION: &str = env!("GIT_VERSION");
 
@@ -11,6 +11,18 @@ const VERSION: &str = env!("GIT_VERSION");
 NOTE:
   Requires a git repository. DB is stored at <git-root>/.worktoolai/taskai/taskai.db
   Run `taskai init` before any other command.
+  --db-path <PATH> (alias --db; or TASKAI_DB_PATH, then TASKAI_DB) uses PATH as the DB
+  instead, git repository or not; the flag wins over the variables. config.json (active
+  plan and settings) lives next to the DB. PATH :memory: gives each command a fresh
+  in-memory DB (no init needed, nothing is kept, settings included).
+  Commands act on --plan <name|id>, else TASKAI_PLAN, else the active plan.
+
+LOCKING:
+  --lock-timeout <MS> makes `task add`, task status changes, `plan load` and
+  `next --claim`/`--reclaim-after`/`--release` take an advisory lock (taskai.lock next to
+  the DB) first, so parallel writers queue instead of failing on SQLite contention.
+  Waiting longer than MS fails with LOCK_TIMEOUT. Read-only commands (`list`, `show`,
+  `status`, `next` without --claim) never take the lock.
 
 EXIT CODES:
   0  Success (task returned, or plan completed)
@@ -25,14 +37,19 @@ BEHAVIOR NOTES:
   `task fail` may return `blocked` (not `ready`) if deps were cancelled while in_progress.
   `task add --after <done-task>` starts as `ready` (dep already satisfied).
   `plan delete` of the active plan clears the active plan config.
-  Terminal states (`done`/`cancelled`/`skipped`) are immutable.
+  `plan archive` of the active plan clears the active plan config.
+  `plan delete --soft` keeps the plan (status `deleted`) until `plan restore`.
+  `next`/`status` treat an archived or deleted plan as not found (PLAN_NOT_FOUND).
+  Terminal states (`done`/`cancelled`/`skipped`) only change via `task reset`.
+  Resetting a `done` task re-blocks dependents that were ready because of it.
 
 AGENT FIELD:
   Tasks can have a pre-assigned `agent` field (who should execute) set at creation time.
   This is separate from `assigned_to` (who actually claimed it at runtime).
   Set via `task add --agent <name>` or `\"agent\"` key in `plan load` JSON.
   The `next` command returns the `agent` field in JSON output so orchestrators can route tasks.
-  Use `next --claim --agent <name>` to set `assigned_to` when an agent picks up a task."
+  Use `next --claim --agent <name>` to set `assigned_to` when an agent picks up a task.
+  Use `task assign <id> --agent <name>` to set `assigned_to` without changing status."
 )]
 pub struct Cli {
     #[command(subcommand)]
@@ -42,9 +59,26 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub json: bool,
 
-    /// Specify plan by name or ID
+    /// Output newline-delimited JSON (one task per line for `task list`,
+    /// `task search` and `status`; other commands behave as with --json)
+    #[arg(long, global = true, conflicts_with = "json")]
+    pub ndjson: bool,
+
+    /// Specify plan by name or ID (default: $TASKAI_PLAN, else the active plan)
     #[arg(long, global = true)]
     pub plan: Option<String>,
+
+    /// Database file to use (overrides TASKAI_DB_PATH / TASKAI_DB and the git-root default)
+    #[arg(long, visible_alias = "db", global = true, value_name = "PATH")]
+    pub db_path: Option<std::path::PathBuf>,
+
+    /// Serialize mutating commands through a lock file, waiting up to MS milliseconds
+    #[arg(long, global = true, value_name = "MS")]
+    pub lock_timeout: Option<u64>,
+
+    /// Trace plan resolution, transactions, status changes and cascades on stderr
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
 }
 
 #[derive(Subcommand)]
@@ -60,13 +94,85 @@ pub enum Commands {
     #[command(subcommand)]
     Task(TaskCommands),
 
+    /// Database maintenance
+    #[command(subcommand)]
+    Db(DbCommands),
+
+    /// Plan and task documents
+    #[command(subcommand)]
+    Document(DocumentCommands),
+
+    /// Show or change settings (stored in config.json next to the database)
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
+    /// Check the repository, database and configuration for problems
+    #[command(after_help = "\
+CHECKS:
+  git_repository         taskai runs inside a git repository (warn only with --db-path)
+  database               the database file exists and is readable
+  schema_version         the schema matches this taskai version
+  active_plan            config.json parses and its active plan exists and is open
+  orphaned_dependencies  no dependency rows point to missing tasks
+  in_progress_started    every in_progress task has started_at
+  wal_size               the WAL file is not excessively large (> 64 MiB)
+
+EXIT CODES:
+  0  Every check passed
+  1  At least one check failed (error)
+  2  Warnings only
+
+The checks open the database read-only; doctor never migrates or repairs anything.")]
+    Doctor,
+
+    /// Print a shell completion script
+    #[command(after_help = "\
+NOTE:
+  Prints the script for SHELL to stdout, e.g.
+    taskai completions bash > /etc/bash_completion.d/taskai
+    taskai completions zsh --out-dir ~/.zfunc
+  --out-dir <DIR> writes it to DIR instead (taskai.bash, _taskai, taskai.fish, _taskai.ps1, ...).
+  Covers every subcommand and flag. In bash, zsh and fish, --plan also completes the
+  plan names of the current repository's database.")]
+    Completions {
+        /// Shell to generate the script for
+        shell: clap_complete::Shell,
+        /// Write the script into this directory instead of stdout
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<std::path::PathBuf>,
+    },
+
+    /// Show the CLI version, build commit and time, and the database schema version
+    #[command(after_help = "\
+NOTE:
+  JSON: {cli_version, git_commit, build_time, db_schema_version, expected_schema_version,
+  db_path}. db_schema_version is null when the database is not initialized; a value below
+  expected_schema_version means the next command will migrate the database.")]
+    Version,
+
+    /// Plan names for shell completion, one per line
+    #[command(name = "__plan-names", hide = true)]
+    PlanNames,
+
     /// Get next ready task (highest priority, then sort order)
     #[command(after_help = "\
 NOTE:
   Without --claim: read-only, returns the next ready task without changing state.
   With    --claim: atomically sets the task to in_progress (SQLite transaction).
-  Use --agent with --claim to record which agent owns the task (sets `assigned_to`).
-  JSON output includes the task's pre-assigned `agent` field for routing decisions.")]
+  Use --agent with --claim to record which agent owns the task (sets `assigned_to`;
+  default: `config default_agent`).
+  JSON output includes the task's pre-assigned `agent` field for routing decisions.
+  --reclaim-after <MINUTES> releases stale in_progress tasks before picking (see `task reclaim`).
+  --release <ID> first hands back that in_progress task (see `task unassign`); JSON adds `released`.
+  --timeout <MINUTES> (or TASKAI_CLAIM_TIMEOUT, or `config claim_expiry_minutes`) sets the claim's `claim_expires_at`. Every `next`
+  and `status` first returns expired claims to ready (blocked if deps regressed); JSON adds `expired`.
+  --for-agent <name> picks only tasks whose `agent` is <name> or unset (--strict: <name> only);
+  JSON adds `agent_filter`. With --claim and no --agent, `assigned_to` becomes <name>.
+  If ready tasks exist but none match, `reason` is NO_TASK_FOR_AGENT (exit code 2).
+  --order created breaks priority ties by `created_at` (oldest first). created_at has
+  one-second resolution, so tasks created in the same second fall back to plan order.
+  --min-priority <n> picks only tasks with priority >= n; if only lower-priority
+  tasks are ready, `reason` is BELOW_PRIORITY_THRESHOLD (exit code 2).")]
     Next {
         /// Atomically claim the task (set to in_progress)
         #[arg(long)]
@@ -75,10 +181,228 @@ NOTE:
         /// Agent identifier for claim
         #[arg(long)]
         agent: Option<String>,
+
+        /// With --claim, release the claim automatically after MINUTES
+        /// (default: $TASKAI_CLAIM_TIMEOUT, else `config claim_expiry_minutes`, else never)
+        #[arg(long, value_name = "MINUTES", requires = "claim")]
+        timeout: Option<i64>,
+
+        /// First release in_progress tasks started more than N minutes ago
+        #[arg(long, value_name = "MINUTES")]
+        reclaim_after: Option<i64>,
+
+        /// First hand back this in_progress task (like `task unassign`)
+        #[arg(long, value_name = "ID")]
+        release: Option<String>,
+
+        /// Only consider tasks pre-assigned to this agent (or unassigned, unless --strict)
+        #[arg(long)]
+        for_agent: Option<String>,
+
+        /// With --for-agent, exclude tasks that have no pre-assigned agent
+        #[arg(long, visible_alias = "strict-agent", requires = "for_agent")]
+        strict: bool,
+
+        /// Only consider ready tasks carrying this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Only consider ready tasks with at least this priority
+        #[arg(long, value_name = "N", allow_negative_numbers = true)]
+        min_priority: Option<i32>,
+
+        /// Return up to N ready tasks as `tasks` (a preview; with --claim all N are claimed at once)
+        #[arg(long, value_name = "N")]
+        count: Option<usize>,
+
+        /// Queue order: priority (default), created (priority, then oldest first)
+        /// or sort (plan order, ignoring priority). Overdue tasks always come first.
+        #[arg(long, default_value = "priority", value_parser = ["priority", "created", "sort"])]
+        order: String,
+
+        /// When only blocked tasks remain, add each one's `unblock_frontier`: the
+        /// unfinished leaf tasks that would unblock it once done
+        #[arg(long)]
+        explain: bool,
     },
 
     /// Show overall status
-    Status,
+    #[command(after_help = "\
+NOTE:
+  In JSON output, each `in_progress` entry has `last_heartbeat_at` and `stale`.
+  A task is stale when neither a heartbeat (`task heartbeat`) nor its start
+  happened within --stale-after minutes.
+  --watch [SECONDS] (default 2) re-polls until the plan completes, then exits 0.
+  Text output redraws the screen; --json prints one compact JSON line per poll.
+  Ctrl-C stops watching with exit code 0.")]
+    Status {
+        /// Minutes without a heartbeat before an in_progress task is flagged `stale`
+        #[arg(long, value_name = "MINUTES", default_value_t = crate::models::DEFAULT_STALE_AFTER_MINUTES)]
+        stale_after: i64,
+        /// Refresh every SECONDS until the plan completes
+        #[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "2")]
+        watch: Option<u64>,
+        /// Print the plan's tasks in this format instead (csv, or a markdown report)
+        #[arg(long, value_parser = ["csv", "markdown"], conflicts_with_all = ["json", "ndjson", "watch"])]
+        format: Option<String>,
+    },
+}
+
+const CONFIG_KEYS_HELP: &str = "\
+KEYS:
+  default_agent         Agent `next --claim` claims under when --agent is not given
+  default_priority      Priority of `task add` without --priority (integer)
+  claim_expiry_minutes  Claim timeout of `next --claim` without --timeout
+                        (TASKAI_CLAIM_TIMEOUT takes precedence)
+  output_format         text or json; json makes --json the default";
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Set a setting
+    #[command(after_help = CONFIG_KEYS_HELP)]
+    Set {
+        key: String,
+        value: String,
+    },
+    /// Print a setting's value (nothing when unset)
+    #[command(after_help = CONFIG_KEYS_HELP)]
+    Get {
+        key: String,
+    },
+    /// Show every setting
+    List,
+    /// Unset one setting, or all of them (the active plan is kept)
+    #[command(after_help = CONFIG_KEYS_HELP)]
+    Reset {
+        key: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// Write a consistent snapshot of the database to a single file
+    #[command(after_help = "\
+NOTE:
+  Uses SQLite's online backup API, so it is safe while other taskai processes run.
+  The WAL is checkpointed first; the snapshot includes every committed change.
+  If PATH already exists the command fails (exit code 1, VALIDATION_ERROR)
+  and nothing is written, unless --force is given.")]
+    Backup {
+        /// Destination file
+        path: String,
+        /// Overwrite PATH if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DocumentCommands {
+    /// Attach a document to a plan or task
+    #[command(after_help = "\
+NOTE:
+  --task <id> attaches to that task (looked up in --plan, or the active plan).
+  Without --task, attaches to the plan given by the global --plan flag.
+  Content comes from --content (`-` reads stdin), or from a file with --content-file <PATH>.")]
+    Add {
+        /// Task ID to attach to (default: the --plan plan itself)
+        #[arg(long)]
+        task: Option<String>,
+        /// Document title
+        #[arg(long)]
+        title: String,
+        /// Document content
+        #[arg(long, required_unless_present = "content_file", conflicts_with = "content_file")]
+        content: Option<String>,
+        /// Read the content from this file
+        #[arg(long, value_name = "PATH")]
+        content_file: Option<String>,
+    },
+    /// Change a document's title and/or content
+    #[command(after_help = "\
+NOTE:
+  Works for plan and task documents alike. Only the given fields change;
+  at least one of --title, --content or --content-file is required.")]
+    Update {
+        /// Document ID
+        id: String,
+        /// New title
+        #[arg(long)]
+        title: Option<String>,
+        /// New content
+        #[arg(long, conflicts_with = "content_file")]
+        content: Option<String>,
+        /// Read the new content from this file
+        #[arg(long, value_name = "PATH")]
+        content_file: Option<String>,
+    },
+    /// Delete a plan or task document
+    Delete {
+        /// Document ID
+        id: String,
+    },
+    /// Full-text search across plan and task documents
+    #[command(after_help = "\
+NOTE:
+  Searches titles and contents of all documents, or only one plan's (with its tasks')
+  when --plan is given. Every word must match; best matches come first.
+  Snippets mark matched words with [brackets].
+  If the SQLite build lacks FTS5, falls back to a substring match (with a warning).")]
+    Search {
+        /// Words to look for
+        query: String,
+    },
+}
+
+const DOC_HELP: &str = "\
+NOTE:
+  DOC is a document ID, ID prefix or title prefix, looked up among the owner's documents.
+  Content comes from --content, from a file with --content-file <PATH>, or from
+  stdin with --content -. Empty content is rejected.";
+
+/// `plan doc` / `task doc`: documents of one plan or task.
+#[derive(Subcommand)]
+pub enum DocCommands {
+    /// Attach a document
+    #[command(after_help = DOC_HELP)]
+    Add {
+        /// Plan name/ID (plan doc) or task ID/prefix (task doc)
+        owner: String,
+        /// Document title
+        #[arg(long)]
+        title: String,
+        /// Document content (`-` reads stdin)
+        #[arg(long, required_unless_present = "content_file", conflicts_with = "content_file")]
+        content: Option<String>,
+        /// Read the content from this file
+        #[arg(long, value_name = "PATH")]
+        content_file: Option<String>,
+    },
+    /// Change a document's title and/or content
+    #[command(after_help = DOC_HELP)]
+    Update {
+        /// Plan name/ID (plan doc) or task ID/prefix (task doc)
+        owner: String,
+        /// Document ID or title prefix
+        doc: String,
+        /// New title
+        #[arg(long)]
+        title: Option<String>,
+        /// New content (`-` reads stdin)
+        #[arg(long, conflicts_with = "content_file")]
+        content: Option<String>,
+        /// Read the new content from this file
+        #[arg(long, value_name = "PATH")]
+        content_file: Option<String>,
+    },
+    /// Delete a document
+    #[command(after_help = DOC_HELP)]
+    Remove {
+        /// Plan name/ID (plan doc) or task ID/prefix (task doc)
+        owner: String,
+        /// Document ID or title prefix
+        doc: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -92,27 +416,194 @@ pub enum PlanCommands {
         #[arg(long)]
         description: Option<String>,
     },
-    /// List all plans
-    List,
+    /// List plans (archived plans are hidden unless --all)
+    List {
+        /// Include archived and soft-deleted plans
+        #[arg(long, visible_alias = "include-archived", conflicts_with = "active_only")]
+        all: bool,
+        /// Only show plans with status `active`
+        #[arg(long)]
+        active_only: bool,
+        /// Output format instead of text (table: aligned NAME, STATUS, TASKS, DONE% columns;
+        /// --json wins)
+        #[arg(long, value_parser = ["table"])]
+        format: Option<String>,
+    },
     /// Show plan details
     Show {
         /// Plan name or ID
         reference: String,
+        /// Print a Markdown report instead (header, progress bar, task table), e.g. for PRs
+        #[arg(long, value_parser = ["markdown"], conflicts_with_all = ["json", "ndjson"])]
+        format: Option<String>,
+    },
+    /// Rename a plan (ID is preserved)
+    Rename {
+        /// Plan name or ID
+        reference: String,
+        /// New plan name (slug)
+        #[arg(required_unless_present = "to", conflicts_with = "to")]
+        new_name: Option<String>,
+        /// New plan name (slug), as a flag
+        #[arg(long, value_name = "NEW_NAME")]
+        to: Option<String>,
+        /// New title
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Update plan title and/or description (only the given flags are changed)
+    Update {
+        /// Plan name or ID
+        reference: String,
+        #[arg(long)]
+        title: Option<String>,
+        /// New description (empty string clears it)
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Copy a plan into a new one with every task reset to its initial state
+    #[command(after_help = "\
+NOTE:
+  Tasks get new IDs (see `id_mapping`) and start as ready, or blocked if they have
+  dependencies. Claims, timestamps, deadlines, notes and history are not copied;
+  documents, tags, priorities, agents and estimates are. The source plan is untouched.
+  The clone is not activated.")]
+    Clone {
+        /// Source plan name or ID
+        reference: String,
+        /// Name (slug) of the new plan
+        #[arg(required_unless_present = "as_name", conflicts_with = "as_name")]
+        new_name: Option<String>,
+        /// Name (slug) of the new plan, as a flag
+        #[arg(long = "as", value_name = "NEW_NAME")]
+        as_name: Option<String>,
+        /// Title of the new plan (defaults to the source title)
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Move every task of a plan into another plan, then delete the source plan
+    #[command(after_help = "\
+NOTE:
+  Atomic: all-or-nothing. Tasks keep their IDs, statuses, dependencies, documents,
+  notes and history, and are appended after the target's tasks in their original order.
+  Plan documents move to the target. Deleting the active source plan clears the config.")]
+    Merge {
+        /// Source plan name or ID (deleted afterwards)
+        source: String,
+        /// Target plan name or ID
+        #[arg(long)]
+        into: String,
+    },
+    /// Archive a plan (hidden from `plan list`, rejected by `next`/`status`)
+    Archive {
+        /// Plan name or ID
+        reference: String,
+    },
+    /// Restore an archived plan to `active` (does not change the active plan)
+    Unarchive {
+        /// Plan name or ID
+        reference: String,
     },
-    /// Set active plan
+    /// Set active plan (un-archives it if archived)
     Activate {
         /// Plan name
         name: String,
     },
-    /// Delete a plan
+    /// Clear the active plan (the plan itself is kept)
+    #[command(after_help = "\
+NOTE:
+  Afterwards, commands without --plan (or TASKAI_PLAN) fail with NO_ACTIVE_PLAN.
+  Succeeds without changes when no plan is active. JSON `deactivated` is the plan that
+  was active ({id, name}; name is null if that plan no longer exists), or null.")]
+    Deactivate,
+    /// Delete a plan (permanently, unless --soft)
+    #[command(after_help = "\
+NOTE:
+  Without --soft the plan, its tasks, documents and history are dropped for good.
+  --soft only marks the plan `deleted`: it disappears from `plan list` (shown with --all)
+  and is rejected by `next`/`status` until `taskai plan restore <plan>`.
+  Either way, deleting the active plan clears the active plan config.")]
     Delete {
         /// Plan name or ID
         reference: String,
+        /// Mark the plan deleted instead of removing it
+        #[arg(long)]
+        soft: bool,
     },
-    /// Load plan from stdin JSON
+    /// Bring a soft-deleted plan back to `active` (does not change the active plan)
+    Restore {
+        /// Plan name or ID
+        reference: String,
+    },
+    /// Print the plan's dependency graph (Graphviz DOT or Mermaid)
+    #[command(after_help = "\
+NOTE:
+  Edges point from a dependency to the task that waits on it.
+  Nodes are colored by status: ready=green, blocked=yellow, in_progress=blue,
+  done=grey, cancelled=red, skipped=dimgrey.
+  Always writes the graph to stdout; --json is ignored.
+  Example: taskai plan graph my-plan | dot -Tsvg > plan.svg")]
+    Graph {
+        /// Plan name or ID
+        reference: String,
+        #[arg(long, default_value = "dot", value_parser = ["dot", "mermaid"])]
+        format: String,
+    },
+    /// Show the longest dependency chain of a plan, weighted by estimates
+    #[command(after_help = "\
+NOTE:
+  Each task weighs its estimated_minutes, or 1 when unestimated. All tasks count,
+  finished or not. Ties go to the task that comes first in plan order.")]
+    CriticalPath {
+        /// Plan name or ID
+        reference: String,
+    },
+    /// Group a plan's tasks into waves that can run in parallel
+    #[command(after_help = "\
+NOTE:
+  Wave 0 holds tasks without dependencies; wave N tasks whose dependencies all sit
+  in earlier waves. The largest wave is how many agents the plan can keep busy.
+  A wave's estimated_minutes is the longest estimate in it (null if none estimated).")]
+    Waves {
+        /// Plan name or ID
+        reference: String,
+    },
+    /// Show aggregate statistics for a plan (defaults to the active plan)
+    Stats {
+        /// Plan name or ID
+        reference: Option<String>,
+    },
+    /// Compare the tasks of two plans
+    #[command(after_help = "\
+NOTE:
+  Tasks are matched by title (IDs differ between plans). Repeated titles pair up in order.
+  added: only in <B>. removed: only in <A>. changed: one entry per differing status/priority.
+  Text output prefixes lines with + (added), - (removed), ~ (changed). Read-only.")]
+    Compare {
+        /// Plan name or ID (the baseline)
+        a: String,
+        /// Plan name or ID to compare against it
+        b: String,
+    },
+    /// Export a plan as a `plan load` document
+    #[command(after_help = "\
+NOTE:
+  Always prints the `plan load` document to stdout (with or without --json).
+  Task IDs are slugs t1, t2, ... in sort order; dependencies are mapped into `after`.
+  Task status is not exported: re-loading recomputes ready/blocked from `after`.
+  `completion_note` from `task done --note` is included for auditing; `plan load` ignores it.
+  --format yaml prints the same document as YAML (also accepted by `plan load`).")]
+    Export {
+        /// Plan name or ID
+        reference: String,
+        #[arg(long, default_value = "json", value_parser = ["json", "yaml"])]
+        format: String,
+    },
+    /// Load plan from stdin JSON (or YAML)
     #[command(after_help = "\
 STDIN FORMAT:
   {\"name\":\"slug\", \"title\":\"...\", \"tasks\":[{\"id\":\"t1\", \"title\":\"...\", \"agent\":\"...\", \"after\":[...]}]}
+  YAML uses the same field names. Without --format, JSON is tried first, then YAML.
 
 TASK FIELDS:
   id          (required) Temporary ID for dependency references
@@ -125,10 +616,51 @@ TASK FIELDS:
 
 NOTE:
   Atomic: all-or-nothing. Validates cycles, duplicate IDs, unknown refs.
-  Plan name must be unique. Existing name → error (no overwrite).
+  Plan name must be unique. Existing name → error (no overwrite), unless --merge.
+  Priorities must lie in -1000..=1000 unless --allow-extreme-priority is given.
+  JSON output has a `warnings` array, e.g. when negative priorities are mixed with
+  default (0) ones: those tasks then wait for every default task, whatever the plan order.
   Tasks without `after` start as `ready`; with `after` start as `blocked`.
-  Auto-activates if no valid active plan exists (none set, or stale reference).")]
-    Load,
+  Auto-activates if no valid active plan exists (none set, or stale reference).
+  --dry-run runs every check (including the name conflict, read-only) and writes nothing.
+  --file <PATH> reads the plan from PATH (relative to the current directory) instead of stdin.
+
+MERGE:
+  --merge updates an existing plan of the same name instead of failing. Tasks are matched
+  by the `id` they were loaded with: unmatched ids are added, matched tasks get their
+  title, priority and description updated. New `after` edges are added; existing
+  dependencies are never removed. Ready/blocked status is recomputed afterwards.
+  Plan-level documents are left untouched. Without a plan of that name, loads as usual.
+
+IDEMPOTENCY:
+  --idempotency-key <KEY> (or `idempotency_key` in the document) is stored on the plan the
+  load creates. Loading again with the same key creates nothing and returns that plan
+  (current ids, ready tasks) with `idempotent_replay: true`, so a timed-out load can be
+  retried safely. A --merge into an existing plan does not record the key: re-running a
+  merge already changes nothing.")]
+    Load {
+        /// Read the plan from this file instead of stdin
+        #[arg(short, long, value_name = "PATH")]
+        file: Option<String>,
+        /// Input format (default: auto-detect)
+        #[arg(long, value_parser = ["json", "yaml"])]
+        format: Option<String>,
+        /// Validate only; do not create the plan
+        #[arg(long, conflicts_with = "merge")]
+        dry_run: bool,
+        /// Merge into an existing plan with the same name
+        #[arg(long)]
+        merge: bool,
+        /// Accept priorities outside -1000..=1000
+        #[arg(long)]
+        allow_extreme_priority: bool,
+        /// Retry-safe load (overrides `idempotency_key` in the document)
+        #[arg(long, value_name = "KEY")]
+        idempotency_key: Option<String>,
+    },
+    /// Manage a plan's documents
+    #[command(subcommand)]
+    Doc(DocCommands),
 }
 
 #[derive(Subcommand)]
@@ -139,21 +671,112 @@ pub enum TaskCommands {
         title: String,
         #[arg(long)]
         description: Option<String>,
-        #[arg(long, default_value = "0")]
-        priority: i32,
+        /// Priority (default: `config default_priority`, else 0)
+        #[arg(long, allow_negative_numbers = true)]
+        priority: Option<i32>,
         /// Agent to execute this task
         #[arg(long)]
         agent: Option<String>,
+        /// Estimated duration in minutes
+        #[arg(long, value_name = "MINUTES")]
+        estimate: Option<i64>,
         /// Add dependency: task runs after this task ID
         #[arg(long)]
         after: Vec<String>,
+        /// Accept a priority outside -1000..=1000
+        #[arg(long)]
+        allow_extreme_priority: bool,
+        /// Retry-safe add: a second add with the same KEY in this plan returns the first task
+        #[arg(long, value_name = "KEY")]
+        idempotency_key: Option<String>,
     },
     /// List tasks in the active plan
-    List,
+    List {
+        /// Only show tasks with this status (repeatable)
+        #[arg(long)]
+        status: Vec<String>,
+        /// Only show tasks pre-assigned to this agent
+        #[arg(long)]
+        agent: Option<String>,
+        /// Only show tasks claimed by this agent (`assigned_to`)
+        #[arg(long)]
+        assigned_to: Option<String>,
+        /// Sort by <field>[:asc|desc] (priority, status, title, created_at, updated_at, sort_order)
+        #[arg(long)]
+        sort: Option<String>,
+        /// Only show tasks with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// JSON only: keep just these keys in each task (comma-separated, e.g. id,title,status)
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+        /// Output format instead of text (csv: id,title,status,priority,sort_order,agent,assigned_to;
+        /// table: aligned ID, STATUS, PRIORITY, AGENT, TITLE columns, --json wins)
+        #[arg(long, value_parser = ["csv", "table"], conflicts_with = "fields")]
+        format: Option<String>,
+    },
+    /// Search tasks by title or description (substring match)
+    Search {
+        /// Text to look for
+        query: String,
+    },
     /// Show task details
     Show {
         /// Task ID or prefix
         id: String,
+        /// Include the full upstream dependency tree
+        #[arg(long)]
+        tree: bool,
+        /// Include tasks that depend on this one
+        #[arg(long)]
+        with_dependents: bool,
+        /// With --with-dependents, include every downstream task (with `depth`)
+        #[arg(long, requires = "with_dependents")]
+        transitive: bool,
+    },
+    /// Update task fields (only the given flags are changed)
+    #[command(visible_alias = "edit")]
+    Update {
+        /// Task ID or prefix
+        id: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        priority: Option<i32>,
+        /// Agent to execute this task
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    /// Change a task's sort order (tie-breaker for `next` at equal priority)
+    #[command(after_help = "\
+NOTE:
+  Exactly one of --position, --before, --after is required.
+  The plan's sort_order is renumbered to contiguous values (0, 1, 2, ...).")]
+    Reorder {
+        /// Task ID or prefix
+        id: String,
+        /// Zero-based position in the plan's order
+        #[arg(long)]
+        position: Option<usize>,
+        /// Place the task right before this task
+        #[arg(long)]
+        before: Option<String>,
+        /// Place the task right after this task
+        #[arg(long)]
+        after: Option<String>,
+    },
+    /// Clone a task (with its documents) as a fresh task in the same plan
+    Clone {
+        /// Task ID or prefix
+        id: String,
+        /// Title for the new task (defaults to the source title)
+        #[arg(long)]
+        title: Option<String>,
+        /// Add dependency: clone runs after this task ID
+        #[arg(long)]
+        after: Vec<String>,
     },
     /// Start a task (ready → in_progress)
     Start {
@@ -161,13 +784,41 @@ pub enum TaskCommands {
         #[arg(long)]
         agent: Option<String>,
     },
+    /// Claim a specific ready task (ready → in_progress, sets `assigned_to`)
+    #[command(after_help = "\
+NOTE:
+  Like `next --claim`, but for the task you name. The status is re-checked inside
+  the write transaction: if another agent claimed it first, this fails with
+  INVALID_STATUS_TRANSITION and nothing changes. JSON matches `next --claim`.")]
+    Claim {
+        id: String,
+        /// Agent claiming the task
+        #[arg(long)]
+        agent: Option<String>,
+    },
     /// Complete a task (ready|in_progress → done)
     Done {
         id: String,
+        /// Short result summary, kept as the task's `completion_note`
+        #[arg(long)]
+        note: Option<String>,
     },
-    /// Fail a task (in_progress → ready, or → blocked if deps no longer met)
+    /// Fail a task (in_progress → ready, or → blocked if deps no longer met; → cancelled past --max-attempts)
+    #[command(after_help = "\
+NOTE:
+  --reason is kept on the task as `failure_reason` (shown by `task show` and `next`)
+  until the task is started again, so the next agent knows what was tried.
+  Every fail increments `fail_count`.
+  --max-attempts <N> cancels the task instead once this fail makes `fail_count`
+  exceed N, so a task that keeps failing does not loop forever. The reason is kept.")]
     Fail {
         id: String,
+        /// Why the task failed
+        #[arg(long)]
+        reason: Option<String>,
+        /// Cancel the task when it has now failed more than N times
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(i64).range(0..))]
+        max_attempts: Option<i64>,
     },
     /// Skip a task (ready|blocked → skipped)
     Skip {
@@ -177,19 +828,229 @@ pub enum TaskCommands {
     Cancel {
         id: String,
     },
+    /// Set the expected duration of a task in minutes
+    Estimate {
+        id: String,
+        /// Estimated minutes (0 or more)
+        #[arg(allow_negative_numbers = true)]
+        minutes: i64,
+    },
+    /// Set or clear the target completion time of a task
+    #[command(after_help = "\
+NOTE:
+  Accepts RFC 3339 (2026-03-01T17:00:00+09:00), `YYYY-MM-DD HH:MM:SS` or
+  `YYYY-MM-DD` (end of that day). Times without an offset are UTC.
+  Overdue ready tasks are returned by `next` before higher-priority ones.
+  The deadline is cleared when the task is done.")]
+    Deadline {
+        id: String,
+        /// Deadline (ISO 8601)
+        #[arg(required_unless_present = "clear")]
+        datetime: Option<String>,
+        /// Remove the deadline
+        #[arg(long, conflicts_with = "datetime")]
+        clear: bool,
+    },
+    /// Pre-assign a task to an agent (status is unchanged)
+    Assign {
+        id: String,
+        #[arg(long)]
+        agent: String,
+    },
+    /// Clear a task's assignee; an in_progress task goes back to ready
+    #[command(after_help = "\
+NOTE:
+  Hands a claimed task back without failing it: an in_progress task returns to
+  `ready` (or `blocked` if a dependency was reopened) with `started_at` cleared,
+  so the next `next` can pick it up. Other statuses are unchanged.
+  JSON includes `previous_assignee`.")]
+    Unassign {
+        id: String,
+    },
+    /// Report that an in_progress task is still being worked on
+    #[command(after_help = "\
+NOTE:
+  --extend <minutes> also moves the claim expiry (see `next --timeout`) to that many
+  minutes from now. Without it, a task claimed with an expiry is renewed by
+  TASKAI_CLAIM_TIMEOUT (or `config claim_expiry_minutes`) when set. A claim that already expired cannot be renewed
+  (INVALID_STATUS_TRANSITION); claim the task again instead.
+  Call it at least as often as your polling interval, e.g. every 30 seconds.")]
+    Heartbeat {
+        id: String,
+        /// Agent sending the heartbeat (must match the assignee, if any)
+        #[arg(long)]
+        agent: Option<String>,
+        /// Renew the claim to expire this many minutes from now
+        #[arg(long, value_name = "MINUTES")]
+        extend: Option<i64>,
+    },
+    /// Reset a finished task (done|cancelled|skipped → ready, or → blocked if deps not met)
+    Reset {
+        id: String,
+    },
+    /// Print only the IDs of the tasks this task depends on, one per line
+    #[command(after_help = "\
+NOTE:
+  Pipe-friendly: no task metadata. JSON is a flat array under `data.dependencies`.
+  See `task dep list` for titles and statuses.
+  Example: taskai task deps <id> --status-filter ready | xargs -n1 taskai task start")]
+    Deps {
+        id: String,
+        /// Only dependencies in this status
+        #[arg(long, value_name = "STATUS")]
+        status_filter: Option<String>,
+    },
+    /// Print only the IDs of the tasks that depend on this task, one per line
+    #[command(after_help = "\
+NOTE:
+  Pipe-friendly: no task metadata. JSON is a flat array under `data.dependents`.")]
+    Blocking {
+        id: String,
+        /// Only dependents in this status
+        #[arg(long, value_name = "STATUS")]
+        status_filter: Option<String>,
+    },
+    /// Show the status-change events of a task (oldest first)
+    History {
+        id: String,
+        /// Only transitions at or after this time (ISO 8601, UTC unless an offset is given)
+        #[arg(long)]
+        since: Option<String>,
+    },
+    /// Move a task to another plan (appended at the end)
+    #[command(after_help = "\
+NOTE:
+  Documents, notes and tags move with the task.
+  Dependencies are plan-local, so every dependency edge touching the task is
+  dropped and listed under `dropped_dependencies`. Blocked tasks on either
+  side are re-evaluated afterwards.")]
+    Move {
+        id: String,
+        /// Target plan name or ID
+        #[arg(long)]
+        to_plan: String,
+    },
+    /// Complete all matching tasks in one transaction
+    #[command(after_help = BULK_HELP)]
+    BulkDone(BulkSelection),
+    /// Cancel all matching tasks in one transaction
+    #[command(after_help = BULK_HELP)]
+    BulkCancel(BulkSelection),
+    /// Skip all matching tasks in one transaction
+    #[command(after_help = BULK_HELP)]
+    BulkSkip(BulkSelection),
+    /// Delete a task (blocked dependents are re-evaluated)
+    Delete {
+        id: String,
+    },
+    /// Release stale in_progress tasks (in_progress → ready, or → blocked if deps no longer met)
+    #[command(after_help = "\
+NOTE:
+  A task is stale when its `started_at` is at least --older-than minutes ago.
+  Released tasks lose their `assigned_to` so another agent can claim them.")]
+    Reclaim {
+        /// Staleness threshold in minutes
+        #[arg(long, value_name = "MINUTES")]
+        older_than: i64,
+    },
     /// Manage task dependencies
     #[command(subcommand)]
     Dep(DepCommands),
+    /// Manage freeform task notes (do not change status)
+    #[command(subcommand)]
+    Note(NoteCommands),
+    /// Manage task tags (labels for routing, e.g. gpu, needs-human)
+    #[command(subcommand)]
+    Tag(TagCommands),
+    /// Manage a task's documents
+    #[command(subcommand)]
+    Doc(DocCommands),
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    /// Add a tag to a task
+    Add {
+        /// Task ID or prefix
+        id: String,
+        /// Tag (lowercase alphanumeric with hyphens)
+        tag: String,
+    },
+    /// Remove a tag from a task
+    Remove {
+        /// Task ID or prefix
+        id: String,
+        tag: String,
+    },
+    /// List tags of a task
+    List {
+        /// Task ID or prefix
+        id: String,
+    },
+}
+
+const BULK_HELP: &str = "\
+NOTE:
+  Tasks are picked by --ids, --status and/or --tag (combined filters must all match)
+  and processed in plan order, so `done` on a chain unblocks as it goes.
+  A task that cannot make the transition is reported under `skipped` with a reason;
+  the rest still apply. Exit code is 1 only when every selected task was skipped.
+  Example: taskai task bulk-cancel --status blocked   # abandon the rest of a plan";
+
+/// Task selection for the `bulk-*` commands.
+#[derive(Args)]
+pub struct BulkSelection {
+    /// Only tasks with these statuses (comma-separated or repeated)
+    #[arg(long, value_delimiter = ',', required_unless_present_any = ["ids", "tag"])]
+    pub status: Vec<String>,
+    /// Only these task IDs or prefixes (comma-separated or repeated)
+    #[arg(long, value_delimiter = ',')]
+    pub ids: Vec<String>,
+    /// Only tasks carrying this tag
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum NoteCommands {
+    /// Add a note to a task
+    Add {
+        /// Task ID or prefix
+        id: String,
+        /// Note text
+        content: String,
+        /// Agent leaving the note
+        #[arg(long)]
+        agent: Option<String>,
+    },
+    /// List notes of a task (newest first)
+    List {
+        /// Task ID or prefix
+        id: String,
+    },
+    /// Delete a note
+    Delete {
+        /// Note ID or prefix
+        note_id: String,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum DepCommands {
     /// Add a dependency
+    #[command(after_help = "\
+NOTE:
+  Both tasks must be in the same plan unless --allow-cross-plan is given; then
+  DEP_ID is resolved across all plans and the task waits for it like any other dependency.
+  Cross-plan dependencies are not included in `plan export`.")]
     Add {
         /// Task ID
         id: String,
         /// Dependency task ID
         dep_id: String,
+        /// Allow DEP_ID to belong to another plan
+        #[arg(long)]
+        allow_cross_plan: bool,
     },
     /// Remove a dependency
     Remove {
@@ -197,5 +1058,23 @@ pub enum DepCommands {
         id: String,
         /// Dependency task ID
         dep_id: String,
+        /// Resolve DEP_ID across all plans
+        #[arg(long)]
+        allow_cross_plan: bool,
+    },
+    /// List what a task depends on (or, with --reverse, what depends on it)
+    #[command(after_help = "\
+NOTE:
+  Direct dependencies only; use `task show --tree` / `--with-dependents --transitive`
+  for the whole graph. JSON returns `dependencies`, `dependents`, or both with --both.")]
+    List {
+        /// Task ID
+        id: String,
+        /// List tasks that depend on this one instead
+        #[arg(long, conflicts_with = "both")]
+        reverse: bool,
+        /// List both directions
+        #[arg(long)]
+        both: bool,
     },
 }
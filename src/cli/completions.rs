@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use clap::CommandFactory;
+use clap_complete::{Generator, Shell};
+use serde_json::json;
+
+use crate::cli::commands::Cli;
+use crate::db::{connection, plan_repo};
+use crate::error::TaskaiError;
+use crate::output;
+
+/// Hidden command the scripts call to complete `--plan` values.
+pub const PLAN_NAMES_COMMAND: &str = "__plan-names";
+
+pub fn run(shell: Shell, out_dir: Option<&Path>, json_output: bool) -> i32 {
+    match run_inner(shell, out_dir, json_output) {
+        Ok(code) => code,
+        Err(e) => {
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&output::json::error(&e)).unwrap());
+            } else {
+                eprintln!("Error: {}", e.message);
+            }
+            1
+        }
+    }
+}
+
+fn run_inner(shell: Shell, out_dir: Option<&Path>, json_output: bool) -> Result<i32, TaskaiError> {
+    let script = completion_script(shell);
+    let Some(dir) = out_dir else {
+        print!("{script}");
+        return Ok(0);
+    };
+    let path = dir.join(shell.file_name("taskai"));
+    std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&path, &script))
+        .map_err(|e| TaskaiError::validation(format!("Cannot write {}: {e}", path.display())))?;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "shell": shell.to_string(),
+            "path": path.to_string_lossy()
+        }))).unwrap());
+    } else {
+        println!("Wrote {} completions to {}", shell, path.display());
+    }
+    Ok(0)
+}
+
+/// clap's script for `shell`, plus a hook completing `--plan` with the plan names in the
+/// current database (bash, zsh and fish).
+fn completion_script(shell: Shell) -> String {
+    let mut buf = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "taskai", &mut buf);
+    let script = String::from_utf8(buf).expect("completion script is UTF-8");
+    match shell {
+        Shell::Bash => format!(
+            "{}
+_taskai_with_plans() {{
+    if [[ \"${{COMP_WORDS[COMP_CWORD-1]}}\" == \"--plan\" ]]; then
+        COMPREPLY=( $(compgen -W \"$(taskai {PLAN_NAMES_COMMAND} 2>/dev/null)\" -- \"${{COMP_WORDS[COMP_CWORD]}}\") )
+        return 0
+    fi
+    _taskai \"$@\"
+}}
+",
+            script.replace("complete -F _taskai ", "complete -F _taskai_with_plans ")
+        ),
+        Shell::Zsh => {
+            // The hook must exist before the autoloaded file calls `_taskai`
+            let hook = format!(
+                "_taskai_plans() {{
+    local -a plans
+    plans=(${{(f)\"$(taskai {PLAN_NAMES_COMMAND} 2>/dev/null)\"}})
+    _describe 'plan' plans
+}}
+"
+            );
+            let script = script.replace(":PLAN:_default", ":PLAN:_taskai_plans");
+            match script.split_once('\n') {
+                Some((compdef, rest)) => format!("{compdef}\n\n{hook}{rest}"),
+                None => format!("{hook}{script}"),
+            }
+        }
+        Shell::Fish => format!(
+            "{script}complete -c taskai -l plan -x -a '(taskai {PLAN_NAMES_COMMAND} 2>/dev/null)'\n"
+        ),
+        _ => script,
+    }
+}
+
+/// Print the visible plan names, one per line. Prints nothing when there is no database.
+pub fn run_plan_names() -> i32 {
+    let plans = connection::open_db_read_only()
+        .ok()
+        .flatten()
+        .and_then(|conn| plan_repo::list_plans_filtered(&conn, false).ok())
+        .unwrap_or_default();
+    for plan in plans {
+        println!("{}", plan.name);
+    }
+    0
+}
@@ -0,0 +1,104 @@
+use serde_json::json;
+
+use crate::cli::commands::ConfigCommands;
+use crate::db::config::{self, CONFIG_KEYS};
+use crate::db::connection;
+use crate::error::TaskaiError;
+use crate::output;
+
+pub fn run(cmd: ConfigCommands, json_output: bool) -> i32 {
+    let result = match cmd {
+        ConfigCommands::Set { key, value } => run_set(&key, &value, json_output),
+        ConfigCommands::Get { key } => run_get(&key, json_output),
+        ConfigCommands::List => run_list(json_output),
+        ConfigCommands::Reset { key } => run_reset(key.as_deref(), json_output),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&output::json::error(&e)).unwrap());
+            } else {
+                eprintln!("Error: {}", e.message);
+            }
+            1
+        }
+    }
+}
+
+fn run_set(key: &str, value: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let mut config = config::load_config();
+    config.set(key, value)?;
+    config::save_config(&config)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "key": key,
+            "value": config.get(key)?
+        }))).unwrap());
+    } else {
+        println!("{key} = {value}");
+    }
+    Ok(0)
+}
+
+fn run_get(key: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let value = config::load_config().get(key)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "key": key,
+            "value": value
+        }))).unwrap());
+    } else if let Some(value) = value {
+        println!("{value}");
+    }
+    Ok(0)
+}
+
+fn run_list(json_output: bool) -> Result<i32, TaskaiError> {
+    let config = config::load_config();
+
+    if json_output {
+        let mut values = serde_json::Map::new();
+        for key in CONFIG_KEYS {
+            values.insert(key.to_string(), json!(config.get(key)?));
+        }
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "config": values,
+            "path": connection::config_path()?.display().to_string()
+        }))).unwrap());
+    } else {
+        for key in CONFIG_KEYS {
+            match config.get(key)? {
+                Some(value) => println!("{key} = {value}"),
+                None => println!("{key} (unset)"),
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// Unset one key, or every setting when `key` is `None`. The active plan is kept.
+fn run_reset(key: Option<&str>, json_output: bool) -> Result<i32, TaskaiError> {
+    let mut config = config::load_config();
+    let keys: Vec<&str> = match key {
+        Some(key) => vec![key],
+        None => CONFIG_KEYS.to_vec(),
+    };
+    for key in &keys {
+        config.reset(key)?;
+    }
+    config::save_config(&config)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({ "reset": keys }))).unwrap());
+    } else {
+        match key {
+            Some(key) => println!("Reset {key}"),
+            None => println!("Reset all settings"),
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde_json::json;
+
+use crate::db::{config, connection, dependency_repo, migrations, plan_repo, task_repo};
+use crate::error::TaskaiError;
+use crate::models::PlanStatus;
+use crate::output;
+
+/// A WAL larger than this means checkpoints are not keeping up.
+const WAL_WARN_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    Ok,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Ok => "ok",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Severity::Ok => "✓",
+            Severity::Warn => "⚠",
+            Severity::Error => "✗",
+        }
+    }
+}
+
+struct Check {
+    name: &'static str,
+    severity: Severity,
+    message: String,
+}
+
+impl Check {
+    fn new(name: &'static str, severity: Severity, message: impl Into<String>) -> Self {
+        Check { name, severity, message: message.into() }
+    }
+}
+
+/// Run every health check and report them. Exit code: 0 all ok, 1 any error, 2 warnings only.
+pub fn run(json_output: bool) -> i32 {
+    let checks = run_checks();
+    let code = match checks.iter().map(|c| c.severity).max() {
+        Some(Severity::Error) => 1,
+        Some(Severity::Warn) => 2,
+        _ => 0,
+    };
+
+    if json_output {
+        let checks_json: Vec<_> = checks
+            .iter()
+            .map(|c| json!({"name": c.name, "severity": c.severity.as_str(), "message": c.message}))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "checks": checks_json
+        }))).unwrap());
+    } else {
+        for c in &checks {
+            println!("{} {}: {}", c.severity.symbol(), c.name, c.message);
+        }
+    }
+    code
+}
+
+type DbCheck = fn(&Connection) -> Result<Check, TaskaiError>;
+
+fn run_checks() -> Vec<Check> {
+    let mut checks = vec![check_git()];
+    let path = match connection::db_path() {
+        Ok(path) => path,
+        Err(e) => {
+            checks.push(Check::new("database", Severity::Error, e.message));
+            return checks;
+        }
+    };
+    let conn = match open(&path) {
+        Ok(conn) => conn,
+        Err(message) => {
+            checks.push(Check::new("database", Severity::Error, message));
+            return checks;
+        }
+    };
+    checks.push(Check::new("database", Severity::Ok, format!("{} is readable", path.display())));
+    let db_checks: [(&'static str, DbCheck); 4] = [
+        ("schema_version", check_schema),
+        ("active_plan", check_active_plan),
+        ("orphaned_dependencies", check_orphaned_dependencies),
+        ("in_progress_started", check_in_progress_started),
+    ];
+    for (name, check) in db_checks {
+        checks.push(check(&conn).unwrap_or_else(|e| Check::new(name, Severity::Error, e.message)));
+    }
+    checks.push(check_wal(&path));
+    checks
+}
+
+fn check_git() -> Check {
+    match connection::find_git_root() {
+        Ok(root) => Check::new("git_repository", Severity::Ok, format!("found at {}", root.display())),
+        // An explicit database location does not need a repository
+        Err(_) if connection::db_path().is_ok() => {
+            Check::new("git_repository", Severity::Warn, "not inside a git repository (database path given explicitly)")
+        }
+        Err(e) => Check::new("git_repository", Severity::Error, e.message),
+    }
+}
+
+/// Open the database read-only, so the checks neither migrate nor create anything.
+fn open(path: &Path) -> Result<Connection, String> {
+    match connection::open_db_read_only() {
+        Ok(Some(conn)) => {
+            conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+                .map_err(|e| format!("{} is not readable: {e}", path.display()))?;
+            Ok(conn)
+        }
+        Ok(None) => Err(format!("{} does not exist. Run `taskai init` first.", path.display())),
+        Err(e) => Err(e.message),
+    }
+}
+
+fn check_schema(conn: &Connection) -> Result<Check, TaskaiError> {
+    let version = migrations::current_version(conn)?;
+    let expected = migrations::SCHEMA_VERSION;
+    Ok(match version.cmp(&expected) {
+        std::cmp::Ordering::Equal => Check::new("schema_version", Severity::Ok, format!("version {version}")),
+        std::cmp::Ordering::Less => Check::new(
+            "schema_version",
+            Severity::Warn,
+            format!("version {version}, expected {expected}; the next write command migrates it"),
+        ),
+        std::cmp::Ordering::Greater => Check::new(
+            "schema_version",
+            Severity::Error,
+            format!("version {version} is newer than this taskai ({expected}); upgrade taskai"),
+        ),
+    })
+}
+
+fn check_active_plan(conn: &Connection) -> Result<Check, TaskaiError> {
+    let path = connection::config_path()?;
+    if let Ok(content) = std::fs::read_to_string(&path) {
+        if let Err(e) = serde_json::from_str::<config::Config>(&content) {
+            return Ok(Check::new("active_plan", Severity::Error, format!("{} is invalid: {e}", path.display())));
+        }
+    }
+    let Some(id) = config::load_config().active_plan_id else {
+        return Ok(Check::new("active_plan", Severity::Warn, "no active plan. Use `taskai plan activate <name>`."));
+    };
+    Ok(match plan_repo::get_plan_by_id(conn, &id) {
+        Ok(plan) if plan.status.is_hidden() => {
+            Check::new("active_plan", Severity::Error, format!("active plan '{}' is deleted", plan.name))
+        }
+        Ok(plan) if plan.status == PlanStatus::Archived => {
+            Check::new("active_plan", Severity::Warn, format!("active plan '{}' is archived", plan.name))
+        }
+        Ok(plan) => Check::new("active_plan", Severity::Ok, format!("'{}' ({})", plan.name, plan.id)),
+        Err(_) => Check::new("active_plan", Severity::Error, format!("active plan {id} does not exist")),
+    })
+}
+
+fn check_orphaned_dependencies(conn: &Connection) -> Result<Check, TaskaiError> {
+    let count = dependency_repo::count_orphaned_dependencies(conn)?;
+    Ok(if count == 0 {
+        Check::new("orphaned_dependencies", Severity::Ok, "none")
+    } else {
+        Check::new(
+            "orphaned_dependencies",
+            Severity::Error,
+            format!("{count} dependency row(s) point to tasks that do not exist"),
+        )
+    })
+}
+
+fn check_in_progress_started(conn: &Connection) -> Result<Check, TaskaiError> {
+    let ids = task_repo::in_progress_without_start(conn)?;
+    Ok(if ids.is_empty() {
+        Check::new("in_progress_started", Severity::Ok, "every in_progress task has started_at")
+    } else {
+        Check::new(
+            "in_progress_started",
+            Severity::Warn,
+            format!("in_progress without started_at: {}", ids.join(", ")),
+        )
+    })
+}
+
+fn check_wal(path: &Path) -> Check {
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    let bytes = std::fs::metadata(&wal).map(|m| m.len()).unwrap_or(0);
+    if bytes > WAL_WARN_BYTES {
+        Check::new(
+            "wal_size",
+            Severity::Warn,
+            format!("WAL is {} MiB; `taskai db backup` checkpoints it", bytes / (1024 * 1024)),
+        )
+    } else {
+        Check::new("wal_size", Severity::Ok, format!("{bytes} bytes"))
+    }
+}
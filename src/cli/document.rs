@@ -0,0 +1,308 @@
+use std::io::Read;
+
+use rusqlite::Connection;
+use serde_json::json;
+
+use crate::cli::commands::{DocCommands, DocumentCommands};
+use crate::cli::plan::resolve_plan_id;
+use crate::db::{connection, document_repo, plan_repo, task_repo};
+use crate::db::document_repo::DocumentSearchHit;
+use crate::error::TaskaiError;
+use crate::models::{Document, Plan, Task};
+use crate::output;
+
+pub fn run(cmd: DocumentCommands, json_output: bool, plan_flag: Option<&str>) -> i32 {
+    let result = match cmd {
+        DocumentCommands::Add { task, title, content, content_file } => {
+            run_add(task.as_deref(), &title, content, content_file.as_deref(), json_output, plan_flag)
+        }
+        DocumentCommands::Update { id, title, content, content_file } => {
+            run_update(&id, title.as_deref(), content, content_file.as_deref(), json_output)
+        }
+        DocumentCommands::Delete { id } => run_delete(&id, json_output),
+        DocumentCommands::Search { query } => run_search(&query, json_output, plan_flag),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&output::json::error(&e)).unwrap());
+            } else {
+                eprintln!("Error: {}", e.message);
+            }
+            1
+        }
+    }
+}
+
+/// Attach a document to `task` (resolved in the --plan/active plan), or to the plan
+/// named by --plan when no task is given.
+fn run_add(
+    task: Option<&str>,
+    title: &str,
+    content: Option<String>,
+    content_file: Option<&str>,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    if task.is_none() && plan_flag.is_none() {
+        return Err(TaskaiError::validation("Specify --plan <plan> or --task <id> to attach the document to"));
+    }
+    let content = read_content(content, content_file)?
+        .ok_or_else(|| TaskaiError::validation("Specify --content or --content-file"))?;
+
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let owner = match task {
+        Some(reference) => Owner::Task(Box::new(task_repo::resolve_task(&conn, &plan_id, reference)?)),
+        None => Owner::Plan(plan_repo::get_plan_by_id(&conn, &plan_id)?),
+    };
+    attach(&conn, &owner, title, &content, json_output)
+}
+
+/// Which kind of owner a `plan doc` / `task doc` command addresses.
+pub enum DocOwner {
+    Plan,
+    Task,
+}
+
+enum Owner {
+    Plan(Plan),
+    Task(Box<Task>),
+}
+
+/// `plan doc` and `task doc`: add, update or remove documents of one plan or task.
+/// A task owner is resolved in the --plan (or active) plan.
+pub fn run_doc(
+    kind: DocOwner,
+    cmd: DocCommands,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let owner_ref = match &cmd {
+        DocCommands::Add { owner, .. } | DocCommands::Update { owner, .. } | DocCommands::Remove { owner, .. } => {
+            owner.clone()
+        }
+    };
+    // Read stdin/file content before touching the database
+    let content = match &cmd {
+        DocCommands::Add { content, content_file, .. } => {
+            Some(read_content(content.clone(), content_file.as_deref())?
+                .ok_or_else(|| TaskaiError::validation("Specify --content or --content-file"))?)
+        }
+        DocCommands::Update { title, content, content_file, .. } => {
+            let content = read_content(content.clone(), content_file.as_deref())?;
+            if title.is_none() && content.is_none() {
+                return Err(TaskaiError::validation(
+                    "Nothing to update. Use --title, --content or --content-file.",
+                ));
+            }
+            content
+        }
+        DocCommands::Remove { .. } => None,
+    };
+
+    let conn = connection::open_db()?;
+    let owner = match kind {
+        DocOwner::Plan => Owner::Plan(plan_repo::resolve_plan(&conn, &owner_ref)?),
+        DocOwner::Task => {
+            let plan_id = resolve_plan_id(&conn, plan_flag)?;
+            Owner::Task(Box::new(task_repo::resolve_task(&conn, &plan_id, &owner_ref)?))
+        }
+    };
+    let resolve_doc = |reference: &str| -> Result<String, TaskaiError> {
+        Ok(match &owner {
+            Owner::Plan(p) => document_repo::resolve_plan_document(&conn, &p.id, reference)?.id,
+            Owner::Task(t) => document_repo::resolve_task_document(&conn, &t.id, reference)?.id,
+        })
+    };
+
+    match cmd {
+        DocCommands::Add { title, .. } => attach(&conn, &owner, &title, &content.unwrap_or_default(), json_output),
+        DocCommands::Update { doc, title, .. } => {
+            let id = resolve_doc(&doc)?;
+            print_updated(&conn, &id, title.as_deref(), content.as_deref(), json_output)
+        }
+        DocCommands::Remove { doc, .. } => {
+            let id = resolve_doc(&doc)?;
+            print_deleted(&conn, &id, json_output)
+        }
+    }
+}
+
+fn attach(conn: &Connection, owner: &Owner, title: &str, content: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let doc_id = ulid::Ulid::new().to_string();
+    let (kind, target, label) = match owner {
+        Owner::Task(task) => {
+            document_repo::create_task_document(conn, &doc_id, &task.id, title, content)?;
+            ("task", json!({ "id": task.id, "title": task.title }), format!("task {}", task.id))
+        }
+        Owner::Plan(plan) => {
+            document_repo::create_plan_document(conn, &doc_id, &plan.id, title, content)?;
+            ("plan", json!({ "id": plan.id, "name": plan.name }), format!("plan {}", plan.name))
+        }
+    };
+
+    if json_output {
+        let mut data = json!({
+            "document": { "id": doc_id, "title": title, "content_length": content.len() }
+        });
+        data[kind] = target;
+        println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
+    } else {
+        println!("Attached document '{title}' to {label}");
+    }
+    Ok(0)
+}
+
+fn run_update(
+    id: &str,
+    title: Option<&str>,
+    content: Option<String>,
+    content_file: Option<&str>,
+    json_output: bool,
+) -> Result<i32, TaskaiError> {
+    let content = read_content(content, content_file)?;
+    if title.is_none() && content.is_none() {
+        return Err(TaskaiError::validation(
+            "Nothing to update. Use --title, --content or --content-file.",
+        ));
+    }
+
+    let conn = connection::open_db()?;
+    print_updated(&conn, id, title, content.as_deref(), json_output)
+}
+
+fn print_updated(
+    conn: &Connection,
+    id: &str,
+    title: Option<&str>,
+    content: Option<&str>,
+    json_output: bool,
+) -> Result<i32, TaskaiError> {
+    let doc = document_repo::update_document(conn, id, title, content)?;
+
+    if json_output {
+        let data = match doc {
+            Document::Plan(ref d) => {
+                let mut v = output::json::plan_document_json(d);
+                v["plan_id"] = json!(d.plan_id);
+                v["updated_at"] = json!(d.updated_at);
+                v
+            }
+            Document::Task(ref d) => {
+                let mut v = output::json::task_document_json(d);
+                v["task_id"] = json!(d.task_id);
+                v["updated_at"] = json!(d.updated_at);
+                v
+            }
+        };
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({ "document": data }))).unwrap());
+    } else {
+        let title = match doc {
+            Document::Plan(ref d) => &d.title,
+            Document::Task(ref d) => &d.title,
+        };
+        println!("Updated document '{title}' ({id})");
+    }
+    Ok(0)
+}
+
+fn run_delete(id: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    print_deleted(&conn, id, json_output)
+}
+
+fn print_deleted(conn: &Connection, id: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let (kind, title) = match document_repo::delete_document(conn, id)? {
+        Document::Plan(d) => ("plan", d.title),
+        Document::Task(d) => ("task", d.title),
+    };
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "deleted": { "id": id, "title": title, "kind": kind }
+        }))).unwrap());
+    } else {
+        println!("Deleted document '{title}'");
+    }
+    Ok(0)
+}
+
+fn run_search(query: &str, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = match plan_flag {
+        Some(reference) => Some(plan_repo::resolve_plan(&conn, reference)?.id),
+        None => None,
+    };
+    let (hits, warning) = if document_repo::has_document_fts(&conn)? {
+        (document_repo::search_documents_fts(&conn, query, plan_id.as_deref())?, None)
+    } else {
+        let hits = document_repo::search_documents_like(&conn, query, plan_id.as_deref())?;
+        (hits, Some("Full-text search (FTS5) is not available; used a substring match"))
+    };
+
+    if json_output {
+        let results: Vec<_> = hits.iter().map(|h| json!({
+            "id": h.id,
+            "title": h.title,
+            "kind": h.kind,
+            "plan_id": h.plan_id,
+            "task_id": h.task_id,
+            "snippet": h.snippet
+        })).collect();
+        let mut data = json!({ "results": results });
+        if let Some(warning) = warning {
+            data["warnings"] = json!([warning]);
+        }
+        println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
+    } else {
+        if let Some(warning) = warning {
+            eprintln!("Warning: {warning}");
+        }
+        if hits.is_empty() {
+            println!("No documents match '{query}'.");
+        }
+        // Group by owner, in order of each owner's best match
+        let mut groups: Vec<(&DocumentSearchHit, Vec<&DocumentSearchHit>)> = Vec::new();
+        for h in &hits {
+            match groups.iter_mut().find(|(first, _)| first.plan_id == h.plan_id && first.task_id == h.task_id) {
+                Some((_, group)) => group.push(h),
+                None => groups.push((h, vec![h])),
+            }
+        }
+        for (owner, group) in groups {
+            match (&owner.task_id, &owner.task_title) {
+                (Some(id), Some(title)) => println!("Task {} ({}) in plan {}:", title, id, owner.plan_name),
+                _ => println!("Plan {}:", owner.plan_name),
+            }
+            for h in group {
+                println!("  {} ({}): {}", h.title, h.id, h.snippet.replace('\n', " "));
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// Content from `--content` (`-` reads stdin), or read from `--content-file`; `None` if
+/// neither was given. Blank content is rejected.
+fn read_content(content: Option<String>, content_file: Option<&str>) -> Result<Option<String>, TaskaiError> {
+    let content = match (content, content_file) {
+        (Some(c), _) if c == "-" => {
+            let mut buf = String::new();
+            std::io::stdin()
+                .read_to_string(&mut buf)
+                .map_err(|e| TaskaiError::validation(format!("Cannot read stdin: {e}")))?;
+            buf
+        }
+        (Some(c), _) => c,
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map_err(|e| TaskaiError::validation(format!("Cannot read content file '{path}': {e}")))?,
+        (None, None) => return Ok(None),
+    };
+    if content.trim().is_empty() {
+        return Err(TaskaiError::validation("Document content must not be empty"));
+    }
+    Ok(Some(content))
+}
@@ -1,8 +1,17 @@
 pub mod commands;
+pub mod completions;
+pub mod config;
+pub mod db;
+pub mod doctor;
+pub mod document;
 pub mod init;
 pub mod plan;
+pub mod plan_compare;
+pub mod plan_graph;
+pub mod plan_export;
 pub mod task;
 pub mod next;
 pub mod status;
+pub mod version;
 
 pub use commands::*;
@@ -1,14 +1,36 @@
 use serde_json::json;
 
-use crate::cli::plan::resolve_plan_id;
-use crate::db::{connection, task_repo, dependency_repo};
+use crate::api;
+use crate::cli::plan::resolve_open_plan_id;
+use crate::db::{config, connection, task_repo};
 use crate::error::TaskaiError;
 use crate::graph::next_tasks;
-use crate::models::TaskStatus;
+use crate::models::{Task, TaskStatus, DEFAULT_STALE_AFTER_MINUTES};
 use crate::output;
 
-pub fn run(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Option<&str>) -> i32 {
-    let result = run_inner(claim, agent, json_output, plan_flag);
+/// `next` flags as given on the command line.
+pub struct NextArgs<'a> {
+    pub claim: bool,
+    pub agent: Option<&'a str>,
+    pub timeout: Option<i64>,
+    pub reclaim_after: Option<i64>,
+    pub release: Option<&'a str>,
+    pub for_agent: Option<&'a str>,
+    pub strict: bool,
+    pub tag: Option<&'a str>,
+    pub min_priority: Option<i32>,
+    pub count: Option<usize>,
+    pub order: task_repo::ReadyOrder,
+    pub explain: bool,
+}
+
+pub fn run(args: NextArgs, json_output: bool, plan_flag: Option<&str>) -> i32 {
+    // Only claiming/reclaiming writes; a plain `next` stays lock-free
+    let result = if args.claim || args.reclaim_after.is_some() || args.release.is_some() {
+        connection::with_write_lock(|| run_inner(args, json_output, plan_flag))
+    } else {
+        run_inner(args, json_output, plan_flag)
+    };
     match result {
         Ok(code) => code,
         Err(e) => {
@@ -22,9 +44,86 @@ pub fn run(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Optio
     }
 }
 
-fn run_inner(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+fn run_inner(args: NextArgs, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let NextArgs { claim, agent, timeout, reclaim_after, release, for_agent, strict, tag, min_priority, count, order, explain } = args;
+    let filter = next_tasks::NextFilter { for_agent, strict, tag, min_priority, order };
+    // An agent asking for its own tasks claims them under its own name
+    let configured = config::load_config();
+    let agent = agent.or(for_agent).or(configured.default_agent.as_deref());
+    if count == Some(0) {
+        return Err(TaskaiError::validation("--count must be at least 1"));
+    }
+    let timeout = match timeout {
+        Some(minutes) => Some(minutes),
+        None if claim => default_claim_timeout()?,
+        None => None,
+    };
+    if timeout.is_some_and(|m| m < 1) {
+        return Err(TaskaiError::validation("--timeout must be at least 1 minute"));
+    }
     let conn = connection::open_db()?;
-    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let plan_id = resolve_open_plan_id(&conn, plan_flag)?;
+    let expired = api::release_expired_claims(&conn, &plan_id)?;
+
+    // Release stale claims before looking at the queue
+    let reclaimed = match reclaim_after {
+        Some(minutes) => {
+            conn.execute_batch("BEGIN IMMEDIATE")?;
+            match next_tasks::reclaim_stale_tasks(&conn, &plan_id, minutes) {
+                Ok(tasks) => {
+                    conn.execute_batch("COMMIT")?;
+                    Some(tasks)
+                }
+                Err(e) => {
+                    let _ = conn.execute_batch("ROLLBACK");
+                    return Err(e);
+                }
+            }
+        }
+        None => None,
+    };
+    // Hand back an explicitly released claim
+    let released = match release {
+        Some(reference) => {
+            let task = task_repo::resolve_task(&conn, &plan_id, reference)?;
+            conn.execute_batch("BEGIN IMMEDIATE")?;
+            if let Err(e) = next_tasks::release_claim(&conn, &task) {
+                let _ = conn.execute_batch("ROLLBACK");
+                return Err(e);
+            }
+            conn.execute_batch("COMMIT")?;
+            Some(task_repo::get_task_by_id(&conn, &task.id)?)
+        }
+        None => None,
+    };
+    let with_reclaimed = |mut data: serde_json::Value| {
+        if let Some(ref tasks) = reclaimed {
+            data["reclaimed"] = json!(tasks.iter().map(|t| t.id.as_str()).collect::<Vec<_>>());
+        }
+        if let Some(ref t) = released {
+            data["released"] = json!(t.id);
+        }
+        if let Some(name) = for_agent {
+            data["agent_filter"] = json!(name);
+        }
+        if !expired.is_empty() {
+            data["expired"] = json!(expired.iter().map(|t| t.id.as_str()).collect::<Vec<_>>());
+        }
+        data
+    };
+    let filter_note = for_agent.map(|name| format!(" (filtered for agent: {name})")).unwrap_or_default();
+    if !json_output {
+        if let Some(ref t) = released {
+            println!("Released: {} - {} → {}", t.id, t.title, t.status.as_str());
+        }
+        for t in reclaimed.iter().flatten() {
+            println!("Reclaimed: {} - {} → {}", t.id, t.title, t.status.as_str());
+        }
+        for t in &expired {
+            println!("Claim expired: {} - {} → {}", t.id, t.title, t.status.as_str());
+        }
+    }
+
     let progress = task_repo::task_progress(&conn, &plan_id)?;
 
     let plan_completed = progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0;
@@ -33,7 +132,7 @@ fn run_inner(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Opt
         if json_output {
             println!("{}", serde_json::to_string_pretty(
                 &output::json::success_with_plan_completed(
-                    json!({ "progress": output::json::progress_json(&progress) }),
+                    with_reclaimed(json!({ "progress": output::json::progress_json(&progress) })),
                     true,
                 )
             ).unwrap());
@@ -47,26 +146,41 @@ fn run_inner(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Opt
     // Get in_progress tasks
     let in_progress = task_repo::in_progress_tasks(&conn, &plan_id)?;
     let in_progress_json: Vec<_> = in_progress.iter().map(|t| {
-        let elapsed = elapsed_minutes(t.started_at.as_deref());
-        output::json::in_progress_entry(t, elapsed)
+        let elapsed = next_tasks::elapsed_minutes(t.started_at.as_deref());
+        output::json::in_progress_entry(t, elapsed, DEFAULT_STALE_AFTER_MINUTES)
     }).collect();
 
-    // Get/claim next ready task
-    let task = if claim {
-        conn.execute_batch("BEGIN IMMEDIATE")?;
-        let result = next_tasks::claim_next_task(&conn, &plan_id, agent);
-        match result {
-            Ok(task) => {
-                conn.execute_batch("COMMIT")?;
-                task
+    // Get/claim next ready task(s); `--count 1` takes the single-task path
+    let task = if let Some(n) = count.filter(|&n| n > 1) {
+        let batch = if claim {
+            conn.execute_batch("BEGIN IMMEDIATE")?;
+            match next_tasks::claim_n_tasks(&conn, &plan_id, n, agent, &filter)
+                .and_then(|tasks| api::with_claim_timeout(&conn, tasks, timeout))
+            {
+                Ok(tasks) => {
+                    conn.execute_batch("COMMIT")?;
+                    tasks
+                }
+                Err(e) => {
+                    let _ = conn.execute_batch("ROLLBACK");
+                    return Err(e);
+                }
             }
-            Err(e) => {
-                let _ = conn.execute_batch("ROLLBACK");
-                return Err(e);
+        } else {
+            next_tasks::pick_n_tasks(&conn, &plan_id, n, &filter)?
+        };
+        if !batch.is_empty() {
+            print_batch(&conn, &plan_id, &batch, claim, in_progress_json, &with_reclaimed, json_output)?;
+            if !json_output && !filter_note.is_empty() {
+                println!("{}", filter_note.trim_start());
             }
+            return Ok(0);
         }
+        None
+    } else if claim {
+        api::claim_next(&conn, &plan_id, agent, &filter, timeout)?
     } else {
-        task_repo::next_ready_task(&conn, &plan_id)?
+        next_tasks::pick_next_task(&conn, &plan_id, &filter)?
     };
 
     if let Some(ref t) = task {
@@ -77,14 +191,14 @@ fn run_inner(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Opt
             let progress = task_repo::task_progress(&conn, &plan_id)?;
             let plan_completed = progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0;
             println!("{}", serde_json::to_string_pretty(
-                &output::json::success_with_plan_completed(json!({
+                &output::json::success_with_plan_completed(with_reclaimed(json!({
                     "task": output::json::task_detail(t, has_docs),
                     "in_progress": in_progress_json,
                     "progress": output::json::progress_json(&progress)
-                }), plan_completed)
+                })), plan_completed)
             ).unwrap());
         } else {
-            println!("Next task: {} ({})", t.title, t.id);
+            println!("Next task: {} ({}){}", t.title, t.id, filter_note);
             if let Some(ref desc) = t.description {
                 println!("  {desc}");
             }
@@ -96,25 +210,61 @@ fn run_inner(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Opt
         return Ok(0);
     }
 
+    // Ready tasks exist, but none matching --for-agent / --tag / --min-priority
+    if progress.ready > 0 {
+        let reason = next_tasks::no_match_reason(&conn, &plan_id, &filter)?;
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(
+                &output::json::success_with_plan_completed(with_reclaimed(json!({
+                    "task": null,
+                    "reason": reason,
+                    "in_progress": in_progress_json,
+                    "progress": output::json::progress_json(&progress)
+                })), false)
+            ).unwrap());
+        } else if let ("NO_TASK_FOR_AGENT", Some(name)) = (reason, for_agent) {
+            println!("No ready tasks for agent {}. {} ready for other agents.", name, progress.ready);
+        } else if let ("NO_TASK_WITH_TAG", Some(tag)) = (reason, tag) {
+            println!("No ready tasks tagged #{}. {} ready without it.", tag, progress.ready);
+        } else {
+            println!(
+                "No ready tasks with priority >= {}. {} ready below it.",
+                min_priority.unwrap_or_default(), progress.ready
+            );
+        }
+        return Ok(2);
+    }
+
     // No ready task — check if blocked remain
     if progress.blocked > 0 {
         if json_output {
-            let blocked_tasks = get_blocked_tasks_detail(&conn, &plan_id)?;
+            let blocked_tasks = get_blocked_tasks_detail(&conn, &plan_id, explain)?;
             println!("{}", serde_json::to_string_pretty(
-                &output::json::success_with_plan_completed(json!({
+                &output::json::success_with_plan_completed(with_reclaimed(json!({
                     "task": null,
                     "reason": "BLOCKED_REMAINING",
                     "blocked_tasks": blocked_tasks,
                     "in_progress": in_progress_json,
                     "progress": output::json::progress_json(&progress)
-                }), false)
+                })), false)
             ).unwrap());
         } else {
             println!("No ready tasks. {} blocked tasks remaining.", progress.blocked);
+            if explain {
+                for t in task_repo::list_tasks_by_plan(&conn, &plan_id)?.iter().filter(|t| t.status == TaskStatus::Blocked) {
+                    let frontier = next_tasks::unblock_frontier(&conn, &t.id)?;
+                    if frontier.is_empty() {
+                        println!("  {} - {} (no unfinished path; needs intervention)", t.id, t.title);
+                    } else {
+                        let titles: Vec<&str> = frontier.iter().map(|f| f.title.as_str()).collect();
+                        println!("  {} - {} (waits on: {})", t.id, t.title, titles.join(", "));
+                    }
+                }
+            }
             if !in_progress.is_empty() {
                 println!("In progress:");
                 for t in &in_progress {
-                    let elapsed = elapsed_minutes(t.started_at.as_deref());
+                    let elapsed = next_tasks::elapsed_minutes(t.started_at.as_deref());
                     println!("  {} - {} ({}min)", t.id, t.title, elapsed);
                 }
             }
@@ -125,12 +275,12 @@ fn run_inner(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Opt
     // in_progress tasks exist but no ready/blocked
     if json_output {
         println!("{}", serde_json::to_string_pretty(
-            &output::json::success_with_plan_completed(json!({
+            &output::json::success_with_plan_completed(with_reclaimed(json!({
                 "task": null,
                 "reason": "ALL_IN_PROGRESS",
                 "in_progress": in_progress_json,
                 "progress": output::json::progress_json(&progress)
-            }), false)
+            })), false)
         ).unwrap());
     } else {
         println!("No ready tasks. {} in progress.", progress.in_progress);
@@ -138,47 +288,87 @@ fn run_inner(claim: bool, agent: Option<&str>, json_output: bool, plan_flag: Opt
     Ok(2)
 }
 
-pub fn elapsed_minutes_pub(started_at: Option<&str>) -> i64 {
-    elapsed_minutes(started_at)
+fn print_batch(
+    conn: &rusqlite::Connection,
+    plan_id: &str,
+    batch: &[Task],
+    claimed: bool,
+    in_progress_json: Vec<serde_json::Value>,
+    with_reclaimed: &dyn Fn(serde_json::Value) -> serde_json::Value,
+    json_output: bool,
+) -> Result<(), TaskaiError> {
+    if json_output {
+        let mut tasks_json = Vec::new();
+        for t in batch {
+            tasks_json.push(output::json::task_detail(t, task_repo::task_has_documents(conn, &t.id)?));
+        }
+        let progress = task_repo::task_progress(conn, plan_id)?;
+        let plan_completed = progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0;
+        println!("{}", serde_json::to_string_pretty(
+            &output::json::success_with_plan_completed(with_reclaimed(json!({
+                "tasks": tasks_json,
+                "in_progress": in_progress_json,
+                "progress": output::json::progress_json(&progress)
+            })), plan_completed)
+        ).unwrap());
+    } else {
+        if claimed {
+            println!("Claimed {} task(s):", batch.len());
+        } else {
+            println!("Next {} task(s):", batch.len());
+        }
+        for t in batch {
+            println!("  {} - {}", t.id, t.title);
+        }
+    }
+    Ok(())
 }
 
-fn elapsed_minutes(started_at: Option<&str>) -> i64 {
-    let Some(started) = started_at else { return 0 };
-    let Ok(started) = chrono::NaiveDateTime::parse_from_str(started, "%Y-%m-%d %H:%M:%S") else {
-        return 0;
-    };
-    let now = chrono::Utc::now().naive_utc();
-    (now - started).num_minutes()
+/// Environment variable holding the default claim timeout in minutes.
+pub const CLAIM_TIMEOUT_ENV: &str = "TASKAI_CLAIM_TIMEOUT";
+
+/// Claim timeout used when `--timeout` is not given: `TASKAI_CLAIM_TIMEOUT`, else the
+/// `claim_expiry_minutes` setting.
+pub fn default_claim_timeout() -> Result<Option<i64>, TaskaiError> {
+    match std::env::var(CLAIM_TIMEOUT_ENV) {
+        Ok(v) if !v.trim().is_empty() => v.trim().parse().map(Some).map_err(|_| {
+            TaskaiError::validation(format!("{CLAIM_TIMEOUT_ENV} must be a number of minutes, got '{v}'"))
+        }),
+        _ => Ok(config::load_config().claim_expiry_minutes),
+    }
+}
+
+pub fn elapsed_minutes_pub(started_at: Option<&str>) -> i64 {
+    next_tasks::elapsed_minutes(started_at)
 }
 
 fn get_blocked_tasks_detail(
     conn: &rusqlite::Connection,
     plan_id: &str,
+    explain: bool,
 ) -> Result<Vec<serde_json::Value>, TaskaiError> {
     let tasks = task_repo::list_tasks_by_plan(conn, plan_id)?;
     let blocked: Vec<_> = tasks.iter().filter(|t| t.status == TaskStatus::Blocked).collect();
 
     let mut result = Vec::new();
     for t in blocked {
-        let deps = dependency_repo::get_dependencies(conn, &t.id)?;
-        let blocked_by: Vec<serde_json::Value> = deps
+        let blocked_by: Vec<serde_json::Value> = next_tasks::blocking_dependencies(conn, &t.id)?
             .iter()
-            .filter_map(|d| task_repo::get_task_by_id(conn, d).ok())
-            .filter(|d| d.status != TaskStatus::Done)
-            .map(|d| {
-                json!({
-                    "id": d.id,
-                    "title": d.title,
-                    "status": d.status.as_str()
-                })
-            })
+            .map(output::json::task_ref)
             .collect();
 
-        result.push(json!({
+        let mut entry = json!({
             "id": t.id,
             "title": t.title,
             "blocked_by": blocked_by
-        }));
+        });
+        if explain {
+            entry["unblock_frontier"] = json!(next_tasks::unblock_frontier(conn, &t.id)?
+                .iter()
+                .map(output::json::task_ref)
+                .collect::<Vec<_>>());
+        }
+        result.push(entry);
     }
     Ok(result)
 }
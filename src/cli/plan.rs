@@ -2,24 +2,60 @@ use std::collections::{HashMap, HashSet};
 use std::io::{self, Read};
 
 use rusqlite::Connection;
-use serde::Deserialize;
 use serde_json::json;
 
+use crate::api;
 use crate::cli::commands::PlanCommands;
-use crate::db::{connection, plan_repo, task_repo, dependency_repo, document_repo};
+use crate::cli::{document, plan_compare, plan_export, plan_graph};
+use crate::db::{config, connection, plan_repo, plan_stats_repo, task_repo, dependency_repo, document_repo, task_tags_repo};
 use crate::error::TaskaiError;
-use crate::graph::cycle;
-use crate::models::TaskStatus;
+use crate::graph::{cycle, next_tasks, waves};
+use crate::models::{Plan, PlanStatus, Task, TaskStatus};
 use crate::output;
+use crate::plan_input::{load_warnings, parse_load_input, validate_load_input, validate_plan_name, PlanLoadInput};
 
 pub fn run(cmd: PlanCommands, json_output: bool) -> i32 {
     let result = match cmd {
         PlanCommands::Create { name, title, description } => run_create(&name, title.as_deref(), description.as_deref(), json_output),
-        PlanCommands::List => run_list(json_output),
-        PlanCommands::Show { reference } => run_show(&reference, json_output),
+        PlanCommands::List { all, active_only, format } => run_list(all, active_only, format.as_deref(), json_output),
+        PlanCommands::Show { reference, format } => {
+            if format.as_deref() == Some("markdown") {
+                run_show_markdown(&reference)
+            } else {
+                run_show(&reference, json_output)
+            }
+        }
+        PlanCommands::Rename { reference, new_name, to, title } => {
+            let new_name = new_name.or(to).unwrap_or_default();
+            run_rename(&reference, &new_name, title.as_deref(), json_output)
+        }
+        PlanCommands::Update { reference, title, description } => {
+            run_update(&reference, title.as_deref(), description.as_deref(), json_output)
+        }
+        PlanCommands::Clone { reference, new_name, as_name, title } => {
+            let new_name = new_name.or(as_name).unwrap_or_default();
+            run_clone(&reference, &new_name, title.as_deref(), json_output)
+        }
+        PlanCommands::Merge { source, into } => run_merge(&source, &into, json_output),
+        PlanCommands::Archive { reference } => run_archive(&reference, json_output),
+        PlanCommands::Unarchive { reference } => run_unarchive(&reference, json_output),
         PlanCommands::Activate { name } => run_activate(&name, json_output),
-        PlanCommands::Delete { reference } => run_delete(&reference, json_output),
-        PlanCommands::Load => run_load(json_output),
+        PlanCommands::Deactivate => run_deactivate(json_output),
+        PlanCommands::Delete { reference, soft } => run_delete(&reference, soft, json_output),
+        PlanCommands::Restore { reference } => run_restore(&reference, json_output),
+        PlanCommands::Graph { reference, format } => plan_graph::run_graph(&reference, &format),
+        PlanCommands::CriticalPath { reference } => run_critical_path(&reference, json_output),
+        PlanCommands::Waves { reference } => run_waves(&reference, json_output),
+        PlanCommands::Stats { reference } => run_stats(reference.as_deref(), json_output),
+        PlanCommands::Export { reference, format } => plan_export::run_export(&reference, &format),
+        PlanCommands::Compare { a, b } => plan_compare::run_compare(&a, &b, json_output),
+        PlanCommands::Doc(doc_cmd) => document::run_doc(document::DocOwner::Plan, doc_cmd, json_output, None),
+        PlanCommands::Load { file, format, dry_run, merge, allow_extreme_priority, idempotency_key } => {
+            read_load_input(file.as_deref()).and_then(|input| {
+                let options = LoadOptions { dry_run, merge, allow_extreme_priority, idempotency_key };
+                run_load(input, format.as_deref(), options, json_output)
+            })
+        }
     };
     match result {
         Ok(code) => code,
@@ -34,35 +70,6 @@ pub fn run(cmd: PlanCommands, json_output: bool) -> i32 {
     }
 }
 
-fn validate_plan_name(name: &str) -> Result<(), TaskaiError> {
-    let re = regex_lite(name);
-    if !re {
-        return Err(TaskaiError::validation(
-            "Plan name must match ^[a-z0-9][a-z0-9-]*[a-z0-9]$ (or single char [a-z0-9])",
-        ));
-    }
-    Ok(())
-}
-
-fn regex_lite(name: &str) -> bool {
-    if name.is_empty() {
-        return false;
-    }
-    if name.len() == 1 {
-        return name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
-    }
-    let chars: Vec<char> = name.chars().collect();
-    let first = chars[0];
-    let last = *chars.last().unwrap();
-    if !(first.is_ascii_lowercase() || first.is_ascii_digit()) {
-        return false;
-    }
-    if !(last.is_ascii_lowercase() || last.is_ascii_digit()) {
-        return false;
-    }
-    chars.iter().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-')
-}
-
 fn run_create(name: &str, title: Option<&str>, description: Option<&str>, json_output: bool) -> Result<i32, TaskaiError> {
     validate_plan_name(name)?;
     let conn = connection::open_db()?;
@@ -78,9 +85,12 @@ fn run_create(name: &str, title: Option<&str>, description: Option<&str>, json_o
     Ok(0)
 }
 
-fn run_list(json_output: bool) -> Result<i32, TaskaiError> {
+fn run_list(all: bool, active_only: bool, format: Option<&str>, json_output: bool) -> Result<i32, TaskaiError> {
     let conn = connection::open_db()?;
-    let plans = plan_repo::list_plans(&conn)?;
+    let plans: Vec<_> = plan_repo::list_plans_filtered(&conn, all)?
+        .into_iter()
+        .filter(|p| !active_only || p.status == PlanStatus::Active)
+        .collect();
     let active_id = get_active_plan_id();
 
     if json_output {
@@ -92,6 +102,13 @@ fn run_list(json_output: bool) -> Result<i32, TaskaiError> {
             v
         }).collect();
         println!("{}", serde_json::to_string_pretty(&output::json::success(json!({ "plans": plans_json }))).unwrap());
+    } else if format == Some("table") {
+        let mut rows = Vec::new();
+        for p in plans {
+            let progress = task_repo::task_progress(&conn, &p.id)?;
+            rows.push((p, progress));
+        }
+        print!("{}", output::table::plans_table(&rows));
     } else {
         if plans.is_empty() {
             println!("No plans found.");
@@ -111,20 +128,29 @@ fn run_show(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
     let tasks = task_repo::list_tasks_by_plan(&conn, &plan.id)?;
     let progress = task_repo::task_progress(&conn, &plan.id)?;
     let docs = document_repo::get_plan_documents(&conn, &plan.id)?;
+    let critical = next_tasks::compute_critical_path(&conn, &plan.id)?;
+    let deps = dependency_repo::get_all_dependencies_for_plan(&conn, &plan.id)?;
+    let external = task_repo::external_dependencies(&conn, &plan.id)?;
+    let waves = waves::compute_waves(&tasks, &deps, &external);
 
     if json_output {
-        let tasks_json: Vec<_> = tasks.iter().map(|t| output::json::task_summary(t)).collect();
-        let docs_json: Vec<_> = docs.iter().map(|d| output::json::plan_document_json(d)).collect();
+        let tasks_json: Vec<_> = tasks.iter().map(output::json::task_summary).collect();
+        let docs_json: Vec<_> = docs.iter().map(output::json::plan_document_json).collect();
         println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
             "plan": output::json::plan_json(&plan),
             "tasks": tasks_json,
             "documents": docs_json,
-            "progress": output::json::progress_json(&progress)
+            "progress": output::json::progress_json(&progress),
+            "critical_path": output::json::critical_path_json(&critical),
+            "critical_path_minutes": critical.total_minutes,
+            "waves": output::json::waves_json(&waves)
         }))).unwrap());
     } else {
         output::text::print_plan(&plan);
         println!();
         output::text::print_progress(&progress);
+        output::text::print_critical_path(&critical);
+        output::text::print_waves(&waves);
         println!("\nTasks:");
         output::text::print_task_list(&tasks);
         if !docs.is_empty() {
@@ -134,17 +160,274 @@ fn run_show(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
     Ok(0)
 }
 
+/// `plan show --format markdown`: header, progress bar and task table for pasting into PRs.
+fn run_show_markdown(reference: &str) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    print_markdown_report(&conn, &plan)?;
+    Ok(0)
+}
+
+/// The Markdown report shared by `plan show` and `status`.
+pub fn print_markdown_report(conn: &Connection, plan: &Plan) -> Result<(), TaskaiError> {
+    let tasks = task_repo::list_tasks_by_plan(conn, &plan.id)?;
+    let progress = task_repo::task_progress(conn, &plan.id)?;
+    let deps = dependency_repo::get_all_dependencies_for_plan(conn, &plan.id)?;
+    output::markdown::print_plan_header(plan);
+    println!();
+    output::markdown::print_progress(&progress);
+    println!();
+    output::markdown::print_task_table(&tasks, &deps);
+    Ok(())
+}
+
+fn run_rename(reference: &str, new_name: &str, title: Option<&str>, json_output: bool) -> Result<i32, TaskaiError> {
+    validate_plan_name(new_name)?;
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    // Active plan is tracked by ID, so the config needs no rewrite
+    let renamed = plan_repo::rename_plan(&conn, &plan.id, new_name, title)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "id": renamed.id,
+            "old_name": plan.name,
+            "new_name": renamed.name,
+            "renamed": { "id": renamed.id, "old_name": plan.name, "name": renamed.name },
+            "plan": output::json::plan_json(&renamed)
+        }))).unwrap());
+    } else {
+        println!("Renamed plan: {} → {} ({})", plan.name, renamed.name, renamed.id);
+    }
+    Ok(0)
+}
+
+fn run_update(
+    reference: &str,
+    title: Option<&str>,
+    description: Option<&str>,
+    json_output: bool,
+) -> Result<i32, TaskaiError> {
+    if title.is_none() && description.is_none() {
+        return Err(TaskaiError::validation("Nothing to update. Pass --title and/or --description"));
+    }
+    if title.is_some_and(|t| t.trim().is_empty()) {
+        return Err(TaskaiError::validation("Plan title must not be empty"));
+    }
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    let updated = plan_repo::update_plan_fields(&conn, &plan.id, title, description)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "plan": output::json::plan_json(&updated)
+        }))).unwrap());
+    } else {
+        println!("Updated plan: {}", updated.name);
+    }
+    Ok(0)
+}
+
+fn run_clone(reference: &str, new_name: &str, title: Option<&str>, json_output: bool) -> Result<i32, TaskaiError> {
+    validate_plan_name(new_name)?;
+    let conn = connection::open_db()?;
+    let source = plan_repo::resolve_plan(&conn, reference)?;
+    if plan_repo::find_plan_by_name(&conn, new_name)?.is_some() {
+        return Err(TaskaiError::plan_name_conflict(new_name));
+    }
+    let tasks = task_repo::list_tasks_by_plan(&conn, &source.id)?;
+    let deps = dependency_repo::get_all_dependencies_for_plan(&conn, &source.id)?;
+    let plan_docs = document_repo::get_plan_documents(&conn, &source.id)?;
+
+    let plan_id = ulid::Ulid::new().to_string();
+    let mut id_mapping: HashMap<String, String> = HashMap::new();
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        let plan = plan_repo::create_plan(
+            &conn, &plan_id, new_name, title.unwrap_or(&source.title), source.description.as_deref(),
+        )?;
+        for doc in &plan_docs {
+            let doc_id = ulid::Ulid::new().to_string();
+            document_repo::create_plan_document(&conn, &doc_id, &plan_id, &doc.title, &doc.content)?;
+        }
+
+        let in_plan: HashSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+        for t in &tasks {
+            let task_id = ulid::Ulid::new().to_string();
+            // Copied deps start unfinished; cross-plan deps keep their real status
+            let mut blocked = false;
+            for d in deps.iter().filter(|d| d.task_id == t.id) {
+                blocked |= in_plan.contains(d.dependency_id.as_str())
+                    || task_repo::get_task_by_id(&conn, &d.dependency_id)?.status != TaskStatus::Done;
+            }
+            let status = if blocked { TaskStatus::Blocked } else { TaskStatus::Ready };
+            task_repo::create_task(
+                &conn, &task_id, &plan_id, &t.title, t.description.as_deref(), t.priority,
+                t.sort_order, &status, t.agent.as_deref(), t.estimated_minutes,
+            )?;
+            for tag in &t.tags {
+                task_tags_repo::add_tag(&conn, &task_id, tag)?;
+            }
+            for doc in document_repo::get_task_documents(&conn, &t.id)? {
+                let doc_id = ulid::Ulid::new().to_string();
+                document_repo::create_task_document(&conn, &doc_id, &task_id, &doc.title, &doc.content)?;
+            }
+            id_mapping.insert(t.id.clone(), task_id);
+        }
+
+        for d in &deps {
+            // Cross-plan dependencies point at the same external task
+            let dependency_id = id_mapping.get(&d.dependency_id).unwrap_or(&d.dependency_id);
+            dependency_repo::add_dependency(&conn, &id_mapping[&d.task_id], dependency_id)?;
+        }
+        Ok(plan)
+    })();
+
+    let plan = match result {
+        Ok(plan) => {
+            conn.execute_batch("COMMIT")?;
+            plan
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(e);
+        }
+    };
+
+    if json_output {
+        let id_map_json: serde_json::Map<String, serde_json::Value> = id_mapping
+            .iter()
+            .map(|(k, v)| (k.clone(), json!(v)))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "source": { "id": source.id, "name": source.name },
+            "plan": output::json::plan_json(&plan),
+            "tasks_created": tasks.len(),
+            "id_mapping": id_map_json
+        }))).unwrap());
+    } else {
+        println!("Cloned plan '{}' → '{}' ({}) with {} tasks.", source.name, plan.name, plan.id, tasks.len());
+    }
+    Ok(0)
+}
+
+fn run_merge(source_ref: &str, target_ref: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let source = plan_repo::resolve_plan(&conn, source_ref)?;
+    let target = plan_repo::resolve_plan(&conn, target_ref)?;
+    if source.id == target.id {
+        return Err(TaskaiError::validation("Cannot merge a plan into itself"));
+    }
+    if target.status.is_hidden() {
+        return Err(TaskaiError::validation(format!("Plan '{}' is {}", target.name, target.status.as_str())));
+    }
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        let source_tasks = task_repo::list_tasks_by_plan(&conn, &source.id)?;
+        let source_deps = dependency_repo::get_all_dependencies_for_plan(&conn, &source.id)?;
+
+        // Check the combined graph before writing anything
+        let mut nodes: Vec<String> = source_tasks.iter().map(|t| t.id.clone()).collect();
+        nodes.extend(task_repo::list_tasks_by_plan(&conn, &target.id)?.into_iter().map(|t| t.id));
+        let edges: Vec<(String, String)> = source_deps
+            .iter()
+            .chain(dependency_repo::get_all_dependencies_for_plan(&conn, &target.id)?.iter())
+            .map(|d| (d.task_id.clone(), d.dependency_id.clone()))
+            .collect();
+        cycle::detect_cycle(&nodes, &edges)?;
+
+        let max_order: i32 = conn.query_row(
+            "SELECT COALESCE(MAX(sort_order), -1) FROM tasks WHERE plan_id = ?1",
+            rusqlite::params![target.id],
+            |row| row.get(0),
+        )?;
+        for (i, t) in source_tasks.iter().enumerate() {
+            task_repo::move_task(&conn, &t.id, &target.id, max_order + 1 + i as i32)?;
+        }
+        let documents_moved = document_repo::move_plan_documents(&conn, &source.id, &target.id)?;
+        plan_repo::delete_plan(&conn, &source.id)?;
+        let target = plan_repo::get_plan_by_id(&conn, &target.id)?;
+        Ok((source_tasks.len(), source_deps.len(), documents_moved, target))
+    })();
+
+    let (tasks_moved, deps_preserved, documents_moved, target) = match result {
+        Ok(r) => {
+            conn.execute_batch("COMMIT")?;
+            r
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(e);
+        }
+    };
+    clear_active_plan_if(&source.id);
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "tasks_moved": tasks_moved,
+            "deps_preserved": deps_preserved,
+            "documents_moved": documents_moved,
+            "source_deleted": { "id": source.id, "name": source.name },
+            "target_plan": output::json::plan_json(&target)
+        }))).unwrap());
+    } else {
+        println!(
+            "Merged plan '{}' into '{}': {} tasks, {} dependencies. '{}' deleted.",
+            source.name, target.name, tasks_moved, deps_preserved, source.name
+        );
+    }
+    Ok(0)
+}
+
+fn run_archive(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    plan_repo::update_plan_status(&conn, &plan.id, &PlanStatus::Archived)?;
+    clear_active_plan_if(&plan.id);
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "archived": { "id": plan.id, "name": plan.name }
+        }))).unwrap());
+    } else {
+        println!("Archived plan: {} ({})", plan.name, plan.id);
+    }
+    Ok(0)
+}
+
+fn run_unarchive(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    if plan.status != PlanStatus::Archived {
+        return Err(TaskaiError::validation(format!("Plan '{}' is not archived", plan.name)));
+    }
+    plan_repo::update_plan_status(&conn, &plan.id, &PlanStatus::Active)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "unarchived": { "id": plan.id, "name": plan.name }
+        }))).unwrap());
+    } else {
+        println!("Unarchived plan: {} ({})", plan.name, plan.id);
+    }
+    Ok(0)
+}
+
 fn run_activate(name: &str, json_output: bool) -> Result<i32, TaskaiError> {
     let conn = connection::open_db()?;
     let plan = plan_repo::resolve_plan(&conn, name)?;
-
-    let config_path = connection::config_path()?;
-    let config = json!({ "active_plan_id": plan.id });
-    if let Some(parent) = config_path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| TaskaiError::database(e.to_string()))?;
+    if plan.status == PlanStatus::Deleted {
+        return Err(TaskaiError::validation(format!(
+            "Plan '{}' is deleted. Run `taskai plan restore {}` first", plan.name, plan.name
+        )));
+    }
+    if plan.status == PlanStatus::Archived {
+        plan_repo::update_plan_status(&conn, &plan.id, &PlanStatus::Active)?;
     }
-    std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap())
-        .map_err(|e| TaskaiError::database(e.to_string()))?;
+
+    set_active_plan_id(Some(&plan.id))?;
 
     if json_output {
         println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
@@ -156,162 +439,370 @@ fn run_activate(name: &str, json_output: bool) -> Result<i32, TaskaiError> {
     Ok(0)
 }
 
-fn run_delete(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
+fn run_deactivate(json_output: bool) -> Result<i32, TaskaiError> {
     let conn = connection::open_db()?;
-    let plan = plan_repo::resolve_plan(&conn, reference)?;
-    plan_repo::delete_plan(&conn, &plan.id)?;
+    let previous = get_active_plan_id();
+    let name = match &previous {
+        Some(id) => plan_repo::get_plan_by_id(&conn, id).ok().map(|p| p.name),
+        None => None,
+    };
+    if previous.is_some() {
+        set_active_plan_id(None)?;
+    }
+
+    if json_output {
+        let deactivated = previous.as_ref().map(|id| json!({ "id": id, "name": name }));
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "deactivated": deactivated
+        }))).unwrap());
+    } else {
+        match (&previous, &name) {
+            (Some(id), Some(name)) => println!("Deactivated plan: {name} ({id})"),
+            (Some(id), None) => println!("Deactivated plan: {id}"),
+            (None, _) => println!("No active plan."),
+        }
+    }
+    Ok(0)
+}
 
-    // Clear active plan if we just deleted it
-    if get_active_plan_id().as_deref() == Some(plan.id.as_str()) {
-        if let Ok(config_path) = connection::config_path() {
-            let _ = std::fs::remove_file(config_path);
+fn run_delete(reference: &str, soft: bool, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    if soft {
+        if plan.status == PlanStatus::Deleted {
+            return Err(TaskaiError::validation(format!("Plan '{}' is already deleted", plan.name)));
         }
+        plan_repo::update_plan_status(&conn, &plan.id, &PlanStatus::Deleted)?;
+    } else {
+        plan_repo::delete_plan(&conn, &plan.id)?;
     }
 
+    clear_active_plan_if(&plan.id);
+
     if json_output {
         println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
-            "deleted": { "id": plan.id, "name": plan.name }
+            "deleted": { "id": plan.id, "name": plan.name },
+            "soft": soft
         }))).unwrap());
+    } else if soft {
+        println!("Deleted plan: {} ({}). Restore with `taskai plan restore {}`", plan.name, plan.id, plan.name);
     } else {
         println!("Deleted plan: {} ({})", plan.name, plan.id);
     }
     Ok(0)
 }
 
-// --- plan load ---
+fn run_restore(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    if plan.status != PlanStatus::Deleted {
+        return Err(TaskaiError::validation(format!("Plan '{}' is not deleted", plan.name)));
+    }
+    plan_repo::update_plan_status(&conn, &plan.id, &PlanStatus::Active)?;
 
-#[derive(Deserialize)]
-struct PlanLoadInput {
-    name: String,
-    title: String,
-    description: Option<String>,
-    #[serde(default)]
-    documents: Vec<DocInput>,
-    tasks: Vec<TaskInput>,
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "restored": { "id": plan.id, "name": plan.name }
+        }))).unwrap());
+    } else {
+        println!("Restored plan: {} ({})", plan.name, plan.id);
+    }
+    Ok(0)
 }
 
-#[derive(Deserialize)]
-struct DocInput {
-    title: String,
-    content: String,
+fn run_critical_path(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    let critical = next_tasks::compute_critical_path(&conn, &plan.id)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "plan": { "id": plan.id, "name": plan.name },
+            "critical_path": output::json::critical_path_json(&critical),
+            "total_minutes": critical.total_minutes
+        }))).unwrap());
+    } else {
+        output::text::print_critical_path(&critical);
+    }
+    Ok(0)
 }
 
-#[derive(Deserialize)]
-struct TaskInput {
-    id: String,
-    title: String,
-    description: Option<String>,
-    #[serde(default)]
-    priority: i32,
-    agent: Option<String>,
-    #[serde(default)]
-    after: Vec<String>,
-    #[serde(default)]
-    documents: Vec<DocInput>,
+fn run_waves(reference: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    let tasks = task_repo::list_tasks_by_plan(&conn, &plan.id)?;
+    let deps = dependency_repo::get_all_dependencies_for_plan(&conn, &plan.id)?;
+    let external = task_repo::external_dependencies(&conn, &plan.id)?;
+    let waves = waves::compute_waves(&tasks, &deps, &external);
+
+    if json_output {
+        let minutes: Vec<_> = waves.iter().map(|w| waves::wave_estimated_minutes(w)).collect();
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "plan": { "id": plan.id, "name": plan.name },
+            "waves": output::json::waves_json(&waves),
+            "external_dependencies": external.iter().map(output::json::task_ref).collect::<Vec<_>>(),
+            "wave_estimated_minutes": minutes,
+            "max_parallelism": waves.iter().map(Vec::len).max().unwrap_or(0)
+        }))).unwrap());
+    } else {
+        output::text::print_waves(&waves);
+    }
+    Ok(0)
 }
 
-fn run_load(json_output: bool) -> Result<i32, TaskaiError> {
-    let mut input = String::new();
-    io::stdin().read_to_string(&mut input).map_err(|e| TaskaiError::validation(e.to_string()))?;
+fn run_stats(reference: Option<&str>, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, reference)?;
+    let plan = plan_repo::get_plan_by_id(&conn, &plan_id)?;
+    let stats = plan_stats_repo::plan_stats(&conn, &plan.id)?;
 
-    let plan_input: PlanLoadInput =
-        serde_json::from_str(&input).map_err(|e| TaskaiError::validation(format!("Invalid JSON: {e}")))?;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "plan": { "id": plan.id, "name": plan.name },
+            "stats": output::json::plan_stats_json(&stats)
+        }))).unwrap());
+    } else {
+        println!("Stats for plan: {} ({})\n", plan.name, plan.id);
+        output::text::print_plan_stats(&stats);
+    }
+    Ok(0)
+}
 
-    // Validate
-    validate_plan_name(&plan_input.name)?;
-    validate_load_input(&plan_input)?;
+// --- plan load ---
+
+/// Read the `plan load` document from `file` if given, otherwise from stdin.
+fn read_load_input(file: Option<&str>) -> Result<String, TaskaiError> {
+    match file {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| TaskaiError::validation(format!("Cannot read plan file '{path}': {e}"))),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map_err(|e| TaskaiError::validation(e.to_string()))?;
+            Ok(input)
+        }
+    }
+}
 
+/// `plan load` flags besides the input and its format.
+struct LoadOptions {
+    dry_run: bool,
+    merge: bool,
+    allow_extreme_priority: bool,
+    /// Overrides `idempotency_key` in the document
+    idempotency_key: Option<String>,
+}
+
+fn run_load(input: String, format: Option<&str>, options: LoadOptions, json_output: bool) -> Result<i32, TaskaiError> {
+    let LoadOptions { dry_run, merge, allow_extreme_priority, idempotency_key } = options;
+    let mut plan_input = parse_load_input(&input, format)?;
+    if idempotency_key.is_some() {
+        plan_input.idempotency_key = idempotency_key;
+    }
+    validate_load_input(&plan_input, allow_extreme_priority)?;
+    let warnings = load_warnings(&plan_input);
+
+    if dry_run {
+        return run_load_dry_run(&plan_input, &warnings, json_output);
+    }
+    connection::with_write_lock(|| write_loaded_plan(&plan_input, merge, &warnings, json_output))
+}
+
+/// Create (or with `merge`, merge into) the plan described by a validated `plan load` document.
+fn write_loaded_plan(
+    plan_input: &PlanLoadInput,
+    merge: bool,
+    warnings: &[String],
+    json_output: bool,
+) -> Result<i32, TaskaiError> {
     let conn = connection::open_db()?;
 
-    // Check name conflict
-    if plan_repo::find_plan_by_name(&conn, &plan_input.name)?.is_some() {
-        return Err(TaskaiError::plan_name_conflict(&plan_input.name));
+    // A retried load replays the first one instead of merging again
+    let replay = match &plan_input.idempotency_key {
+        Some(key) => plan_repo::find_plan_by_idempotency_key(&conn, key)?.is_some(),
+        None => false,
+    };
+    if merge && !replay {
+        if let Some(existing) = plan_repo::find_plan_by_name(&conn, &plan_input.name)? {
+            return run_load_merge(&conn, &existing.id, plan_input, warnings, json_output);
+        }
     }
 
-    // Create everything in a transaction
-    let plan_id = ulid::Ulid::new().to_string();
-    let mut id_mapping: HashMap<String, String> = HashMap::new();
+    let outcome = api::create_loaded_plan(&conn, plan_input)?;
 
-    conn.execute_batch("BEGIN IMMEDIATE")?;
+    // Auto-activate if no valid active plan
+    let should_activate = !outcome.idempotent_replay
+        && match get_active_plan_id() {
+            None => true,
+            Some(ref id) => plan_repo::get_plan_by_id(&conn, id).is_err(),
+        };
+    if should_activate {
+        set_active_plan_id(Some(&outcome.plan.id))?;
+    }
 
-    // Create plan
-    let result = (|| -> Result<_, TaskaiError> {
-        conn.execute(
-            "INSERT INTO plans (id, name, title, description) VALUES (?1, ?2, ?3, ?4)",
-            rusqlite::params![plan_id, plan_input.name, plan_input.title, plan_input.description],
-        )?;
+    print_loaded_plan(&outcome, plan_input, json_output)
+}
 
-        // Plan documents
-        for doc in &plan_input.documents {
-            let doc_id = ulid::Ulid::new().to_string();
-            document_repo::create_plan_document(&conn, &doc_id, &plan_id, &doc.title, &doc.content)?;
+/// `plan load` output. `idempotent_replay` is only reported when an idempotency key was given.
+fn print_loaded_plan(outcome: &api::LoadOutcome, plan_input: &PlanLoadInput, json_output: bool) -> Result<i32, TaskaiError> {
+    let api::LoadOutcome { plan, id_mapping, ready_now, warnings, idempotent_replay } = outcome;
+    let replay = plan_input.idempotency_key.as_ref().map(|_| *idempotent_replay);
+
+    if json_output {
+        let id_map_json: serde_json::Map<String, serde_json::Value> = id_mapping
+            .iter()
+            .map(|(k, v)| (k.clone(), json!(v)))
+            .collect();
+        let ready_json: Vec<_> = ready_now.iter().map(|t| json!({ "id": t.id, "title": t.title })).collect();
+        let mut data = json!({
+            "plan": { "name": plan.name, "id": plan.id },
+            "tasks_created": plan_input.tasks.len(),
+            "id_mapping": id_map_json,
+            "ready_now": ready_json,
+            "warnings": warnings
+        });
+        if let Some(replay) = replay {
+            data["idempotent_replay"] = json!(replay);
+        }
+        println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
+    } else {
+        print_load_warnings(warnings);
+        if replay == Some(true) {
+            println!("Plan '{}' was already loaded with this idempotency key.", plan.name);
+        } else {
+            println!("Loaded plan '{}' with {} tasks.", plan.name, plan_input.tasks.len());
         }
+        if !ready_now.is_empty() {
+            println!("Ready now:");
+            for t in ready_now {
+                println!("  {} - {}", t.id, t.title);
+            }
+        }
+    }
+    Ok(0)
+}
 
-        // Create tasks
-        for (i, task_input) in plan_input.tasks.iter().enumerate() {
-            let task_id = ulid::Ulid::new().to_string();
-            id_mapping.insert(task_input.id.clone(), task_id.clone());
+/// `plan load --merge` into an existing plan: add tasks whose temp id is new, update
+/// title/priority/description of the ones already loaded, add missing dependencies,
+/// then recompute ready/blocked across the plan.
+fn run_load_merge(
+    conn: &Connection,
+    plan_id: &str,
+    plan_input: &PlanLoadInput,
+    warnings: &[String],
+    json_output: bool,
+) -> Result<i32, TaskaiError> {
+    let plan = plan_repo::get_plan_by_id(conn, plan_id)?;
+    if plan.status.is_hidden() {
+        return Err(TaskaiError::validation(format!("Plan '{}' is {}", plan.name, plan.status.as_str())));
+    }
+
+    let mut id_mapping: HashMap<String, String> = HashMap::new();
+    let mut existing: HashMap<String, Task> = HashMap::new();
+    for task_input in &plan_input.tasks {
+        match task_repo::find_task_id_by_external_id(conn, plan_id, &task_input.id)? {
+            Some(id) => {
+                existing.insert(task_input.id.clone(), task_repo::get_task_by_id(conn, &id)?);
+                id_mapping.insert(task_input.id.clone(), id);
+            }
+            None => {
+                id_mapping.insert(task_input.id.clone(), ulid::Ulid::new().to_string());
+            }
+        }
+    }
+    let new_edges: Vec<(String, String)> = plan_input
+        .tasks
+        .iter()
+        .flat_map(|t| t.after.iter().map(|dep| (id_mapping[&t.id].clone(), id_mapping[dep].clone())))
+        .collect();
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        // Check the merged graph before writing anything
+        let plan_tasks = task_repo::list_tasks_by_plan(conn, plan_id)?;
+        let mut nodes: Vec<String> = plan_tasks.iter().map(|t| t.id.clone()).collect();
+        nodes.extend(
+            plan_input.tasks.iter().filter(|t| !existing.contains_key(&t.id)).map(|t| id_mapping[&t.id].clone()),
+        );
+        let mut edges: Vec<(String, String)> = dependency_repo::get_all_dependencies_for_plan(conn, plan_id)?
+            .into_iter()
+            .map(|d| (d.task_id, d.dependency_id))
+            .collect();
+        edges.extend(new_edges.iter().cloned());
+        cycle::detect_cycle(&nodes, &edges)?;
+
+        let max_order: i32 = conn.query_row(
+            "SELECT COALESCE(MAX(sort_order), -1) FROM tasks WHERE plan_id = ?1",
+            rusqlite::params![plan_id],
+            |row| row.get(0),
+        )?;
+        let (mut added, mut updated, mut unchanged) = (0, 0, 0);
+        for task_input in &plan_input.tasks {
+            let task_id = &id_mapping[&task_input.id];
+            if let Some(task) = existing.get(&task_input.id) {
+                let title = (task.title != task_input.title).then_some(task_input.title.as_str());
+                let priority = (task.priority != task_input.priority).then_some(task_input.priority);
+                let description = task_input
+                    .description
+                    .as_deref()
+                    .filter(|d| task.description.as_deref() != Some(*d));
+                if title.is_none() && priority.is_none() && description.is_none() {
+                    unchanged += 1;
+                } else {
+                    task_repo::update_task_fields(conn, task_id, title, description, priority, None)?;
+                    updated += 1;
+                }
+                continue;
+            }
 
             let status = if task_input.after.is_empty() {
                 TaskStatus::Ready
             } else {
                 TaskStatus::Blocked
             };
-
             task_repo::create_task(
-                &conn, &task_id, &plan_id, &task_input.title,
+                conn, task_id, plan_id, &task_input.title,
                 task_input.description.as_deref(), task_input.priority,
-                i as i32, &status, task_input.agent.as_deref(),
+                max_order + 1 + added, &status, task_input.agent.as_deref(), task_input.estimated_minutes,
             )?;
-
-            // Task documents
+            task_repo::set_external_id(conn, task_id, &task_input.id)?;
+            for tag in &task_input.tags {
+                task_tags_repo::add_tag(conn, task_id, tag)?;
+            }
             for doc in &task_input.documents {
                 let doc_id = ulid::Ulid::new().to_string();
-                document_repo::create_task_document(&conn, &doc_id, &task_id, &doc.title, &doc.content)?;
+                document_repo::create_task_document(conn, &doc_id, task_id, &doc.title, &doc.content)?;
             }
+            added += 1;
         }
 
-        // Create dependencies
-        for task_input in &plan_input.tasks {
-            let task_id = &id_mapping[&task_input.id];
-            for dep_temp_id in &task_input.after {
-                let dep_id = &id_mapping[dep_temp_id];
-                dependency_repo::add_dependency(&conn, task_id, dep_id)?;
-            }
+        for (task_id, dep_id) in &new_edges {
+            dependency_repo::add_dependency(conn, task_id, dep_id)?;
         }
 
-        Ok(())
+        // New edges can block ready tasks; new tasks depending on done ones can be ready
+        let plan_tasks = task_repo::list_tasks_by_plan(conn, plan_id)?;
+        for t in plan_tasks.iter().filter(|t| t.status == TaskStatus::Ready) {
+            if !dependency_repo::all_dependencies_done(conn, &t.id)? {
+                task_repo::update_task_status(conn, &t.id, &TaskStatus::Blocked, None)?;
+            }
+        }
+        let ids: Vec<String> = plan_tasks.into_iter().map(|t| t.id).collect();
+        next_tasks::unblock_if_deps_done(conn, &ids)?;
+        Ok((added, updated, unchanged))
     })();
-
-    match result {
-        Ok(()) => {
+    let (added, updated, unchanged) = match result {
+        Ok(counts) => {
             conn.execute_batch("COMMIT")?;
+            counts
         }
         Err(e) => {
             let _ = conn.execute_batch("ROLLBACK");
             return Err(e);
         }
-    }
-
-    // Auto-activate if no valid active plan
-    let should_activate = match get_active_plan_id() {
-        None => true,
-        Some(ref id) => plan_repo::get_plan_by_id(&conn, id).is_err(),
     };
-    if should_activate {
-        let config_path = connection::config_path()?;
-        let config = json!({ "active_plan_id": plan_id });
-        if let Some(parent) = config_path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| TaskaiError::database(e.to_string()))?;
-        }
-        std::fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap())
-            .map_err(|e| TaskaiError::database(e.to_string()))?;
-    }
 
-    // Build response
-    let tasks = task_repo::list_tasks_by_plan(&conn, &plan_id)?;
+    let tasks = task_repo::list_tasks_by_plan(conn, plan_id)?;
     let ready_now: Vec<_> = tasks.iter().filter(|t| t.status == TaskStatus::Ready).collect();
-
     if json_output {
         let id_map_json: serde_json::Map<String, serde_json::Value> = id_mapping
             .iter()
@@ -319,13 +810,20 @@ fn run_load(json_output: bool) -> Result<i32, TaskaiError> {
             .collect();
         let ready_json: Vec<_> = ready_now.iter().map(|t| json!({ "id": t.id, "title": t.title })).collect();
         println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
-            "plan": { "name": plan_input.name, "id": plan_id },
-            "tasks_created": plan_input.tasks.len(),
+            "plan": { "name": plan.name, "id": plan.id },
+            "tasks_added": added,
+            "tasks_updated": updated,
+            "tasks_unchanged": unchanged,
             "id_mapping": id_map_json,
-            "ready_now": ready_json
+            "ready_now": ready_json,
+            "warnings": warnings
         }))).unwrap());
     } else {
-        println!("Loaded plan '{}' with {} tasks.", plan_input.name, plan_input.tasks.len());
+        print_load_warnings(warnings);
+        println!(
+            "Merged into plan '{}': {} added, {} updated, {} unchanged.",
+            plan.name, added, updated, unchanged
+        );
         if !ready_now.is_empty() {
             println!("Ready now:");
             for t in &ready_now {
@@ -336,72 +834,122 @@ fn run_load(json_output: bool) -> Result<i32, TaskaiError> {
     Ok(0)
 }
 
-fn validate_load_input(input: &PlanLoadInput) -> Result<(), TaskaiError> {
-    if input.name.is_empty() {
-        return Err(TaskaiError::validation("Plan name is required"));
+/// Report what `plan load` would create. Only the name-conflict check touches the
+/// database, read-only; an uninitialized database cannot conflict.
+fn run_load_dry_run(plan_input: &PlanLoadInput, warnings: &[String], json_output: bool) -> Result<i32, TaskaiError> {
+    if let Some(conn) = connection::open_db_read_only()? {
+        if plan_repo::find_plan_by_name(&conn, &plan_input.name)?.is_some() {
+            return Err(TaskaiError::plan_name_conflict(&plan_input.name));
+        }
     }
-    if input.title.is_empty() {
-        return Err(TaskaiError::validation("Plan title is required"));
+    let ready_count = plan_input.tasks.iter().filter(|t| t.after.is_empty()).count();
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "valid": true,
+            "plan_name": plan_input.name,
+            "tasks_count": plan_input.tasks.len(),
+            "ready_tasks_count": ready_count,
+            "warnings": warnings
+        }))).unwrap());
+    } else {
+        print_load_warnings(warnings);
+        println!(
+            "Plan '{}' is valid: {} tasks ({} ready). Nothing was written (--dry-run).",
+            plan_input.name, plan_input.tasks.len(), ready_count
+        );
     }
-    if input.tasks.is_empty() {
-        return Err(TaskaiError::validation("At least one task is required"));
+    Ok(0)
+}
+
+fn print_load_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("Warning: {warning}");
     }
+}
 
-    // Check duplicate temp IDs
-    let mut seen_ids = HashSet::new();
-    for t in &input.tasks {
-        if t.id.is_empty() {
-            return Err(TaskaiError::validation("Task id is required"));
-        }
-        if t.title.is_empty() {
-            return Err(TaskaiError::validation(format!("Task '{}' has empty title", t.id)));
-        }
-        if !seen_ids.insert(&t.id) {
-            return Err(TaskaiError::validation(format!("Duplicate task id: {}", t.id)));
+pub fn get_active_plan_id() -> Option<String> {
+    config::load_config().active_plan_id
+}
+
+/// Store (or clear) the active plan, keeping the other config settings.
+fn set_active_plan_id(plan_id: Option<&str>) -> Result<(), TaskaiError> {
+    let mut config = config::load_config();
+    config.active_plan_id = plan_id.map(String::from);
+    config::save_config(&config)
+}
+
+/// Clear the active plan if it is `plan_id`.
+fn clear_active_plan_if(plan_id: &str) {
+    if get_active_plan_id().as_deref() == Some(plan_id) {
+        let _ = set_active_plan_id(None);
+    }
+}
+
+/// Environment variable naming the plan to use when --plan is not given.
+pub const PLAN_ENV: &str = "TASKAI_PLAN";
+
+/// Where the plan of a command came from: --plan, TASKAI_PLAN or the active plan in config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanSource {
+    Flag,
+    Env,
+    Config,
+}
+
+impl PlanSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlanSource::Flag => "flag",
+            PlanSource::Env => "env",
+            PlanSource::Config => "config",
         }
     }
+}
 
-    // Check after references
-    for t in &input.tasks {
-        for dep in &t.after {
-            if dep == &t.id {
-                return Err(TaskaiError::validation(format!("Task '{}' depends on itself", t.id)));
-            }
-            if !seen_ids.contains(dep) {
-                return Err(TaskaiError::validation(format!(
-                    "Task '{}' references unknown dependency '{}'",
-                    t.id, dep
-                )));
-            }
+/// Look up the plan from --plan, then TASKAI_PLAN, then the active plan.
+fn resolve_plan_source(conn: &Connection, plan_flag: Option<&str>) -> Result<(Plan, PlanSource), TaskaiError> {
+    if let Some(reference) = plan_flag {
+        return Ok((plan_repo::resolve_plan(conn, reference)?, PlanSource::Flag));
+    }
+    if let Ok(reference) = std::env::var(PLAN_ENV) {
+        if !reference.trim().is_empty() {
+            return Ok((plan_repo::resolve_plan(conn, reference.trim())?, PlanSource::Env));
         }
     }
+    let id = get_active_plan_id().ok_or_else(TaskaiError::no_active_plan)?;
+    Ok((plan_repo::get_plan_by_id(conn, &id)?, PlanSource::Config))
+}
 
-    // Cycle detection
-    let nodes: Vec<String> = input.tasks.iter().map(|t| t.id.clone()).collect();
-    let edges: Vec<(String, String)> = input
-        .tasks
-        .iter()
-        .flat_map(|t| t.after.iter().map(move |dep| (t.id.clone(), dep.clone())))
-        .collect();
-    cycle::detect_cycle(&nodes, &edges)?;
+/// `resolve_plan_source`, reporting the outcome under --verbose.
+fn resolve_plan_logged(conn: &Connection, plan_flag: Option<&str>) -> Result<(Plan, PlanSource), TaskaiError> {
+    let (plan, source) = resolve_plan_source(conn, plan_flag)?;
+    crate::verbose!("plan: {} ({}) from {}", plan.name, plan.id, source.as_str());
+    Ok((plan, source))
+}
 
-    Ok(())
+/// Like `resolve_plan_id`, but an archived plan counts as not found. Used by `next`/`status`.
+pub fn resolve_open_plan_id(conn: &Connection, plan_flag: Option<&str>) -> Result<String, TaskaiError> {
+    resolve_open_plan(conn, plan_flag).map(|(id, _)| id)
 }
 
-pub fn get_active_plan_id() -> Option<String> {
-    let config_path = connection::config_path().ok()?;
-    let content = std::fs::read_to_string(config_path).ok()?;
-    let config: serde_json::Value = serde_json::from_str(&content).ok()?;
-    config["active_plan_id"].as_str().map(|s| s.to_string())
+/// `resolve_open_plan_id` that also reports where the plan came from.
+pub fn resolve_open_plan(conn: &Connection, plan_flag: Option<&str>) -> Result<(String, PlanSource), TaskaiError> {
+    let (plan, source) = resolve_plan_logged(conn, plan_flag)?;
+    if plan.status.is_hidden() {
+        return Err(TaskaiError::plan_not_found(&plan.name));
+    }
+    Ok((plan.id, source))
 }
 
 pub fn resolve_plan_id(conn: &Connection, plan_flag: Option<&str>) -> Result<String, TaskaiError> {
-    if let Some(reference) = plan_flag {
-        let plan = plan_repo::resolve_plan(conn, reference)?;
-        return Ok(plan.id);
+    let (plan, source) = resolve_plan_logged(conn, plan_flag)?;
+    // An explicitly named plan may be archived; the active plan may not
+    if source == PlanSource::Config && plan.status == PlanStatus::Archived {
+        return Err(TaskaiError::validation(format!(
+            "Active plan '{}' is archived. Use `--plan {}` or `taskai plan activate {}`.",
+            plan.name, plan.name, plan.name
+        )));
     }
-    let id = get_active_plan_id().ok_or_else(TaskaiError::no_active_plan)?;
-    // Validate that the active plan still exists
-    plan_repo::get_plan_by_id(conn, &id)?;
-    Ok(id)
+    Ok(plan.id)
 }
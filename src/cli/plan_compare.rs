@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use crate::db::{connection, plan_repo, task_repo};
+use crate::error::TaskaiError;
+use crate::models::Task;
+use crate::output;
+
+/// A field that differs between two tasks with the same title.
+struct FieldChange {
+    title: String,
+    field: &'static str,
+    a_value: serde_json::Value,
+    b_value: serde_json::Value,
+}
+
+/// Diff the tasks of plan `a_ref` against plan `b_ref`, aligned by title. Read-only.
+pub fn run_compare(a_ref: &str, b_ref: &str, json_output: bool) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let a = plan_repo::resolve_plan(&conn, a_ref)?;
+    let b = plan_repo::resolve_plan(&conn, b_ref)?;
+    let a_tasks = task_repo::list_tasks_by_plan(&conn, &a.id)?;
+    let b_tasks = task_repo::list_tasks_by_plan(&conn, &b.id)?;
+
+    // Repeated titles pair up by position: the first "Test" in A with the first in B, ...
+    let mut b_by_title: HashMap<&str, Vec<&Task>> = HashMap::new();
+    for t in b_tasks.iter().rev() {
+        b_by_title.entry(t.title.as_str()).or_default().push(t);
+    }
+    let mut removed = Vec::new();
+    let mut changed = Vec::new();
+    for ta in &a_tasks {
+        let Some(tb) = b_by_title.get_mut(ta.title.as_str()).and_then(|v| v.pop()) else {
+            removed.push(ta);
+            continue;
+        };
+        if ta.status != tb.status {
+            changed.push(FieldChange {
+                title: ta.title.clone(),
+                field: "status",
+                a_value: json!(ta.status.as_str()),
+                b_value: json!(tb.status.as_str()),
+            });
+        }
+        if ta.priority != tb.priority {
+            changed.push(FieldChange {
+                title: ta.title.clone(),
+                field: "priority",
+                a_value: json!(ta.priority),
+                b_value: json!(tb.priority),
+            });
+        }
+    }
+    // Whatever is left unpaired exists only in B; report it in B's order
+    let leftover: Vec<&str> = b_by_title.values().flatten().map(|t| t.id.as_str()).collect();
+    let added: Vec<&Task> = b_tasks.iter().filter(|t| leftover.contains(&t.id.as_str())).collect();
+
+    if json_output {
+        let entry = |t: &Task| json!({ "id": t.id, "title": t.title, "status": t.status.as_str(), "priority": t.priority });
+        let changed_json: Vec<_> = changed
+            .iter()
+            .map(|c| json!({ "title": c.title, "field": c.field, "a_value": c.a_value, "b_value": c.b_value }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "a": { "id": a.id, "name": a.name },
+            "b": { "id": b.id, "name": b.name },
+            "added": added.iter().map(|t| entry(t)).collect::<Vec<_>>(),
+            "removed": removed.iter().map(|t| entry(t)).collect::<Vec<_>>(),
+            "changed": changed_json
+        }))).unwrap());
+    } else {
+        println!("Comparing {} → {}", a.name, b.name);
+        if added.is_empty() && removed.is_empty() && changed.is_empty() {
+            println!("  (no differences)");
+        }
+        for t in &added {
+            println!("+ {}", t.title);
+        }
+        for t in &removed {
+            println!("- {}", t.title);
+        }
+        for c in &changed {
+            println!("~ {}: {} {} → {}", c.title, c.field, plain(&c.a_value), plain(&c.b_value));
+        }
+    }
+    Ok(0)
+}
+
+/// Render a JSON scalar without string quotes.
+fn plain(v: &serde_json::Value) -> String {
+    v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string())
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use crate::db::{connection, dependency_repo, document_repo, plan_repo, task_repo};
+use crate::error::TaskaiError;
+use crate::plan_input::{DocInput, PlanLoadInput, TaskInput};
+
+/// Print `reference` as a `plan load` document in `format` (json or yaml).
+pub fn run_export(reference: &str, format: &str) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let export = build_export(&conn, reference)?;
+
+    match format {
+        "yaml" => {
+            let yaml = serde_yaml::to_string(&export).map_err(|e| TaskaiError::database(e.to_string()))?;
+            print!("{yaml}");
+        }
+        _ => println!("{}", serde_json::to_string_pretty(&export).unwrap()),
+    }
+    Ok(0)
+}
+
+fn build_export(conn: &rusqlite::Connection, reference: &str) -> Result<PlanLoadInput, TaskaiError> {
+    let plan = plan_repo::resolve_plan(conn, reference)?;
+    let tasks = task_repo::list_tasks_by_plan(conn, &plan.id)?;
+    let deps = dependency_repo::get_all_dependencies_for_plan(conn, &plan.id)?;
+
+    // Readable IDs in sort order: t1, t2, ...
+    let slugs: HashMap<&str, String> = tasks
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.id.as_str(), format!("t{}", i + 1)))
+        .collect();
+    let mut after: HashMap<&str, Vec<String>> = HashMap::new();
+    // Cross-plan dependencies have no slug and cannot be expressed in `after`
+    for d in deps.iter().filter(|d| slugs.contains_key(d.dependency_id.as_str())) {
+        after.entry(d.task_id.as_str()).or_default().push(slugs[d.dependency_id.as_str()].clone());
+    }
+
+    let mut task_inputs = Vec::new();
+    for t in &tasks {
+        let mut task_after = after.remove(t.id.as_str()).unwrap_or_default();
+        task_after.sort_by_key(|slug| slug[1..].parse::<usize>().unwrap_or_default());
+        task_inputs.push(TaskInput {
+            id: slugs[t.id.as_str()].clone(),
+            title: t.title.clone(),
+            description: t.description.clone(),
+            priority: t.priority,
+            agent: t.agent.clone(),
+            estimated_minutes: t.estimated_minutes,
+            tags: t.tags.clone(),
+            after: task_after,
+            documents: document_repo::get_task_documents(conn, &t.id)?
+                .into_iter()
+                .map(|d| DocInput { title: d.title, content: d.content })
+                .collect(),
+            completion_note: t.completion_note.clone(),
+        });
+    }
+
+    Ok(PlanLoadInput {
+        name: plan.name,
+        title: plan.title,
+        description: plan.description,
+        documents: document_repo::get_plan_documents(conn, &plan.id)?
+            .into_iter()
+            .map(|d| DocInput { title: d.title, content: d.content })
+            .collect(),
+        tasks: task_inputs,
+        idempotency_key: None,
+    })
+}
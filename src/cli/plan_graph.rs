@@ -0,0 +1,20 @@
+use crate::db::{connection, dependency_repo, plan_repo, task_repo};
+use crate::error::TaskaiError;
+use crate::output;
+
+/// Print the plan's dependency graph to stdout. There is no JSON envelope: the
+/// output is meant to be piped straight into `dot` or pasted into Markdown.
+pub fn run_graph(reference: &str, format: &str) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan = plan_repo::resolve_plan(&conn, reference)?;
+    let tasks = task_repo::list_tasks_by_plan(&conn, &plan.id)?;
+    let deps = dependency_repo::get_all_dependencies_for_plan(&conn, &plan.id)?;
+    let external = task_repo::external_dependencies(&conn, &plan.id)?;
+
+    let graph = match format {
+        "mermaid" => output::graph::to_mermaid(&tasks, &external, &deps),
+        _ => output::graph::to_dot(&plan.name, &tasks, &external, &deps),
+    };
+    print!("{graph}");
+    Ok(0)
+}
@@ -1,16 +1,33 @@
+use std::thread;
+use std::time::Duration;
+
 use serde_json::json;
 
-use crate::cli::plan::resolve_plan_id;
+use crate::cli::plan::{resolve_open_plan, resolve_open_plan_id};
 use crate::db::{connection, plan_repo, task_repo};
 use crate::error::TaskaiError;
 use crate::output;
 
-pub fn run(json_output: bool, plan_flag: Option<&str>) -> i32 {
-    let result = run_inner(json_output, plan_flag);
+pub fn run(
+    stale_after: i64,
+    watch: Option<u64>,
+    format: Option<&str>,
+    json_output: bool,
+    ndjson: bool,
+    plan_flag: Option<&str>,
+) -> i32 {
+    let result = match watch {
+        _ if format == Some("csv") => run_csv(plan_flag),
+        _ if format == Some("markdown") => run_markdown(plan_flag),
+        Some(interval) => run_watch(stale_after, interval, json_output, ndjson, plan_flag),
+        None => run_inner(stale_after, false, json_output, ndjson, plan_flag).map(|_| 0),
+    };
     match result {
         Ok(code) => code,
         Err(e) => {
-            if json_output {
+            if ndjson {
+                output::json::print_ndjson_line(&output::json::error(&e));
+            } else if json_output {
                 println!("{}", serde_json::to_string_pretty(&output::json::error(&e)).unwrap());
             } else {
                 eprintln!("Error: {}", e.message);
@@ -20,35 +37,136 @@ pub fn run(json_output: bool, plan_flag: Option<&str>) -> i32 {
     }
 }
 
-fn run_inner(json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+/// Print every task of the plan as CSV.
+fn run_csv(plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_open_plan_id(&conn, plan_flag)?;
+    let tasks = task_repo::list_tasks_by_plan(&conn, &plan_id)?;
+    print!("{}", output::csv::tasks_to_csv(&tasks));
+    Ok(0)
+}
+
+/// Print the plan as a Markdown report (see `plan show --format markdown`).
+fn run_markdown(plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_open_plan_id(&conn, plan_flag)?;
+    let plan = plan_repo::get_plan_by_id(&conn, &plan_id)?;
+    crate::cli::plan::print_markdown_report(&conn, &plan)?;
+    Ok(0)
+}
+
+/// Re-print the status every `interval` seconds until the plan completes.
+/// Each poll opens its own connection, so changes from other processes show up.
+fn run_watch(
+    stale_after: i64,
+    interval: u64,
+    json_output: bool,
+    ndjson: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    if interval == 0 {
+        return Err(TaskaiError::validation("--watch interval must be at least 1 second"));
+    }
+    // Stopping the watch is the normal way out, not an error
+    ctrlc::set_handler(|| std::process::exit(0))
+        .map_err(|e| TaskaiError::internal(format!("cannot install Ctrl-C handler: {e}")))?;
+    loop {
+        if !json_output {
+            // Clear the screen and move the cursor home
+            print!("\x1b[2J\x1b[H");
+        }
+        if run_inner(stale_after, true, json_output, ndjson, plan_flag)? {
+            return Ok(0);
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+/// Print the status once. Returns whether the plan is completed.
+/// With `watch`, JSON goes out as a single line per call.
+fn run_inner(
+    stale_after: i64,
+    watch: bool,
+    json_output: bool,
+    ndjson: bool,
+    plan_flag: Option<&str>,
+) -> Result<bool, TaskaiError> {
+    if stale_after < 0 {
+        return Err(TaskaiError::validation("--stale-after must be >= 0 minutes"));
+    }
     let conn = connection::open_db()?;
-    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let (plan_id, plan_source) = resolve_open_plan(&conn, plan_flag)?;
+    let expired = crate::api::release_expired_claims(&conn, &plan_id)?;
     let plan = plan_repo::get_plan_by_id(&conn, &plan_id)?;
     let tasks = task_repo::list_tasks_by_plan(&conn, &plan_id)?;
     let progress = task_repo::task_progress(&conn, &plan_id)?;
     let in_progress = task_repo::in_progress_tasks(&conn, &plan_id)?;
 
     let plan_completed = progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0;
+    // Finished tasks (done, cancelled, skipped) no longer contribute remaining work
+    let remaining: Vec<_> = tasks.iter().filter(|t| !t.status.is_terminal()).collect();
+    let estimated_remaining: i64 = remaining.iter().filter_map(|t| t.estimated_minutes).sum();
+    let unestimated = remaining.iter().filter(|t| t.estimated_minutes.is_none()).count();
+    let overdue_count = tasks.iter().filter(|t| t.is_overdue()).count();
+    let workload = task_repo::agent_workload(&conn, &plan_id)?;
 
-    if json_output {
+    if ndjson {
+        // One plan line, then one line per task
+        output::json::print_ndjson_line(&json!({
+            "type": "plan",
+            "plan": output::json::plan_json(&plan),
+            "plan_source": plan_source.as_str(),
+            "progress": output::json::progress_json(&progress),
+            "estimated_remaining_minutes": estimated_remaining,
+            "unestimated": unestimated,
+            "overdue_count": overdue_count,
+            "agents": output::json::agent_workload_json(&workload),
+            "plan_completed": plan_completed
+        }));
+        for t in &tasks {
+            let mut v = output::json::task_summary(t);
+            v["type"] = json!("task");
+            output::json::print_ndjson_line(&v);
+        }
+    } else if json_output {
         let in_progress_json: Vec<_> = in_progress.iter().map(|t| {
             let elapsed = crate::cli::next::elapsed_minutes_pub(t.started_at.as_deref());
-            output::json::in_progress_entry(t, elapsed)
+            output::json::in_progress_entry(t, elapsed, stale_after)
         }).collect();
-        let tasks_json: Vec<_> = tasks.iter().map(|t| output::json::task_summary(t)).collect();
+        let tasks_json: Vec<_> = tasks.iter().map(output::json::task_summary).collect();
 
-        println!("{}", serde_json::to_string_pretty(
-            &output::json::success_with_plan_completed(json!({
-                "plan": output::json::plan_json(&plan),
-                "tasks": tasks_json,
-                "in_progress": in_progress_json,
-                "progress": output::json::progress_json(&progress)
-            }), plan_completed)
-        ).unwrap());
+        let mut response = output::json::success_with_plan_completed(json!({
+            "plan": output::json::plan_json(&plan),
+            "plan_source": plan_source.as_str(),
+            "tasks": tasks_json,
+            "in_progress": in_progress_json,
+            "progress": output::json::progress_json(&progress),
+            "estimated_remaining_minutes": estimated_remaining,
+            "unestimated": unestimated,
+            "overdue_count": overdue_count,
+            "agents": output::json::agent_workload_json(&workload)
+        }), plan_completed);
+        if !expired.is_empty() {
+            response["data"]["expired"] = json!(expired.iter().map(|t| t.id.as_str()).collect::<Vec<_>>());
+        }
+        if watch {
+            output::json::print_ndjson_line(&response);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&response).unwrap());
+        }
     } else {
         output::text::print_plan(&plan);
         println!();
+        for t in &expired {
+            println!("Claim expired: {} - {} → {}", t.id, t.title, t.status.as_str());
+        }
         output::text::print_progress(&progress);
+        if overdue_count > 0 {
+            println!("Overdue: {overdue_count}");
+        }
+        if estimated_remaining > 0 {
+            println!("Estimated remaining: {estimated_remaining}min ({unestimated} tasks unestimated)");
+        }
         if plan_completed {
             println!("\nPlan completed!");
         }
@@ -56,11 +174,14 @@ fn run_inner(json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiEr
             println!("\nIn progress:");
             for t in &in_progress {
                 let assigned = t.assigned_to.as_deref().unwrap_or("?");
-                println!("  {} - {} (@{})", t.id, t.title, assigned);
+                let stale = if t.is_stale(stale_after) { " [stale]" } else { "" };
+                println!("  {} - {} (@{}){}", t.id, t.title, assigned, stale);
             }
         }
+        println!();
+        output::text::print_agent_workload(&workload);
         println!("\nAll tasks:");
         output::text::print_task_list(&tasks);
     }
-    Ok(0)
+    Ok(plan_completed)
 }
@@ -1,32 +1,109 @@
 use serde_json::json;
 
+use crate::api::{self, TransitionAction};
 use crate::cli::commands::TaskCommands;
+use crate::cli::document;
 use crate::cli::plan::resolve_plan_id;
-use crate::db::{connection, task_repo, dependency_repo, document_repo};
-use crate::error::TaskaiError;
-use crate::graph::{cycle, next_tasks};
-use crate::models::TaskStatus;
+use crate::db::{
+    config, connection, plan_repo, task_repo, dependency_repo, document_repo, task_notes_repo, task_tags_repo, transition_repo,
+};
+use crate::error::{ErrorCode, TaskaiError};
+use crate::graph::{dep_tree, next_tasks};
+use crate::models::{TaskStatus, PRIORITY_RANGE};
 use crate::output;
+use crate::plan_input::validate_tag;
 
-pub fn run(cmd: TaskCommands, json_output: bool, plan_flag: Option<&str>) -> i32 {
+pub fn run(cmd: TaskCommands, json_output: bool, ndjson: bool, plan_flag: Option<&str>) -> i32 {
     let result = match cmd {
-        TaskCommands::Add { title, description, priority, agent, after } => {
-            run_add(&title, description.as_deref(), priority, agent.as_deref(), &after, json_output, plan_flag)
-        }
-        TaskCommands::List => run_list(json_output, plan_flag),
-        TaskCommands::Show { id } => run_show(&id, json_output, plan_flag),
-        TaskCommands::Start { id, agent } => run_transition(&id, "start", agent.as_deref(), json_output, plan_flag),
-        TaskCommands::Done { id } => run_transition(&id, "done", None, json_output, plan_flag),
-        TaskCommands::Fail { id } => run_transition(&id, "fail", None, json_output, plan_flag),
-        TaskCommands::Skip { id } => run_transition(&id, "skip", None, json_output, plan_flag),
-        TaskCommands::Cancel { id } => run_transition(&id, "cancel", None, json_output, plan_flag),
+        TaskCommands::Add { title, description, priority, agent, estimate, after, allow_extreme_priority, idempotency_key } => {
+            let priority = priority.unwrap_or_else(|| config::load_config().default_priority.unwrap_or(0));
+            validate_priority(priority, allow_extreme_priority).and_then(|()| connection::with_write_lock(|| run_add(
+                &title, description.as_deref(), priority, agent.as_deref(), estimate, &after,
+                idempotency_key.as_deref(), json_output, plan_flag,
+            )))
+        }
+        TaskCommands::List { status, agent, assigned_to, sort, tag, fields, format } => {
+            let filter = ListFilterArgs { status, agent, assigned_to, tag };
+            run_list(filter, sort.as_deref(), &fields, format.as_deref(), json_output, ndjson, plan_flag)
+        }
+        TaskCommands::Search { query } => run_search(&query, json_output, ndjson, plan_flag),
+        TaskCommands::Show { id, tree, with_dependents, transitive } => {
+            let dependents = with_dependents.then_some(transitive);
+            run_show(&id, tree, dependents, json_output, plan_flag)
+        }
+        TaskCommands::Update { id, title, description, priority, agent } => run_update(
+            &id, title.as_deref(), description.as_deref(), priority, agent.as_deref(), json_output, plan_flag,
+        ),
+        TaskCommands::Clone { id, title, after } => {
+            run_clone(&id, title.as_deref(), &after, json_output, plan_flag)
+        }
+        TaskCommands::Reorder { id, position, before, after } => {
+            run_reorder(&id, position, before.as_deref(), after.as_deref(), json_output, plan_flag)
+        }
+        TaskCommands::Start { id, agent } => connection::with_write_lock(|| {
+            run_transition(&id, TransitionAction::Start { agent: agent.as_deref() }, json_output, plan_flag)
+        }),
+        TaskCommands::Claim { id, agent } => connection::with_write_lock(|| {
+            run_claim(&id, agent.as_deref(), json_output, plan_flag)
+        }),
+        TaskCommands::Done { id, note } => connection::with_write_lock(|| {
+            run_transition(&id, TransitionAction::Done { note: note.as_deref() }, json_output, plan_flag)
+        }),
+        TaskCommands::Fail { id, reason, max_attempts } => connection::with_write_lock(|| {
+            let action = TransitionAction::Fail { reason: reason.as_deref(), max_attempts };
+            run_transition(&id, action, json_output, plan_flag)
+        }),
+        TaskCommands::Skip { id } => connection::with_write_lock(|| {
+            run_transition(&id, TransitionAction::Skip, json_output, plan_flag)
+        }),
+        TaskCommands::Cancel { id } => connection::with_write_lock(|| {
+            run_transition(&id, TransitionAction::Cancel, json_output, plan_flag)
+        }),
+        TaskCommands::Estimate { id, minutes } => run_estimate(&id, minutes, json_output, plan_flag),
+        TaskCommands::Deadline { id, datetime, clear: _ } => {
+            run_deadline(&id, datetime.as_deref(), json_output, plan_flag)
+        }
+        TaskCommands::Assign { id, agent } => run_assign(&id, Some(&agent), json_output, plan_flag),
+        TaskCommands::Heartbeat { id, agent, extend } => {
+            run_heartbeat(&id, agent.as_deref(), extend, json_output, plan_flag)
+        }
+        TaskCommands::Unassign { id } => run_assign(&id, None, json_output, plan_flag),
+        TaskCommands::Reset { id } => connection::with_write_lock(|| {
+            run_transition(&id, TransitionAction::Reset, json_output, plan_flag)
+        }),
+        TaskCommands::Deps { id, status_filter } => {
+            run_dep_ids(&id, false, status_filter.as_deref(), json_output, plan_flag)
+        }
+        TaskCommands::Blocking { id, status_filter } => {
+            run_dep_ids(&id, true, status_filter.as_deref(), json_output, plan_flag)
+        }
+        TaskCommands::History { id, since } => run_history(&id, since.as_deref(), json_output, plan_flag),
+        TaskCommands::Move { id, to_plan } => run_move(&id, &to_plan, json_output, plan_flag),
+        TaskCommands::BulkDone(selection) => connection::with_write_lock(|| {
+            run_bulk(TransitionAction::Done { note: None }, selection, json_output, plan_flag)
+        }),
+        TaskCommands::BulkCancel(selection) => connection::with_write_lock(|| {
+            run_bulk(TransitionAction::Cancel, selection, json_output, plan_flag)
+        }),
+        TaskCommands::BulkSkip(selection) => connection::with_write_lock(|| {
+            run_bulk(TransitionAction::Skip, selection, json_output, plan_flag)
+        }),
+        TaskCommands::Delete { id } => run_delete(&id, json_output, plan_flag),
+        TaskCommands::Reclaim { older_than } => run_reclaim(older_than, json_output, plan_flag),
         TaskCommands::Dep(dep_cmd) => run_dep(dep_cmd, json_output, plan_flag),
+        TaskCommands::Note(note_cmd) => run_note(note_cmd, json_output, plan_flag),
+        TaskCommands::Tag(tag_cmd) => run_tag(tag_cmd, json_output, plan_flag),
+        TaskCommands::Doc(doc_cmd) => document::run_doc(document::DocOwner::Task, doc_cmd, json_output, plan_flag),
     };
     match result {
         Ok(code) => code,
         Err(e) => {
             if json_output {
-                println!("{}", serde_json::to_string_pretty(&output::json::error(&e)).unwrap());
+                if ndjson {
+                    output::json::print_ndjson_line(&output::json::error(&e));
+                } else {
+                    println!("{}", serde_json::to_string_pretty(&output::json::error(&e)).unwrap());
+                }
             } else {
                 eprintln!("Error: {}", e.message);
             }
@@ -35,17 +112,32 @@ pub fn run(cmd: TaskCommands, json_output: bool, plan_flag: Option<&str>) -> i32
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_add(
     title: &str,
     description: Option<&str>,
     priority: i32,
     agent: Option<&str>,
+    estimate: Option<i64>,
     after: &[String],
+    idempotency_key: Option<&str>,
     json_output: bool,
     plan_flag: Option<&str>,
 ) -> Result<i32, TaskaiError> {
+    if estimate.is_some_and(|m| m < 0) {
+        return Err(TaskaiError::validation("Estimate must be >= 0 minutes"));
+    }
+    if idempotency_key.is_some_and(|k| k.trim().is_empty()) {
+        return Err(TaskaiError::validation("--idempotency-key must not be empty"));
+    }
     let conn = connection::open_db()?;
     let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    if let Some(key) = idempotency_key {
+        if let Some(task) = task_repo::find_task_by_idempotency_key(&conn, &plan_id, key)? {
+            print_added_task(&task, Some(true), json_output);
+            return Ok(0);
+        }
+    }
 
     // Resolve deps first (before any writes) to fail fast
     let mut resolved_deps = Vec::new();
@@ -71,8 +163,14 @@ fn run_add(
         // Initial status: ready (will be corrected after deps are checked)
         task_repo::create_task(
             &conn, &task_id, &plan_id, title, description, priority,
-            max_order + 1, &TaskStatus::Ready, agent,
+            max_order + 1, &TaskStatus::Ready, agent, estimate,
         )?;
+        // Lost a race against another add with the same key: replay that one instead
+        if let Some(key) = idempotency_key {
+            if !task_repo::set_task_idempotency_key(&conn, &task_id, key)? {
+                return Ok(false);
+            }
+        }
 
         for dep_task in &resolved_deps {
             dependency_repo::add_dependency(&conn, &task_id, &dep_task.id)?;
@@ -83,37 +181,160 @@ fn run_add(
             task_repo::update_task_status(&conn, &task_id, &TaskStatus::Blocked, None)?;
         }
 
-        Ok(())
+        Ok(true)
     })();
 
-    match result {
-        Ok(()) => conn.execute_batch("COMMIT")?,
+    let created = match result {
+        Ok(true) => {
+            conn.execute_batch("COMMIT")?;
+            true
+        }
+        Ok(false) => {
+            conn.execute_batch("ROLLBACK")?;
+            false
+        }
         Err(e) => {
             let _ = conn.execute_batch("ROLLBACK");
             return Err(e);
         }
-    }
+    };
 
-    let task = task_repo::get_task_by_id(&conn, &task_id)?;
+    let task = match (created, idempotency_key) {
+        (false, Some(key)) => task_repo::find_task_by_idempotency_key(&conn, &plan_id, key)?
+            .ok_or_else(|| TaskaiError::task_not_found(key))?,
+        _ => task_repo::get_task_by_id(&conn, &task_id)?,
+    };
+    print_added_task(&task, idempotency_key.map(|_| !created), json_output);
+    Ok(0)
+}
+
+/// `task add` output. `replay` is set when an idempotency key was given: whether the task
+/// already existed.
+fn print_added_task(task: &crate::models::Task, replay: Option<bool>, json_output: bool) {
     if json_output {
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&output::json::success(json!({
-                "task": output::json::task_summary(&task)
-            })))
-            .unwrap()
-        );
+        let mut data = json!({ "task": output::json::task_summary(task) });
+        if let Some(replay) = replay {
+            data["idempotent_replay"] = json!(replay);
+        }
+        println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
+    } else if replay == Some(true) {
+        println!("Task already added: {} ({})", task.title, task.id);
     } else {
         println!("Added task: {} ({})", task.title, task.id);
     }
+}
+
+/// Raw `task list` filter flags as given on the command line.
+struct ListFilterArgs {
+    status: Vec<String>,
+    agent: Option<String>,
+    assigned_to: Option<String>,
+    tag: Option<String>,
+}
+
+fn run_list(
+    args: ListFilterArgs,
+    sort: Option<&str>,
+    fields: &[String],
+    format: Option<&str>,
+    json_output: bool,
+    ndjson: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let sort = match sort {
+        Some(spec) => parse_sort(spec)?,
+        None => task_repo::TaskSort::default(),
+    };
+    let ListFilterArgs { status, agent, assigned_to, tag } = args;
+    let statuses = parse_statuses(&status)?;
+    let filter = task_repo::TaskFilter { statuses, agent, assigned_to, tag };
+
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let tasks = task_repo::list_tasks_by_plan_ordered(&conn, &plan_id, &filter, &sort)?;
+    match format {
+        // csv has no JSON counterpart: it wins over output_format=json but not over --json
+        Some("csv") if json_output && !config::load_config().json_output() => {
+            return Err(TaskaiError::validation("--format csv cannot be combined with --json"));
+        }
+        Some("csv") => {
+            print!("{}", output::csv::tasks_to_csv(&tasks));
+            return Ok(0);
+        }
+        Some("table") if !json_output => {
+            print!("{}", output::table::tasks_table(&tasks));
+            return Ok(0);
+        }
+        _ => {}
+    }
+    let mut extra = json!({
+        "sort": {
+            "field": sort.field.as_str(),
+            "direction": if sort.descending { "desc" } else { "asc" }
+        }
+    });
+    if !filter.is_empty() {
+        extra["filter"] = json!({
+            "status": status,
+            "agent": filter.agent,
+            "assigned_to": filter.assigned_to,
+            "tag": filter.tag
+        });
+    }
+    print_tasks(&conn, &plan_id, &tasks, Some(extra), fields, json_output, ndjson)?;
     Ok(0)
 }
 
-fn run_list(json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+fn parse_statuses(values: &[String]) -> Result<Vec<TaskStatus>, TaskaiError> {
+    values
+        .iter()
+        .map(|s| {
+            TaskStatus::from_str(s).ok_or_else(|| {
+                TaskaiError::validation(format!(
+                    "Unknown status '{s}'. Valid: blocked, ready, in_progress, done, cancelled, skipped"
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Parse `<field>[:asc|desc]` for `task list --sort`.
+fn parse_sort(spec: &str) -> Result<task_repo::TaskSort, TaskaiError> {
+    let (field, dir) = spec.split_once(':').unwrap_or((spec, "asc"));
+    let field = task_repo::TaskSortField::from_str(field).ok_or_else(|| {
+        TaskaiError::validation(format!(
+            "Unknown sort field '{field}'. Use priority, status, title, created_at, updated_at or sort_order."
+        ))
+    })?;
+    let descending = match dir {
+        "asc" => false,
+        "desc" => true,
+        _ => return Err(TaskaiError::validation(format!("Unknown sort direction '{dir}'. Use asc or desc."))),
+    };
+    Ok(task_repo::TaskSort { field, descending })
+}
+
+fn run_search(query: &str, json_output: bool, ndjson: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
     let conn = connection::open_db()?;
     let plan_id = resolve_plan_id(&conn, plan_flag)?;
-    let tasks = task_repo::list_tasks_by_plan(&conn, &plan_id)?;
+    let tasks = task_repo::search_tasks(&conn, &plan_id, query)?;
+    print_tasks(&conn, &plan_id, &tasks, None, &[], json_output, ndjson)?;
+    Ok(0)
+}
 
+/// Print a task list in the `task list` shape (tasks + plan progress).
+/// Keys in `extra` (active filter, sort) are merged into the JSON `data` object.
+/// A non-empty `fields` keeps only those keys in each task object.
+/// With `ndjson`, each task object is printed on its own line and nothing else.
+fn print_tasks(
+    conn: &rusqlite::Connection,
+    plan_id: &str,
+    tasks: &[crate::models::Task],
+    extra: Option<serde_json::Value>,
+    fields: &[String],
+    json_output: bool,
+    ndjson: bool,
+) -> Result<(), TaskaiError> {
     if json_output {
         let tasks_json: Vec<_> = tasks.iter().map(|t| {
             let mut v = output::json::task_summary(t);
@@ -123,29 +344,62 @@ fn run_list(json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiErr
             if let Some(ref a) = t.assigned_to {
                 v["assigned_to"] = json!(a);
             }
+            if !fields.is_empty() {
+                if let Some(obj) = v.as_object_mut() {
+                    obj.retain(|k, _| fields.iter().any(|f| f == k));
+                }
+            }
             v
         }).collect();
-        let progress = task_repo::task_progress(&conn, &plan_id)?;
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&output::json::success(json!({
-                "tasks": tasks_json,
-                "progress": output::json::progress_json(&progress)
-            })))
-            .unwrap()
-        );
+        if ndjson {
+            tasks_json.iter().for_each(output::json::print_ndjson_line);
+            return Ok(());
+        }
+        let progress = task_repo::task_progress(conn, plan_id)?;
+        let mut data = json!({
+            "tasks": tasks_json,
+            "progress": output::json::progress_json(&progress)
+        });
+        if let Some(serde_json::Value::Object(extra)) = extra {
+            for (k, v) in extra {
+                data[k] = v;
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
     } else {
-        output::text::print_task_list(&tasks);
+        output::text::print_task_list(tasks);
     }
-    Ok(0)
+    Ok(())
 }
 
-fn run_show(id: &str, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+/// `dependents`: `None` to omit them, `Some(transitive)` to include direct or all downstream tasks.
+fn run_show(
+    id: &str,
+    tree: bool,
+    dependents: Option<bool>,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
     let conn = connection::open_db()?;
     let plan_id = resolve_plan_id(&conn, plan_flag)?;
     let task = task_repo::resolve_task(&conn, &plan_id, id)?;
     let deps = dependency_repo::get_dependencies(&conn, &task.id)?;
     let docs = document_repo::get_task_documents(&conn, &task.id)?;
+    let notes = task_notes_repo::get_task_notes(&conn, &task.id)?;
+    let event_count = transition_repo::count_task_transitions(&conn, &task.id)?;
+    let dep_tree = if tree { Some(dep_tree::build_dep_tree(&conn, &task.id)?) } else { None };
+    let tree_warning = dep_tree.as_ref().filter(|t| t.is_truncated()).map(|_| {
+        format!("Dependency tree truncated at depth {}", dep_tree::MAX_TREE_DEPTH)
+    });
+    let dependents = match dependents {
+        Some(transitive) => Some(dep_tree::collect_dependents(&conn, &task.id, transitive)?),
+        None => None,
+    };
+    let blocked_by = if task.status == TaskStatus::Blocked {
+        Some(next_tasks::blocking_dependencies(&conn, &task.id)?)
+    } else {
+        None
+    };
 
     if json_output {
         let dep_tasks: Vec<_> = deps
@@ -153,31 +407,66 @@ fn run_show(id: &str, json_output: bool, plan_flag: Option<&str>) -> Result<i32,
             .filter_map(|d| task_repo::get_task_by_id(&conn, d).ok())
             .map(|t| output::json::task_summary(&t))
             .collect();
-        let docs_json: Vec<_> = docs.iter().map(|d| output::json::task_document_json(d)).collect();
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&output::json::success(json!({
-                "task": {
-                    "id": task.id,
-                    "title": task.title,
-                    "description": task.description,
-                    "status": task.status.as_str(),
-                    "priority": task.priority,
-                    "sort_order": task.sort_order,
-                    "agent": task.agent,
-                    "assigned_to": task.assigned_to,
-                    "created_at": task.created_at,
-                    "updated_at": task.updated_at,
-                    "started_at": task.started_at,
-                    "completed_at": task.completed_at,
-                },
-                "dependencies": dep_tasks,
-                "documents": docs_json,
-            })))
-            .unwrap()
-        );
+        let docs_json: Vec<_> = docs.iter().map(output::json::task_document_json).collect();
+        let notes_json: Vec<_> = notes.iter().map(output::json::task_note_json).collect();
+        let mut data = json!({
+            "task": {
+                "id": task.id,
+                "title": task.title,
+                "description": task.description,
+                "status": task.status.as_str(),
+                "priority": task.priority,
+                "sort_order": task.sort_order,
+                "agent": task.agent,
+                "assigned_to": task.assigned_to,
+                "created_at": task.created_at,
+                "updated_at": task.updated_at,
+                "started_at": task.started_at,
+                "completed_at": task.completed_at,
+                "estimated_minutes": task.estimated_minutes,
+                "deadline": task.deadline,
+                "overdue": task.is_overdue(),
+                "last_heartbeat_at": task.last_heartbeat_at,
+                "claim_expires_at": task.claim_expires_at,
+                "expired": task.is_claim_expired(),
+                "fail_count": task.fail_count,
+                "completion_note": task.completion_note,
+                "tags": task.tags,
+            },
+            "dependencies": dep_tasks,
+            "documents": docs_json,
+            "notes": notes_json,
+            "event_count": event_count,
+        });
+        if let Some(ref reason) = task.failure_reason {
+            data["task"]["failure_reason"] = json!(reason);
+        }
+        if let Some(ref blockers) = blocked_by {
+            data["blocked_by"] = json!(blockers.iter().map(output::json::task_ref).collect::<Vec<_>>());
+            let chain = next_tasks::blocking_chain(&conn, &task.id)?;
+            data["blocking_chain"] = json!(chain.iter().map(output::json::task_ref).collect::<Vec<_>>());
+        }
+        if let Some(ref dependents) = dependents {
+            data["dependents"] = json!(dependents.iter().map(|(t, depth)| json!({
+                "id": t.id,
+                "title": t.title,
+                "status": t.status.as_str(),
+                "depth": depth
+            })).collect::<Vec<_>>());
+        }
+        if let Some(ref root) = dep_tree {
+            data["tree"] = output::json::dep_tree_json(root);
+        }
+        if let Some(ref warning) = tree_warning {
+            data["warnings"] = json!([warning]);
+        }
+        println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
     } else {
         output::text::print_task(&task);
+        if let Some(ref blockers) = blocked_by {
+            let titles: Vec<&str> = blockers.iter().map(|t| t.title.as_str()).collect();
+            println!("Blocked by: {}", titles.join(", "));
+        }
         if !deps.is_empty() {
             println!("\nDependencies:");
             for d in &deps {
@@ -186,168 +475,955 @@ fn run_show(id: &str, json_output: bool, plan_flag: Option<&str>) -> Result<i32,
                 }
             }
         }
+        if let Some(ref dependents) = dependents {
+            println!("\nDepended on by:");
+            if dependents.is_empty() {
+                println!("  (none)");
+            }
+            for (t, depth) in dependents {
+                println!("  {}[{}] {} ({})", "  ".repeat(depth - 1), t.status.as_str(), t.title, t.id);
+            }
+        }
         if !docs.is_empty() {
             output::text::print_task_documents(&docs);
         }
+        if !notes.is_empty() {
+            println!("\nNotes:");
+            output::text::print_task_notes(&notes);
+        }
+        if let Some(ref root) = dep_tree {
+            println!("\nDependency tree:");
+            output::text::print_dep_tree(root);
+        }
+        if let Some(ref warning) = tree_warning {
+            eprintln!("Warning: {warning}");
+        }
     }
     Ok(0)
 }
 
-fn run_transition(
+fn run_clone(
     id: &str,
-    action: &str,
-    agent: Option<&str>,
+    title: Option<&str>,
+    after: &[String],
     json_output: bool,
     plan_flag: Option<&str>,
 ) -> Result<i32, TaskaiError> {
     let conn = connection::open_db()?;
     let plan_id = resolve_plan_id(&conn, plan_flag)?;
-    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    let source = task_repo::resolve_task(&conn, &plan_id, id)?;
+    let docs = document_repo::get_task_documents(&conn, &source.id)?;
+
+    // Resolve deps first (before any writes) to fail fast
+    let mut resolved_deps = Vec::new();
+    for dep_ref in after {
+        let dep_task = task_repo::resolve_task(&conn, &plan_id, dep_ref)?;
+        resolved_deps.push(dep_task);
+    }
+
+    let max_order: i32 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(sort_order), -1) FROM tasks WHERE plan_id = ?1",
+            rusqlite::params![plan_id],
+            |row| row.get(0),
+        )
+        .unwrap_or(-1);
 
-    let new_status = validate_transition(&task.status, action)?;
+    let task_id = ulid::Ulid::new().to_string();
+    let title = title.unwrap_or(&source.title);
 
+    // Atomic: task + documents + deps in transaction
     conn.execute_batch("BEGIN IMMEDIATE")?;
     let result = (|| -> Result<_, TaskaiError> {
-        // For fail: check if deps are still met before going back to ready
-        let actual_status = if new_status == TaskStatus::Ready
-            && action == "fail"
-            && !dependency_repo::all_dependencies_done(&conn, &task.id)?
-        {
-            TaskStatus::Blocked
-        } else {
-            new_status.clone()
-        };
+        task_repo::create_task(
+            &conn, &task_id, &plan_id, title, source.description.as_deref(), source.priority,
+            max_order + 1, &TaskStatus::Ready, source.agent.as_deref(), source.estimated_minutes,
+        )?;
 
-        task_repo::update_task_status(&conn, &task.id, &actual_status, agent)?;
+        for doc in &docs {
+            let doc_id = ulid::Ulid::new().to_string();
+            document_repo::create_task_document(&conn, &doc_id, &task_id, &doc.title, &doc.content)?;
+        }
 
-        let mut newly_ready = Vec::new();
-        if actual_status == TaskStatus::Done {
-            newly_ready = next_tasks::cascade_unblock(&conn, &task.id)?;
+        for dep_task in &resolved_deps {
+            dependency_repo::add_dependency(&conn, &task_id, &dep_task.id)?;
         }
 
-        let updated_task = task_repo::get_task_by_id(&conn, &task.id)?;
-        let progress = task_repo::task_progress(&conn, &plan_id)?;
-        Ok((updated_task, newly_ready, progress))
+        if !resolved_deps.is_empty() && !dependency_repo::all_dependencies_done(&conn, &task_id)? {
+            task_repo::update_task_status(&conn, &task_id, &TaskStatus::Blocked, None)?;
+        }
+
+        Ok(())
     })();
 
     match result {
-        Ok((updated_task, newly_ready, progress)) => {
-            conn.execute_batch("COMMIT")?;
-
-            let plan_completed = progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0;
-
-            if json_output {
-                let mut data = json!({
-                    "completed_task": {
-                        "id": updated_task.id,
-                        "title": updated_task.title,
-                        "status": updated_task.status.as_str()
-                    },
-                    "progress": output::json::progress_json(&progress)
-                });
-                if !newly_ready.is_empty() {
-                    data["newly_ready"] = json!(newly_ready.iter().map(|t| json!({
-                        "id": t.id,
-                        "title": t.title,
-                        "priority": t.priority
-                    })).collect::<Vec<_>>());
-                }
-                println!("{}", serde_json::to_string_pretty(
-                    &output::json::success_with_plan_completed(data, plan_completed)
-                ).unwrap());
-            } else {
-                println!("Task {} → {}", updated_task.id, updated_task.status.as_str());
-                if !newly_ready.is_empty() {
-                    println!("Newly ready:");
-                    for t in &newly_ready {
-                        println!("  {} - {}", t.id, t.title);
-                    }
-                }
-                if plan_completed {
-                    println!("Plan completed!");
-                }
-            }
-            Ok(0)
-        }
+        Ok(()) => conn.execute_batch("COMMIT")?,
         Err(e) => {
             let _ = conn.execute_batch("ROLLBACK");
-            Err(e)
+            return Err(e);
         }
     }
-}
 
-fn validate_transition(current: &TaskStatus, action: &str) -> Result<TaskStatus, TaskaiError> {
-    match (current, action) {
-        (TaskStatus::Ready, "start") => Ok(TaskStatus::InProgress),
-        (TaskStatus::Ready, "done") => Ok(TaskStatus::Done),
-        (TaskStatus::InProgress, "done") => Ok(TaskStatus::Done),
-        (TaskStatus::InProgress, "fail") => Ok(TaskStatus::Ready),
-        (TaskStatus::Ready | TaskStatus::Blocked, "skip") => Ok(TaskStatus::Skipped),
-        (TaskStatus::Ready | TaskStatus::Blocked | TaskStatus::InProgress, "cancel") => {
-            Ok(TaskStatus::Cancelled)
-        }
-        _ => Err(TaskaiError::invalid_transition(current.as_str(), action)),
+    let task = task_repo::get_task_by_id(&conn, &task_id)?;
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output::json::success(json!({
+                "original_id": source.id,
+                "task": output::json::task_summary(&task),
+                "documents_copied": docs.len()
+            })))
+            .unwrap()
+        );
+    } else {
+        println!("Cloned task {} → {} ({})", source.id, task.title, task.id);
     }
+    Ok(0)
 }
 
-fn run_dep(
-    cmd: crate::cli::commands::DepCommands,
+fn run_update(
+    id: &str,
+    title: Option<&str>,
+    description: Option<&str>,
+    priority: Option<i32>,
+    agent: Option<&str>,
     json_output: bool,
     plan_flag: Option<&str>,
 ) -> Result<i32, TaskaiError> {
+    if title.is_none() && description.is_none() && priority.is_none() && agent.is_none() {
+        return Err(TaskaiError::validation(
+            "Nothing to update. Use --title, --description, --priority or --agent.",
+        ));
+    }
     let conn = connection::open_db()?;
     let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
 
-    match cmd {
-        crate::cli::commands::DepCommands::Add { id, dep_id } => {
-            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
-            let dep_task = task_repo::resolve_task(&conn, &plan_id, &dep_id)?;
+    if let Some(t) = title {
+        if t.is_empty() {
+            return Err(TaskaiError::validation("Task title must not be empty"));
+        }
+    }
+    // Terminal tasks are immutable except for their description
+    if task.status.is_terminal() && (title.is_some() || priority.is_some() || agent.is_some()) {
+        return Err(TaskaiError::invalid_transition(task.status.as_str(), "update"));
+    }
 
-            // Same plan check
-            if task.plan_id != dep_task.plan_id {
-                return Err(TaskaiError::cross_plan_dependency());
-            }
+    task_repo::update_task_fields(&conn, &task.id, title, description, priority, agent)?;
+
+    let updated = task_repo::get_task_by_id(&conn, &task.id)?;
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output::json::success(json!({
+                "task": output::json::task_summary(&updated)
+            })))
+            .unwrap()
+        );
+    } else {
+        println!("Updated task: {} ({})", updated.title, updated.id);
+    }
+    Ok(0)
+}
 
-            // Cycle check
-            let all_tasks = task_repo::list_tasks_by_plan(&conn, &plan_id)?;
-            let nodes: Vec<String> = all_tasks.iter().map(|t| t.id.clone()).collect();
-            let existing_deps = dependency_repo::get_all_dependencies_for_plan(&conn, &plan_id)?;
-            let edges: Vec<(String, String)> = existing_deps.iter().map(|d| (d.task_id.clone(), d.dependency_id.clone())).collect();
-            cycle::would_create_cycle(&nodes, &edges, &task.id, &dep_task.id)?;
+fn run_reorder(
+    id: &str,
+    position: Option<usize>,
+    before: Option<&str>,
+    after: Option<&str>,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let given = [position.is_some(), before.is_some(), after.is_some()];
+    if given.iter().filter(|g| **g).count() != 1 {
+        return Err(TaskaiError::validation(
+            "Exactly one of --position, --before or --after is required",
+        ));
+    }
 
-            dependency_repo::add_dependency(&conn, &task.id, &dep_task.id)?;
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
 
-            // If task was ready and new dep is not done, set to blocked
-            if task.status == TaskStatus::Ready && dep_task.status != TaskStatus::Done {
-                task_repo::update_task_status(&conn, &task.id, &TaskStatus::Blocked, None)?;
+    let new_position = match position {
+        Some(p) => p,
+        None => {
+            let anchor_ref = before.or(after).unwrap();
+            let anchor = task_repo::resolve_task(&conn, &plan_id, anchor_ref)?;
+            if anchor.id == task.id {
+                return Err(TaskaiError::validation("Cannot reorder a task relative to itself"));
             }
-
-            if json_output {
-                println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
-                    "added": { "task_id": task.id, "dependency_id": dep_task.id }
-                }))).unwrap());
+            // Index of the anchor once the moved task is taken out of the order
+            let others: Vec<_> = task_repo::list_tasks_by_plan(&conn, &plan_id)?
+                .into_iter()
+                .filter(|t| t.id != task.id)
+                .collect();
+            let anchor_index = others.iter().position(|t| t.id == anchor.id).unwrap_or(others.len());
+            if before.is_some() {
+                anchor_index
             } else {
-                println!("Added dependency: {} depends on {}", task.id, dep_task.id);
+                anchor_index + 1
             }
-            Ok(0)
         }
-        crate::cli::commands::DepCommands::Remove { id, dep_id } => {
-            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
-            let dep_task = task_repo::resolve_task(&conn, &plan_id, &dep_id)?;
+    };
 
-            dependency_repo::remove_dependency(&conn, &task.id, &dep_task.id)?;
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let changed = match task_repo::reorder_task(&conn, &plan_id, &task.id, new_position) {
+        Ok(changed) => {
+            conn.execute_batch("COMMIT")?;
+            changed
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(e);
+        }
+    };
 
-            // Check if task can be unblocked
-            if task.status == TaskStatus::Blocked && dependency_repo::all_dependencies_done(&conn, &task.id)? {
-                // Also check if there are no remaining deps at all, or all are done
-                let remaining = dependency_repo::get_dependencies(&conn, &task.id)?;
-                if remaining.is_empty() || dependency_repo::all_dependencies_done(&conn, &task.id)? {
-                    task_repo::update_task_status(&conn, &task.id, &TaskStatus::Ready, None)?;
-                }
-            }
+    let updated = task_repo::get_task_by_id(&conn, &task.id)?;
+    let order = task_repo::list_tasks_by_plan(&conn, &plan_id)?;
+    if json_output {
+        let changed_json: Vec<_> = changed
+            .iter()
+            .map(|t| json!({ "id": t.id, "title": t.title, "sort_order": t.sort_order }))
+            .collect();
+        let order_json: Vec<_> = order.iter().map(|t| json!(t.id)).collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output::json::success(json!({
+                "task": output::json::task_summary(&updated),
+                "changed": changed_json,
+                "order": order_json
+            })))
+            .unwrap()
+        );
+    } else {
+        println!("Task {} → position {}", updated.id, updated.sort_order);
+        output::text::print_task_list(&order);
+    }
+    Ok(0)
+}
 
-            if json_output {
-                println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+/// Claim one named task. The ready check happens inside `BEGIN IMMEDIATE`, so of two
+/// agents racing for the same task exactly one wins.
+fn run_claim(id: &str, agent: Option<&str>, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        let current = task_repo::get_task_by_id(&conn, &task.id)?;
+        if current.status != TaskStatus::Ready {
+            return Err(TaskaiError::invalid_transition(current.status.as_str(), "claim"));
+        }
+        task_repo::update_task_status(&conn, &task.id, &TaskStatus::InProgress, agent)?;
+        next_tasks::claimed_task(&conn, &current)
+    })();
+    let claimed = match result {
+        Ok(t) => {
+            conn.execute_batch("COMMIT")?;
+            t
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(e);
+        }
+    };
+
+    if json_output {
+        let has_docs = task_repo::task_has_documents(&conn, &claimed.id)?;
+        let in_progress_json: Vec<_> = task_repo::in_progress_tasks(&conn, &plan_id)?
+            .iter()
+            .map(|t| {
+                let elapsed = crate::cli::next::elapsed_minutes_pub(t.started_at.as_deref());
+                output::json::in_progress_entry(t, elapsed, crate::models::DEFAULT_STALE_AFTER_MINUTES)
+            })
+            .collect();
+        let progress = task_repo::task_progress(&conn, &plan_id)?;
+        println!("{}", serde_json::to_string_pretty(&output::json::success_with_plan_completed(json!({
+            "task": output::json::task_detail(&claimed, has_docs),
+            "in_progress": in_progress_json,
+            "progress": output::json::progress_json(&progress)
+        }), false)).unwrap());
+    } else {
+        let by = claimed.assigned_to.as_deref().map(|a| format!(" by {a}")).unwrap_or_default();
+        println!("Claimed{}: {} ({})", by, claimed.title, claimed.id);
+    }
+    Ok(0)
+}
+
+fn run_transition(
+    id: &str,
+    action: TransitionAction,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    let api::TransitionOutcome { task: updated_task, newly_ready, reblocked, in_progress_on_unmet_deps, progress, plan_completed } =
+        api::transition(&conn, &task.id, action)?;
+    let is_fail = matches!(action, TransitionAction::Fail { .. });
+
+    if json_output {
+        let mut data = json!({
+            "completed_task": {
+                "id": updated_task.id,
+                "title": updated_task.title,
+                "status": updated_task.status.as_str()
+            },
+            "progress": output::json::progress_json(&progress)
+        });
+        if is_fail {
+            data["completed_task"]["fail_count"] = json!(updated_task.fail_count);
+        }
+        if !newly_ready.is_empty() {
+            data["newly_ready"] = json!(newly_ready.iter().map(|t| json!({
+                "id": t.id,
+                "title": t.title,
+                "priority": t.priority
+            })).collect::<Vec<_>>());
+        }
+        // Only a reset reopens a done task, so only a reset can block dependents again
+        if matches!(action, TransitionAction::Reset) {
+            data["newly_blocked"] = json!(reblocked.iter().map(|t| json!({
+                "id": t.id,
+                "title": t.title
+            })).collect::<Vec<_>>());
+            data["in_progress_on_unmet_deps"] = json!(in_progress_on_unmet_deps.iter().map(|t| json!({
+                "id": t.id,
+                "title": t.title,
+                "assigned_to": t.assigned_to
+            })).collect::<Vec<_>>());
+        }
+        println!("{}", serde_json::to_string_pretty(
+            &output::json::success_with_plan_completed(data, plan_completed)
+        ).unwrap());
+    } else {
+        println!("Task {} → {}", updated_task.id, updated_task.status.as_str());
+        if is_fail && updated_task.status == TaskStatus::Cancelled {
+            println!("Failed {}x, more than --max-attempts allows", updated_task.fail_count);
+        }
+        if !newly_ready.is_empty() {
+            println!("Newly ready:");
+            for t in &newly_ready {
+                println!("  {} - {}", t.id, t.title);
+            }
+        }
+        if !reblocked.is_empty() {
+            println!("Re-blocked:");
+            for t in &reblocked {
+                println!("  {} - {}", t.id, t.title);
+            }
+        }
+        if !in_progress_on_unmet_deps.is_empty() {
+            println!("Still in progress, though this task is no longer done:");
+            for t in &in_progress_on_unmet_deps {
+                println!("  {} - {}", t.id, t.title);
+            }
+        }
+        if plan_completed {
+            println!("Plan completed!");
+        }
+    }
+    Ok(0)
+}
+
+/// Apply one action to many tasks in a single transaction. Each task is checked with
+/// `api::validate_transition` and runs in its own savepoint, so a task that cannot make the
+/// transition is reported under `skipped` without undoing the others.
+fn run_bulk(
+    action: TransitionAction,
+    selection: crate::cli::commands::BulkSelection,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let statuses = parse_statuses(&selection.status)?;
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+
+    let mut skipped: Vec<(String, TaskaiError)> = Vec::new();
+    let candidates = if selection.ids.is_empty() {
+        task_repo::list_tasks_by_plan(&conn, &plan_id)?
+    } else {
+        let mut tasks = Vec::new();
+        for reference in &selection.ids {
+            match task_repo::resolve_task(&conn, &plan_id, reference) {
+                Ok(t) => tasks.push(t),
+                Err(e) => skipped.push((reference.clone(), e)),
+            }
+        }
+        tasks
+    };
+    let targets: Vec<_> = candidates
+        .into_iter()
+        .filter(|t| statuses.is_empty() || statuses.contains(&t.status))
+        .filter(|t| selection.tag.as_ref().is_none_or(|tag| t.tags.contains(tag)))
+        .collect();
+
+    let mut updated = Vec::new();
+    let mut newly_ready = Vec::new();
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    for target in &targets {
+        // Re-read: an earlier task in the batch may have cascaded into this one
+        let result = task_repo::get_task_by_id(&conn, &target.id).and_then(|task| {
+            api::validate_transition(&task.status, action.name())?;
+            conn.execute_batch("SAVEPOINT bulk_item")?;
+            match api::apply_transition(&conn, &task, action) {
+                Ok(outcome) => {
+                    conn.execute_batch("RELEASE bulk_item")?;
+                    Ok((task.status, outcome))
+                }
+                Err(e) => {
+                    conn.execute_batch("ROLLBACK TO bulk_item; RELEASE bulk_item")?;
+                    Err(e)
+                }
+            }
+        });
+        match result {
+            Ok((from, (task, ready, _))) => {
+                updated.push((task.id.clone(), from, task.status.clone()));
+                newly_ready.extend(ready);
+            }
+            Err(e) => skipped.push((target.id.clone(), e)),
+        }
+    }
+    if let Err(e) = conn.execute_batch("COMMIT") {
+        let _ = conn.execute_batch("ROLLBACK");
+        return Err(e.into());
+    }
+    // Tasks finished later in the same batch are not "ready" anymore
+    newly_ready.retain(|t| !updated.iter().any(|(id, _, _)| id == &t.id));
+
+    let progress = task_repo::task_progress(&conn, &plan_id)?;
+    let plan_completed = progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0;
+    if json_output {
+        let mut data = json!({
+            "transitioned": updated.iter().map(|(id, from, to)| json!({
+                "id": id,
+                "from_status": from.as_str(),
+                "to_status": to.as_str()
+            })).collect::<Vec<_>>(),
+            "skipped": skipped.iter().map(|(id, e)| json!({
+                "id": id,
+                "code": e.code.as_str(),
+                "reason": e.message
+            })).collect::<Vec<_>>(),
+            "progress": output::json::progress_json(&progress)
+        });
+        if !newly_ready.is_empty() {
+            data["newly_ready"] = json!(newly_ready.iter().map(|t| json!({
+                "id": t.id,
+                "title": t.title,
+                "priority": t.priority
+            })).collect::<Vec<_>>());
+        }
+        println!("{}", serde_json::to_string_pretty(
+            &output::json::success_with_plan_completed(data, plan_completed)
+        ).unwrap());
+    } else {
+        for (id, from, to) in &updated {
+            println!("Task {} {} → {}", id, from.as_str(), to.as_str());
+        }
+        for (id, e) in &skipped {
+            eprintln!("Skipped {}: {}", id, e.message);
+        }
+        println!("{} transitioned, {} skipped", updated.len(), skipped.len());
+    }
+
+    if updated.is_empty() && !skipped.is_empty() {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
+}
+
+fn run_delete(id: &str, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        let dependents = dependency_repo::get_dependents(&conn, &task.id)?;
+        conn.execute(
+            "DELETE FROM task_dependencies WHERE task_id = ?1 OR dependency_id = ?1",
+            rusqlite::params![task.id],
+        )?;
+        task_repo::delete_task(&conn, &task.id)?;
+
+        let newly_ready = next_tasks::unblock_if_deps_done(&conn, &dependents)?;
+        let progress = task_repo::task_progress(&conn, &plan_id)?;
+        Ok((newly_ready, progress))
+    })();
+
+    match result {
+        Ok((newly_ready, progress)) => {
+            conn.execute_batch("COMMIT")?;
+
+            let plan_completed = progress.ready == 0 && progress.blocked == 0 && progress.in_progress == 0;
+
+            if json_output {
+                let mut data = json!({
+                    "deleted": { "id": task.id, "title": task.title },
+                    "progress": output::json::progress_json(&progress)
+                });
+                if !newly_ready.is_empty() {
+                    data["newly_ready"] = json!(newly_ready.iter().map(|t| json!({
+                        "id": t.id,
+                        "title": t.title,
+                        "priority": t.priority
+                    })).collect::<Vec<_>>());
+                }
+                println!("{}", serde_json::to_string_pretty(
+                    &output::json::success_with_plan_completed(data, plan_completed)
+                ).unwrap());
+            } else {
+                println!("Deleted task: {} ({})", task.title, task.id);
+                if !newly_ready.is_empty() {
+                    println!("Newly ready:");
+                    for t in &newly_ready {
+                        println!("  {} - {}", t.id, t.title);
+                    }
+                }
+            }
+            Ok(0)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+fn run_move(id: &str, to_plan: &str, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    let target = plan_repo::resolve_plan(&conn, to_plan)?;
+    if target.id == plan_id {
+        return Err(TaskaiError::validation(format!("Task is already in plan '{}'", target.name)));
+    }
+    if target.status.is_hidden() {
+        return Err(TaskaiError::validation(format!("Plan '{}' is {}", target.name, target.status.as_str())));
+    }
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let result = (|| -> Result<_, TaskaiError> {
+        let dependencies = dependency_repo::get_dependencies(&conn, &task.id)?;
+        let dependents = dependency_repo::get_dependents(&conn, &task.id)?;
+        let mut dropped: Vec<(String, String)> = dependencies
+            .iter()
+            .map(|d| (task.id.clone(), d.clone()))
+            .collect();
+        dropped.extend(dependents.iter().map(|d| (d.clone(), task.id.clone())));
+        conn.execute(
+            "DELETE FROM task_dependencies WHERE task_id = ?1 OR dependency_id = ?1",
+            rusqlite::params![task.id],
+        )?;
+
+        let max_order: i32 = conn.query_row(
+            "SELECT COALESCE(MAX(sort_order), -1) FROM tasks WHERE plan_id = ?1",
+            rusqlite::params![target.id],
+            |row| row.get(0),
+        )?;
+        task_repo::move_task(&conn, &task.id, &target.id, max_order + 1)?;
+
+        // The moved task has no dependencies left; its former dependents may be free now
+        next_tasks::unblock_if_deps_done(&conn, std::slice::from_ref(&task.id))?;
+        let newly_ready = next_tasks::unblock_if_deps_done(&conn, &dependents)?;
+        let moved = task_repo::get_task_by_id(&conn, &task.id)?;
+        Ok((moved, dropped, newly_ready))
+    })();
+
+    match result {
+        Ok((moved, dropped, newly_ready)) => {
+            conn.execute_batch("COMMIT")?;
+
+            if json_output {
+                let mut data = json!({
+                    "task": output::json::task_summary(&moved),
+                    "from_plan": plan_id,
+                    "to_plan": { "id": target.id, "name": target.name },
+                    "dropped_dependencies": dropped.iter().map(|(t, d)| json!({
+                        "task_id": t,
+                        "dependency_id": d
+                    })).collect::<Vec<_>>()
+                });
+                if !newly_ready.is_empty() {
+                    data["newly_ready"] = json!(newly_ready.iter().map(|t| json!({
+                        "id": t.id,
+                        "title": t.title,
+                        "priority": t.priority
+                    })).collect::<Vec<_>>());
+                }
+                println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
+            } else {
+                println!("Moved {} to plan {} ({})", moved.id, target.name, moved.status.as_str());
+                if !dropped.is_empty() {
+                    eprintln!("Warning: dropped {} cross-plan dependencies:", dropped.len());
+                    for (t, d) in &dropped {
+                        eprintln!("  {t} → {d}");
+                    }
+                }
+                if !newly_ready.is_empty() {
+                    println!("Newly ready:");
+                    for t in &newly_ready {
+                        println!("  {} - {}", t.id, t.title);
+                    }
+                }
+            }
+            Ok(0)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+fn run_estimate(id: &str, minutes: i64, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    if minutes < 0 {
+        return Err(TaskaiError::validation("Estimate must be >= 0 minutes"));
+    }
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    task_repo::set_task_estimate(&conn, &task.id, Some(minutes))?;
+    let task = task_repo::get_task_by_id(&conn, &task.id)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "task": output::json::task_summary(&task)
+        }))).unwrap());
+    } else {
+        println!("Estimated {}: {}min", task.id, minutes);
+    }
+    Ok(0)
+}
+
+fn run_assign(id: &str, agent: Option<&str>, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    if agent.is_some_and(|a| a.trim().is_empty()) {
+        return Err(TaskaiError::validation("Agent name must not be empty"));
+    }
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    if agent.is_some() && task.status.is_terminal() {
+        return Err(TaskaiError::invalid_transition(task.status.as_str(), "assign"));
+    }
+    // Unassigning an in_progress task hands it back to the queue
+    if agent.is_none() && task.status == TaskStatus::InProgress {
+        next_tasks::release_claim(&conn, &task)?;
+    } else {
+        task_repo::assign_task(&conn, &task.id, agent)?;
+    }
+    let previous_assignee = task.assigned_to;
+    let task = task_repo::get_task_by_id(&conn, &task.id)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "task": output::json::task_summary(&task),
+            "previous_assignee": previous_assignee
+        }))).unwrap());
+    } else {
+        match agent {
+            Some(a) => println!("Assigned {} to {}", task.id, a),
+            None => println!("Unassigned {}", task.id),
+        }
+    }
+    Ok(0)
+}
+
+fn run_heartbeat(
+    id: &str,
+    agent: Option<&str>,
+    extend: Option<i64>,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    if extend.is_some_and(|m| m < 1) {
+        return Err(TaskaiError::validation("--extend must be at least 1 minute"));
+    }
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    if task.status != TaskStatus::InProgress {
+        return Err(TaskaiError::invalid_transition(task.status.as_str(), "heartbeat"));
+    }
+    if let (Some(agent), Some(owner)) = (agent, task.assigned_to.as_deref()) {
+        if agent != owner {
+            return Err(TaskaiError::validation(format!(
+                "Task {} is assigned to {owner}, not {agent}", task.id
+            )));
+        }
+    }
+    let claim_expired = || {
+        TaskaiError::new(
+            ErrorCode::InvalidStatusTransition,
+            format!("Claim on task {} has expired; claim it again", task.id),
+        )
+    };
+    if task.is_claim_expired() {
+        return Err(claim_expired());
+    }
+    let extend = match extend {
+        Some(minutes) => Some(minutes),
+        None if task.claim_expires_at.is_some() => crate::cli::next::default_claim_timeout()?,
+        None => None,
+    };
+    match extend {
+        // Conditional update: loses cleanly to a concurrent release of the expired claim
+        Some(minutes) => {
+            if !task_repo::renew_claim(&conn, &task.id, minutes)? {
+                return Err(claim_expired());
+            }
+        }
+        None => task_repo::touch_heartbeat(&conn, &task.id)?,
+    }
+    let task = task_repo::get_task_by_id(&conn, &task.id)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "task": {
+                "id": task.id,
+                "status": task.status.as_str(),
+                "assigned_to": task.assigned_to,
+                "last_heartbeat_at": task.last_heartbeat_at,
+                "claim_expires_at": task.claim_expires_at
+            }
+        }))).unwrap());
+    } else {
+        match task.claim_expires_at {
+            Some(ref expires) => println!("Heartbeat recorded for {} (claim expires {expires})", task.id),
+            None => println!("Heartbeat recorded for {}", task.id),
+        }
+    }
+    Ok(0)
+}
+
+fn run_deadline(
+    id: &str,
+    datetime: Option<&str>,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let deadline = datetime.map(parse_deadline).transpose()?;
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    if task.status.is_terminal() && deadline.is_some() {
+        return Err(TaskaiError::invalid_transition(task.status.as_str(), "deadline"));
+    }
+    task_repo::set_task_deadline(&conn, &task.id, deadline.as_deref())?;
+    let task = task_repo::get_task_by_id(&conn, &task.id)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "task": output::json::task_summary(&task)
+        }))).unwrap());
+    } else {
+        match task.deadline {
+            Some(ref d) => println!("Deadline for {}: {} UTC", task.id, d),
+            None => println!("Cleared deadline for {}", task.id),
+        }
+    }
+    Ok(0)
+}
+
+fn parse_deadline(input: &str) -> Result<String, TaskaiError> {
+    parse_utc_datetime(input, "23:59:59").ok_or_else(|| {
+        TaskaiError::validation(format!(
+            "Invalid deadline '{input}'. Use ISO 8601, e.g. 2026-03-01T17:00:00Z or 2026-03-01"
+        ))
+    })
+}
+
+/// Normalize an ISO 8601 datetime to the `YYYY-MM-DD HH:MM:SS` UTC form SQLite compares against.
+/// A bare date gets `date_only_time` as its time of day.
+fn parse_utc_datetime(input: &str, date_only_time: &str) -> Option<String> {
+    const STORED: &str = "%Y-%m-%d %H:%M:%S";
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(input) {
+        return Some(dt.with_timezone(&chrono::Utc).format(STORED).to_string());
+    }
+    for fmt in ["%Y-%m-%dT%H:%M:%S", STORED, "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(input, fmt) {
+            return Some(dt.format(STORED).to_string());
+        }
+    }
+    chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .map(|date| format!("{} {}", date.format("%Y-%m-%d"), date_only_time))
+}
+
+/// `task deps` / `task blocking`: bare IDs of a task's dependencies (or, with
+/// `dependents`, of the tasks depending on it), in plan order.
+fn run_dep_ids(
+    id: &str,
+    dependents: bool,
+    status_filter: Option<&str>,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let status = status_filter
+        .map(|s| parse_statuses(&[s.to_string()]).map(|mut v| v.remove(0)))
+        .transpose()?;
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    let ids = if dependents {
+        dependency_repo::get_dependents(&conn, &task.id)?
+    } else {
+        dependency_repo::get_dependencies(&conn, &task.id)?
+    };
+    let mut tasks = ids
+        .iter()
+        .map(|id| task_repo::get_task_by_id(&conn, id))
+        .collect::<Result<Vec<_>, _>>()?;
+    tasks.retain(|t| status.as_ref().is_none_or(|s| t.status == *s));
+    tasks.sort_by_key(|t| t.sort_order);
+    let ids: Vec<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+
+    if json_output {
+        let key = if dependents { "dependents" } else { "dependencies" };
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({ key: ids }))).unwrap());
+    } else {
+        for id in ids {
+            println!("{id}");
+        }
+    }
+    Ok(0)
+}
+
+fn run_history(id: &str, since: Option<&str>, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let since = since
+        .map(|s| {
+            parse_utc_datetime(s, "00:00:00").ok_or_else(|| {
+                TaskaiError::validation(format!("Invalid --since '{s}'. Use ISO 8601, e.g. 2026-03-01T09:00:00Z"))
+            })
+        })
+        .transpose()?;
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+    let task = task_repo::resolve_task(&conn, &plan_id, id)?;
+    let transitions = transition_repo::get_task_transitions(&conn, &task.id, since.as_deref())?;
+
+    if json_output {
+        let events_json: Vec<_> = transitions.iter().map(output::json::task_transition_json).collect();
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "task": output::json::task_summary(&task),
+            "events": events_json
+        }))).unwrap());
+    } else if transitions.is_empty() {
+        println!("No transitions recorded.");
+    } else {
+        println!("History of {} ({}):", task.title, task.id);
+        output::text::print_task_transitions(&transitions);
+    }
+    Ok(0)
+}
+
+fn run_reclaim(older_than: i64, json_output: bool, plan_flag: Option<&str>) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let reclaimed = match next_tasks::reclaim_stale_tasks(&conn, &plan_id, older_than) {
+        Ok(tasks) => {
+            conn.execute_batch("COMMIT")?;
+            tasks
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            return Err(e);
+        }
+    };
+
+    if json_output {
+        let progress = task_repo::task_progress(&conn, &plan_id)?;
+        let reclaimed_json: Vec<_> = reclaimed.iter().map(output::json::task_summary).collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&output::json::success(json!({
+                "reclaimed": reclaimed_json,
+                "progress": output::json::progress_json(&progress)
+            })))
+            .unwrap()
+        );
+    } else if reclaimed.is_empty() {
+        println!("No stale tasks.");
+    } else {
+        for t in &reclaimed {
+            println!("Reclaimed: {} - {} → {}", t.id, t.title, t.status.as_str());
+        }
+    }
+    Ok(0)
+}
+
+fn run_dep(
+    cmd: crate::cli::commands::DepCommands,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+
+    match cmd {
+        crate::cli::commands::DepCommands::Add { id, dep_id, allow_cross_plan } => {
+            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
+            let dep_task = if allow_cross_plan {
+                task_repo::resolve_task_any_plan(&conn, &dep_id)?
+            } else {
+                task_repo::resolve_task(&conn, &plan_id, &dep_id)?
+            };
+
+            // Same plan check
+            if task.plan_id != dep_task.plan_id && !allow_cross_plan {
+                return Err(TaskaiError::cross_plan_dependency());
+            }
+
+            // Cycle check across all plans: a cross-plan edge can close a loop through a third plan
+            if task.id == dep_task.id || dependency_repo::depends_on(&conn, &dep_task.id, &task.id)? {
+                return Err(TaskaiError::cycle_detected());
+            }
+
+            dependency_repo::add_dependency(&conn, &task.id, &dep_task.id)?;
+
+            // If task was ready and new dep is not done, set to blocked
+            if task.status == TaskStatus::Ready && dep_task.status != TaskStatus::Done {
+                task_repo::update_task_status(&conn, &task.id, &TaskStatus::Blocked, None)?;
+            }
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+                    "added": { "task_id": task.id, "dependency_id": dep_task.id }
+                }))).unwrap());
+            } else {
+                println!("Added dependency: {} depends on {}", task.id, dep_task.id);
+            }
+            Ok(0)
+        }
+        crate::cli::commands::DepCommands::Remove { id, dep_id, allow_cross_plan } => {
+            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
+            let dep_task = if allow_cross_plan {
+                task_repo::resolve_task_any_plan(&conn, &dep_id)?
+            } else {
+                task_repo::resolve_task(&conn, &plan_id, &dep_id)?
+            };
+
+            dependency_repo::remove_dependency(&conn, &task.id, &dep_task.id)?;
+
+            // Check if task can be unblocked
+            if task.status == TaskStatus::Blocked && dependency_repo::all_dependencies_done(&conn, &task.id)? {
+                // Also check if there are no remaining deps at all, or all are done
+                let remaining = dependency_repo::get_dependencies(&conn, &task.id)?;
+                if remaining.is_empty() || dependency_repo::all_dependencies_done(&conn, &task.id)? {
+                    task_repo::update_task_status(&conn, &task.id, &TaskStatus::Ready, None)?;
+                }
+            }
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
                     "removed": { "task_id": task.id, "dependency_id": dep_task.id }
                 }))).unwrap());
             } else {
@@ -355,5 +1431,162 @@ fn run_dep(
             }
             Ok(0)
         }
+        crate::cli::commands::DepCommands::List { id, reverse, both } => {
+            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
+            let fetch = |ids: Vec<String>| -> Vec<crate::models::Task> {
+                ids.iter().filter_map(|d| task_repo::get_task_by_id(&conn, d).ok()).collect()
+            };
+            let dependencies = if reverse {
+                None
+            } else {
+                Some(fetch(dependency_repo::get_dependencies(&conn, &task.id)?))
+            };
+            let dependents = if reverse || both {
+                Some(fetch(dependency_repo::get_dependents(&conn, &task.id)?))
+            } else {
+                None
+            };
+
+            if json_output {
+                let mut data = json!({});
+                if let Some(ref tasks) = dependencies {
+                    data["dependencies"] = json!(tasks.iter().map(output::json::task_summary).collect::<Vec<_>>());
+                }
+                if let Some(ref tasks) = dependents {
+                    data["dependents"] = json!(tasks.iter().map(output::json::task_summary).collect::<Vec<_>>());
+                }
+                println!("{}", serde_json::to_string_pretty(&output::json::success(data)).unwrap());
+            } else {
+                let sections = [("Depends on", &dependencies), ("Depended on by", &dependents)];
+                for (heading, tasks) in sections {
+                    let Some(tasks) = tasks else { continue };
+                    println!("{heading}:");
+                    if tasks.is_empty() {
+                        println!("  (none)");
+                    }
+                    for t in tasks {
+                        println!("  {} ({}) [{}]", t.title, t.id, t.status.as_str());
+                    }
+                }
+            }
+            Ok(0)
+        }
     }
 }
+
+fn run_note(
+    cmd: crate::cli::commands::NoteCommands,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+
+    match cmd {
+        crate::cli::commands::NoteCommands::Add { id, content, agent } => {
+            if content.is_empty() {
+                return Err(TaskaiError::validation("Note content must not be empty"));
+            }
+            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
+            let note_id = ulid::Ulid::new().to_string();
+            let note = task_notes_repo::add_note(&conn, &note_id, &task.id, &content, agent.as_deref())?;
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+                    "note": output::json::task_note_json(&note)
+                }))).unwrap());
+            } else {
+                println!("Added note: {} (task {})", note.id, task.id);
+            }
+            Ok(0)
+        }
+        crate::cli::commands::NoteCommands::List { id } => {
+            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
+            let notes = task_notes_repo::get_task_notes(&conn, &task.id)?;
+
+            if json_output {
+                let notes_json: Vec<_> = notes.iter().map(output::json::task_note_json).collect();
+                println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+                    "task_id": task.id,
+                    "notes": notes_json
+                }))).unwrap());
+            } else if notes.is_empty() {
+                println!("No notes found.");
+            } else {
+                output::text::print_task_notes(&notes);
+            }
+            Ok(0)
+        }
+        crate::cli::commands::NoteCommands::Delete { note_id } => {
+            let note = task_notes_repo::resolve_note(&conn, &plan_id, &note_id)?;
+            task_notes_repo::delete_note(&conn, &note.id)?;
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+                    "deleted": { "id": note.id, "task_id": note.task_id }
+                }))).unwrap());
+            } else {
+                println!("Deleted note: {}", note.id);
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// Reject priorities outside `PRIORITY_RANGE` unless `allow_extreme` is set.
+pub fn validate_priority(priority: i32, allow_extreme: bool) -> Result<(), TaskaiError> {
+    if allow_extreme || PRIORITY_RANGE.contains(&priority) {
+        return Ok(());
+    }
+    Err(TaskaiError::validation(format!(
+        "Priority {priority} is outside {}..={}. Use --allow-extreme-priority if this is intended.",
+        PRIORITY_RANGE.start(),
+        PRIORITY_RANGE.end()
+    )))
+}
+
+fn run_tag(
+    cmd: crate::cli::commands::TagCommands,
+    json_output: bool,
+    plan_flag: Option<&str>,
+) -> Result<i32, TaskaiError> {
+    let conn = connection::open_db()?;
+    let plan_id = resolve_plan_id(&conn, plan_flag)?;
+
+    let (task, action) = match cmd {
+        crate::cli::commands::TagCommands::Add { id, tag } => {
+            validate_tag(&tag)?;
+            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
+            task_tags_repo::add_tag(&conn, &task.id, &tag)?;
+            (task, Some(format!("Tagged {id} with #{tag}")))
+        }
+        crate::cli::commands::TagCommands::Remove { id, tag } => {
+            let task = task_repo::resolve_task(&conn, &plan_id, &id)?;
+            if !task_tags_repo::remove_tag(&conn, &task.id, &tag)? {
+                return Err(TaskaiError::validation(format!("Task {} has no tag '{tag}'", task.id)));
+            }
+            (task, Some(format!("Removed #{tag} from {id}")))
+        }
+        crate::cli::commands::TagCommands::List { id } => {
+            (task_repo::resolve_task(&conn, &plan_id, &id)?, None)
+        }
+    };
+
+    let tags = task_tags_repo::get_task_tags(&conn, &task.id)?;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "task_id": task.id,
+            "tags": tags
+        }))).unwrap());
+    } else {
+        if let Some(msg) = action {
+            println!("{msg}");
+        }
+        if tags.is_empty() {
+            println!("No tags.");
+        } else {
+            println!("Tags: {}", tags.join(", "));
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,45 @@
+use serde_json::json;
+
+use crate::db::{connection, migrations};
+use crate::output;
+
+/// `taskai version`: build information plus the schema of the database in use.
+/// Never fails: a missing repository or database just leaves those fields null.
+pub fn run(json_output: bool) -> i32 {
+    let build_time = env!("BUILD_EPOCH")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+    let db_path = connection::db_path().ok();
+    let db_schema_version = connection::open_db_read_only()
+        .ok()
+        .flatten()
+        .and_then(|conn| migrations::current_version(&conn).ok());
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&output::json::success(json!({
+            "cli_version": env!("GIT_VERSION"),
+            "git_commit": env!("GIT_COMMIT"),
+            "build_time": build_time,
+            "db_schema_version": db_schema_version,
+            "expected_schema_version": migrations::SCHEMA_VERSION,
+            "db_path": db_path.as_ref().map(|p| p.to_string_lossy())
+        }))).unwrap());
+    } else {
+        let or_dash = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+        println!("taskai {}", env!("GIT_VERSION"));
+        println!("  {:<16} {}", "Commit", env!("GIT_COMMIT"));
+        println!("  {:<16} {}", "Built", or_dash(build_time.map(|t| format!("{t} UTC"))));
+        println!("  {:<16} {}", "Database", or_dash(db_path.map(|p| p.display().to_string())));
+        let schema = match db_schema_version {
+            Some(v) if v < migrations::SCHEMA_VERSION => {
+                format!("{v} (this taskai uses {}; the next command migrates it)", migrations::SCHEMA_VERSION)
+            }
+            Some(v) => v.to_string(),
+            None => "- (not initialized)".to_string(),
+        };
+        println!("  {:<16} {}", "Schema version", schema);
+    }
+    0
+}
@@ -0,0 +1,123 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::TaskaiError;
+
+use super::connection;
+
+/// Settings stored in `config.json` next to the database.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Plan used when --plan is not given (managed by `plan activate`, not `config set`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_plan_id: Option<String>,
+    /// Agent name `next --claim` claims under when --agent is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_agent: Option<String>,
+    /// Priority of `task add` without --priority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_priority: Option<i32>,
+    /// Claim timeout of `next --claim` without --timeout (TASKAI_CLAIM_TIMEOUT wins)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim_expiry_minutes: Option<i64>,
+    /// "json" makes --json the default output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
+}
+
+/// Keys accepted by `config set/get/reset`, in `config list` order.
+pub const CONFIG_KEYS: &[&str] = &["default_agent", "default_priority", "claim_expiry_minutes", "output_format"];
+
+impl Config {
+    /// Current value of `key` as text (`None` when unset).
+    pub fn get(&self, key: &str) -> Result<Option<String>, TaskaiError> {
+        Ok(match key {
+            "default_agent" => self.default_agent.clone(),
+            "default_priority" => self.default_priority.map(|p| p.to_string()),
+            "claim_expiry_minutes" => self.claim_expiry_minutes.map(|m| m.to_string()),
+            "output_format" => self.output_format.clone(),
+            _ => return Err(unknown_key(key)),
+        })
+    }
+
+    /// Validate and store `value` under `key`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), TaskaiError> {
+        let invalid = |expected: &str| TaskaiError::validation(format!("Invalid {key} '{value}': expected {expected}"));
+        match key {
+            "default_agent" => {
+                if value.trim().is_empty() {
+                    return Err(invalid("an agent name"));
+                }
+                self.default_agent = Some(value.to_string());
+            }
+            "default_priority" => {
+                self.default_priority = Some(value.parse().map_err(|_| invalid("an integer"))?);
+            }
+            "claim_expiry_minutes" => {
+                let minutes: i64 = value.parse().map_err(|_| invalid("a number of minutes"))?;
+                if minutes < 1 {
+                    return Err(invalid("at least 1 minute"));
+                }
+                self.claim_expiry_minutes = Some(minutes);
+            }
+            "output_format" => {
+                if value != "text" && value != "json" {
+                    return Err(invalid("text or json"));
+                }
+                self.output_format = Some(value.to_string());
+            }
+            _ => return Err(unknown_key(key)),
+        }
+        Ok(())
+    }
+
+    /// Unset `key`, falling back to the built-in default.
+    pub fn reset(&mut self, key: &str) -> Result<(), TaskaiError> {
+        match key {
+            "default_agent" => self.default_agent = None,
+            "default_priority" => self.default_priority = None,
+            "claim_expiry_minutes" => self.claim_expiry_minutes = None,
+            "output_format" => self.output_format = None,
+            _ => return Err(unknown_key(key)),
+        }
+        Ok(())
+    }
+
+    pub fn json_output(&self) -> bool {
+        self.output_format.as_deref() == Some("json")
+    }
+}
+
+fn unknown_key(key: &str) -> TaskaiError {
+    TaskaiError::validation(format!("Unknown config key '{key}'. Valid: {}", CONFIG_KEYS.join(", ")))
+}
+
+/// Settings of an in-memory database, which has no directory to keep `config.json` in.
+static MEMORY_CONFIG: Mutex<Option<Config>> = Mutex::new(None);
+
+/// Read `config.json`. A missing or unreadable file is an empty config.
+pub fn load_config() -> Config {
+    if connection::is_in_memory() {
+        return MEMORY_CONFIG.lock().unwrap().clone().unwrap_or_default();
+    }
+    connection::config_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Write `config` to `config.json`, creating its directory if needed.
+pub fn save_config(config: &Config) -> Result<(), TaskaiError> {
+    if connection::is_in_memory() {
+        *MEMORY_CONFIG.lock().unwrap() = Some(config.clone());
+        return Ok(());
+    }
+    let path = connection::config_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| TaskaiError::database(e.to_string()))?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(config).unwrap())
+        .map_err(|e| TaskaiError::database(e.to_string()))
+}
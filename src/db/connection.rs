@@ -1,13 +1,39 @@
 use std::env;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
 
 use crate::error::{ErrorCode, TaskaiError};
 
 use super::migrations;
 
+/// Environment variable that overrides the database location.
+pub const DB_PATH_ENV: &str = "TASKAI_DB_PATH";
+/// Shorter spelling of `DB_PATH_ENV`, used when that one is unset.
+pub const DB_ENV: &str = "TASKAI_DB";
+/// Database location selecting a throwaway in-memory database instead of a file.
+pub const MEMORY_DB_PATH: &str = ":memory:";
+
+/// Database location given by the global `--db-path` flag, if any.
+static DB_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `path` as the database for the rest of the process (the `--db-path` flag).
+pub fn set_db_path_override(path: PathBuf) {
+    let _ = DB_PATH_OVERRIDE.set(path);
+}
+
+/// How long to wait for the write lock (the `--lock-timeout` flag). Unset: no file lock.
+static LOCK_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Take the advisory write lock in mutating commands, waiting at most `timeout`.
+pub fn set_lock_timeout(timeout: Duration) {
+    let _ = LOCK_TIMEOUT.set(timeout);
+}
+
 /// Find the .git root by walking up from current directory.
 pub fn find_git_root() -> Result<PathBuf, TaskaiError> {
     let mut dir = env::current_dir().map_err(|e| TaskaiError::database(e.to_string()))?;
@@ -18,52 +44,162 @@ pub fn find_git_root() -> Result<PathBuf, TaskaiError> {
         if !dir.pop() {
             return Err(TaskaiError::new(
                 ErrorCode::NotInitialized,
-                "Not inside a git repository. taskai requires a git repository.",
+                "Not inside a git repository. taskai requires a git repository \
+                 (or a database location via --db-path / TASKAI_DB_PATH).",
             ));
         }
     }
 }
 
-/// Get the path to the taskai database.
+/// Get the path to the taskai database: `--db-path`, else `TASKAI_DB_PATH`, else
+/// `TASKAI_DB`, else `<git-root>/.worktoolai/taskai/taskai.db`.
 pub fn db_path() -> Result<PathBuf, TaskaiError> {
+    if let Some(path) = DB_PATH_OVERRIDE.get() {
+        return Ok(path.clone());
+    }
+    for var in [DB_PATH_ENV, DB_ENV] {
+        if let Some(path) = env::var_os(var).filter(|p| !p.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+    }
     let root = find_git_root()?;
     Ok(root.join(".worktoolai").join("taskai").join("taskai.db"))
 }
 
-/// Get the config file path.
+/// Whether the database location is `:memory:`, so nothing outlives the process.
+pub fn is_in_memory() -> bool {
+    db_path().is_ok_and(|path| path.as_os_str() == MEMORY_DB_PATH)
+}
+
+/// Get the config file path (next to the database).
 pub fn config_path() -> Result<PathBuf, TaskaiError> {
-    let root = find_git_root()?;
-    Ok(root.join(".worktoolai").join("taskai").join("config.json"))
+    Ok(db_path()?.with_file_name("config.json"))
 }
 
 /// Open a connection to the database. Returns error if not initialized.
+/// Migrations are re-applied so databases created by older versions gain new tables.
 pub fn open_db() -> Result<Connection, TaskaiError> {
     let path = db_path()?;
+    if path.as_os_str() == MEMORY_DB_PATH {
+        return open_in_memory();
+    }
     if !path.exists() {
-        return Err(TaskaiError::not_initialized());
+        return Err(TaskaiError::not_initialized(&path));
     }
-    let conn = Connection::open(&path)?;
-    configure_connection(&conn)?;
+    let mut conn = Connection::open(&path)?;
+    configure_connection(&mut conn)?;
+    migrations::run_migrations(&conn)?;
+    Ok(conn)
+}
+
+/// Open the database read-only, without running migrations. Returns `None` if not initialized.
+pub fn open_db_read_only() -> Result<Option<Connection>, TaskaiError> {
+    let path = db_path()?;
+    if path.as_os_str() == MEMORY_DB_PATH {
+        return open_in_memory().map(Some);
+    }
+    if !path.exists() {
+        return Ok(None);
+    }
+    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    conn.execute_batch("PRAGMA busy_timeout=5000;")?;
+    Ok(Some(conn))
+}
+
+/// Open a fresh in-memory database with the full schema. Every call starts empty and the
+/// data is gone once the connection drops; `journal_mode=WAL` is a no-op here because
+/// SQLite keeps the journal of a memory database in memory.
+pub fn open_in_memory() -> Result<Connection, TaskaiError> {
+    let mut conn = Connection::open_in_memory()?;
+    configure_connection(&mut conn)?;
+    migrations::run_migrations(&conn)?;
     Ok(conn)
 }
 
 /// Initialize the database: create directories, database, and run migrations.
 pub fn init_db() -> Result<PathBuf, TaskaiError> {
     let path = db_path()?;
+    if path.as_os_str() == MEMORY_DB_PATH {
+        open_in_memory()?;
+        return Ok(path);
+    }
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| TaskaiError::database(e.to_string()))?;
     }
-    let conn = Connection::open(&path)?;
-    configure_connection(&conn)?;
+    let mut conn = Connection::open(&path)?;
+    configure_connection(&mut conn)?;
     migrations::run_migrations(&conn)?;
     Ok(path)
 }
 
-fn configure_connection(conn: &Connection) -> Result<(), TaskaiError> {
+/// Run `f` holding the advisory write lock (`taskai.lock` next to the database), so
+/// mutating commands from parallel processes queue up instead of racing for SQLite's
+/// write lock. A no-op unless `--lock-timeout` was given. The lock is released when `f`
+/// returns, on success and error alike.
+pub fn with_write_lock<T>(f: impl FnOnce() -> Result<T, TaskaiError>) -> Result<T, TaskaiError> {
+    let Some(timeout) = LOCK_TIMEOUT.get() else {
+        return f();
+    };
+    let path = db_path()?.with_file_name("taskai.lock");
+    // No database directory yet: nothing to serialize, and `f` reports NOT_INITIALIZED
+    if !path.parent().is_some_and(|dir| dir.exists()) {
+        return f();
+    }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| TaskaiError::database(e.to_string()))?;
+
+    let deadline = Instant::now() + *timeout;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(fs::TryLockError::WouldBlock) if Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(fs::TryLockError::WouldBlock) => return Err(TaskaiError::lock_timeout(&path, *timeout)),
+            Err(fs::TryLockError::Error(e)) => return Err(TaskaiError::database(e.to_string())),
+        }
+    }
+    let result = f();
+    let _ = file.unlock();
+    result
+}
+
+fn configure_connection(conn: &mut Connection) -> Result<(), TaskaiError> {
     conn.execute_batch(
         "PRAGMA journal_mode=WAL;
          PRAGMA busy_timeout=5000;
          PRAGMA foreign_keys=ON;",
     )?;
+    if crate::verbose::enabled() {
+        conn.trace(Some(trace_transaction));
+    }
     Ok(())
 }
+
+/// SQL trace hook for `--verbose`: report transaction and savepoint boundaries only.
+fn trace_transaction(sql: &str) {
+    let keyword = sql.split_whitespace().next().unwrap_or("").to_ascii_uppercase();
+    if matches!(keyword.as_str(), "BEGIN" | "COMMIT" | "END" | "ROLLBACK" | "SAVEPOINT" | "RELEASE") {
+        crate::verbose!("sql: {}", sql.trim());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api;
+
+    #[test]
+    fn test_in_memory_databases_are_separate() {
+        let first = open_in_memory().unwrap();
+        assert_eq!(migrations::current_version(&first).unwrap(), migrations::SCHEMA_VERSION);
+        api::load_plan(&first, r#"{"name":"mem","title":"Mem","tasks":[{"id":"a","title":"A"}]}"#).unwrap();
+        let second = open_in_memory().unwrap();
+        let plans: i64 = second.query_row("SELECT COUNT(*) FROM plans", [], |r| r.get(0)).unwrap();
+        assert_eq!(plans, 0);
+    }
+}
@@ -60,6 +60,21 @@ pub fn get_all_dependencies_for_plan(conn: &Connection, plan_id: &str) -> Result
     Ok(deps)
 }
 
+/// Whether `task_id` depends on `dependency_id`, directly or through other tasks, in any plan.
+pub fn depends_on(conn: &Connection, task_id: &str, dependency_id: &str) -> Result<bool, TaskaiError> {
+    let found = conn.query_row(
+        "WITH RECURSIVE reach(id) AS (
+             SELECT dependency_id FROM task_dependencies WHERE task_id = ?1
+             UNION
+             SELECT td.dependency_id FROM task_dependencies td JOIN reach r ON td.task_id = r.id
+         )
+         SELECT EXISTS (SELECT 1 FROM reach WHERE id = ?2)",
+        params![task_id, dependency_id],
+        |row| row.get(0),
+    )?;
+    Ok(found)
+}
+
 /// Check if all dependencies of a task are done.
 pub fn all_dependencies_done(conn: &Connection, task_id: &str) -> Result<bool, TaskaiError> {
     let count: i64 = conn.query_row(
@@ -71,3 +86,15 @@ pub fn all_dependencies_done(conn: &Connection, task_id: &str) -> Result<bool, T
     )?;
     Ok(count == 0)
 }
+
+/// Dependency rows whose task or dependency no longer exists, across all plans.
+pub fn count_orphaned_dependencies(conn: &Connection) -> Result<i64, TaskaiError> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM task_dependencies td
+         WHERE NOT EXISTS (SELECT 1 FROM tasks WHERE id = td.task_id)
+            OR NOT EXISTS (SELECT 1 FROM tasks WHERE id = td.dependency_id)",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
@@ -1,7 +1,7 @@
 use rusqlite::{params, Connection};
 
 use crate::error::TaskaiError;
-use crate::models::{PlanDocument, TaskDocument};
+use crate::models::{Document, PlanDocument, TaskDocument};
 
 pub fn create_plan_document(
     conn: &Connection,
@@ -11,25 +11,28 @@ pub fn create_plan_document(
     content: &str,
 ) -> Result<(), TaskaiError> {
     conn.execute(
-        "INSERT INTO plan_documents (id, plan_id, title, content) VALUES (?1, ?2, ?3, ?4)",
+        "INSERT INTO plan_documents (id, plan_id, title, content, updated_at)
+         VALUES (?1, ?2, ?3, ?4, datetime('now'))",
         params![id, plan_id, title, content],
     )?;
     Ok(())
 }
 
+/// Re-parent every plan document of `from_plan_id` to `to_plan_id`. Returns the number moved.
+pub fn move_plan_documents(conn: &Connection, from_plan_id: &str, to_plan_id: &str) -> Result<usize, TaskaiError> {
+    let moved = conn.execute(
+        "UPDATE plan_documents SET plan_id = ?2 WHERE plan_id = ?1",
+        params![from_plan_id, to_plan_id],
+    )?;
+    Ok(moved)
+}
+
 pub fn get_plan_documents(conn: &Connection, plan_id: &str) -> Result<Vec<PlanDocument>, TaskaiError> {
     let mut stmt = conn.prepare(
-        "SELECT id, plan_id, title, content FROM plan_documents WHERE plan_id = ?1",
+        "SELECT id, plan_id, title, content, updated_at FROM plan_documents WHERE plan_id = ?1",
     )?;
     let docs = stmt
-        .query_map(params![plan_id], |row| {
-            Ok(PlanDocument {
-                id: row.get(0)?,
-                plan_id: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-            })
-        })?
+        .query_map(params![plan_id], row_to_plan_document)?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(docs)
 }
@@ -42,7 +45,8 @@ pub fn create_task_document(
     content: &str,
 ) -> Result<(), TaskaiError> {
     conn.execute(
-        "INSERT INTO task_documents (id, task_id, title, content) VALUES (?1, ?2, ?3, ?4)",
+        "INSERT INTO task_documents (id, task_id, title, content, updated_at)
+         VALUES (?1, ?2, ?3, ?4, datetime('now'))",
         params![id, task_id, title, content],
     )?;
     Ok(())
@@ -50,17 +54,222 @@ pub fn create_task_document(
 
 pub fn get_task_documents(conn: &Connection, task_id: &str) -> Result<Vec<TaskDocument>, TaskaiError> {
     let mut stmt = conn.prepare(
-        "SELECT id, task_id, title, content FROM task_documents WHERE task_id = ?1",
+        "SELECT id, task_id, title, content, updated_at FROM task_documents WHERE task_id = ?1",
     )?;
     let docs = stmt
-        .query_map(params![task_id], |row| {
-            Ok(TaskDocument {
-                id: row.get(0)?,
-                task_id: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-            })
-        })?
+        .query_map(params![task_id], row_to_task_document)?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(docs)
 }
+
+/// Look up a document by ID in both the plan and task document tables.
+pub fn get_document(conn: &Connection, id: &str) -> Result<Document, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, plan_id, title, content, updated_at FROM plan_documents WHERE id = ?1",
+    )?;
+    if let Some(doc) = stmt.query_map(params![id], row_to_plan_document)?.next() {
+        return Ok(Document::Plan(doc?));
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id, task_id, title, content, updated_at FROM task_documents WHERE id = ?1",
+    )?;
+    if let Some(doc) = stmt.query_map(params![id], row_to_task_document)?.next() {
+        return Ok(Document::Task(doc?));
+    }
+    Err(TaskaiError::document_not_found(id))
+}
+
+/// Resolve one of a plan's documents by exact ID, ID prefix or title prefix.
+pub fn resolve_plan_document(conn: &Connection, plan_id: &str, reference: &str) -> Result<PlanDocument, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, plan_id, title, content, updated_at FROM plan_documents
+         WHERE plan_id = ?1 AND (id LIKE ?2 OR title LIKE ?2)",
+    )?;
+    let docs = stmt
+        .query_map(params![plan_id, format!("{reference}%")], row_to_plan_document)?
+        .collect::<Result<Vec<_>, _>>()?;
+    pick_document(docs, reference, |d| (&d.id, &d.title))
+}
+
+/// Resolve one of a task's documents by exact ID, ID prefix or title prefix.
+pub fn resolve_task_document(conn: &Connection, task_id: &str, reference: &str) -> Result<TaskDocument, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, task_id, title, content, updated_at FROM task_documents
+         WHERE task_id = ?1 AND (id LIKE ?2 OR title LIKE ?2)",
+    )?;
+    let docs = stmt
+        .query_map(params![task_id, format!("{reference}%")], row_to_task_document)?
+        .collect::<Result<Vec<_>, _>>()?;
+    pick_document(docs, reference, |d| (&d.id, &d.title))
+}
+
+/// The single match among `docs`; an exact ID or title wins over prefix matches.
+fn pick_document<D>(
+    docs: Vec<D>,
+    reference: &str,
+    key: impl Fn(&D) -> (&String, &String),
+) -> Result<D, TaskaiError> {
+    if docs.len() > 1 {
+        if let Some(i) = docs.iter().position(|d| key(d).0 == reference) {
+            return Ok(docs.into_iter().nth(i).unwrap());
+        }
+        let exact: Vec<usize> = (0..docs.len()).filter(|&i| key(&docs[i]).1 == reference).collect();
+        if let [i] = exact[..] {
+            return Ok(docs.into_iter().nth(i).unwrap());
+        }
+        let candidates: Vec<String> = docs.iter().map(|d| format!("{} ({})", key(d).1, key(d).0)).collect();
+        return Err(TaskaiError::ambiguous_ref(reference, &candidates));
+    }
+    docs.into_iter().next().ok_or_else(|| TaskaiError::document_not_found(reference))
+}
+
+/// Update a document's title and/or content, whichever table it lives in.
+/// Only `Some` fields are written. Returns the updated document.
+pub fn update_document(
+    conn: &Connection,
+    id: &str,
+    title: Option<&str>,
+    content: Option<&str>,
+) -> Result<Document, TaskaiError> {
+    let table = match get_document(conn, id)? {
+        Document::Plan(_) => "plan_documents",
+        Document::Task(_) => "task_documents",
+    };
+    conn.execute(
+        &format!(
+            "UPDATE {table} SET title = COALESCE(?1, title), content = COALESCE(?2, content),
+             updated_at = datetime('now') WHERE id = ?3"
+        ),
+        params![title, content, id],
+    )?;
+    get_document(conn, id)
+}
+
+/// Delete a document from whichever table it lives in. Returns the deleted document.
+pub fn delete_document(conn: &Connection, id: &str) -> Result<Document, TaskaiError> {
+    let doc = get_document(conn, id)?;
+    let table = match doc {
+        Document::Plan(_) => "plan_documents",
+        Document::Task(_) => "task_documents",
+    };
+    conn.execute(&format!("DELETE FROM {table} WHERE id = ?1"), params![id])?;
+    Ok(doc)
+}
+
+/// One `document search` match.
+pub struct DocumentSearchHit {
+    pub id: String,
+    /// "plan" or "task"
+    pub kind: String,
+    pub title: String,
+    pub plan_id: String,
+    pub plan_name: String,
+    pub task_id: Option<String>,
+    pub task_title: Option<String>,
+    pub snippet: String,
+}
+
+/// Whether the FTS5 document index exists (it is skipped when SQLite lacks FTS5).
+pub fn has_document_fts(conn: &Connection) -> Result<bool, TaskaiError> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'document_fts'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Full-text search over document titles and contents, best matches first.
+/// Every whitespace-separated word of `query` must match. `plan_id` limits the search to
+/// one plan's documents (including its tasks').
+pub fn search_documents_fts(
+    conn: &Connection,
+    query: &str,
+    plan_id: Option<&str>,
+) -> Result<Vec<DocumentSearchHit>, TaskaiError> {
+    // Quote each word so FTS5 operators in user input are taken literally
+    let fts_query = query
+        .split_whitespace()
+        .map(|w| format!("\"{}\"", w.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if fts_query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.doc_id, f.kind, f.title, {DOC_OWNER_COLUMNS}, f.snippet
+         FROM (SELECT doc_id, kind, title, snippet(document_fts, 1, '[', ']', '…', 12) AS snippet, rank
+               FROM document_fts WHERE document_fts MATCH ?1) f
+         {DOC_OWNER_JOINS}
+         WHERE ?2 IS NULL OR p.id = ?2
+         ORDER BY f.rank"
+    ))?;
+    let hits = stmt
+        .query_map(params![fts_query, plan_id], row_to_search_hit)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(hits)
+}
+
+/// Substring fallback for `search_documents_fts` when FTS5 is unavailable. The snippet
+/// is the start of the content.
+pub fn search_documents_like(
+    conn: &Connection,
+    query: &str,
+    plan_id: Option<&str>,
+) -> Result<Vec<DocumentSearchHit>, TaskaiError> {
+    let pattern = format!("%{query}%");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.doc_id, f.kind, f.title, {DOC_OWNER_COLUMNS}, substr(f.content, 1, 80)
+         FROM (SELECT id AS doc_id, 'plan' AS kind, title, content FROM plan_documents
+               UNION ALL
+               SELECT id, 'task', title, content FROM task_documents) f
+         {DOC_OWNER_JOINS}
+         WHERE (f.title LIKE ?1 OR f.content LIKE ?1) AND (?2 IS NULL OR p.id = ?2)
+         ORDER BY p.name, f.title"
+    ))?;
+    let hits = stmt
+        .query_map(params![pattern, plan_id], row_to_search_hit)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(hits)
+}
+
+/// Owner columns of a search row `f` (doc_id, kind): plan id/name, task id/title.
+const DOC_OWNER_COLUMNS: &str = "p.id, p.name, td.task_id, t.title";
+const DOC_OWNER_JOINS: &str = "
+         LEFT JOIN plan_documents pd ON f.kind = 'plan' AND pd.id = f.doc_id
+         LEFT JOIN task_documents td ON f.kind = 'task' AND td.id = f.doc_id
+         LEFT JOIN tasks t ON t.id = td.task_id
+         JOIN plans p ON p.id = COALESCE(pd.plan_id, t.plan_id)";
+
+fn row_to_search_hit(row: &rusqlite::Row) -> rusqlite::Result<DocumentSearchHit> {
+    Ok(DocumentSearchHit {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        title: row.get(2)?,
+        plan_id: row.get(3)?,
+        plan_name: row.get(4)?,
+        task_id: row.get(5)?,
+        task_title: row.get(6)?,
+        snippet: row.get(7)?,
+    })
+}
+
+fn row_to_plan_document(row: &rusqlite::Row) -> rusqlite::Result<PlanDocument> {
+    Ok(PlanDocument {
+        id: row.get(0)?,
+        plan_id: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+fn row_to_task_document(row: &rusqlite::Row) -> rusqlite::Result<TaskDocument> {
+    Ok(TaskDocument {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
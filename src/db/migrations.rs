@@ -2,7 +2,97 @@ use rusqlite::Connection;
 
 use crate::error::TaskaiError;
 
+/// Schema version of a fully migrated database.
+pub const SCHEMA_VERSION: i64 = 14;
+
+type Migration = fn(&Connection) -> Result<(), TaskaiError>;
+
+/// Forward migrations, in order. Each entry upgrades the schema to its version.
+/// Steps stay idempotent because databases from before `schema_version` existed
+/// may already have some of these changes.
+const MIGRATIONS: &[(i64, Migration)] = &[
+    (2, migration_002),
+    (3, migration_003),
+    (4, migration_004),
+    (5, migration_005),
+    (6, migration_006),
+    (7, migration_007),
+    (8, migration_008),
+    (9, migration_009),
+    (10, migration_010),
+    (11, migration_011),
+    (12, migration_012),
+    (13, migration_013),
+    (14, migration_014),
+];
+
+/// Bring the database up to `SCHEMA_VERSION`: create the base tables (`migration_001`), then apply
+/// every pending step from `MIGRATIONS`, recording each in `schema_version`.
+/// Each step commits on its own, so a failing step keeps the ones before it applied.
 pub fn run_migrations(conn: &Connection) -> Result<(), TaskaiError> {
+    // Always run: it is idempotent and creates the `schema_version` table read below
+    migration_001(conn)?;
+    let current = current_version(conn)?;
+    if current >= SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    if current < 1 {
+        in_transaction(conn, |conn| match current_version(conn)? {
+            0 => record_version(conn, 1),
+            _ => Ok(()),
+        })?;
+    }
+    for (version, step) in MIGRATIONS.iter().filter(|(v, _)| *v > current) {
+        in_transaction(conn, |conn| {
+            // Re-check under the write lock: another process may have applied it meanwhile
+            if current_version(conn)? >= *version {
+                return Ok(());
+            }
+            step(conn)?;
+            record_version(conn, *version)
+        })
+        .map_err(|e| TaskaiError::database(format!("Migration to schema version {version} failed: {}", e.message)))?;
+    }
+    Ok(())
+}
+
+fn in_transaction(conn: &Connection, f: impl FnOnce(&Connection) -> Result<(), TaskaiError>) -> Result<(), TaskaiError> {
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    match f(conn) {
+        Ok(()) => {
+            conn.execute_batch("COMMIT")?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+/// Highest applied schema version (0 for a database that predates versioning).
+pub fn current_version(conn: &Connection) -> Result<i64, TaskaiError> {
+    // Read-only callers (version, doctor) see legacy databases before any migration runs
+    let versioned: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !versioned {
+        return Ok(0);
+    }
+    let version = conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))?;
+    Ok(version)
+}
+
+fn record_version(conn: &Connection, version: i64) -> Result<(), TaskaiError> {
+    conn.execute("INSERT INTO schema_version (version) VALUES (?1)", [version])?;
+    Ok(())
+}
+
+/// Base tables (v1) and the `schema_version` table.
+fn migration_001(conn: &Connection) -> Result<(), TaskaiError> {
     conn.execute_batch(
         "
         CREATE TABLE IF NOT EXISTS plans (
@@ -11,7 +101,7 @@ pub fn run_migrations(conn: &Connection) -> Result<(), TaskaiError> {
             title TEXT NOT NULL,
             description TEXT,
             status TEXT NOT NULL DEFAULT 'active'
-                CHECK (status IN ('active', 'completed', 'archived')),
+                CHECK (status IN ('active', 'completed', 'archived', 'deleted')),
             created_at TEXT NOT NULL DEFAULT (datetime('now')),
             updated_at TEXT NOT NULL DEFAULT (datetime('now'))
         );
@@ -54,12 +144,183 @@ pub fn run_migrations(conn: &Connection) -> Result<(), TaskaiError> {
             content TEXT NOT NULL
         );
 
+        CREATE TABLE IF NOT EXISTS task_notes (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS task_tags (
+            task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (task_id, tag)
+        );
+
+        CREATE TABLE IF NOT EXISTS task_transitions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            from_status TEXT NOT NULL,
+            to_status TEXT NOT NULL,
+            agent TEXT,
+            at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
         CREATE INDEX IF NOT EXISTS idx_tasks_plan_status ON tasks(plan_id, status);
         CREATE INDEX IF NOT EXISTS idx_tasks_ready ON tasks(status, priority, sort_order)
             WHERE status = 'ready';
         CREATE INDEX IF NOT EXISTS idx_deps_task ON task_dependencies(task_id);
         CREATE INDEX IF NOT EXISTS idx_deps_dep ON task_dependencies(dependency_id);
+        CREATE INDEX IF NOT EXISTS idx_notes_task ON task_notes(task_id);
+        CREATE INDEX IF NOT EXISTS idx_tags_tag ON task_tags(tag);
+        CREATE INDEX IF NOT EXISTS idx_transitions_task ON task_transitions(task_id);
+
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+fn migration_002(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "task_notes", "agent", "TEXT")
+}
+
+fn migration_003(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "tasks", "estimated_minutes", "INTEGER")
+}
+
+fn migration_004(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "tasks", "deadline", "TEXT")
+}
+
+fn migration_005(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "task_transitions", "event_type", "TEXT NOT NULL DEFAULT 'status_change'")
+}
+
+fn migration_006(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "tasks", "last_heartbeat_at", "TEXT")
+}
+
+fn migration_007(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "tasks", "external_id", "TEXT")?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_tasks_external ON tasks(plan_id, external_id)")?;
+    Ok(())
+}
+
+fn migration_008(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "plan_documents", "updated_at", "TEXT")?;
+    add_column_if_missing(conn, "task_documents", "updated_at", "TEXT")
+}
+
+/// Full-text index over plan and task documents, kept in sync by triggers.
+/// Skipped when the linked SQLite lacks FTS5; `document search` then falls back to LIKE.
+fn migration_009(conn: &Connection) -> Result<(), TaskaiError> {
+    let has_fts5: bool = conn.query_row("SELECT sqlite_compileoption_used('ENABLE_FTS5')", [], |row| row.get(0))?;
+    if !has_fts5 {
+        return Ok(());
+    }
+    conn.execute_batch(
+        "
+        CREATE VIRTUAL TABLE IF NOT EXISTS document_fts
+            USING fts5(title, content, doc_id UNINDEXED, kind UNINDEXED);
+
+        CREATE TRIGGER IF NOT EXISTS plan_documents_fts_insert AFTER INSERT ON plan_documents BEGIN
+            INSERT INTO document_fts (title, content, doc_id, kind) VALUES (new.title, new.content, new.id, 'plan');
+        END;
+        CREATE TRIGGER IF NOT EXISTS plan_documents_fts_update AFTER UPDATE OF title, content ON plan_documents BEGIN
+            UPDATE document_fts SET title = new.title, content = new.content WHERE doc_id = old.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS plan_documents_fts_delete AFTER DELETE ON plan_documents BEGIN
+            DELETE FROM document_fts WHERE doc_id = old.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS task_documents_fts_insert AFTER INSERT ON task_documents BEGIN
+            INSERT INTO document_fts (title, content, doc_id, kind) VALUES (new.title, new.content, new.id, 'task');
+        END;
+        CREATE TRIGGER IF NOT EXISTS task_documents_fts_update AFTER UPDATE OF title, content ON task_documents BEGIN
+            UPDATE document_fts SET title = new.title, content = new.content WHERE doc_id = old.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS task_documents_fts_delete AFTER DELETE ON task_documents BEGIN
+            DELETE FROM document_fts WHERE doc_id = old.id;
+        END;
+
+        DELETE FROM document_fts;
+        INSERT INTO document_fts (title, content, doc_id, kind)
+            SELECT title, content, id, 'plan' FROM plan_documents;
+        INSERT INTO document_fts (title, content, doc_id, kind)
+            SELECT title, content, id, 'task' FROM task_documents;
         ",
     )?;
     Ok(())
 }
+
+/// Widen the `plans.status` CHECK to accept 'deleted' (soft delete).
+/// SQLite cannot alter a constraint, and rebuilding `plans` would cascade-delete every
+/// task while foreign keys are on, so the stored CREATE TABLE text is edited in place:
+/// loosening a CHECK leaves existing rows valid, which SQLite documents as safe.
+fn migration_010(conn: &Connection) -> Result<(), TaskaiError> {
+    let schema_version: i64 = conn.query_row("PRAGMA schema_version", [], |row| row.get(0))?;
+    conn.execute_batch("PRAGMA writable_schema = ON")?;
+    let changed = conn.execute(
+        "UPDATE sqlite_master
+         SET sql = replace(sql, '''completed'', ''archived'')', '''completed'', ''archived'', ''deleted'')')
+         WHERE type = 'table' AND name = 'plans' AND sql NOT LIKE '%''deleted''%'",
+        [],
+    );
+    let result = match changed {
+        // Bump the schema cookie so open connections re-read the definition
+        Ok(n) if n > 0 => conn.execute_batch(&format!("PRAGMA schema_version = {}", schema_version + 1)),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    };
+    conn.execute_batch("PRAGMA writable_schema = OFF")?;
+    result?;
+    Ok(())
+}
+
+fn migration_011(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "tasks", "claim_expires_at", "TEXT")
+}
+
+fn migration_012(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "tasks", "failure_reason", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "fail_count", "INTEGER NOT NULL DEFAULT 0")
+}
+
+fn migration_013(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "tasks", "completion_note", "TEXT")
+}
+
+fn migration_014(conn: &Connection) -> Result<(), TaskaiError> {
+    add_column_if_missing(conn, "plans", "idempotency_key", "TEXT")?;
+    add_column_if_missing(conn, "tasks", "idempotency_key", "TEXT")?;
+    conn.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_plans_idempotency ON plans(idempotency_key);
+         CREATE UNIQUE INDEX IF NOT EXISTS idx_tasks_idempotency ON tasks(plan_id, idempotency_key);",
+    )?;
+    Ok(())
+}
+
+/// Add a column to an existing table unless it is already there.
+/// `CREATE TABLE IF NOT EXISTS` leaves older databases untouched, so columns added
+/// after a table first shipped go through a migration step using this.
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> Result<(), TaskaiError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .any(|c| c == column);
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))?;
+    }
+    Ok(())
+}
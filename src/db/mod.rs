@@ -1,8 +1,13 @@
+pub mod config;
 pub mod connection;
 pub mod migrations;
 pub mod plan_repo;
+pub mod plan_stats_repo;
 pub mod task_repo;
 pub mod dependency_repo;
 pub mod document_repo;
+pub mod task_notes_repo;
+pub mod task_tags_repo;
+pub mod transition_repo;
 
 pub use connection::*;
@@ -1,4 +1,4 @@
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 
 use crate::error::TaskaiError;
 use crate::models::{Plan, PlanStatus};
@@ -64,6 +64,14 @@ pub fn find_plan_by_name(conn: &Connection, name: &str) -> Result<Option<Plan>,
     }
 }
 
+/// Plan created by a `plan load` with idempotency key `key`, if any.
+pub fn find_plan_by_idempotency_key(conn: &Connection, key: &str) -> Result<Option<Plan>, TaskaiError> {
+    let id: Option<String> = conn
+        .query_row("SELECT id FROM plans WHERE idempotency_key = ?1", params![key], |row| row.get(0))
+        .optional()?;
+    id.map(|id| get_plan_by_id(conn, &id)).transpose()
+}
+
 /// Resolve a plan reference: exact name → ULID prefix → name partial match.
 pub fn resolve_plan(conn: &Connection, reference: &str) -> Result<Plan, TaskaiError> {
     // 1. Exact name match
@@ -127,6 +135,14 @@ pub fn resolve_plan(conn: &Connection, reference: &str) -> Result<Plan, TaskaiEr
     }
 }
 
+/// All plans, optionally leaving out archived and soft-deleted ones.
+pub fn list_plans_filtered(conn: &Connection, include_hidden: bool) -> Result<Vec<Plan>, TaskaiError> {
+    Ok(list_plans(conn)?
+        .into_iter()
+        .filter(|p| include_hidden || !p.status.is_hidden())
+        .collect())
+}
+
 pub fn list_plans(conn: &Connection) -> Result<Vec<Plan>, TaskaiError> {
     let mut stmt = conn.prepare(
         "SELECT id, name, title, description, status, created_at, updated_at FROM plans ORDER BY created_at DESC",
@@ -155,6 +171,38 @@ pub fn delete_plan(conn: &Connection, id: &str) -> Result<(), TaskaiError> {
     Ok(())
 }
 
+pub fn rename_plan(conn: &Connection, id: &str, name: &str, title: Option<&str>) -> Result<Plan, TaskaiError> {
+    if let Some(existing) = find_plan_by_name(conn, name)? {
+        if existing.id != id {
+            return Err(TaskaiError::plan_name_conflict(name));
+        }
+    }
+
+    conn.execute(
+        "UPDATE plans SET name = ?1, title = COALESCE(?2, title), updated_at = datetime('now') WHERE id = ?3",
+        params![name, title, id],
+    )?;
+    get_plan_by_id(conn, id)
+}
+
+/// Update plan metadata. Only `Some` fields are written; an empty description clears it.
+pub fn update_plan_fields(
+    conn: &Connection,
+    id: &str,
+    title: Option<&str>,
+    description: Option<&str>,
+) -> Result<Plan, TaskaiError> {
+    conn.execute(
+        "UPDATE plans SET
+             title = COALESCE(?1, title),
+             description = CASE WHEN ?2 IS NULL THEN description ELSE NULLIF(?2, '') END,
+             updated_at = datetime('now')
+         WHERE id = ?3",
+        params![title, description, id],
+    )?;
+    get_plan_by_id(conn, id)
+}
+
 pub fn update_plan_status(conn: &Connection, id: &str, status: &PlanStatus) -> Result<(), TaskaiError> {
     conn.execute(
         "UPDATE plans SET status = ?1, updated_at = datetime('now') WHERE id = ?2",
@@ -0,0 +1,143 @@
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection};
+
+use crate::db::task_repo::{self, TaskProgress};
+use crate::error::TaskaiError;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AgentWorkload {
+    pub agent: String,
+    pub completed: i64,
+}
+
+/// Summary of a set of durations in minutes. Averages and percentiles are `None` for
+/// an empty sample.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize)]
+pub struct DurationStats {
+    pub sample_size: usize,
+    pub avg: Option<f64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+}
+
+impl DurationStats {
+    fn from_minutes(mut samples: Vec<f64>) -> Self {
+        samples.sort_by(f64::total_cmp);
+        let n = samples.len();
+        // Nearest-rank percentile
+        let percentile = |p: f64| (n > 0).then(|| samples[((p * n as f64).ceil() as usize).clamp(1, n) - 1]);
+        DurationStats {
+            sample_size: n,
+            avg: (n > 0).then(|| samples.iter().sum::<f64>() / n as f64),
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct PlanStats {
+    /// Task counts by status, with total and completion percentage
+    pub progress: TaskProgress,
+    /// Mean `completed_at - started_at` of done tasks that were started; `None` if there are none
+    pub avg_in_progress_minutes: Option<f64>,
+    /// Tasks with at least one `fail` (in_progress → ready/blocked) in their event log
+    pub failed_at_least_once: i64,
+    pub with_documents: i64,
+    /// Done tasks per `assigned_to`, busiest first
+    pub agent_workload: Vec<AgentWorkload>,
+    /// `completed_at - started_at` of done tasks that have both
+    pub cycle_time: DurationStats,
+    /// `started_at - created_at` of every task that has been started
+    pub wait_time: DurationStats,
+    /// Done tasks per hour between the first start and the last completion among them
+    pub throughput_per_hour: Option<f64>,
+}
+
+/// Aggregate statistics over a plan's tasks and their event history.
+pub fn plan_stats(conn: &Connection, plan_id: &str) -> Result<PlanStats, TaskaiError> {
+    let progress = task_repo::task_progress(conn, plan_id)?;
+
+    let avg_in_progress_minutes: Option<f64> = conn.query_row(
+        "SELECT AVG((julianday(completed_at) - julianday(started_at)) * 1440)
+         FROM tasks
+         WHERE plan_id = ?1 AND status = 'done'
+           AND started_at IS NOT NULL AND completed_at IS NOT NULL",
+        params![plan_id],
+        |row| row.get(0),
+    )?;
+
+    // Fails logged before `task fail` had its own event type are plain status changes
+    let failed_at_least_once: i64 = conn.query_row(
+        "SELECT COUNT(DISTINCT tt.task_id)
+         FROM task_transitions tt
+         JOIN tasks t ON t.id = tt.task_id
+         WHERE t.plan_id = ?1 AND tt.event_type IN ('status_change', 'fail')
+           AND tt.from_status = 'in_progress' AND tt.to_status IN ('ready', 'blocked')",
+        params![plan_id],
+        |row| row.get(0),
+    )?;
+
+    let with_documents: i64 = conn.query_row(
+        "SELECT COUNT(DISTINCT td.task_id)
+         FROM task_documents td
+         JOIN tasks t ON t.id = td.task_id
+         WHERE t.plan_id = ?1",
+        params![plan_id],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT assigned_to, COUNT(*) FROM tasks
+         WHERE plan_id = ?1 AND status = 'done' AND assigned_to IS NOT NULL
+         GROUP BY assigned_to
+         ORDER BY COUNT(*) DESC, assigned_to ASC",
+    )?;
+    let agent_workload = stmt
+        .query_map(params![plan_id], |row| {
+            Ok(AgentWorkload { agent: row.get(0)?, completed: row.get(1)? })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let timestamps = task_repo::task_timestamps(conn, plan_id)?;
+    let mut cycle = Vec::new();
+    let mut wait = Vec::new();
+    let mut first_start: Option<NaiveDateTime> = None;
+    let mut last_completion: Option<NaiveDateTime> = None;
+    for t in &timestamps {
+        let started = t.started_at.as_deref().and_then(parse_timestamp);
+        if let (Some(created), Some(started)) = (parse_timestamp(&t.created_at), started) {
+            wait.push(minutes_between(created, started));
+        }
+        let completed = t.completed_at.as_deref().and_then(parse_timestamp);
+        if let (true, Some(started), Some(completed)) = (t.done, started, completed) {
+            cycle.push(minutes_between(started, completed));
+            first_start = Some(first_start.map_or(started, |f| f.min(started)));
+            last_completion = last_completion.max(Some(completed));
+        }
+    }
+    let throughput_per_hour = match (first_start, last_completion) {
+        (Some(start), Some(end)) if end > start => Some(cycle.len() as f64 / (minutes_between(start, end) / 60.0)),
+        _ => None,
+    };
+
+    Ok(PlanStats {
+        progress,
+        avg_in_progress_minutes,
+        failed_at_least_once,
+        with_documents,
+        agent_workload,
+        cycle_time: DurationStats::from_minutes(cycle),
+        wait_time: DurationStats::from_minutes(wait),
+        throughput_per_hour,
+    })
+}
+
+/// Parse a `datetime('now')` timestamp (UTC, second resolution).
+fn parse_timestamp(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()
+}
+
+fn minutes_between(from: NaiveDateTime, to: NaiveDateTime) -> f64 {
+    (to - from).num_seconds() as f64 / 60.0
+}
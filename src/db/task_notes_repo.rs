@@ -0,0 +1,74 @@
+use rusqlite::{params, Connection};
+
+use crate::error::TaskaiError;
+use crate::models::TaskNote;
+
+pub fn add_note(
+    conn: &Connection,
+    id: &str,
+    task_id: &str,
+    content: &str,
+    agent: Option<&str>,
+) -> Result<TaskNote, TaskaiError> {
+    conn.execute(
+        "INSERT INTO task_notes (id, task_id, content, agent) VALUES (?1, ?2, ?3, ?4)",
+        params![id, task_id, content, agent],
+    )?;
+    conn.query_row(
+        "SELECT id, task_id, content, agent, created_at FROM task_notes WHERE id = ?1",
+        params![id],
+        row_to_note,
+    )
+    .map_err(TaskaiError::from)
+}
+
+/// Get all notes of a task, newest first.
+pub fn get_task_notes(conn: &Connection, task_id: &str) -> Result<Vec<TaskNote>, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, task_id, content, agent, created_at FROM task_notes
+         WHERE task_id = ?1
+         ORDER BY created_at DESC, rowid DESC",
+    )?;
+    let notes = stmt
+        .query_map(params![task_id], row_to_note)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+/// Resolve a note by ID prefix among the tasks of a plan.
+pub fn resolve_note(conn: &Connection, plan_id: &str, reference: &str) -> Result<TaskNote, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT n.id, n.task_id, n.content, n.agent, n.created_at
+         FROM task_notes n
+         JOIN tasks t ON n.task_id = t.id
+         WHERE t.plan_id = ?1 AND n.id LIKE ?2",
+    )?;
+    let prefix = format!("{reference}%");
+    let notes: Vec<TaskNote> = stmt
+        .query_map(params![plan_id, prefix], row_to_note)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match notes.len() {
+        0 => Err(TaskaiError::note_not_found(reference)),
+        1 => Ok(notes.into_iter().next().unwrap()),
+        _ => {
+            let candidates: Vec<String> = notes.iter().map(|n| n.id.clone()).collect();
+            Err(TaskaiError::ambiguous_ref(reference, &candidates))
+        }
+    }
+}
+
+pub fn delete_note(conn: &Connection, id: &str) -> Result<(), TaskaiError> {
+    conn.execute("DELETE FROM task_notes WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+fn row_to_note(row: &rusqlite::Row) -> rusqlite::Result<TaskNote> {
+    Ok(TaskNote {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        content: row.get(2)?,
+        agent: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
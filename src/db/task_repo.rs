@@ -1,8 +1,12 @@
-use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
 
-use crate::error::TaskaiError;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::db::transition_repo;
+use crate::error::{is_constraint_violation, TaskaiError};
 use crate::models::{Task, TaskStatus};
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_task(
     conn: &Connection,
     id: &str,
@@ -13,11 +17,12 @@ pub fn create_task(
     sort_order: i32,
     status: &TaskStatus,
     agent: Option<&str>,
+    estimated_minutes: Option<i64>,
 ) -> Result<Task, TaskaiError> {
     conn.execute(
-        "INSERT INTO tasks (id, plan_id, title, description, priority, sort_order, status, agent)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![id, plan_id, title, description, priority, sort_order, status.as_str(), agent],
+        "INSERT INTO tasks (id, plan_id, title, description, priority, sort_order, status, agent, estimated_minutes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![id, plan_id, title, description, priority, sort_order, status.as_str(), agent, estimated_minutes],
     )?;
     get_task_by_id(conn, id)
 }
@@ -25,7 +30,9 @@ pub fn create_task(
 pub fn get_task_by_id(conn: &Connection, id: &str) -> Result<Task, TaskaiError> {
     conn.query_row(
         "SELECT id, plan_id, title, description, status, priority, sort_order,
-                agent, assigned_to, created_at, updated_at, started_at, completed_at
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
          FROM tasks WHERE id = ?1",
         params![id],
         row_to_task,
@@ -48,7 +55,9 @@ pub fn resolve_task(conn: &Connection, plan_id: &str, reference: &str) -> Result
     // ID prefix match within plan
     let mut stmt = conn.prepare(
         "SELECT id, plan_id, title, description, status, priority, sort_order,
-                agent, assigned_to, created_at, updated_at, started_at, completed_at
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
          FROM tasks WHERE plan_id = ?1 AND id LIKE ?2",
     )?;
     let prefix = format!("{reference}%");
@@ -66,10 +75,40 @@ pub fn resolve_task(conn: &Connection, plan_id: &str, reference: &str) -> Result
     }
 }
 
+/// Like `resolve_task`, but matches IDs and prefixes in every plan.
+pub fn resolve_task_any_plan(conn: &Connection, reference: &str) -> Result<Task, TaskaiError> {
+    if let Ok(task) = get_task_by_id(conn, reference) {
+        return Ok(task);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, plan_id, title, description, status, priority, sort_order,
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
+         FROM tasks WHERE id LIKE ?1",
+    )?;
+    let prefix = format!("{reference}%");
+    let tasks: Vec<Task> = stmt
+        .query_map(params![prefix], row_to_task)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match tasks.len() {
+        0 => Err(TaskaiError::task_not_found(reference)),
+        1 => Ok(tasks.into_iter().next().unwrap()),
+        _ => {
+            let candidates: Vec<String> = tasks.iter().map(|t| format!("{} ({})", t.title, t.id)).collect();
+            Err(TaskaiError::ambiguous_ref(reference, &candidates))
+        }
+    }
+}
+
 pub fn list_tasks_by_plan(conn: &Connection, plan_id: &str) -> Result<Vec<Task>, TaskaiError> {
     let mut stmt = conn.prepare(
         "SELECT id, plan_id, title, description, status, priority, sort_order,
-                agent, assigned_to, created_at, updated_at, started_at, completed_at
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
          FROM tasks WHERE plan_id = ?1 ORDER BY sort_order ASC",
     )?;
     let tasks = stmt
@@ -78,21 +117,225 @@ pub fn list_tasks_by_plan(conn: &Connection, plan_id: &str) -> Result<Vec<Task>,
     Ok(tasks)
 }
 
+/// Optional restrictions for `list_tasks_by_plan_filtered`. Empty fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct TaskFilter {
+    pub statuses: Vec<TaskStatus>,
+    pub agent: Option<String>,
+    pub assigned_to: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl TaskFilter {
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty() && self.agent.is_none() && self.assigned_to.is_none() && self.tag.is_none()
+    }
+}
+
+/// Column a task list can be ordered by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskSortField {
+    Priority,
+    Status,
+    Title,
+    CreatedAt,
+    UpdatedAt,
+    SortOrder,
+}
+
+impl TaskSortField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Priority => "priority",
+            Self::Status => "status",
+            Self::Title => "title",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+            Self::SortOrder => "sort_order",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "priority" => Some(Self::Priority),
+            "status" => Some(Self::Status),
+            "title" => Some(Self::Title),
+            "created_at" => Some(Self::CreatedAt),
+            "updated_at" => Some(Self::UpdatedAt),
+            "sort_order" => Some(Self::SortOrder),
+            _ => None,
+        }
+    }
+
+    /// SQL expression for the ORDER BY clause. Status sorts in lifecycle order.
+    fn sql(&self) -> &'static str {
+        match self {
+            Self::Priority => "priority",
+            Self::Status => {
+                "CASE status WHEN 'blocked' THEN 0 WHEN 'ready' THEN 1 WHEN 'in_progress' THEN 2
+                 WHEN 'done' THEN 3 WHEN 'skipped' THEN 4 ELSE 5 END"
+            }
+            Self::Title => "title",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+            Self::SortOrder => "sort_order",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskSort {
+    pub field: TaskSortField,
+    pub descending: bool,
+}
+
+impl Default for TaskSort {
+    fn default() -> Self {
+        Self { field: TaskSortField::SortOrder, descending: false }
+    }
+}
+
+/// Queue order for `next`. Overdue tasks always come first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadyOrder {
+    /// Highest priority first, then plan order
+    #[default]
+    Priority,
+    /// Highest priority first, then oldest `created_at`, then plan order
+    Created,
+    /// Plan order only; priority is ignored
+    Sort,
+}
+
+impl ReadyOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Priority => "priority",
+            Self::Created => "created",
+            Self::Sort => "sort",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "priority" => Some(Self::Priority),
+            "created" => Some(Self::Created),
+            "sort" => Some(Self::Sort),
+            _ => None,
+        }
+    }
+
+    /// ORDER BY clause. `created_at` has one-second resolution, so tasks created in the
+    /// same second (e.g. by one `plan load`) still fall back to sort_order.
+    fn sql(&self) -> &'static str {
+        match self {
+            Self::Priority => {
+                "(deadline IS NOT NULL AND deadline < datetime('now')) DESC, priority DESC, sort_order ASC"
+            }
+            Self::Created => {
+                "(deadline IS NOT NULL AND deadline < datetime('now')) DESC, priority DESC,
+                 created_at ASC, sort_order ASC"
+            }
+            Self::Sort => "(deadline IS NOT NULL AND deadline < datetime('now')) DESC, sort_order ASC",
+        }
+    }
+}
+
+/// List tasks in a plan matching every condition in `filter`.
+pub fn list_tasks_by_plan_filtered(
+    conn: &Connection,
+    plan_id: &str,
+    filter: &TaskFilter,
+) -> Result<Vec<Task>, TaskaiError> {
+    list_tasks_by_plan_ordered(conn, plan_id, filter, &TaskSort::default())
+}
+
+/// List tasks in a plan matching `filter`, ordered by `sort` (ties broken by sort_order).
+pub fn list_tasks_by_plan_ordered(
+    conn: &Connection,
+    plan_id: &str,
+    filter: &TaskFilter,
+    sort: &TaskSort,
+) -> Result<Vec<Task>, TaskaiError> {
+    let status_strs: Vec<&str> = filter.statuses.iter().map(|s| s.as_str()).collect();
+    let mut clauses: Vec<String> = Vec::new();
+    let mut values: Vec<&dyn rusqlite::ToSql> = vec![&plan_id];
+
+    if !status_strs.is_empty() {
+        let placeholders: Vec<&str> = status_strs.iter().map(|_| "?").collect();
+        clauses.push(format!("AND status IN ({})", placeholders.join(", ")));
+        for s in &status_strs {
+            values.push(s);
+        }
+    }
+    if let Some(ref agent) = filter.agent {
+        clauses.push("AND agent = ?".to_string());
+        values.push(agent);
+    }
+    if let Some(ref assigned) = filter.assigned_to {
+        clauses.push("AND assigned_to = ?".to_string());
+        values.push(assigned);
+    }
+    if let Some(ref tag) = filter.tag {
+        clauses.push("AND id IN (SELECT task_id FROM task_tags WHERE tag = ?)".to_string());
+        values.push(tag);
+    }
+
+    let sql = format!(
+        "SELECT id, plan_id, title, description, status, priority, sort_order,
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
+         FROM tasks WHERE plan_id = ? {} ORDER BY {} {}, sort_order ASC",
+        clauses.join(" "),
+        sort.field.sql(),
+        if sort.descending { "DESC" } else { "ASC" }
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let tasks = stmt
+        .query_map(values.as_slice(), row_to_task)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
+/// Find tasks in a plan whose title or description contains `query`.
+pub fn search_tasks(conn: &Connection, plan_id: &str, query: &str) -> Result<Vec<Task>, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, plan_id, title, description, status, priority, sort_order,
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
+         FROM tasks
+         WHERE plan_id = ?1 AND (title LIKE ?2 OR description LIKE ?2)
+         ORDER BY priority DESC, sort_order ASC",
+    )?;
+    let pattern = format!("%{query}%");
+    let tasks = stmt
+        .query_map(params![plan_id, pattern], row_to_task)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
 pub fn update_task_status(
     conn: &Connection,
     id: &str,
     status: &TaskStatus,
     assigned_to: Option<&str>,
 ) -> Result<(), TaskaiError> {
+    log_status_change(conn, id, "status_change", status, assigned_to)?;
     let (started_clause, completed_clause) = match status {
-        TaskStatus::InProgress => ("started_at = datetime('now'),", ""),
-        TaskStatus::Done => ("", "completed_at = datetime('now'),"),
+        TaskStatus::InProgress => {
+            ("started_at = datetime('now'), last_heartbeat_at = NULL, failure_reason = NULL,", "")
+        }
+        TaskStatus::Done => ("", "completed_at = datetime('now'), deadline = NULL,"),
         _ => ("", ""),
     };
 
     let sql = format!(
         "UPDATE tasks SET status = ?1, {started_clause} {completed_clause}
-         assigned_to = COALESCE(?2, assigned_to),
+         assigned_to = COALESCE(?2, assigned_to), claim_expires_at = NULL,
          updated_at = datetime('now')
          WHERE id = ?3"
     );
@@ -100,28 +343,443 @@ pub fn update_task_status(
     Ok(())
 }
 
-/// Get the next ready task for a plan (highest priority, lowest sort_order).
-pub fn next_ready_task(conn: &Connection, plan_id: &str) -> Result<Option<Task>, TaskaiError> {
+/// `update_task_status`, also storing `note` as the task's `completion_note`.
+pub fn update_task_status_with_note(
+    conn: &Connection,
+    id: &str,
+    status: &TaskStatus,
+    assigned_to: Option<&str>,
+    note: Option<&str>,
+) -> Result<(), TaskaiError> {
+    update_task_status(conn, id, status, assigned_to)?;
+    conn.execute("UPDATE tasks SET completion_note = ?1 WHERE id = ?2", params![note, id])?;
+    Ok(())
+}
+
+/// Update editable task fields. Only `Some` fields are written.
+pub fn update_task_fields(
+    conn: &Connection,
+    id: &str,
+    title: Option<&str>,
+    description: Option<&str>,
+    priority: Option<i32>,
+    agent: Option<&str>,
+) -> Result<(), TaskaiError> {
+    let mut sets: Vec<&str> = Vec::new();
+    let mut values: Vec<&dyn rusqlite::ToSql> = Vec::new();
+    if let Some(ref t) = title {
+        sets.push("title = ?");
+        values.push(t);
+    }
+    if let Some(ref d) = description {
+        sets.push("description = ?");
+        values.push(d);
+    }
+    if let Some(ref p) = priority {
+        sets.push("priority = ?");
+        values.push(p);
+    }
+    if let Some(ref a) = agent {
+        sets.push("agent = ?");
+        values.push(a);
+    }
+    if sets.is_empty() {
+        return Ok(());
+    }
+    values.push(&id);
+
+    let sql = format!(
+        "UPDATE tasks SET {}, updated_at = datetime('now') WHERE id = ?",
+        sets.join(", ")
+    );
+    conn.execute(&sql, values.as_slice())?;
+    Ok(())
+}
+
+/// Delete a task. Dependency edges and documents are removed by cascade.
+pub fn delete_task(conn: &Connection, id: &str) -> Result<(), TaskaiError> {
+    let changed = conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
+    if changed == 0 {
+        return Err(TaskaiError::task_not_found(id));
+    }
+    Ok(())
+}
+
+/// Move a task to index `new_position` in the plan's order and renumber
+/// (within an existing transaction). Returns the tasks whose sort_order changed.
+pub fn reorder_task(
+    conn: &Connection,
+    plan_id: &str,
+    task_id: &str,
+    new_position: usize,
+) -> Result<Vec<Task>, TaskaiError> {
+    let before = list_tasks_by_plan(conn, plan_id)?;
+
+    let mut ordered: Vec<String> = before
+        .iter()
+        .filter(|t| t.id != task_id)
+        .map(|t| t.id.clone())
+        .collect();
+    let index = new_position.min(ordered.len());
+    ordered.insert(index, task_id.to_string());
+    renumber_sort_order(conn, &ordered)?;
+
+    let after = list_tasks_by_plan(conn, plan_id)?;
+    let changed = after
+        .into_iter()
+        .filter(|t| {
+            before
+                .iter()
+                .any(|b| b.id == t.id && b.sort_order != t.sort_order)
+        })
+        .collect();
+    Ok(changed)
+}
+
+/// Reassign contiguous sort_order values (0, 1, 2, ...) following `ordered_ids`.
+pub fn renumber_sort_order(conn: &Connection, ordered_ids: &[String]) -> Result<(), TaskaiError> {
+    for (i, id) in ordered_ids.iter().enumerate() {
+        conn.execute(
+            "UPDATE tasks SET sort_order = ?1, updated_at = datetime('now')
+             WHERE id = ?2 AND sort_order != ?1",
+            params![i as i32, id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Reopen a terminal task: set `status` and clear `completed_at`.
+pub fn reopen_task(conn: &Connection, id: &str, status: &TaskStatus) -> Result<(), TaskaiError> {
+    log_status_change(conn, id, "reset", status, None)?;
+    conn.execute(
+        "UPDATE tasks SET status = ?1, started_at = NULL, completed_at = NULL, assigned_to = NULL,
+             completion_note = NULL, updated_at = datetime('now') WHERE id = ?2",
+        params![status.as_str(), id],
+    )?;
+    Ok(())
+}
+
+/// `task fail`: put an in_progress task back into the queue as `status`, remembering why
+/// it failed (until it is started again) and counting the failure.
+pub fn fail_task(conn: &Connection, id: &str, status: &TaskStatus, reason: Option<&str>) -> Result<(), TaskaiError> {
+    log_status_change(conn, id, "fail", status, None)?;
+    conn.execute(
+        "UPDATE tasks SET status = ?1, failure_reason = ?2, fail_count = fail_count + 1,
+             claim_expires_at = NULL, updated_at = datetime('now') WHERE id = ?3",
+        params![status.as_str(), reason, id],
+    )?;
+    Ok(())
+}
+
+/// Put an in_progress task back into the queue and drop its claim and start time.
+pub fn release_task(conn: &Connection, id: &str, status: &TaskStatus) -> Result<(), TaskaiError> {
+    log_status_change(conn, id, "reclaim", status, None)?;
+    conn.execute(
+        "UPDATE tasks SET status = ?1, assigned_to = NULL, started_at = NULL, last_heartbeat_at = NULL,
+             claim_expires_at = NULL, updated_at = datetime('now') WHERE id = ?2",
+        params![status.as_str(), id],
+    )?;
+    Ok(())
+}
+
+/// Hand an in_progress task back unworked: drop the claim and its start time.
+pub fn unclaim_task(conn: &Connection, id: &str, status: &TaskStatus) -> Result<(), TaskaiError> {
+    log_status_change(conn, id, "unassign", status, None)?;
+    conn.execute(
+        "UPDATE tasks SET status = ?1, assigned_to = NULL, started_at = NULL, last_heartbeat_at = NULL,
+             claim_expires_at = NULL, updated_at = datetime('now') WHERE id = ?2",
+        params![status.as_str(), id],
+    )?;
+    Ok(())
+}
+
+/// Make the current claim on `id` lapse `minutes` from now (see `release_expired_claims`).
+pub fn set_claim_expiry(conn: &Connection, id: &str, minutes: i64) -> Result<(), TaskaiError> {
+    conn.execute(
+        "UPDATE tasks SET claim_expires_at = datetime('now', ?1) WHERE id = ?2",
+        params![format!("+{minutes} minutes"), id],
+    )?;
+    Ok(())
+}
+
+/// Heartbeat that also pushes the claim expiry `extend_minutes` from now. Only applies to
+/// an in_progress task whose claim has not expired; returns false if nothing was renewed.
+pub fn renew_claim(conn: &Connection, task_id: &str, extend_minutes: i64) -> Result<bool, TaskaiError> {
+    let renewed = conn.execute(
+        "UPDATE tasks SET claim_expires_at = datetime('now', ?1), last_heartbeat_at = datetime('now')
+         WHERE id = ?2 AND status = 'in_progress'
+           AND (claim_expires_at IS NULL OR claim_expires_at >= datetime('now'))",
+        params![format!("+{extend_minutes} minutes"), task_id],
+    )?;
+    Ok(renewed > 0)
+}
+
+/// Put in_progress tasks whose claim expired back in the queue (within an existing
+/// transaction): ready, or blocked if a dependency is no longer done. The claim and its
+/// start time are dropped, as with `unclaim_task`. Returns the released tasks.
+pub fn release_expired_claims(conn: &Connection, plan_id: &str) -> Result<Vec<Task>, TaskaiError> {
     let mut stmt = conn.prepare(
-        "SELECT id, plan_id, title, description, status, priority, sort_order,
-                agent, assigned_to, created_at, updated_at, started_at, completed_at
+        "SELECT id,
+                EXISTS (SELECT 1 FROM task_dependencies d JOIN tasks dep ON dep.id = d.dependency_id
+                        WHERE d.task_id = tasks.id AND dep.status != 'done')
          FROM tasks
-         WHERE plan_id = ?1 AND status = 'ready'
-         ORDER BY priority DESC, sort_order ASC
-         LIMIT 1",
+         WHERE plan_id = ?1 AND status = 'in_progress'
+           AND claim_expires_at IS NOT NULL AND claim_expires_at < datetime('now')
+         ORDER BY claim_expires_at ASC",
+    )?;
+    let expired = stmt
+        .query_map(params![plan_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut released = Vec::new();
+    for (id, blocked) in expired {
+        let status = if blocked { TaskStatus::Blocked } else { TaskStatus::Ready };
+        log_status_change(conn, &id, "expire", &status, None)?;
+        conn.execute(
+            "UPDATE tasks SET status = ?1, assigned_to = NULL, started_at = NULL, last_heartbeat_at = NULL,
+                 claim_expires_at = NULL, updated_at = datetime('now') WHERE id = ?2",
+            params![status.as_str(), id],
+        )?;
+        released.push(get_task_by_id(conn, &id)?);
+    }
+    Ok(released)
+}
+
+/// Whether any in_progress task of the plan holds an expired claim. Read-only.
+pub fn has_expired_claims(conn: &Connection, plan_id: &str) -> Result<bool, TaskaiError> {
+    let found = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM tasks WHERE plan_id = ?1 AND status = 'in_progress'
+                        AND claim_expires_at IS NOT NULL AND claim_expires_at < datetime('now'))",
+        params![plan_id],
+        |row| row.get(0),
+    )?;
+    Ok(found)
+}
+
+/// Move a task to another plan at the given position. Documents, notes and tags follow
+/// the task; dependencies must be cleaned up by the caller.
+pub fn move_task(conn: &Connection, id: &str, plan_id: &str, sort_order: i32) -> Result<(), TaskaiError> {
+    conn.execute(
+        "UPDATE tasks SET plan_id = ?1, sort_order = ?2, updated_at = datetime('now') WHERE id = ?3",
+        params![plan_id, sort_order, id],
+    )?;
+    Ok(())
+}
+
+/// Remember the `plan load` temp id a task was created from, so `plan load --merge`
+/// can match it up again.
+pub fn set_external_id(conn: &Connection, id: &str, external_id: &str) -> Result<(), TaskaiError> {
+    conn.execute(
+        "UPDATE tasks SET external_id = ?1 WHERE id = ?2",
+        params![external_id, id],
     )?;
-    let mut rows = stmt.query(params![plan_id])?;
+    Ok(())
+}
+
+/// Task in `plan_id` added with idempotency key `key`, if any.
+pub fn find_task_by_idempotency_key(conn: &Connection, plan_id: &str, key: &str) -> Result<Option<Task>, TaskaiError> {
+    let id: Option<String> = conn
+        .query_row(
+            "SELECT id FROM tasks WHERE plan_id = ?1 AND idempotency_key = ?2",
+            params![plan_id, key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    id.map(|id| get_task_by_id(conn, &id)).transpose()
+}
+
+/// Record `key` on the task. Returns false (writing nothing) when another task of the
+/// same plan already has it.
+pub fn set_task_idempotency_key(conn: &Connection, id: &str, key: &str) -> Result<bool, TaskaiError> {
+    match conn.execute("UPDATE tasks SET idempotency_key = ?1 WHERE id = ?2", params![key, id]) {
+        Ok(_) => Ok(true),
+        Err(e) if is_constraint_violation(&e) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Task in `plan_id` created from the `plan load` temp id `external_id`, if any.
+pub fn find_task_id_by_external_id(
+    conn: &Connection,
+    plan_id: &str,
+    external_id: &str,
+) -> Result<Option<String>, TaskaiError> {
+    let mut stmt = conn.prepare("SELECT id FROM tasks WHERE plan_id = ?1 AND external_id = ?2")?;
+    let mut rows = stmt.query(params![plan_id, external_id])?;
     match rows.next()? {
-        Some(row) => Ok(Some(row_to_task(row)?)),
+        Some(row) => Ok(Some(row.get(0)?)),
         None => Ok(None),
     }
 }
 
+/// Record a liveness heartbeat for an in_progress task.
+pub fn touch_heartbeat(conn: &Connection, id: &str) -> Result<(), TaskaiError> {
+    conn.execute(
+        "UPDATE tasks SET last_heartbeat_at = datetime('now') WHERE id = ?1",
+        params![id],
+    )?;
+    Ok(())
+}
+
+/// Set or clear `assigned_to` without touching the status.
+pub fn assign_task(conn: &Connection, id: &str, agent: Option<&str>) -> Result<(), TaskaiError> {
+    conn.execute(
+        "UPDATE tasks SET assigned_to = ?1, updated_at = datetime('now') WHERE id = ?2",
+        params![agent, id],
+    )?;
+    Ok(())
+}
+
+/// Record the pending status change of a task in the transition log.
+/// Must run before the UPDATE so the current status is still readable.
+fn log_status_change(
+    conn: &Connection,
+    id: &str,
+    event_type: &str,
+    status: &TaskStatus,
+    agent: Option<&str>,
+) -> Result<(), TaskaiError> {
+    let (from, assigned_to): (String, Option<String>) = conn.query_row(
+        "SELECT status, assigned_to FROM tasks WHERE id = ?1",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    crate::verbose!("task {id}: {from} -> {} ({event_type})", status.as_str());
+    if from == status.as_str() {
+        return Ok(());
+    }
+    let agent = agent.or(assigned_to.as_deref());
+    transition_repo::record_transition(conn, id, event_type, &from, status.as_str(), agent)
+}
+
+pub fn set_task_estimate(conn: &Connection, id: &str, minutes: Option<i64>) -> Result<(), TaskaiError> {
+    conn.execute(
+        "UPDATE tasks SET estimated_minutes = ?1, updated_at = datetime('now') WHERE id = ?2",
+        params![minutes, id],
+    )?;
+    Ok(())
+}
+
+/// Set or clear a task's deadline (`YYYY-MM-DD HH:MM:SS`, UTC).
+pub fn set_task_deadline(conn: &Connection, id: &str, deadline: Option<&str>) -> Result<(), TaskaiError> {
+    conn.execute(
+        "UPDATE tasks SET deadline = ?1, updated_at = datetime('now') WHERE id = ?2",
+        params![deadline, id],
+    )?;
+    Ok(())
+}
+
+/// Get the next ready task for a plan in `order` (by default: overdue first, then
+/// highest priority, lowest sort_order).
+pub fn next_ready_task(conn: &Connection, plan_id: &str, order: ReadyOrder) -> Result<Option<Task>, TaskaiError> {
+    Ok(next_n_ready_tasks(conn, plan_id, 1, order)?.into_iter().next())
+}
+
+/// The first `n` ready tasks in `next_ready_task` order.
+pub fn next_n_ready_tasks(
+    conn: &Connection,
+    plan_id: &str,
+    n: usize,
+    order: ReadyOrder,
+) -> Result<Vec<Task>, TaskaiError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, plan_id, title, description, status, priority, sort_order,
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
+         FROM tasks
+         WHERE plan_id = ?1 AND status = 'ready'
+         ORDER BY {}
+         LIMIT ?2",
+        order.sql()
+    ))?;
+    let tasks = stmt
+        .query_map(params![plan_id, n as i64], row_to_task)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
+/// Like `next_ready_task`, optionally restricted to tasks pre-assigned to `agent`
+/// (tasks without an agent also match unless `strict` is set), to tasks carrying `tag`
+/// and to tasks with `priority >= min_priority`.
+#[allow(clippy::too_many_arguments)]
+pub fn next_ready_task_matching(
+    conn: &Connection,
+    plan_id: &str,
+    agent: Option<&str>,
+    strict: bool,
+    tag: Option<&str>,
+    min_priority: Option<i32>,
+    order: ReadyOrder,
+) -> Result<Option<Task>, TaskaiError> {
+    Ok(next_n_ready_tasks_matching(conn, plan_id, 1, agent, strict, tag, min_priority, order)?.into_iter().next())
+}
+
+/// The first `n` ready tasks in `next_ready_task_matching` order.
+#[allow(clippy::too_many_arguments)]
+pub fn next_n_ready_tasks_matching(
+    conn: &Connection,
+    plan_id: &str,
+    n: usize,
+    agent: Option<&str>,
+    strict: bool,
+    tag: Option<&str>,
+    min_priority: Option<i32>,
+    order: ReadyOrder,
+) -> Result<Vec<Task>, TaskaiError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, plan_id, title, description, status, priority, sort_order,
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
+         FROM tasks
+         WHERE plan_id = ?1 AND status = 'ready'
+           AND (?2 IS NULL OR agent = ?2 OR (?3 = 0 AND agent IS NULL))
+           AND (?4 IS NULL OR id IN (SELECT task_id FROM task_tags WHERE tag = ?4))
+           AND (?5 IS NULL OR priority >= ?5)
+         ORDER BY {}
+         LIMIT ?6",
+        order.sql()
+    ))?;
+    let tasks = stmt
+        .query_map(params![plan_id, agent, strict, tag, min_priority, n as i64], row_to_task)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
+/// IDs of in_progress tasks (any plan) that have no `started_at`.
+pub fn in_progress_without_start(conn: &Connection) -> Result<Vec<String>, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM tasks WHERE status = 'in_progress' AND started_at IS NULL ORDER BY id",
+    )?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ids)
+}
+
+/// Tasks of other plans that tasks of `plan_id` depend on (`--allow-cross-plan`), by id.
+pub fn external_dependencies(conn: &Connection, plan_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT d.id
+         FROM task_dependencies td
+         JOIN tasks t ON td.task_id = t.id
+         JOIN tasks d ON td.dependency_id = d.id
+         WHERE t.plan_id = ?1 AND d.plan_id != ?1
+         ORDER BY d.id",
+    )?;
+    let ids = stmt
+        .query_map(params![plan_id], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    ids.iter().map(|id| get_task_by_id(conn, id)).collect()
+}
+
 /// Get all in_progress tasks for a plan.
 pub fn in_progress_tasks(conn: &Connection, plan_id: &str) -> Result<Vec<Task>, TaskaiError> {
     let mut stmt = conn.prepare(
         "SELECT id, plan_id, title, description, status, priority, sort_order,
-                agent, assigned_to, created_at, updated_at, started_at, completed_at
+                agent, assigned_to, created_at, updated_at, started_at, completed_at, estimated_minutes,
+                deadline, last_heartbeat_at, (SELECT group_concat(tag) FROM task_tags WHERE task_id = tasks.id),
+                claim_expires_at, failure_reason, fail_count, completion_note
          FROM tasks
          WHERE plan_id = ?1 AND status = 'in_progress'
          ORDER BY started_at ASC",
@@ -132,6 +790,33 @@ pub fn in_progress_tasks(conn: &Connection, plan_id: &str) -> Result<Vec<Task>,
     Ok(tasks)
 }
 
+/// Lifecycle timestamps of one task, for `plan stats` durations.
+#[derive(Debug, Clone)]
+pub struct TaskTimestamps {
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub done: bool,
+}
+
+/// Creation, start and completion times of every task in a plan.
+pub fn task_timestamps(conn: &Connection, plan_id: &str) -> Result<Vec<TaskTimestamps>, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT created_at, started_at, completed_at, status = 'done' FROM tasks WHERE plan_id = ?1",
+    )?;
+    let rows = stmt
+        .query_map(params![plan_id], |row| {
+            Ok(TaskTimestamps {
+                created_at: row.get(0)?,
+                started_at: row.get(1)?,
+                completed_at: row.get(2)?,
+                done: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
 /// Get task status counts for a plan.
 pub fn task_progress(conn: &Connection, plan_id: &str) -> Result<TaskProgress, TaskaiError> {
     let mut stmt = conn.prepare(
@@ -143,26 +828,52 @@ pub fn task_progress(conn: &Connection, plan_id: &str) -> Result<TaskProgress, T
     })?;
     for row in rows {
         let (status, count) = row?;
-        match status.as_str() {
-            "blocked" => progress.blocked = count,
-            "ready" => progress.ready = count,
-            "in_progress" => progress.in_progress = count,
-            "done" => progress.done = count,
-            "cancelled" => progress.cancelled = count,
-            "skipped" => progress.skipped = count,
-            _ => {}
-        }
+        progress.add(&status, count);
     }
-    progress.total = progress.blocked + progress.ready + progress.in_progress
-        + progress.done + progress.cancelled + progress.skipped;
-    progress.percentage = if progress.total > 0 {
-        (progress.done as f64 / progress.total as f64) * 100.0
-    } else {
-        0.0
-    };
     Ok(progress)
 }
 
+/// Task counts and in-progress time for one agent.
+#[derive(Debug, Default, Clone)]
+pub struct AgentLoad {
+    pub progress: TaskProgress,
+    /// Sum of elapsed minutes over the agent's in_progress tasks
+    pub in_progress_minutes: i64,
+}
+
+/// Tasks of a plan grouped per agent, keyed by name; tasks without one are under `unassigned`.
+#[derive(Debug, Default, Clone)]
+pub struct AgentWorkload {
+    /// Grouped by who claimed the task (`assigned_to`)
+    pub by_assigned_to: BTreeMap<String, AgentLoad>,
+    /// Grouped by the pre-assigned `agent` field
+    pub by_agent: BTreeMap<String, AgentLoad>,
+}
+
+/// Per-agent status counts for a plan, both by `assigned_to` and by `agent`.
+pub fn agent_workload(conn: &Connection, plan_id: &str) -> Result<AgentWorkload, TaskaiError> {
+    let mut workload = AgentWorkload::default();
+    for (column, groups) in [("assigned_to", &mut workload.by_assigned_to), ("agent", &mut workload.by_agent)] {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT COALESCE({column}, 'unassigned'), status, COUNT(*),
+                    SUM(CASE WHEN status = 'in_progress' AND started_at IS NOT NULL
+                        THEN CAST((julianday('now') - julianday(started_at)) * 1440 AS INTEGER) ELSE 0 END)
+             FROM tasks WHERE plan_id = ?1
+             GROUP BY 1, 2"
+        ))?;
+        let rows = stmt.query_map(params![plan_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+        })?;
+        for row in rows {
+            let (name, status, count, minutes) = row?;
+            let load = groups.entry(name).or_default();
+            load.progress.add(&status, count);
+            load.in_progress_minutes += minutes;
+        }
+    }
+    Ok(workload)
+}
+
 /// Check if task has any documents.
 pub fn task_has_documents(conn: &Connection, task_id: &str) -> Result<bool, TaskaiError> {
     let count: i64 = conn.query_row(
@@ -185,6 +896,23 @@ pub struct TaskProgress {
     pub percentage: f64,
 }
 
+impl TaskProgress {
+    /// Count `count` more tasks with `status`, keeping total and percentage current.
+    fn add(&mut self, status: &str, count: i64) {
+        match status {
+            "blocked" => self.blocked += count,
+            "ready" => self.ready += count,
+            "in_progress" => self.in_progress += count,
+            "done" => self.done += count,
+            "cancelled" => self.cancelled += count,
+            "skipped" => self.skipped += count,
+            _ => return,
+        }
+        self.total += count;
+        self.percentage = (self.done as f64 / self.total as f64) * 100.0;
+    }
+}
+
 fn row_to_task(row: &rusqlite::Row) -> rusqlite::Result<Task> {
     Ok(Task {
         id: row.get(0)?,
@@ -200,5 +928,21 @@ fn row_to_task(row: &rusqlite::Row) -> rusqlite::Result<Task> {
         updated_at: row.get(10)?,
         started_at: row.get(11)?,
         completed_at: row.get(12)?,
+        estimated_minutes: row.get(13)?,
+        deadline: row.get(14)?,
+        last_heartbeat_at: row.get(15)?,
+        tags: split_tags(row.get::<_, Option<String>>(16)?),
+        claim_expires_at: row.get(17)?,
+        failure_reason: row.get(18)?,
+        fail_count: row.get(19)?,
+        completion_note: row.get(20)?,
     })
 }
+
+fn split_tags(concat: Option<String>) -> Vec<String> {
+    let mut tags: Vec<String> = concat
+        .map(|s| s.split(',').map(|t| t.to_string()).collect())
+        .unwrap_or_default();
+    tags.sort();
+    tags
+}
@@ -0,0 +1,32 @@
+use rusqlite::{params, Connection};
+
+use crate::error::TaskaiError;
+
+/// Add a tag to a task. Adding an existing tag is a no-op.
+pub fn add_tag(conn: &Connection, task_id: &str, tag: &str) -> Result<(), TaskaiError> {
+    conn.execute(
+        "INSERT OR IGNORE INTO task_tags (task_id, tag) VALUES (?1, ?2)",
+        params![task_id, tag],
+    )?;
+    Ok(())
+}
+
+/// Remove a tag from a task. Returns false if the task did not have it.
+pub fn remove_tag(conn: &Connection, task_id: &str, tag: &str) -> Result<bool, TaskaiError> {
+    let changed = conn.execute(
+        "DELETE FROM task_tags WHERE task_id = ?1 AND tag = ?2",
+        params![task_id, tag],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Get the tags of a task, sorted by name.
+pub fn get_task_tags(conn: &Connection, task_id: &str) -> Result<Vec<String>, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT tag FROM task_tags WHERE task_id = ?1 ORDER BY tag ASC",
+    )?;
+    let tags = stmt
+        .query_map(params![task_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(tags)
+}
@@ -0,0 +1,58 @@
+use rusqlite::{params, Connection};
+
+use crate::error::TaskaiError;
+use crate::models::TaskTransition;
+
+/// Append a status change to the audit log. Call on the same connection (and transaction)
+/// as the status update so both commit or roll back together.
+pub fn record_transition(
+    conn: &Connection,
+    task_id: &str,
+    event_type: &str,
+    from_status: &str,
+    to_status: &str,
+    agent: Option<&str>,
+) -> Result<(), TaskaiError> {
+    conn.execute(
+        "INSERT INTO task_transitions (task_id, event_type, from_status, to_status, agent)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![task_id, event_type, from_status, to_status, agent],
+    )?;
+    Ok(())
+}
+
+/// Get the transitions of a task in chronological order, optionally only those at or after `since`.
+pub fn get_task_transitions(
+    conn: &Connection,
+    task_id: &str,
+    since: Option<&str>,
+) -> Result<Vec<TaskTransition>, TaskaiError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, task_id, event_type, from_status, to_status, agent, at FROM task_transitions
+         WHERE task_id = ?1 AND (?2 IS NULL OR at >= ?2)
+         ORDER BY id ASC",
+    )?;
+    let transitions = stmt
+        .query_map(params![task_id, since], |row| {
+            Ok(TaskTransition {
+                id: row.get(0)?,
+                task_id: row.get(1)?,
+                event_type: row.get(2)?,
+                from_status: row.get(3)?,
+                to_status: row.get(4)?,
+                agent: row.get(5)?,
+                at: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(transitions)
+}
+
+pub fn count_task_transitions(conn: &Connection, task_id: &str) -> Result<i64, TaskaiError> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM task_transitions WHERE task_id = ?1",
+        params![task_id],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
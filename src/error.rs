@@ -6,6 +6,8 @@ pub enum ErrorCode {
     NoActivePlan,
     PlanNotFound,
     TaskNotFound,
+    NoteNotFound,
+    DocumentNotFound,
     AmbiguousRef,
     TaskBlocked,
     CycleDetected,
@@ -13,7 +15,9 @@ pub enum ErrorCode {
     CrossPlanDependency,
     PlanNameConflict,
     ValidationError,
+    LockTimeout,
     DatabaseError,
+    InternalError,
 }
 
 impl ErrorCode {
@@ -23,6 +27,8 @@ impl ErrorCode {
             Self::NoActivePlan => "NO_ACTIVE_PLAN",
             Self::PlanNotFound => "PLAN_NOT_FOUND",
             Self::TaskNotFound => "TASK_NOT_FOUND",
+            Self::NoteNotFound => "NOTE_NOT_FOUND",
+            Self::DocumentNotFound => "DOCUMENT_NOT_FOUND",
             Self::AmbiguousRef => "AMBIGUOUS_REF",
             Self::TaskBlocked => "TASK_BLOCKED",
             Self::CycleDetected => "CYCLE_DETECTED",
@@ -30,7 +36,9 @@ impl ErrorCode {
             Self::CrossPlanDependency => "CROSS_PLAN_DEPENDENCY",
             Self::PlanNameConflict => "PLAN_NAME_CONFLICT",
             Self::ValidationError => "VALIDATION_ERROR",
+            Self::LockTimeout => "LOCK_TIMEOUT",
             Self::DatabaseError => "DATABASE_ERROR",
+            Self::InternalError => "INTERNAL_ERROR",
         }
     }
 }
@@ -40,6 +48,8 @@ impl ErrorCode {
 pub struct TaskaiError {
     pub code: ErrorCode,
     pub message: String,
+    /// Individual problems when one error reports several (e.g. plan load validation)
+    pub errors: Vec<String>,
 }
 
 impl TaskaiError {
@@ -47,13 +57,14 @@ impl TaskaiError {
         Self {
             code,
             message: message.into(),
+            errors: Vec::new(),
         }
     }
 
-    pub fn not_initialized() -> Self {
+    pub fn not_initialized(db_path: &std::path::Path) -> Self {
         Self::new(
             ErrorCode::NotInitialized,
-            "taskai is not initialized. Run `taskai init` first.",
+            format!("taskai is not initialized (no database at {}). Run `taskai init` first.", db_path.display()),
         )
     }
 
@@ -78,6 +89,20 @@ impl TaskaiError {
         )
     }
 
+    pub fn note_not_found(reference: &str) -> Self {
+        Self::new(
+            ErrorCode::NoteNotFound,
+            format!("Note not found: {reference}"),
+        )
+    }
+
+    pub fn document_not_found(id: &str) -> Self {
+        Self::new(
+            ErrorCode::DocumentNotFound,
+            format!("Document not found: {id}"),
+        )
+    }
+
     pub fn ambiguous_ref(reference: &str, candidates: &[String]) -> Self {
         Self::new(
             ErrorCode::AmbiguousRef,
@@ -121,13 +146,39 @@ impl TaskaiError {
         )
     }
 
+    pub fn lock_timeout(lock_path: &std::path::Path, waited: std::time::Duration) -> Self {
+        Self::new(
+            ErrorCode::LockTimeout,
+            format!("Timed out after {}ms waiting for the write lock ({})", waited.as_millis(), lock_path.display()),
+        )
+    }
+
     pub fn validation(message: impl Into<String>) -> Self {
         Self::new(ErrorCode::ValidationError, message)
     }
 
+    pub fn validation_errors(errors: Vec<String>) -> Self {
+        let mut err = Self::new(
+            ErrorCode::ValidationError,
+            format!("{} validation error(s): {}", errors.len(), errors.join("; ")),
+        );
+        err.errors = errors;
+        err
+    }
+
     pub fn database(message: impl Into<String>) -> Self {
         Self::new(ErrorCode::DatabaseError, message)
     }
+
+    /// A failure of the process environment rather than of the data or the request.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InternalError, message)
+    }
+}
+
+/// Whether `e` is SQLite refusing a write because of a UNIQUE (or other) constraint.
+pub fn is_constraint_violation(e: &rusqlite::Error) -> bool {
+    matches!(e, rusqlite::Error::SqliteFailure(f, _) if f.code == rusqlite::ErrorCode::ConstraintViolation)
 }
 
 impl From<rusqlite::Error> for TaskaiError {
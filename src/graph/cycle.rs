@@ -28,10 +28,8 @@ pub fn detect_cycle(nodes: &[String], edges: &[(String, String)]) -> Result<(),
     }
 
     for node in adj.keys() {
-        if color[node] == 0 {
-            if has_cycle_dfs(node, &adj, &mut color) {
-                return Err(TaskaiError::cycle_detected());
-            }
+        if color[node] == 0 && has_cycle_dfs(node, &adj, &mut color) {
+            return Err(TaskaiError::cycle_detected());
         }
     }
     Ok(())
@@ -45,13 +43,9 @@ fn has_cycle_dfs<'a>(
     color.insert(node, 1); // gray
     if let Some(neighbors) = adj.get(node) {
         for &neighbor in neighbors {
-            match color.get(neighbor) {
+            match color.get(neighbor).copied() {
                 Some(1) => return true,  // back edge = cycle
-                Some(0) | None => {
-                    if has_cycle_dfs(neighbor, adj, color) {
-                        return true;
-                    }
-                }
+                Some(0) | None if has_cycle_dfs(neighbor, adj, color) => return true,
                 _ => {} // black, already processed
             }
         }
@@ -60,6 +54,43 @@ fn has_cycle_dfs<'a>(
     false
 }
 
+/// Order `nodes` so every dependency comes before the tasks that depend on it.
+/// Same DFS 3-color walk as `detect_cycle`, emitting nodes in post-order; ties keep
+/// the order of `nodes`. Errs with CYCLE_DETECTED if the graph is not a DAG.
+pub fn topological_sort(nodes: &[String], edges: &[(String, String)]) -> Result<Vec<String>, TaskaiError> {
+    let mut adj: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (task_id, dep_id) in edges {
+        adj.entry(task_id.as_str()).or_default().push(dep_id.as_str());
+    }
+    let mut color: HashMap<&str, u8> = HashMap::new();
+    let mut order = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        if color.get(node.as_str()).copied().unwrap_or(0) == 0 {
+            topo_dfs(node, &adj, &mut color, &mut order)?;
+        }
+    }
+    Ok(order)
+}
+
+fn topo_dfs<'a>(
+    node: &'a str,
+    adj: &HashMap<&'a str, Vec<&'a str>>,
+    color: &mut HashMap<&'a str, u8>,
+    order: &mut Vec<String>,
+) -> Result<(), TaskaiError> {
+    color.insert(node, 1); // gray
+    for &neighbor in adj.get(node).map(Vec::as_slice).unwrap_or_default() {
+        match color.get(neighbor).copied().unwrap_or(0) {
+            1 => return Err(TaskaiError::cycle_detected()),
+            0 => topo_dfs(neighbor, adj, color, order)?,
+            _ => {}
+        }
+    }
+    color.insert(node, 2); // black
+    order.push(node.to_string());
+    Ok(())
+}
+
 /// Check if adding edge (task_id → dep_id) would create a cycle.
 pub fn would_create_cycle(
     nodes: &[String],
@@ -94,6 +125,17 @@ mod tests {
         assert!(detect_cycle(&nodes, &edges).is_err());
     }
 
+    #[test]
+    fn test_topological_sort() {
+        let nodes = vec!["c".into(), "b".into(), "a".into(), "d".into()];
+        let edges = vec![("b".into(), "a".into()), ("c".into(), "b".into())];
+        let order = topological_sort(&nodes, &edges).unwrap();
+        assert_eq!(order, vec!["a", "b", "c", "d"]);
+
+        let edges = vec![("a".into(), "b".into()), ("b".into(), "a".into())];
+        assert!(topological_sort(&nodes, &edges).is_err());
+    }
+
     #[test]
     fn test_self_cycle() {
         let nodes = vec!["a".into()];
@@ -0,0 +1,85 @@
+use std::collections::{HashSet, VecDeque};
+
+use rusqlite::Connection;
+
+use crate::db::{dependency_repo, task_repo};
+use crate::error::TaskaiError;
+use crate::models::Task;
+
+/// Deepest level `build_dep_tree` descends to before truncating.
+pub const MAX_TREE_DEPTH: usize = 20;
+
+/// A task with its transitive (upstream) dependencies.
+pub struct DepNode {
+    pub task: Task,
+    pub dependencies: Vec<DepNode>,
+    /// The task already appears on the path from the root (not expanded again)
+    pub cycle: bool,
+    /// Dependencies were not expanded because the depth cap was reached
+    pub truncated: bool,
+}
+
+/// Build the upstream dependency tree of a task. Shared dependencies are repeated under
+/// each dependent; a task that reappears on its own path is marked as a cycle.
+pub fn build_dep_tree(conn: &Connection, task_id: &str) -> Result<DepNode, TaskaiError> {
+    let mut path = Vec::new();
+    build_node(conn, task_id, 0, &mut path)
+}
+
+fn build_node(
+    conn: &Connection,
+    task_id: &str,
+    depth: usize,
+    path: &mut Vec<String>,
+) -> Result<DepNode, TaskaiError> {
+    let task = task_repo::get_task_by_id(conn, task_id)?;
+    if path.iter().any(|id| id == task_id) {
+        return Ok(DepNode { task, dependencies: Vec::new(), cycle: true, truncated: false });
+    }
+    let dep_ids = dependency_repo::get_dependencies(conn, task_id)?;
+    if depth >= MAX_TREE_DEPTH {
+        let truncated = !dep_ids.is_empty();
+        return Ok(DepNode { task, dependencies: Vec::new(), cycle: false, truncated });
+    }
+
+    path.push(task_id.to_string());
+    let mut dependencies = Vec::new();
+    for dep_id in &dep_ids {
+        dependencies.push(build_node(conn, dep_id, depth + 1, path)?);
+    }
+    path.pop();
+
+    Ok(DepNode { task, dependencies, cycle: false, truncated: false })
+}
+
+/// Downstream tasks of `task_id`, breadth-first: direct dependents only, or the whole
+/// forward closure when `transitive`. Each task appears once, with its shortest depth.
+pub fn collect_dependents(
+    conn: &Connection,
+    task_id: &str,
+    transitive: bool,
+) -> Result<Vec<(Task, usize)>, TaskaiError> {
+    let mut seen: HashSet<String> = HashSet::from([task_id.to_string()]);
+    let mut queue: VecDeque<(String, usize)> = VecDeque::from([(task_id.to_string(), 0)]);
+    let mut result = Vec::new();
+
+    while let Some((id, depth)) = queue.pop_front() {
+        if depth > 0 && !transitive {
+            break;
+        }
+        for dependent_id in dependency_repo::get_dependents(conn, &id)? {
+            if seen.insert(dependent_id.clone()) {
+                result.push((task_repo::get_task_by_id(conn, &dependent_id)?, depth + 1));
+                queue.push_back((dependent_id, depth + 1));
+            }
+        }
+    }
+    Ok(result)
+}
+
+impl DepNode {
+    /// True if any node in the tree hit the depth cap.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated || self.dependencies.iter().any(DepNode::is_truncated)
+    }
+}
@@ -1,2 +1,4 @@
 pub mod cycle;
+pub mod dep_tree;
 pub mod next_tasks;
+pub mod waves;
@@ -1,24 +1,35 @@
+use std::collections::{HashMap, HashSet};
+
 use rusqlite::Connection;
 
 use crate::db::{dependency_repo, task_repo};
 use crate::error::TaskaiError;
+use crate::graph::cycle;
 use crate::models::{Task, TaskStatus};
 
 /// Cascade unblock: after a task is done, check its dependents and unblock if all deps are done.
 /// Returns the list of newly unblocked (ready) task IDs.
 pub fn cascade_unblock(conn: &Connection, completed_task_id: &str) -> Result<Vec<Task>, TaskaiError> {
     let dependents = dependency_repo::get_dependents(conn, completed_task_id)?;
+    crate::verbose!("cascade: {completed_task_id} done, checking {} dependent(s)", dependents.len());
+    unblock_if_deps_done(conn, &dependents)
+}
+
+/// Move each blocked task whose dependencies are all done to ready.
+/// Returns the tasks that became ready.
+pub fn unblock_if_deps_done(conn: &Connection, task_ids: &[String]) -> Result<Vec<Task>, TaskaiError> {
     let mut newly_ready = Vec::new();
 
-    for dependent_id in dependents {
-        let task = task_repo::get_task_by_id(conn, &dependent_id)?;
+    for dependent_id in task_ids {
+        let task = task_repo::get_task_by_id(conn, dependent_id)?;
         if task.status != TaskStatus::Blocked {
             continue;
         }
 
-        if dependency_repo::all_dependencies_done(conn, &dependent_id)? {
-            task_repo::update_task_status(conn, &dependent_id, &TaskStatus::Ready, None)?;
-            let updated = task_repo::get_task_by_id(conn, &dependent_id)?;
+        if dependency_repo::all_dependencies_done(conn, dependent_id)? {
+            crate::verbose!("cascade: unblocking {dependent_id}, all dependencies done");
+            task_repo::update_task_status(conn, dependent_id, &TaskStatus::Ready, None)?;
+            let updated = task_repo::get_task_by_id(conn, dependent_id)?;
             newly_ready.push(updated);
         }
     }
@@ -26,16 +37,323 @@ pub fn cascade_unblock(conn: &Connection, completed_task_id: &str) -> Result<Vec
     Ok(newly_ready)
 }
 
+/// Cascade reblock: after a done task is reopened, move its ready dependents back to blocked.
+/// Returns the list of re-blocked tasks. In-progress dependents keep running, since an agent
+/// is already on them; `in_progress_dependents` lists them so the caller can report them.
+pub fn cascade_reblock(conn: &Connection, reopened_task_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    let dependents = dependency_repo::get_dependents(conn, reopened_task_id)?;
+    let mut reblocked = Vec::new();
+
+    for dependent_id in dependents {
+        let task = task_repo::get_task_by_id(conn, &dependent_id)?;
+        if task.status != TaskStatus::Ready {
+            continue;
+        }
+        crate::verbose!("cascade: re-blocking {dependent_id}, {reopened_task_id} was reopened");
+        task_repo::update_task_status(conn, &dependent_id, &TaskStatus::Blocked, None)?;
+        reblocked.push(task_repo::get_task_by_id(conn, &dependent_id)?);
+    }
+
+    Ok(reblocked)
+}
+
+/// Dependents of `task_id` that are in_progress.
+pub fn in_progress_dependents(conn: &Connection, task_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    let mut running = Vec::new();
+    for dependent_id in dependency_repo::get_dependents(conn, task_id)? {
+        let task = task_repo::get_task_by_id(conn, &dependent_id)?;
+        if task.status == TaskStatus::InProgress {
+            running.push(task);
+        }
+    }
+    Ok(running)
+}
+
+/// Give up the claim on an in_progress task without failing it. The task goes back to
+/// ready (or blocked, if a dependency was reopened meanwhile) with no assignee.
+/// Returns the status it landed in.
+pub fn release_claim(conn: &Connection, task: &Task) -> Result<TaskStatus, TaskaiError> {
+    if task.status != TaskStatus::InProgress {
+        return Err(TaskaiError::invalid_transition(task.status.as_str(), "release"));
+    }
+    let status = if dependency_repo::all_dependencies_done(conn, &task.id)? {
+        TaskStatus::Ready
+    } else {
+        TaskStatus::Blocked
+    };
+    task_repo::unclaim_task(conn, &task.id, &status)?;
+    Ok(status)
+}
+
+/// Release in_progress tasks started at least `older_than_minutes` ago (within an existing
+/// transaction). Tasks go back to ready, or blocked if their deps are no longer all done.
+pub fn reclaim_stale_tasks(
+    conn: &Connection,
+    plan_id: &str,
+    older_than_minutes: i64,
+) -> Result<Vec<Task>, TaskaiError> {
+    if older_than_minutes < 0 {
+        return Err(TaskaiError::validation("Reclaim threshold must be >= 0 minutes"));
+    }
+    let mut reclaimed = Vec::new();
+    for t in task_repo::in_progress_tasks(conn, plan_id)? {
+        if t.started_at.is_none() || elapsed_minutes(t.started_at.as_deref()) < older_than_minutes {
+            continue;
+        }
+        let status = if dependency_repo::all_dependencies_done(conn, &t.id)? {
+            TaskStatus::Ready
+        } else {
+            TaskStatus::Blocked
+        };
+        task_repo::release_task(conn, &t.id, &status)?;
+        reclaimed.push(task_repo::get_task_by_id(conn, &t.id)?);
+    }
+    Ok(reclaimed)
+}
+
+/// Whole minutes since `started_at` (a SQLite UTC timestamp); 0 if unset or unparsable.
+pub fn elapsed_minutes(started_at: Option<&str>) -> i64 {
+    let Some(started) = started_at else { return 0 };
+    let Ok(started) = chrono::NaiveDateTime::parse_from_str(started, "%Y-%m-%d %H:%M:%S") else {
+        return 0;
+    };
+    let now = chrono::Utc::now().naive_utc();
+    (now - started).num_minutes()
+}
+
+/// Dependencies of `task_id` that are not done yet, i.e. what keeps it blocked.
+pub fn blocking_dependencies(conn: &Connection, task_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    let blockers = dependency_repo::get_dependencies(conn, task_id)?
+        .iter()
+        .filter_map(|d| task_repo::get_task_by_id(conn, d).ok())
+        .filter(|d| d.status != TaskStatus::Done)
+        .collect();
+    Ok(blockers)
+}
+
+/// Longest path of unfinished dependencies below `task_id`, from the direct blocker down
+/// to the root cause (an unfinished task with nothing unfinished underneath).
+/// Empty when nothing blocks the task.
+pub fn blocking_chain(conn: &Connection, task_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    longest_blocking_chain(conn, task_id, &mut HashMap::new())
+}
+
+fn longest_blocking_chain(
+    conn: &Connection,
+    task_id: &str,
+    memo: &mut HashMap<String, Vec<Task>>,
+) -> Result<Vec<Task>, TaskaiError> {
+    if let Some(chain) = memo.get(task_id) {
+        return Ok(chain.clone());
+    }
+    // Placeholder first, so a (normally impossible) cycle terminates
+    memo.insert(task_id.to_string(), Vec::new());
+    let mut longest = Vec::new();
+    for dep in blocking_dependencies(conn, task_id)? {
+        let below = longest_blocking_chain(conn, &dep.id, memo)?;
+        if below.len() + 1 > longest.len() {
+            longest = std::iter::once(dep).chain(below).collect();
+        }
+    }
+    memo.insert(task_id.to_string(), longest.clone());
+    Ok(longest)
+}
+
+/// The unfinished tasks whose completion would, transitively, unblock `task_id`: walk the
+/// not-done dependencies downwards and keep the non-terminal leaves (ready or in_progress
+/// tasks with nothing unfinished beneath them), in discovery order.
+/// A chain ending in a cancelled/skipped dependency adds nothing, so an empty frontier for
+/// a blocked task means it cannot unblock without intervention.
+pub fn unblock_frontier(conn: &Connection, task_id: &str) -> Result<Vec<Task>, TaskaiError> {
+    let mut visited: HashSet<String> = HashSet::from([task_id.to_string()]);
+    let mut frontier = Vec::new();
+    let mut stack: Vec<Task> = blocking_dependencies(conn, task_id)?;
+    stack.reverse();
+    while let Some(dep) = stack.pop() {
+        // Guards against cycles and against diamonds listing a task twice
+        if !visited.insert(dep.id.clone()) || dep.status.is_terminal() {
+            continue;
+        }
+        let below = blocking_dependencies(conn, &dep.id)?;
+        if below.is_empty() {
+            frontier.push(dep);
+        } else {
+            stack.extend(below.into_iter().rev());
+        }
+    }
+    Ok(frontier)
+}
+
+/// The heaviest dependency chain of a plan and its summed weight.
+pub struct CriticalPath {
+    /// From the first task to start down to the last to finish
+    pub tasks: Vec<Task>,
+    pub total_minutes: i64,
+}
+
+/// Longest path through the plan's dependency DAG, weighting each task by
+/// `estimated_minutes` (1 when unestimated). Every task counts, finished or not,
+/// so the result describes the plan as a whole. Ties go to the task earlier in plan order.
+/// Dependencies on tasks of other plans (`--allow-cross-plan`) are outside the plan's
+/// schedule and do not count.
+pub fn compute_critical_path(conn: &Connection, plan_id: &str) -> Result<CriticalPath, TaskaiError> {
+    let tasks = task_repo::list_tasks_by_plan(conn, plan_id)?;
+    let ids: Vec<String> = tasks.iter().map(|t| t.id.clone()).collect();
+    let by_id: HashMap<&str, &Task> = tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+    let edges: Vec<(String, String)> = dependency_repo::get_all_dependencies_for_plan(conn, plan_id)?
+        .into_iter()
+        .filter(|d| by_id.contains_key(d.task_id.as_str()) && by_id.contains_key(d.dependency_id.as_str()))
+        .map(|d| (d.task_id, d.dependency_id))
+        .collect();
+    let order = cycle::topological_sort(&ids, &edges)?;
+
+    let mut deps_of: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (task_id, dep_id) in &edges {
+        deps_of.entry(task_id.as_str()).or_default().push(dep_id.as_str());
+    }
+    // Heaviest chain ending at each task, and the dependency it came through
+    let mut dist: HashMap<&str, i64> = HashMap::new();
+    let mut prev: HashMap<&str, &str> = HashMap::new();
+    for id in &order {
+        let id = id.as_str();
+        let mut best: Option<(&str, i64)> = None;
+        for &dep in deps_of.get(id).map(Vec::as_slice).unwrap_or_default() {
+            let Some(&dep_dist) = dist.get(dep) else { continue };
+            if best.is_none_or(|(_, d)| dep_dist > d) {
+                best = Some((dep, dep_dist));
+            }
+        }
+        let Some(task) = by_id.get(id) else { continue };
+        let weight = task.estimated_minutes.unwrap_or(1);
+        dist.insert(id, weight + best.map_or(0, |(_, d)| d));
+        if let Some((dep, _)) = best {
+            prev.insert(id, dep);
+        }
+    }
+
+    let mut end: Option<(&str, i64)> = None;
+    for t in &tasks {
+        let d = dist.get(t.id.as_str()).copied().unwrap_or(0);
+        if end.is_none_or(|(_, e)| d > e) {
+            end = Some((t.id.as_str(), d));
+        }
+    }
+    let mut path = Vec::new();
+    let mut cursor = end.map(|(id, _)| id);
+    while let Some(task) = cursor.and_then(|id| by_id.get(id)) {
+        path.push((*task).clone());
+        cursor = prev.get(task.id.as_str()).copied();
+    }
+    path.reverse();
+    Ok(CriticalPath {
+        total_minutes: end.map_or(0, |(_, d)| d),
+        tasks: path,
+    })
+}
+
+/// Restrictions on which ready task `next` may pick, and the queue order.
+#[derive(Default)]
+pub struct NextFilter<'a> {
+    /// Only tasks pre-assigned to this agent (or unassigned, unless `strict`)
+    pub for_agent: Option<&'a str>,
+    pub strict: bool,
+    /// Only tasks carrying this tag
+    pub tag: Option<&'a str>,
+    /// Only tasks with at least this priority
+    pub min_priority: Option<i32>,
+    pub order: task_repo::ReadyOrder,
+}
+
+/// Pick the next ready task matching `filter`.
+pub fn pick_next_task(
+    conn: &Connection,
+    plan_id: &str,
+    filter: &NextFilter,
+) -> Result<Option<Task>, TaskaiError> {
+    if filter.for_agent.is_none() && filter.tag.is_none() && filter.min_priority.is_none() {
+        return task_repo::next_ready_task(conn, plan_id, filter.order);
+    }
+    task_repo::next_ready_task_matching(
+        conn, plan_id, filter.for_agent, filter.strict, filter.tag, filter.min_priority, filter.order,
+    )
+}
+
+/// Why `filter` leaves no ready task: the first of --for-agent, --tag and --min-priority
+/// that, applied on top of the ones before it, matches nothing. Read-only.
+pub fn no_match_reason(conn: &Connection, plan_id: &str, filter: &NextFilter) -> Result<&'static str, TaskaiError> {
+    let (for_agent, strict) = (filter.for_agent, filter.strict);
+    let steps = [
+        ("NO_TASK_FOR_AGENT", filter.for_agent.is_some(), NextFilter { for_agent, strict, ..Default::default() }),
+        ("NO_TASK_WITH_TAG", filter.tag.is_some(), NextFilter { for_agent, strict, tag: filter.tag, ..Default::default() }),
+        (
+            "BELOW_PRIORITY_THRESHOLD",
+            filter.min_priority.is_some(),
+            NextFilter { for_agent, strict, tag: filter.tag, min_priority: filter.min_priority, ..Default::default() },
+        ),
+    ];
+    let mut reason = "BELOW_PRIORITY_THRESHOLD";
+    for (step_reason, given, partial) in steps {
+        if !given {
+            continue;
+        }
+        reason = step_reason;
+        if pick_next_task(conn, plan_id, &partial)?.is_none() {
+            break;
+        }
+    }
+    Ok(reason)
+}
+
+/// The first `count` ready tasks matching `filter`, in `next` order. Read-only.
+pub fn pick_n_tasks(
+    conn: &Connection,
+    plan_id: &str,
+    count: usize,
+    filter: &NextFilter,
+) -> Result<Vec<Task>, TaskaiError> {
+    if filter.for_agent.is_none() && filter.tag.is_none() && filter.min_priority.is_none() {
+        return task_repo::next_n_ready_tasks(conn, plan_id, count, filter.order);
+    }
+    task_repo::next_n_ready_tasks_matching(
+        conn, plan_id, count, filter.for_agent, filter.strict, filter.tag, filter.min_priority, filter.order,
+    )
+}
+
 /// Claim the next ready task atomically (within an existing transaction).
 pub fn claim_next_task(
     conn: &Connection,
     plan_id: &str,
     agent: Option<&str>,
+    filter: &NextFilter,
 ) -> Result<Option<Task>, TaskaiError> {
-    let task = task_repo::next_ready_task(conn, plan_id)?;
+    let task = pick_next_task(conn, plan_id, filter)?;
     if let Some(ref task) = task {
         task_repo::update_task_status(conn, &task.id, &TaskStatus::InProgress, agent)?;
-        return Ok(Some(task_repo::get_task_by_id(conn, &task.id)?));
+        return Ok(Some(claimed_task(conn, task)?));
     }
     Ok(None)
 }
+
+/// Claim up to `count` ready tasks (within an existing transaction), in `next` order.
+pub fn claim_n_tasks(
+    conn: &Connection,
+    plan_id: &str,
+    count: usize,
+    agent: Option<&str>,
+    filter: &NextFilter,
+) -> Result<Vec<Task>, TaskaiError> {
+    let mut claimed = Vec::new();
+    for task in pick_n_tasks(conn, plan_id, count, filter)? {
+        task_repo::update_task_status(conn, &task.id, &TaskStatus::InProgress, agent)?;
+        claimed.push(claimed_task(conn, &task)?);
+    }
+    Ok(claimed)
+}
+
+/// Re-read a task just claimed from `before`. Starting clears `failure_reason`, but the
+/// claiming agent still gets to see what went wrong last time.
+pub fn claimed_task(conn: &Connection, before: &Task) -> Result<Task, TaskaiError> {
+    let mut task = task_repo::get_task_by_id(conn, &before.id)?;
+    task.failure_reason = before.failure_reason.clone();
+    Ok(task)
+}
@@ -0,0 +1,61 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::{Task, TaskDependency, TaskStatus};
+
+/// Group tasks into parallel execution waves: wave 0 holds tasks without dependencies,
+/// wave N tasks whose dependencies all sit in earlier waves (at least one in wave N-1).
+/// Tasks inside a wave can run side by side; order within a wave follows `tasks`.
+/// `external` are the tasks of other plans that `tasks` depend on: an unfinished one counts
+/// as finishing in wave 0, so its dependents start at wave 1 at the earliest (leaving wave 0
+/// empty when nothing else can start). Other dependencies pointing outside `tasks` are
+/// ignored; tasks on a cycle are left out.
+pub fn compute_waves(tasks: &[Task], deps: &[TaskDependency], external: &[Task]) -> Vec<Vec<Task>> {
+    let mut deps_of: HashMap<&str, Vec<&str>> = HashMap::new();
+    for d in deps {
+        deps_of.entry(d.task_id.as_str()).or_default().push(d.dependency_id.as_str());
+    }
+    let known: HashSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+    let pending_external: HashSet<&str> = external
+        .iter()
+        .filter(|t| t.status != TaskStatus::Done)
+        .map(|t| t.id.as_str())
+        .collect();
+
+    let mut placed: HashSet<&str> = HashSet::new();
+    let mut waves: Vec<Vec<Task>> = Vec::new();
+    loop {
+        // Every task whose dependencies are all placed joins the next wave
+        let first_wave = waves.is_empty();
+        let waits_on_external = |ds: &Vec<&str>| ds.iter().any(|d| pending_external.contains(d));
+        let next: Vec<&Task> = tasks
+            .iter()
+            .filter(|t| !placed.contains(t.id.as_str()))
+            .filter(|t| {
+                deps_of.get(t.id.as_str()).is_none_or(|ds| {
+                    !(first_wave && waits_on_external(ds))
+                        && ds.iter().all(|d| !known.contains(d) || placed.contains(d))
+                })
+            })
+            .collect();
+        if next.is_empty() {
+            // Everything startable waits on another plan: wave 0 is that plan's work
+            let blocked_outside = tasks
+                .iter()
+                .any(|t| deps_of.get(t.id.as_str()).is_some_and(waits_on_external));
+            if first_wave && blocked_outside {
+                waves.push(Vec::new());
+                continue;
+            }
+            break;
+        }
+        placed.extend(next.iter().map(|t| t.id.as_str()));
+        waves.push(next.into_iter().cloned().collect());
+    }
+    waves
+}
+
+/// Longest estimate in a wave, i.e. its wall-clock time with one agent per task.
+/// `None` when no task in the wave is estimated.
+pub fn wave_estimated_minutes(wave: &[Task]) -> Option<i64> {
+    wave.iter().filter_map(|t| t.estimated_minutes).max()
+}
@@ -4,3 +4,6 @@ pub mod db;
 pub mod models;
 pub mod graph;
 pub mod output;
+pub mod verbose;
+pub mod api;
+pub mod plan_input;
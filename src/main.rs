@@ -6,17 +6,52 @@ use taskai::cli;
 
 fn main() {
     let cli_args = Cli::parse();
-    let json_output = cli_args.json;
+    if cli_args.verbose {
+        taskai::verbose::enable();
+    }
+    // Before reading config: --db-path decides which config.json applies
+    if let Some(path) = cli_args.db_path.clone() {
+        taskai::db::connection::set_db_path_override(path);
+    }
+    if let Some(ms) = cli_args.lock_timeout {
+        taskai::db::connection::set_lock_timeout(std::time::Duration::from_millis(ms));
+    }
+    let ndjson = cli_args.ndjson;
+    let json_output = cli_args.json || ndjson || taskai::db::config::load_config().json_output();
     let plan_flag = cli_args.plan.clone();
 
     let exit_code = match cli_args.command {
         Commands::Init => cli::init::run(json_output),
         Commands::Plan(cmd) => cli::plan::run(cmd, json_output),
-        Commands::Task(cmd) => cli::task::run(cmd, json_output, plan_flag.as_deref()),
-        Commands::Next { claim, agent } => {
-            cli::next::run(claim, agent.as_deref(), json_output, plan_flag.as_deref())
+        Commands::Task(cmd) => cli::task::run(cmd, json_output, ndjson, plan_flag.as_deref()),
+        Commands::Next { claim, agent, timeout, reclaim_after, release, for_agent, strict, tag, min_priority, count, order, explain } => cli::next::run(
+            cli::next::NextArgs {
+                claim,
+                agent: agent.as_deref(),
+                timeout,
+                reclaim_after,
+                release: release.as_deref(),
+                for_agent: for_agent.as_deref(),
+                strict,
+                tag: tag.as_deref(),
+                min_priority,
+                count,
+                order: taskai::db::task_repo::ReadyOrder::from_str(&order).unwrap_or_default(),
+                explain,
+            },
+            json_output,
+            plan_flag.as_deref(),
+        ),
+        Commands::Db(cmd) => cli::db::run(cmd, json_output),
+        Commands::Doctor => cli::doctor::run(json_output),
+        Commands::Completions { shell, out_dir } => cli::completions::run(shell, out_dir.as_deref(), json_output),
+        Commands::Version => cli::version::run(json_output),
+        Commands::PlanNames => cli::completions::run_plan_names(),
+        Commands::Config(cmd) => cli::config::run(cmd, json_output),
+        Commands::Document(cmd) => cli::document::run(cmd, json_output, plan_flag.as_deref()),
+        Commands::Status { stale_after, watch, format } => {
+            cli::status::run(stale_after, watch, format.as_deref(), json_output, ndjson, plan_flag.as_deref())
         }
-        Commands::Status => cli::status::run(json_output, plan_flag.as_deref()),
     };
 
     process::exit(exit_code);
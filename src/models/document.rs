@@ -6,6 +6,7 @@ pub struct PlanDocument {
     pub plan_id: String,
     pub title: String,
     pub content: String,
+    pub updated_at: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,4 +15,12 @@ pub struct TaskDocument {
     pub task_id: String,
     pub title: String,
     pub content: String,
+    pub updated_at: Option<String>,
+}
+
+/// A document looked up by ID alone: it belongs to either a plan or a task.
+#[derive(Debug, Clone)]
+pub enum Document {
+    Plan(PlanDocument),
+    Task(TaskDocument),
 }
@@ -2,8 +2,12 @@ pub mod plan;
 pub mod task;
 pub mod dependency;
 pub mod document;
+pub mod note;
+pub mod transition;
 
 pub use plan::*;
 pub use task::*;
 pub use dependency::*;
 pub use document::*;
+pub use note::*;
+pub use transition::*;
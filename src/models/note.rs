@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskNote {
+    pub id: String,
+    pub task_id: String,
+    pub content: String,
+    pub agent: Option<String>,
+    pub created_at: String,
+}
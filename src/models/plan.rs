@@ -6,6 +6,8 @@ pub enum PlanStatus {
     Active,
     Completed,
     Archived,
+    /// Soft-deleted via `plan delete --soft`; brought back by `plan restore`
+    Deleted,
 }
 
 impl PlanStatus {
@@ -14,17 +16,25 @@ impl PlanStatus {
             Self::Active => "active",
             Self::Completed => "completed",
             Self::Archived => "archived",
+            Self::Deleted => "deleted",
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "active" => Some(Self::Active),
             "completed" => Some(Self::Completed),
             "archived" => Some(Self::Archived),
+            "deleted" => Some(Self::Deleted),
             _ => None,
         }
     }
+
+    /// Archived and soft-deleted plans are hidden from `plan list` and closed to work.
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, Self::Archived | Self::Deleted)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
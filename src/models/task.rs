@@ -23,6 +23,7 @@ impl TaskStatus {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "blocked" => Some(Self::Blocked),
@@ -55,4 +56,68 @@ pub struct Task {
     pub updated_at: String,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
+    pub estimated_minutes: Option<i64>,
+    pub deadline: Option<String>,
+    pub last_heartbeat_at: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When the current claim lapses and `next`/`status` put the task back in the queue
+    #[serde(default)]
+    pub claim_expires_at: Option<String>,
+    /// Reason given to the last `task fail`; cleared when the task is started again
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    #[serde(default)]
+    pub fail_count: i64,
+    /// Result summary given to `task done --note`; cleared by `task reset`
+    #[serde(default)]
+    pub completion_note: Option<String>,
+}
+
+/// Priorities accepted by `plan load` and `task add` unless --allow-extreme-priority is given.
+/// Keeps a typo like 10000 from dominating `next` forever.
+pub const PRIORITY_RANGE: std::ops::RangeInclusive<i32> = -1000..=1000;
+
+/// Minutes without a heartbeat (or since start) before an in_progress task counts as stale.
+pub const DEFAULT_STALE_AFTER_MINUTES: i64 = 30;
+
+impl Task {
+    /// True when the task is in_progress and neither a heartbeat nor the start
+    /// happened within the last `after_minutes`.
+    pub fn is_stale(&self, after_minutes: i64) -> bool {
+        if self.status != TaskStatus::InProgress {
+            return false;
+        }
+        let Some(last_seen) = self.last_heartbeat_at.as_ref().or(self.started_at.as_ref()) else {
+            return false;
+        };
+        match chrono::NaiveDateTime::parse_from_str(last_seen, "%Y-%m-%d %H:%M:%S") {
+            Ok(last_seen) => (chrono::Utc::now().naive_utc() - last_seen).num_minutes() >= after_minutes,
+            Err(_) => false,
+        }
+    }
+
+    /// True when the task has a deadline in the past and is not finished.
+    pub fn is_overdue(&self) -> bool {
+        if self.status.is_terminal() {
+            return false;
+        }
+        let Some(ref deadline) = self.deadline else { return false };
+        match chrono::NaiveDateTime::parse_from_str(deadline, "%Y-%m-%d %H:%M:%S") {
+            Ok(deadline) => deadline < chrono::Utc::now().naive_utc(),
+            Err(_) => false,
+        }
+    }
+
+    /// True when the task is in_progress and its claim expiry has passed.
+    pub fn is_claim_expired(&self) -> bool {
+        if self.status != TaskStatus::InProgress {
+            return false;
+        }
+        let Some(ref expires) = self.claim_expires_at else { return false };
+        match chrono::NaiveDateTime::parse_from_str(expires, "%Y-%m-%d %H:%M:%S") {
+            Ok(expires) => expires < chrono::Utc::now().naive_utc(),
+            Err(_) => false,
+        }
+    }
 }
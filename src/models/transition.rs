@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskTransition {
+    pub id: i64,
+    pub task_id: String,
+    pub event_type: String,
+    pub from_status: String,
+    pub to_status: String,
+    pub agent: Option<String>,
+    pub at: String,
+}
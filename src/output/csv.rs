@@ -0,0 +1,37 @@
+use crate::models::Task;
+
+/// Columns written by `--format csv`, in order.
+pub const TASK_HEADER: [&str; 7] = ["id", "title", "status", "priority", "sort_order", "agent", "assigned_to"];
+
+/// Quote a field per RFC 4180 when it contains a comma, quote or line break.
+pub fn escape_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Join already-stringified fields into one CSV record (without the line ending).
+pub fn record<S: AsRef<str>>(fields: &[S]) -> String {
+    fields.iter().map(|f| escape_field(f.as_ref())).collect::<Vec<_>>().join(",")
+}
+
+/// Render tasks as CSV with a header row. `None` fields become empty strings.
+pub fn tasks_to_csv(tasks: &[Task]) -> String {
+    let mut out = record(&TASK_HEADER);
+    out.push('\n');
+    for t in tasks {
+        out.push_str(&record(&[
+            t.id.clone(),
+            t.title.clone(),
+            t.status.as_str().to_string(),
+            t.priority.to_string(),
+            t.sort_order.to_string(),
+            t.agent.clone().unwrap_or_default(),
+            t.assigned_to.clone().unwrap_or_default(),
+        ]));
+        out.push('\n');
+    }
+    out
+}
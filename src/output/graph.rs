@@ -0,0 +1,86 @@
+use crate::models::{Task, TaskDependency, TaskStatus};
+
+fn status_color(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Ready => "green",
+        TaskStatus::Blocked => "yellow",
+        TaskStatus::InProgress => "blue",
+        TaskStatus::Done => "grey",
+        TaskStatus::Cancelled => "red",
+        TaskStatus::Skipped => "dimgrey",
+    }
+}
+
+fn status_hex(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Ready => "#00ff00",
+        TaskStatus::Blocked => "#ffff00",
+        TaskStatus::InProgress => "#0000ff",
+        TaskStatus::Done => "#bebebe",
+        TaskStatus::Cancelled => "#ff0000",
+        TaskStatus::Skipped => "#696969",
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;").replace('\n', " ")
+}
+
+/// Render the dependency graph as Graphviz DOT. Edges point from dependency to dependent.
+/// `external` (tasks of other plans depended on) are drawn dashed.
+pub fn to_dot(plan_name: &str, tasks: &[Task], external: &[Task], deps: &[TaskDependency]) -> String {
+    let mut out = format!("digraph \"{}\" {{\n", escape_dot(plan_name));
+    out.push_str("  rankdir=LR;\n");
+    out.push_str("  node [shape=box, style=\"rounded,filled\"];\n");
+    for t in tasks {
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\\n[{}]\", fillcolor={}];\n",
+            t.id,
+            escape_dot(&t.title),
+            t.status.as_str(),
+            status_color(&t.status)
+        ));
+    }
+    for t in external {
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\\n[{}]\", fillcolor={}, style=\"rounded,filled,dashed\"];\n",
+            t.id,
+            escape_dot(&t.title),
+            t.status.as_str(),
+            status_color(&t.status)
+        ));
+    }
+    for d in deps {
+        out.push_str(&format!("  \"{}\" -> \"{}\";\n", d.dependency_id, d.task_id));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render the dependency graph as a Mermaid `graph TD` block.
+/// `external` (tasks of other plans depended on) get a dashed border.
+pub fn to_mermaid(tasks: &[Task], external: &[Task], deps: &[TaskDependency]) -> String {
+    let mut out = String::from("graph TD\n");
+    for t in tasks.iter().chain(external) {
+        out.push_str(&format!(
+            "  {}[\"{} [{}]\"]\n",
+            t.id,
+            escape_mermaid(&t.title),
+            t.status.as_str()
+        ));
+    }
+    for d in deps {
+        out.push_str(&format!("  {} --> {}\n", d.dependency_id, d.task_id));
+    }
+    for t in tasks {
+        out.push_str(&format!("  style {} fill:{}\n", t.id, status_hex(&t.status)));
+    }
+    for t in external {
+        out.push_str(&format!("  style {} fill:{},stroke-dasharray:5 5\n", t.id, status_hex(&t.status)));
+    }
+    out
+}
@@ -1,8 +1,13 @@
+use std::collections::BTreeMap;
+
 use serde_json::{json, Value};
 
-use crate::db::task_repo::TaskProgress;
+use crate::db::plan_stats_repo::{DurationStats, PlanStats};
+use crate::db::task_repo::{AgentLoad, AgentWorkload, TaskProgress};
 use crate::error::TaskaiError;
-use crate::models::{Plan, Task, TaskDocument, PlanDocument};
+use crate::graph::dep_tree::DepNode;
+use crate::graph::next_tasks::CriticalPath;
+use crate::models::{Plan, Task, TaskDocument, TaskNote, TaskTransition, PlanDocument};
 
 pub fn success(data: Value) -> Value {
     json!({
@@ -20,13 +25,22 @@ pub fn success_with_plan_completed(data: Value, plan_completed: bool) -> Value {
 }
 
 pub fn error(err: &TaskaiError) -> Value {
-    json!({
+    let mut v = json!({
         "success": false,
         "error": {
             "code": err.code.as_str(),
             "message": err.message
         }
-    })
+    });
+    if !err.errors.is_empty() {
+        v["error"]["errors"] = json!(err.errors);
+    }
+    v
+}
+
+/// Print one compact JSON value per line (`--ndjson`).
+pub fn print_ndjson_line(v: &Value) {
+    println!("{}", serde_json::to_string(v).unwrap());
 }
 
 pub fn progress_json(p: &TaskProgress) -> Value {
@@ -42,16 +56,128 @@ pub fn progress_json(p: &TaskProgress) -> Value {
     })
 }
 
+/// `status` agents section: per-agent counts by `assigned_to` and by `agent`.
+pub fn agent_workload_json(w: &AgentWorkload) -> Value {
+    let group = |loads: &BTreeMap<String, AgentLoad>| {
+        loads
+            .iter()
+            .map(|(name, load)| {
+                let mut v = progress_json(&load.progress);
+                v["in_progress_elapsed_minutes"] = json!(load.in_progress_minutes);
+                (name.clone(), v)
+            })
+            .collect::<serde_json::Map<_, _>>()
+    };
+    json!({
+        "by_assigned_to": group(&w.by_assigned_to),
+        "by_agent": group(&w.by_agent)
+    })
+}
+
+fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+fn duration_stats_json(d: &DurationStats) -> Value {
+    json!({
+        "sample_size": d.sample_size,
+        "avg": d.avg.map(round1),
+        "p50": d.p50.map(round1),
+        "p90": d.p90.map(round1)
+    })
+}
+
+pub fn plan_stats_json(s: &PlanStats) -> Value {
+    json!({
+        "total": s.progress.total,
+        "completion_percentage": (s.progress.percentage * 10.0).round() / 10.0,
+        "by_status": {
+            "blocked": s.progress.blocked,
+            "ready": s.progress.ready,
+            "in_progress": s.progress.in_progress,
+            "done": s.progress.done,
+            "skipped": s.progress.skipped,
+            "cancelled": s.progress.cancelled
+        },
+        "avg_in_progress_minutes": s.avg_in_progress_minutes.map(round1),
+        "failed_at_least_once": s.failed_at_least_once,
+        "with_documents": s.with_documents,
+        "cycle_time_minutes": duration_stats_json(&s.cycle_time),
+        "wait_time_minutes": {
+            "sample_size": s.wait_time.sample_size,
+            "avg": s.wait_time.avg.map(round1)
+        },
+        "throughput_per_hour": s.throughput_per_hour.map(round1),
+        "agent_workload": s.agent_workload.iter().map(|w| json!({
+            "agent": w.agent,
+            "completed": w.completed
+        })).collect::<Vec<_>>()
+    })
+}
+
+/// Critical path entries: id, title and estimate, in execution order.
+pub fn critical_path_json(path: &CriticalPath) -> Vec<Value> {
+    path.tasks
+        .iter()
+        .map(|t| json!({ "id": t.id, "title": t.title, "estimated_minutes": t.estimated_minutes }))
+        .collect()
+}
+
+/// Parallel execution waves as an array of arrays of `{id, title, status, estimated_minutes}`.
+pub fn waves_json(waves: &[Vec<Task>]) -> Vec<Value> {
+    waves
+        .iter()
+        .map(|wave| {
+            json!(wave
+                .iter()
+                .map(|t| json!({
+                    "id": t.id,
+                    "title": t.title,
+                    "status": t.status.as_str(),
+                    "estimated_minutes": t.estimated_minutes
+                }))
+                .collect::<Vec<_>>())
+        })
+        .collect()
+}
+
+/// Minimal task reference: id, title and status.
+pub fn task_ref(t: &Task) -> Value {
+    json!({
+        "id": t.id,
+        "title": t.title,
+        "status": t.status.as_str()
+    })
+}
+
 pub fn task_summary(t: &Task) -> Value {
     let mut v = json!({
         "id": t.id,
         "title": t.title,
         "status": t.status.as_str(),
-        "priority": t.priority
+        "priority": t.priority,
+        "tags": t.tags
     });
     if let Some(ref agent) = t.agent {
         v["agent"] = json!(agent);
     }
+    if let Some(ref assigned) = t.assigned_to {
+        v["assigned_to"] = json!(assigned);
+    }
+    if let Some(minutes) = t.estimated_minutes {
+        v["estimated_minutes"] = json!(minutes);
+    }
+    if let Some(ref deadline) = t.deadline {
+        v["deadline"] = json!(deadline);
+        v["overdue"] = json!(t.is_overdue());
+    }
+    if let Some(ref expires) = t.claim_expires_at {
+        v["claim_expires_at"] = json!(expires);
+    }
+    if let Some(ref reason) = t.failure_reason {
+        v["failure_reason"] = json!(reason);
+    }
+    v["fail_count"] = json!(t.fail_count);
     v
 }
 
@@ -62,6 +188,7 @@ pub fn task_detail(t: &Task, has_documents: bool) -> Value {
         "description": t.description,
         "status": t.status.as_str(),
         "priority": t.priority,
+        "tags": t.tags,
         "has_documents": has_documents
     });
     if let Some(ref agent) = t.agent {
@@ -70,16 +197,33 @@ pub fn task_detail(t: &Task, has_documents: bool) -> Value {
     if let Some(ref assigned) = t.assigned_to {
         v["assigned_to"] = json!(assigned);
     }
+    if let Some(minutes) = t.estimated_minutes {
+        v["estimated_minutes"] = json!(minutes);
+    }
+    if let Some(ref deadline) = t.deadline {
+        v["deadline"] = json!(deadline);
+        v["overdue"] = json!(t.is_overdue());
+    }
+    if let Some(ref expires) = t.claim_expires_at {
+        v["claim_expires_at"] = json!(expires);
+    }
+    if let Some(ref reason) = t.failure_reason {
+        v["failure_reason"] = json!(reason);
+    }
+    v["fail_count"] = json!(t.fail_count);
     v
 }
 
-pub fn in_progress_entry(t: &Task, elapsed_minutes: i64) -> Value {
+pub fn in_progress_entry(t: &Task, elapsed_minutes: i64, stale_after_minutes: i64) -> Value {
     json!({
         "id": t.id,
         "title": t.title,
         "assigned_to": t.assigned_to,
         "started_at": t.started_at,
-        "elapsed_minutes": elapsed_minutes
+        "elapsed_minutes": elapsed_minutes,
+        "last_heartbeat_at": t.last_heartbeat_at,
+        "claim_expires_at": t.claim_expires_at,
+        "stale": t.is_stale(stale_after_minutes)
     })
 }
 
@@ -110,3 +254,41 @@ pub fn task_document_json(d: &TaskDocument) -> Value {
         "content": d.content
     })
 }
+
+pub fn task_transition_json(t: &TaskTransition) -> Value {
+    json!({
+        "id": t.id,
+        "task_id": t.task_id,
+        "event_type": t.event_type,
+        "from_status": t.from_status,
+        "to_status": t.to_status,
+        "agent": t.agent,
+        "at": t.at
+    })
+}
+
+pub fn dep_tree_json(node: &DepNode) -> Value {
+    let mut v = json!({
+        "id": node.task.id,
+        "title": node.task.title,
+        "status": node.task.status.as_str(),
+        "dependencies": node.dependencies.iter().map(dep_tree_json).collect::<Vec<_>>()
+    });
+    if node.cycle {
+        v["cycle"] = json!(true);
+    }
+    if node.truncated {
+        v["truncated"] = json!(true);
+    }
+    v
+}
+
+pub fn task_note_json(n: &TaskNote) -> Value {
+    json!({
+        "id": n.id,
+        "task_id": n.task_id,
+        "content": n.content,
+        "agent": n.agent,
+        "created_at": n.created_at
+    })
+}
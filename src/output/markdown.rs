@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use crate::db::task_repo::TaskProgress;
+use crate::models::{Plan, Task, TaskDependency, TaskStatus};
+use crate::output::short_ids;
+
+/// Cells in the `--format markdown` progress bar.
+const BAR_WIDTH: usize = 20;
+
+pub fn print_plan_header(p: &Plan) {
+    println!("# {}", escape(&p.title));
+    println!();
+    println!("`{}` · {}", p.name, p.status.as_str());
+    if let Some(ref desc) = p.description {
+        println!();
+        println!("{}", desc.trim());
+    }
+}
+
+pub fn print_progress(p: &TaskProgress) {
+    let filled = if p.total == 0 { 0 } else { (p.done as usize * BAR_WIDTH) / p.total as usize };
+    println!(
+        "**Progress:** `{}{}` {:.0}% ({}/{} done)",
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled),
+        p.percentage,
+        p.done,
+        p.total
+    );
+    println!(
+        "blocked {} · ready {} · in progress {} · done {} · skipped {} · cancelled {}",
+        p.blocked, p.ready, p.in_progress, p.done, p.skipped, p.cancelled
+    );
+}
+
+/// One row per task: short id, title, status badge and the short ids of its dependencies.
+pub fn print_task_table(tasks: &[Task], deps: &[TaskDependency]) {
+    if tasks.is_empty() {
+        println!("_No tasks._");
+        return;
+    }
+    let ids = short_ids(tasks);
+    let mut after: HashMap<&str, Vec<&str>> = HashMap::new();
+    for d in deps {
+        if let Some(dep) = ids.get(d.dependency_id.as_str()) {
+            after.entry(d.task_id.as_str()).or_default().push(dep);
+        }
+    }
+    println!("| id | title | status | after |");
+    println!("|----|-------|--------|-------|");
+    for t in tasks {
+        let after = after.get(t.id.as_str()).map(|d| d.join(", ")).unwrap_or_else(|| "—".to_string());
+        println!("| `{}` | {} | {} | {} |", ids[t.id.as_str()], escape(&t.title), status_badge(&t.status), after);
+    }
+}
+
+fn status_badge(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Blocked => "⏳ blocked",
+        TaskStatus::Ready => "🟢 ready",
+        TaskStatus::InProgress => "🔄 in progress",
+        TaskStatus::Done => "✅ done",
+        TaskStatus::Skipped => "⏭️ skipped",
+        TaskStatus::Cancelled => "❌ cancelled",
+    }
+}
+
+/// Keep user text from breaking out of a table cell or line.
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
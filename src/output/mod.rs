@@ -1,2 +1,23 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::Task;
+
+pub mod csv;
+pub mod graph;
 pub mod json;
+pub mod markdown;
+pub mod table;
 pub mod text;
+
+/// Shortest id prefix (at least 8 characters) that still tells `tasks` apart;
+/// tasks created together share their leading ULID timestamp characters.
+pub fn short_ids(tasks: &[Task]) -> HashMap<&str, &str> {
+    let longest = tasks.iter().map(|t| t.id.len()).max().unwrap_or(0);
+    let len = (8..longest)
+        .find(|&len| {
+            let mut seen = HashSet::new();
+            tasks.iter().all(|t| seen.insert(&t.id[..len.min(t.id.len())]))
+        })
+        .unwrap_or(longest);
+    tasks.iter().map(|t| (t.id.as_str(), &t.id[..len.min(t.id.len())])).collect()
+}
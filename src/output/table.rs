@@ -0,0 +1,75 @@
+use crate::db::task_repo::TaskProgress;
+use crate::models::{Plan, Task};
+use crate::output::short_ids;
+
+/// Longest title `--format table` shows before cutting it off with an ellipsis.
+pub const TITLE_WIDTH: usize = 48;
+
+/// Cut `s` to at most `max` characters, marking the cut with `…`.
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let mut cut: String = s.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Render rows under a header as left-aligned columns separated by two spaces.
+/// Trailing padding is trimmed, so the last column never ends in spaces.
+pub fn render<S: AsRef<str>>(header: &[&str], rows: &[Vec<S>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.as_ref().chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell:<width$}"))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    let mut out = line(header.to_vec());
+    for row in rows {
+        out.push_str(&line(row.iter().map(|c| c.as_ref()).collect()));
+    }
+    out
+}
+
+/// `task list --format table`: ID (shortest unique prefix, at least 8 chars), STATUS,
+/// PRIORITY, AGENT, TITLE.
+pub fn tasks_table(tasks: &[Task]) -> String {
+    let ids = short_ids(tasks);
+    let rows: Vec<Vec<String>> = tasks
+        .iter()
+        .map(|t| {
+            vec![
+                ids[t.id.as_str()].to_string(),
+                t.status.as_str().to_string(),
+                t.priority.to_string(),
+                t.agent.clone().unwrap_or_default(),
+                truncate(&t.title, TITLE_WIDTH),
+            ]
+        })
+        .collect();
+    render(&["ID", "STATUS", "PRIORITY", "AGENT", "TITLE"], &rows)
+}
+
+/// `plan list --format table`: NAME, STATUS, TASKS, DONE% (done out of all tasks).
+pub fn plans_table(plans: &[(Plan, TaskProgress)]) -> String {
+    let rows: Vec<Vec<String>> = plans
+        .iter()
+        .map(|(p, progress)| {
+            vec![
+                p.name.clone(),
+                p.status.as_str().to_string(),
+                progress.total.to_string(),
+                format!("{:.0}%", progress.percentage),
+            ]
+        })
+        .collect();
+    render(&["NAME", "STATUS", "TASKS", "DONE%"], &rows)
+}
@@ -1,5 +1,8 @@
-use crate::db::task_repo::TaskProgress;
-use crate::models::{Plan, Task, PlanDocument, TaskDocument};
+use crate::db::plan_stats_repo::PlanStats;
+use crate::db::task_repo::{AgentWorkload, TaskProgress};
+use crate::graph::dep_tree::DepNode;
+use crate::graph::next_tasks::CriticalPath;
+use crate::models::{Plan, Task, PlanDocument, TaskDocument, TaskNote, TaskTransition};
 
 pub fn print_plan(p: &Plan) {
     println!("Plan: {} ({})", p.name, p.id);
@@ -11,6 +14,29 @@ pub fn print_plan(p: &Plan) {
     println!("  Created: {}", p.created_at);
 }
 
+pub fn print_critical_path(path: &CriticalPath) {
+    if path.tasks.is_empty() {
+        println!("Critical path: (no tasks)");
+        return;
+    }
+    let chain: Vec<&str> = path.tasks.iter().map(|t| t.title.as_str()).collect();
+    println!("Critical path ({} min): {}", path.total_minutes, chain.join(" → "));
+}
+
+pub fn print_waves(waves: &[Vec<Task>]) {
+    for (n, wave) in waves.iter().enumerate() {
+        if wave.is_empty() {
+            println!("Wave {n}: waiting on other plans");
+            continue;
+        }
+        let titles: Vec<&str> = wave.iter().map(|t| t.title.as_str()).collect();
+        let estimate = crate::graph::waves::wave_estimated_minutes(wave)
+            .map(|m| format!("  (~{m}min)"))
+            .unwrap_or_default();
+        println!("Wave {} ({} tasks): {}{}", n, wave.len(), titles.join(", "), estimate);
+    }
+}
+
 pub fn print_plan_list(plans: &[Plan]) {
     if plans.is_empty() {
         println!("No plans found.");
@@ -31,6 +57,19 @@ pub fn print_task(t: &Task) {
     if let Some(ref agent) = t.agent {
         println!("  Agent: {agent}");
     }
+    if !t.tags.is_empty() {
+        println!("  Tags: {}", t.tags.join(", "));
+    }
+    if let Some(minutes) = t.estimated_minutes {
+        println!("  Estimate: {minutes}min");
+    }
+    if let Some(ref deadline) = t.deadline {
+        if t.is_overdue() {
+            println!("  Deadline: {deadline} UTC (overdue)");
+        } else {
+            println!("  Deadline: {deadline} UTC");
+        }
+    }
     if let Some(ref assigned) = t.assigned_to {
         println!("  Assigned to: {assigned}");
     }
@@ -40,6 +79,15 @@ pub fn print_task(t: &Task) {
     if let Some(ref completed) = t.completed_at {
         println!("  Completed: {completed}");
     }
+    if let Some(ref note) = t.completion_note {
+        println!("  Completion note: {note}");
+    }
+    if t.fail_count > 0 {
+        match t.failure_reason {
+            Some(ref reason) => println!("  Failed: {}x, last: {reason}", t.fail_count),
+            None => println!("  Failed: {}x", t.fail_count),
+        }
+    }
 }
 
 pub fn print_task_list(tasks: &[Task]) {
@@ -57,6 +105,12 @@ pub fn print_task_list(tasks: &[Task]) {
         if !assigned.is_empty() {
             suffix.push_str(&format!(" @{assigned}"));
         }
+        for tag in &t.tags {
+            suffix.push_str(&format!(" #{tag}"));
+        }
+        if t.completion_note.is_some() {
+            suffix.push_str(" (has note)");
+        }
         println!(
             "  [{}] {} ({}) p={}{}",
             t.status.as_str(),
@@ -76,6 +130,49 @@ pub fn print_progress(p: &TaskProgress) {
     );
 }
 
+pub fn print_agent_workload(w: &AgentWorkload) {
+    for (heading, loads) in [("assigned_to", &w.by_assigned_to), ("agent", &w.by_agent)] {
+        println!("Agents by {heading}:");
+        println!("  {:<20} {:>7} {:>7} {:>11} {:>5} {:>7}", "AGENT", "BLOCKED", "READY", "IN_PROGRESS", "DONE", "ELAPSED");
+        for (name, load) in loads {
+            let p = &load.progress;
+            println!(
+                "  {:<20} {:>7} {:>7} {:>11} {:>5} {:>6}m",
+                name, p.blocked, p.ready, p.in_progress, p.done, load.in_progress_minutes
+            );
+        }
+    }
+}
+
+pub fn print_plan_stats(s: &PlanStats) {
+    let p = &s.progress;
+    let avg = s.avg_in_progress_minutes.map_or("-".to_string(), |m| format!("{m:.1}min"));
+    println!("  {:<24} {}", "Tasks", p.total);
+    println!("  {:<24} {:.1}%", "Completed", p.percentage);
+    println!(
+        "  {:<24} blocked={} ready={} in_progress={} done={} skipped={} cancelled={}",
+        "By status", p.blocked, p.ready, p.in_progress, p.done, p.skipped, p.cancelled
+    );
+    println!("  {:<24} {}", "Avg time in progress", avg);
+    println!("  {:<24} {}", "Failed at least once", s.failed_at_least_once);
+    println!("  {:<24} {}", "With documents", s.with_documents);
+    let minutes = |m: Option<f64>| m.map_or("-".to_string(), |m| format!("{m:.1}min"));
+    let c = &s.cycle_time;
+    println!(
+        "  {:<24} avg={} p50={} p90={} (n={})",
+        "Cycle time", minutes(c.avg), minutes(c.p50), minutes(c.p90), c.sample_size
+    );
+    println!("  {:<24} avg={} (n={})", "Wait time", minutes(s.wait_time.avg), s.wait_time.sample_size);
+    let throughput = s.throughput_per_hour.map_or("-".to_string(), |t| format!("{t:.1}/h"));
+    println!("  {:<24} {}", "Throughput", throughput);
+    if !s.agent_workload.is_empty() {
+        println!("\n  {:<24} {:>9}", "Agent", "Completed");
+        for w in &s.agent_workload {
+            println!("  {:<24} {:>9}", w.agent, w.completed);
+        }
+    }
+}
+
 pub fn print_task_documents(docs: &[TaskDocument]) {
     for d in docs {
         println!("\n--- Document: {} ---", d.title);
@@ -89,3 +186,49 @@ pub fn print_plan_documents(docs: &[PlanDocument]) {
         println!("{}", d.content);
     }
 }
+
+pub fn print_task_transitions(transitions: &[TaskTransition]) {
+    for t in transitions {
+        let agent = t.agent.as_ref().map(|a| format!(" (@{a})")).unwrap_or_default();
+        println!("  [{}] {}: {} → {}{}", t.at, t.event_type, t.from_status, t.to_status, agent);
+    }
+}
+
+pub fn print_dep_tree(root: &DepNode) {
+    println!("{} ({}) [{}]", root.task.title, root.task.id, root.task.status.as_str());
+    print_dep_children(root, "");
+}
+
+fn print_dep_children(node: &DepNode, prefix: &str) {
+    let count = node.dependencies.len();
+    for (i, child) in node.dependencies.iter().enumerate() {
+        let last = i + 1 == count;
+        let marker = if child.cycle {
+            " (cycle)"
+        } else if child.truncated {
+            " (…)"
+        } else {
+            ""
+        };
+        println!(
+            "{}{} {} ({}) [{}]{}",
+            prefix,
+            if last { "└─" } else { "├─" },
+            child.task.title,
+            child.task.id,
+            child.task.status.as_str(),
+            marker
+        );
+        let child_prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        print_dep_children(child, &child_prefix);
+    }
+}
+
+pub fn print_task_notes(notes: &[TaskNote]) {
+    for n in notes {
+        match n.agent {
+            Some(ref agent) => println!("  [{}] {}: {} ({})", n.created_at, agent, n.content, n.id),
+            None => println!("  [{}] {} ({})", n.created_at, n.content, n.id),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! The `plan load` document: its serde shape, parsing (JSON or YAML) and validation.
+//! Shared by the `plan load`/`plan export` commands and `api::load_plan`.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::TaskaiError;
+use crate::graph::cycle;
+use crate::models::PRIORITY_RANGE;
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct PlanLoadInput {
+    pub(crate) name: String,
+    pub(crate) title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) documents: Vec<DocInput>,
+    pub(crate) tasks: Vec<TaskInput>,
+    /// Makes retried loads return the first load's plan instead of failing or duplicating
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) idempotency_key: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct DocInput {
+    pub(crate) title: String,
+    pub(crate) content: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct TaskInput {
+    pub(crate) id: String,
+    pub(crate) title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    #[serde(default)]
+    pub(crate) priority: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) agent: Option<String>,
+    #[serde(default, alias = "estimate_minutes", skip_serializing_if = "Option::is_none")]
+    pub(crate) estimated_minutes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) after: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) documents: Vec<DocInput>,
+    /// Written by `plan export` for auditing; `plan load` ignores it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) completion_note: Option<String>,
+}
+
+/// Parse and type a `plan load` document, reporting every schema problem at once.
+pub(crate) fn parse_load_input(input: &str, format: Option<&str>) -> Result<PlanLoadInput, TaskaiError> {
+    let value = parse_load_document(input, format)?;
+
+    // Validate the raw structure first so every problem is reported at once
+    let errors = collect_load_errors(&value);
+    if !errors.is_empty() {
+        return Err(TaskaiError::validation_errors(errors));
+    }
+    let plan_input: PlanLoadInput =
+        serde_json::from_value(value).map_err(|e| TaskaiError::validation(format!("Invalid plan: {e}")))?;
+    Ok(plan_input)
+}
+
+/// Parse `plan load` input as JSON or YAML. Without a format, JSON is tried first and
+/// YAML only counts if it yields a mapping; otherwise the JSON error is reported.
+fn parse_load_document(input: &str, format: Option<&str>) -> Result<serde_json::Value, TaskaiError> {
+    let json = || serde_json::from_str(input).map_err(|e| TaskaiError::validation(format!("Invalid JSON: {e}")));
+    let yaml = || serde_yaml::from_str(input).map_err(|e| TaskaiError::validation(format!("Invalid YAML: {e}")));
+    match format {
+        Some("json") => json(),
+        Some("yaml") => yaml(),
+        _ => json().or_else(|json_err| match yaml() {
+            Ok(value @ serde_json::Value::Object(_)) => Ok(value),
+            _ => Err(json_err),
+        }),
+    }
+}
+
+/// Walk raw `plan load` JSON and collect every schema problem, each prefixed with
+/// its field path (e.g. `tasks[2].priority must be an integer`).
+fn collect_load_errors(value: &serde_json::Value) -> Vec<String> {
+    use serde_json::Value;
+
+    let mut errors = Vec::new();
+    let Some(root) = value.as_object() else {
+        return vec!["plan must be a JSON object".to_string()];
+    };
+
+    let required_string = |errors: &mut Vec<String>, obj: &serde_json::Map<String, Value>, path: &str, key: &str| {
+        match obj.get(key) {
+            None | Some(Value::Null) => errors.push(format!("{path}{key} is required")),
+            Some(Value::String(s)) if s.is_empty() => errors.push(format!("{path}{key} must not be empty")),
+            Some(Value::String(_)) => {}
+            Some(_) => errors.push(format!("{path}{key} must be a string")),
+        }
+    };
+    let optional_string = |errors: &mut Vec<String>, obj: &serde_json::Map<String, Value>, path: &str, key: &str| {
+        if let Some(v) = obj.get(key) {
+            if !v.is_null() && !v.is_string() {
+                errors.push(format!("{path}{key} must be a string"));
+            }
+        }
+    };
+    let documents = |errors: &mut Vec<String>, obj: &serde_json::Map<String, Value>, path: &str| {
+        match obj.get("documents") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(docs)) => {
+                for (i, doc) in docs.iter().enumerate() {
+                    let doc_path = format!("{path}documents[{i}].");
+                    match doc.as_object() {
+                        Some(d) => {
+                            required_string(errors, d, &doc_path, "title");
+                            match d.get("content") {
+                                Some(Value::String(_)) => {}
+                                None | Some(Value::Null) => errors.push(format!("{doc_path}content is required")),
+                                Some(_) => errors.push(format!("{doc_path}content must be a string")),
+                            }
+                        }
+                        None => errors.push(format!("{path}documents[{i}] must be an object")),
+                    }
+                }
+            }
+            Some(_) => errors.push(format!("{path}documents must be an array")),
+        }
+    };
+
+    required_string(&mut errors, root, "", "name");
+    if let Some(name) = root.get("name").and_then(Value::as_str) {
+        if !name.is_empty() && validate_plan_name(name).is_err() {
+            errors.push("name must match ^[a-z0-9][a-z0-9-]*[a-z0-9]$ (or single char [a-z0-9])".to_string());
+        }
+    }
+    required_string(&mut errors, root, "", "title");
+    optional_string(&mut errors, root, "", "description");
+    optional_string(&mut errors, root, "", "idempotency_key");
+    documents(&mut errors, root, "");
+
+    let tasks = match root.get("tasks") {
+        Some(Value::Array(tasks)) if tasks.is_empty() => {
+            errors.push("tasks must contain at least one task".to_string());
+            return errors;
+        }
+        Some(Value::Array(tasks)) => tasks,
+        None | Some(Value::Null) => {
+            errors.push("tasks is required".to_string());
+            return errors;
+        }
+        Some(_) => {
+            errors.push("tasks must be an array".to_string());
+            return errors;
+        }
+    };
+
+    // Known temp IDs first, so `after` can be checked regardless of order
+    let mut seen_ids = HashSet::new();
+    for (i, task) in tasks.iter().enumerate() {
+        if let Some(id) = task.get("id").and_then(Value::as_str) {
+            if !id.is_empty() && !seen_ids.insert(id) {
+                errors.push(format!("tasks[{i}].id '{id}': Duplicate task id"));
+            }
+        }
+    }
+
+    for (i, task) in tasks.iter().enumerate() {
+        let path = format!("tasks[{i}].");
+        let Some(t) = task.as_object() else {
+            errors.push(format!("tasks[{i}] must be an object"));
+            continue;
+        };
+        required_string(&mut errors, t, &path, "id");
+        required_string(&mut errors, t, &path, "title");
+        optional_string(&mut errors, t, &path, "description");
+        optional_string(&mut errors, t, &path, "agent");
+
+        if let Some(v) = t.get("priority") {
+            if !v.is_null() && v.as_i64().and_then(|p| i32::try_from(p).ok()).is_none() {
+                errors.push(format!("{path}priority must be an integer"));
+            }
+        }
+        for key in ["estimated_minutes", "estimate_minutes"] {
+            if let Some(v) = t.get(key) {
+                if !v.is_null() && v.as_i64().is_none_or(|m| m < 0) {
+                    errors.push(format!("{path}{key} must be a non-negative integer"));
+                }
+            }
+        }
+
+        match t.get("tags") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(tags)) => {
+                for (j, tag) in tags.iter().enumerate() {
+                    match tag.as_str() {
+                        Some(s) if validate_tag(s).is_ok() => {}
+                        Some(_) => errors.push(format!("{path}tags[{j}] must match ^[a-z0-9][a-z0-9-]*$")),
+                        None => errors.push(format!("{path}tags[{j}] must be a string")),
+                    }
+                }
+            }
+            Some(_) => errors.push(format!("{path}tags must be an array")),
+        }
+
+        let own_id = t.get("id").and_then(Value::as_str);
+        match t.get("after") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(after)) => {
+                for (j, dep) in after.iter().enumerate() {
+                    match dep.as_str() {
+                        Some(d) if Some(d) == own_id => {
+                            errors.push(format!("{path}after[{j}] '{d}': task depends on itself"));
+                        }
+                        Some(d) if !seen_ids.contains(d) => {
+                            errors.push(format!("{path}after[{j}] references unknown dependency '{d}'"));
+                        }
+                        Some(_) => {}
+                        None => errors.push(format!("{path}after[{j}] must be a string")),
+                    }
+                }
+            }
+            Some(_) => errors.push(format!("{path}after must be an array")),
+        }
+
+        documents(&mut errors, t, &path);
+    }
+
+    errors
+}
+
+/// Checks that need the whole typed input (the field-level ones live in `collect_load_errors`).
+pub(crate) fn validate_load_input(input: &PlanLoadInput, allow_extreme_priority: bool) -> Result<(), TaskaiError> {
+    if input.idempotency_key.as_deref().is_some_and(|k| k.trim().is_empty()) {
+        return Err(TaskaiError::validation("idempotency_key must not be empty"));
+    }
+    if !allow_extreme_priority {
+        let errors: Vec<String> = input
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| !PRIORITY_RANGE.contains(&t.priority))
+            .map(|(i, t)| {
+                format!(
+                    "tasks[{i}] '{}': priority {} is outside {}..={} (use --allow-extreme-priority)",
+                    t.id, t.priority, PRIORITY_RANGE.start(), PRIORITY_RANGE.end()
+                )
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(TaskaiError::validation_errors(errors));
+        }
+    }
+
+    // Cycle detection
+    let nodes: Vec<String> = input.tasks.iter().map(|t| t.id.clone()).collect();
+    let edges: Vec<(String, String)> = input
+        .tasks
+        .iter()
+        .flat_map(|t| t.after.iter().map(move |dep| (t.id.clone(), dep.clone())))
+        .collect();
+    cycle::detect_cycle(&nodes, &edges)?;
+
+    Ok(())
+}
+
+/// Non-fatal surprises in a `plan load` document, reported under `warnings`.
+pub(crate) fn load_warnings(input: &PlanLoadInput) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let negative: Vec<&str> = input.tasks.iter().filter(|t| t.priority < 0).map(|t| t.id.as_str()).collect();
+    if !negative.is_empty() && input.tasks.iter().any(|t| t.priority == 0) {
+        warnings.push(format!(
+            "Tasks with negative priority ({}) run after every task left at the default priority 0, \
+             regardless of plan order",
+            negative.join(", ")
+        ));
+    }
+    warnings
+}
+
+pub(crate) fn validate_plan_name(name: &str) -> Result<(), TaskaiError> {
+    let re = regex_lite(name);
+    if !re {
+        return Err(TaskaiError::validation(
+            "Plan name must match ^[a-z0-9][a-z0-9-]*[a-z0-9]$ (or single char [a-z0-9])",
+        ));
+    }
+    Ok(())
+}
+
+fn regex_lite(name: &str) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+    if name.len() == 1 {
+        return name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+    }
+    let chars: Vec<char> = name.chars().collect();
+    let first = chars[0];
+    let last = *chars.last().unwrap();
+    if !(first.is_ascii_lowercase() || first.is_ascii_digit()) {
+        return false;
+    }
+    if !(last.is_ascii_lowercase() || last.is_ascii_digit()) {
+        return false;
+    }
+    chars.iter().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-')
+}
+
+pub(crate) fn validate_tag(tag: &str) -> Result<(), TaskaiError> {
+    let mut chars = tag.chars();
+    let valid_first = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+    let valid_rest = chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid_first || !valid_rest {
+        return Err(TaskaiError::validation("Tag must match ^[a-z0-9][a-z0-9-]*$"));
+    }
+    Ok(())
+}
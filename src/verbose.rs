@@ -0,0 +1,25 @@
+//! `--verbose` diagnostics. Everything goes to stderr so the stdout payload (text or
+//! JSON) stays machine-readable.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Turn on `verbose!` output for the rest of the process.
+pub fn enable() {
+    VERBOSE.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// `eprintln!` with a `[taskai]` prefix, only when `--verbose` is on.
+#[macro_export]
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::verbose::enabled() {
+            eprintln!("[taskai] {}", format_args!($($arg)*));
+        }
+    };
+}
@@ -23,9 +23,14 @@ impl TestEnv {
         Self { dir }
     }
 
+    #[allow(deprecated)]
     fn cmd(&self) -> Command {
         let mut cmd = Command::cargo_bin("taskai").expect("binary");
         cmd.current_dir(self.dir.path());
+        cmd.env_remove("TASKAI_DB_PATH")
+            .env_remove("TASKAI_DB")
+            .env_remove("TASKAI_CLAIM_TIMEOUT")
+            .env_remove("TASKAI_PLAN");
         cmd
     }
 
@@ -50,6 +55,11 @@ impl TestEnv {
         v
     }
 
+    fn db(&self) -> rusqlite::Connection {
+        rusqlite::Connection::open(self.dir.path().join(".worktoolai/taskai/taskai.db"))
+            .expect("open db")
+    }
+
     fn write_plan(&self, filename: &str, content: &str) -> PathBuf {
         let p = self.dir.path().join(filename);
         fs::write(&p, content).expect("write plan file");
@@ -223,6 +233,26 @@ fn test_plan_not_found() {
     assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
 }
 
+#[test]
+fn test_plan_deactivate() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    let plan_id = env.run_ok(&["plan", "show", "test-plan"])["data"]["plan"]["id"].clone();
+
+    let v = env.run_ok(&["plan", "deactivate"]);
+    assert_eq!(v["data"]["deactivated"], serde_json::json!({"id": plan_id, "name": "test-plan"}));
+    let v = env.run_err(&["next"]);
+    assert_eq!(v["error"]["code"], "NO_ACTIVE_PLAN");
+    let v = env.run_err(&["status"]);
+    assert_eq!(v["error"]["code"], "NO_ACTIVE_PLAN");
+
+    // Nothing active: still a success
+    let v = env.run_ok(&["plan", "deactivate"]);
+    assert!(v["data"]["deactivated"].is_null());
+    env.run_ok(&["plan", "show", "test-plan"]);
+    env.run_ok(&["status", "--plan", "test-plan"]);
+}
+
 // ─── 3. plan load ──────────────────────────────────────────────────
 
 #[test]
@@ -1226,3 +1256,4419 @@ fn test_text_output_error() {
         .code(1)
         .stderr(predicate::str::contains("not initialized"));
 }
+
+// ─── 16. task update / edit ────────────────────────────────────────
+
+#[test]
+fn test_task_edit_updates_given_fields_only() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_ok(&["task", "edit", &t1, "--title", "Renamed", "--priority", "3"]);
+    assert_eq!(v["data"]["task"]["title"], "Renamed");
+    assert_eq!(v["data"]["task"]["priority"], 3);
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["description"], "Do first", "untouched field preserved");
+}
+
+#[test]
+fn test_task_edit_terminal_task() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "done", &t1]);
+
+    let v = env.run_ok(&["task", "edit", &t1, "--description", "post-mortem"]);
+    assert_eq!(v["data"]["task"]["status"], "done");
+
+    let v = env.run_err(&["task", "edit", &t1, "--priority", "1"]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+    let v = env.run_err(&["task", "edit", &t1, "--title", "New"]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+#[test]
+fn test_task_update_priority_affects_next() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"upd","title":"U","tasks":[
+            {"id":"a","title":"A","priority":5},
+            {"id":"b","title":"B","priority":1}
+        ]
+    })
+    .to_string();
+    let loaded = env.load_plan(&json);
+    let b = get_task_id(&loaded, "b");
+
+    let v = env.run_ok(&["task", "update", &b, "--priority", "9", "--agent", "coder"]);
+    assert_eq!(v["data"]["task"]["agent"], "coder");
+
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["title"], "B");
+}
+
+#[test]
+fn test_task_update_requires_a_field() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let v = env.run_err(&["task", "update", &get_task_id(&loaded, "t1")]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 17. task reorder ──────────────────────────────────────────────
+
+#[test]
+fn test_task_reorder_changes_next_tie_break() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"ro","title":"RO","tasks":[
+            {"id":"a","title":"A"},
+            {"id":"b","title":"B"},
+            {"id":"c","title":"C"}
+        ]
+    })
+    .to_string();
+    let loaded = env.load_plan(&json);
+    let a = get_task_id(&loaded, "a");
+    let c = get_task_id(&loaded, "c");
+
+    let v = env.run_ok(&["task", "reorder", &c, "--before", &a]);
+    assert_eq!(v["data"]["changed"].as_array().unwrap().len(), 3);
+
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["title"], "C");
+
+    env.run_ok(&["task", "reorder", &c, "--position", "10"]);
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["title"], "A");
+}
+
+#[test]
+fn test_task_reorder_after_renumbers_contiguously() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"ro2","title":"RO2","tasks":[
+            {"id":"a","title":"A"},
+            {"id":"b","title":"B"},
+            {"id":"c","title":"C"}
+        ]
+    })
+    .to_string();
+    let loaded = env.load_plan(&json);
+    let (a, b, c) = (get_task_id(&loaded, "a"), get_task_id(&loaded, "b"), get_task_id(&loaded, "c"));
+
+    let v = env.run_ok(&["task", "reorder", &a, "--after", &b]);
+    let order: Vec<&str> = v["data"]["order"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x.as_str().unwrap())
+        .collect();
+    assert_eq!(order, vec![b.as_str(), a.as_str(), c.as_str()]);
+
+    let v = env.run_err(&["task", "reorder", &a, "--before", &b, "--after", &c]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[test]
+fn test_task_reorder_requires_one_target() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let v = env.run_err(&["task", "reorder", &t1]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 18. task clone ────────────────────────────────────────────────
+
+#[test]
+fn test_task_clone_copies_fields_and_documents() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "start", &t1, "--agent", "bot"]);
+
+    let v = env.run_ok(&["task", "clone", &t1, "--title", "First Again"]);
+    assert_eq!(v["data"]["original_id"], t1.as_str());
+    assert_eq!(v["data"]["documents_copied"], 1);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+    assert_eq!(v["data"]["task"]["priority"], 10);
+    let clone_id = v["data"]["task"]["id"].as_str().unwrap().to_string();
+    assert_ne!(clone_id, t1);
+
+    let v = env.run_ok(&["task", "show", &clone_id]);
+    assert_eq!(v["data"]["task"]["title"], "First Again");
+    assert_eq!(v["data"]["task"]["description"], "Do first");
+    assert!(v["data"]["task"]["assigned_to"].is_null());
+    assert!(v["data"]["task"]["started_at"].is_null());
+    assert_eq!(v["data"]["documents"][0]["title"], "Task Doc");
+}
+
+#[test]
+fn test_task_clone_with_after_is_blocked() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t4 = get_task_id(&loaded, "t4");
+
+    let v = env.run_ok(&["task", "clone", &t1, "--after", &t4]);
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+}
+
+// ─── 19. task delete ───────────────────────────────────────────────
+
+#[test]
+fn test_task_delete_unblocks_dependents() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"del-task","title":"DT","tasks":[
+            {"id":"a","title":"A"},
+            {"id":"b","title":"B","after":["a"]}
+        ]
+    })
+    .to_string();
+    let loaded = env.load_plan(&json);
+    let a = get_task_id(&loaded, "a");
+    let b = get_task_id(&loaded, "b");
+
+    let v = env.run_ok(&["task", "delete", &a]);
+    assert_eq!(v["data"]["deleted"]["id"], a.as_str());
+    assert_eq!(v["data"]["newly_ready"][0]["id"], b.as_str());
+
+    let v = env.run_ok(&["task", "show", &b]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+    assert_eq!(v["data"]["dependencies"].as_array().unwrap().len(), 0);
+
+    let v = env.run_err(&["task", "show", &a]);
+    assert_eq!(v["error"]["code"], "TASK_NOT_FOUND");
+}
+
+#[test]
+fn test_task_delete_keeps_other_blockers() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t4 = get_task_id(&loaded, "t4");
+    env.run_ok(&["task", "done", &t1]);
+
+    let v = env.run_ok(&["task", "delete", &t2]);
+    assert!(v["data"]["newly_ready"].is_null(), "t4 still waits on t3");
+    let v = env.run_ok(&["task", "show", &t4]);
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+}
+
+// ─── 20. task search ───────────────────────────────────────────────
+
+#[test]
+fn test_task_search_matches_title_and_description() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_ok(&["task", "search", "Task"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 4);
+    assert_eq!(tasks[0]["title"], "First Task", "highest priority first");
+
+    let v = env.run_ok(&["task", "search", "do first"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["title"], "First Task");
+
+    let v = env.run_ok(&["task", "search", "nothing-like-this"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_task_search_no_active_plan() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let v = env.run_err(&["task", "search", "x"]);
+    assert_eq!(v["error"]["code"], "NO_ACTIVE_PLAN");
+}
+
+// ─── 21. plan archive ──────────────────────────────────────────────
+
+#[test]
+fn test_plan_archive_hides_from_next_and_list() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_ok(&["plan", "archive", "test-plan"]);
+    assert_eq!(v["data"]["archived"]["name"], "test-plan");
+
+    // Archiving the active plan clears the config
+    let v = env.run_err(&["next"]);
+    assert_eq!(v["error"]["code"], "NO_ACTIVE_PLAN");
+    let v = env.run_err(&["next", "--plan", "test-plan"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+    let v = env.run_err(&["status", "--plan", "test-plan"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+
+    let v = env.run_ok(&["plan", "list"]);
+    assert_eq!(v["data"]["plans"].as_array().unwrap().len(), 0);
+    let v = env.run_ok(&["plan", "list", "--all"]);
+    assert_eq!(v["data"]["plans"][0]["status"], "archived");
+    let v = env.run_ok(&["plan", "list", "--include-archived"]);
+    assert_eq!(v["data"]["plans"][0]["status"], "archived");
+}
+
+#[test]
+fn test_plan_unarchive() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    env.run_ok(&["plan", "archive", "test-plan"]);
+
+    let v = env.run_ok(&["plan", "unarchive", "test-plan"]);
+    assert_eq!(v["data"]["unarchived"]["name"], "test-plan");
+    let v = env.run_ok(&["next", "--plan", "test-plan"]);
+    assert_eq!(v["data"]["task"]["title"], "First Task");
+    let v = env.run_ok(&["plan", "list"]);
+    assert_eq!(v["data"]["plans"][0]["status"], "active");
+
+    let v = env.run_err(&["plan", "unarchive", "test-plan"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[test]
+fn test_plan_activate_unarchives() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    env.run_ok(&["plan", "archive", "test-plan"]);
+    env.run_ok(&["plan", "activate", "test-plan"]);
+
+    let v = env.run_ok(&["plan", "show", "test-plan"]);
+    assert_eq!(v["data"]["plan"]["status"], "active");
+    env.run_ok(&["next"]);
+}
+
+// ─── 22. task list filters ─────────────────────────────────────────
+
+#[test]
+fn test_task_list_status_filter() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    env.run_ok(&["task", "start", &get_task_id(&loaded, "t1")]);
+
+    let v = env.run_ok(&["task", "list", "--status", "blocked"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 3);
+    assert_eq!(v["data"]["progress"]["total"], 4, "progress covers the whole plan");
+
+    let v = env.run_ok(&["task", "list", "--status", "in_progress", "--status", "ready"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["status"], "in_progress");
+
+    let v = env.run_err(&["task", "list", "--status", "bogus"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    assert!(v["error"]["message"].as_str().unwrap().contains("in_progress"));
+}
+
+#[test]
+fn test_task_list_fields_selection() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_ok(&["task", "list", "--fields", "id,status"]);
+    let first = v["data"]["tasks"][0].as_object().unwrap();
+    let mut keys: Vec<&str> = first.keys().map(|k| k.as_str()).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["id", "status"]);
+}
+
+#[test]
+fn test_task_list_agent_and_assigned_filters() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"agents","title":"AG","tasks":[
+            {"id":"a","title":"A","agent":"coder"},
+            {"id":"b","title":"B","agent":"coder"},
+            {"id":"c","title":"C","agent":"reviewer"}
+        ]
+    })
+    .to_string();
+    let loaded = env.load_plan(&json);
+    env.run_ok(&["task", "start", &get_task_id(&loaded, "a"), "--agent", "bot-1"]);
+
+    let v = env.run_ok(&["task", "list", "--agent", "coder"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 2);
+    assert_eq!(v["data"]["filter"]["agent"], "coder");
+
+    let v = env.run_ok(&["task", "list", "--agent", "coder", "--status", "ready"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["title"], "B");
+
+    let v = env.run_ok(&["task", "list", "--assigned-to", "bot-1"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["title"], "A");
+
+    let v = env.run_ok(&["task", "list"]);
+    assert!(v["data"]["filter"].is_null());
+}
+
+#[test]
+fn test_task_list_sort() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"sorting","title":"S","tasks":[
+            {"id":"a","title":"Charlie","priority":1},
+            {"id":"b","title":"Alpha","priority":5},
+            {"id":"c","title":"Bravo","priority":3}
+        ]
+    })
+    .to_string();
+    env.load_plan(&json);
+    let titles = |v: &Value| -> Vec<String> {
+        v["data"]["tasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let v = env.run_ok(&["task", "list", "--sort", "title"]);
+    assert_eq!(titles(&v), vec!["Alpha", "Bravo", "Charlie"]);
+    assert_eq!(v["data"]["sort"]["field"], "title");
+
+    let v = env.run_ok(&["task", "list", "--sort", "priority:desc"]);
+    assert_eq!(titles(&v), vec!["Alpha", "Bravo", "Charlie"]);
+    assert_eq!(v["data"]["sort"]["direction"], "desc");
+
+    let v = env.run_err(&["task", "list", "--sort", "title; DROP TABLE tasks"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["task", "list", "--sort", "title:sideways"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 23. plan rename ───────────────────────────────────────────────
+
+#[test]
+fn test_plan_rename_keeps_active_pointer() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_ok(&["plan", "rename", "test-plan", "better-name", "--title", "Better"]);
+    assert_eq!(v["data"]["plan"]["name"], "better-name");
+    assert_eq!(v["data"]["plan"]["title"], "Better");
+
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["plan"]["name"], "better-name");
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["title"], "First Task");
+}
+
+#[test]
+fn test_plan_rename_conflict_and_validation() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    env.run_ok(&["plan", "create", "other"]);
+
+    let v = env.run_err(&["plan", "rename", "test-plan", "other"]);
+    assert_eq!(v["error"]["code"], "PLAN_NAME_CONFLICT");
+    let v = env.run_err(&["plan", "rename", "test-plan", "Bad Name"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[test]
+fn test_plan_rename_with_to_flag() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let plan_id = loaded["data"]["plan"]["id"].as_str().unwrap().to_string();
+
+    let v = env.run_ok(&["plan", "rename", "test-plan", "--to", "renamed"]);
+    assert_eq!(v["data"]["id"], plan_id.as_str());
+    assert_eq!(v["data"]["old_name"], "test-plan");
+    assert_eq!(v["data"]["new_name"], "renamed");
+
+    let v = env.run_ok(&["plan", "show", "renamed"]);
+    assert_eq!(v["data"]["plan"]["id"], plan_id.as_str());
+    env.run_err(&["plan", "show", "test-plan"]);
+}
+
+// ─── 24. plan export ───────────────────────────────────────────────
+
+#[test]
+fn test_plan_export_round_trip() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let output = env.cmd().args(["plan", "export", "test-plan"]).output().unwrap();
+    assert!(output.status.success());
+    let mut exported: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(exported["tasks"].as_array().unwrap().len(), 4);
+    assert_eq!(exported["documents"][0]["title"], "Design Doc");
+    assert_eq!(exported["tasks"][0]["priority"], 10);
+    assert_eq!(exported["tasks"][3]["after"].as_array().unwrap().len(), 2);
+
+    exported["name"] = Value::from("test-plan-copy");
+    let v = env.load_plan(&exported.to_string());
+    assert_eq!(v["data"]["tasks_created"], 4);
+    let ready: Vec<&str> = v["data"]["ready_now"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(ready, vec!["First Task"]);
+
+    let a = env.run_ok(&["task", "list", "--plan", "test-plan"]);
+    let b = env.run_ok(&["task", "list", "--plan", "test-plan-copy"]);
+    let titles = |v: &Value| -> Vec<String> {
+        v["data"]["tasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(titles(&a), titles(&b), "sort order preserved");
+}
+
+#[test]
+fn test_plan_export_uses_slug_ids() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let output = env.cmd().args(["plan", "export", "test-plan"]).output().unwrap();
+    let exported: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let ids: Vec<&str> = exported["tasks"].as_array().unwrap()
+        .iter().map(|t| t["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["t1", "t2", "t3", "t4"]);
+    assert_eq!(exported["tasks"][3]["after"], serde_json::json!(["t2", "t3"]));
+}
+
+#[test]
+fn test_plan_export_yaml() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let output = env.cmd().args(["plan", "export", "test-plan", "--format", "yaml"]).output().unwrap();
+    assert!(output.status.success());
+    let yaml = String::from_utf8(output.stdout).unwrap();
+    assert!(yaml.starts_with("name: test-plan\n"), "{yaml}");
+    assert!(yaml.contains("- id: t1\n"), "{yaml}");
+    assert!(yaml.contains("  priority: 10\n"), "{yaml}");
+    assert!(yaml.contains("  after:\n  - t2\n  - t3\n"), "{yaml}");
+}
+
+// ─── 25. reclaim stale in_progress tasks ───────────────────────────
+
+fn backdate_start(env: &TestEnv, task_id: &str, minutes: i64) {
+    env.db()
+        .execute(
+            "UPDATE tasks SET started_at = datetime('now', ?1) WHERE id = ?2",
+            rusqlite::params![format!("-{minutes} minutes"), task_id],
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_task_reclaim_releases_stale_tasks() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"lease","title":"L","tasks":[
+            {"id":"a","title":"A"},
+            {"id":"b","title":"B"}
+        ]
+    })
+    .to_string();
+    let loaded = env.load_plan(&json);
+    let a = get_task_id(&loaded, "a");
+    let b = get_task_id(&loaded, "b");
+    env.run_ok(&["task", "start", &a, "--agent", "crashed"]);
+    env.run_ok(&["task", "start", &b, "--agent", "alive"]);
+    backdate_start(&env, &a, 90);
+
+    let v = env.run_ok(&["task", "reclaim", "--older-than", "60"]);
+    let reclaimed = v["data"]["reclaimed"].as_array().unwrap();
+    assert_eq!(reclaimed.len(), 1);
+    assert_eq!(reclaimed[0]["id"], a.as_str());
+    assert_eq!(reclaimed[0]["status"], "ready");
+
+    let v = env.run_ok(&["task", "show", &a]);
+    assert!(v["data"]["task"]["assigned_to"].is_null());
+    assert!(v["data"]["task"]["started_at"].is_null(), "{v}");
+    let v = env.run_ok(&["task", "show", &b]);
+    assert_eq!(v["data"]["task"]["status"], "in_progress");
+}
+
+#[test]
+fn test_next_reclaim_after_then_claims() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name":"lease2","title":"L2","tasks":[{"id":"a","title":"A"}]
+    })
+    .to_string();
+    let loaded = env.load_plan(&json);
+    let a = get_task_id(&loaded, "a");
+    env.run_ok(&["next", "--claim", "--agent", "crashed"]);
+
+    let v = env.run_ok(&["next", "--reclaim-after", "30"]);
+    assert_eq!(v["data"]["reason"], "ALL_IN_PROGRESS");
+    assert_eq!(v["data"]["reclaimed"].as_array().unwrap().len(), 0);
+
+    backdate_start(&env, &a, 45);
+    let v = env.run_ok(&["next", "--claim", "--agent", "fresh", "--reclaim-after", "30"]);
+    assert_eq!(v["data"]["reclaimed"][0], a.as_str());
+    assert_eq!(v["data"]["task"]["id"], a.as_str());
+    assert_eq!(v["data"]["task"]["assigned_to"], "fresh");
+}
+
+// ─── 26. task reset ────────────────────────────────────────────────
+
+#[test]
+fn test_task_reset_done_reblocks_dependents() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    env.run_ok(&["task", "done", &t1]);
+
+    let v = env.run_ok(&["task", "reset", &t1]);
+    assert_eq!(v["data"]["completed_task"]["status"], "ready");
+    assert_eq!(v["data"]["newly_blocked"].as_array().unwrap().len(), 2);
+    assert!(v["data"].get("reblocked").is_none(), "one key for the list: {v}");
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert!(v["data"]["task"]["completed_at"].is_null());
+    let v = env.run_ok(&["task", "show", &t2]);
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+}
+
+#[test]
+fn test_task_reset_cancelled_with_unmet_deps_is_blocked() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t2 = get_task_id(&loaded, "t2");
+    env.run_ok(&["task", "cancel", &t2]);
+
+    let v = env.run_ok(&["task", "reset", &t2]);
+    assert_eq!(v["data"]["completed_task"]["status"], "blocked");
+    assert_eq!(v["data"]["newly_blocked"], serde_json::json!([]));
+
+    let v = env.run_err(&["task", "reset", &t2]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+#[test]
+fn test_task_reset_clears_claim_and_reports_newly_blocked() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t3 = get_task_id(&loaded, "t3");
+    env.run_ok(&["task", "start", &t1, "--agent", "worker"]);
+    env.run_ok(&["task", "done", &t1]);
+
+    let v = env.run_ok(&["task", "reset", &t1]);
+    let mut ids: Vec<&str> = v["data"]["newly_blocked"].as_array().unwrap()
+        .iter().map(|t| t["id"].as_str().unwrap()).collect();
+    ids.sort();
+    let mut expected = vec![t2.as_str(), t3.as_str()];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+    assert!(v["data"]["task"]["started_at"].is_null());
+    assert!(v["data"]["task"]["assigned_to"].is_null());
+}
+
+#[test]
+fn test_task_reset_reports_in_progress_dependents() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t3 = get_task_id(&loaded, "t3");
+    env.run_ok(&["task", "done", &t1]);
+    env.run_ok(&["task", "start", &t2, "--agent", "worker"]);
+
+    let v = env.run_ok(&["task", "reset", &t1]);
+    assert_eq!(v["data"]["newly_blocked"][0]["id"], t3.as_str(), "{v}");
+    let running = v["data"]["in_progress_on_unmet_deps"].as_array().unwrap();
+    assert_eq!(running.len(), 1, "{v}");
+    assert_eq!(running[0]["id"], t2.as_str());
+
+    // Left running: the agent already on it decides what to do
+    let v = env.run_ok(&["task", "show", &t2]);
+    assert_eq!(v["data"]["task"]["status"], "in_progress");
+}
+
+// ─── 27. next --for-agent ──────────────────────────────────────────
+
+fn agent_plan_json() -> String {
+    serde_json::json!({
+        "name":"routing","title":"R","tasks":[
+            {"id":"a","title":"For reviewer","priority":9,"agent":"reviewer"},
+            {"id":"b","title":"Unassigned","priority":5},
+            {"id":"c","title":"For coder","priority":1,"agent":"coder"}
+        ]
+    })
+    .to_string()
+}
+
+#[test]
+fn test_next_for_agent_filters_candidates() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&agent_plan_json());
+
+    let v = env.run_ok(&["next", "--for-agent", "coder"]);
+    assert_eq!(v["data"]["task"]["title"], "Unassigned");
+
+    let v = env.run_ok(&["next", "--for-agent", "coder", "--strict", "--claim", "--agent", "c1"]);
+    assert_eq!(v["data"]["task"]["title"], "For coder");
+    assert_eq!(v["data"]["task"]["status"], "in_progress");
+
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["title"], "For reviewer");
+}
+
+#[test]
+fn test_next_for_agent_no_match() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&agent_plan_json());
+
+    let output = env
+        .cmd()
+        .args(["next", "--for-agent", "tester", "--strict", "--json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(v["data"]["task"].is_null());
+    assert_eq!(v["data"]["reason"], "NO_TASK_FOR_AGENT");
+}
+
+#[test]
+fn test_next_for_agent_reports_filter_and_claims_as_agent() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&agent_plan_json());
+
+    let v = env.run_ok(&["next", "--for-agent", "coder", "--strict-agent", "--claim"]);
+    assert_eq!(v["data"]["agent_filter"], "coder");
+    assert_eq!(v["data"]["task"]["title"], "For coder");
+    assert_eq!(v["data"]["task"]["assigned_to"], "coder", "claims under the filter name");
+
+    let v = env.run_ok(&["next"]);
+    assert!(v["data"]["agent_filter"].is_null());
+
+    let output = env.cmd().args(["next", "--for-agent", "reviewer"]).output().unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("For reviewer") && text.contains("(filtered for agent: reviewer)"), "{text}");
+}
+
+// ─── 28. task notes ────────────────────────────────────────────────
+
+#[test]
+fn test_task_note_add_list_delete() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_ok(&["task", "note", "add", &t1, "compile error in foo.rs"]);
+    let note_id = v["data"]["note"]["id"].as_str().unwrap().to_string();
+    env.run_ok(&["task", "note", "add", &t1, "fixed by bumping dep"]);
+
+    let v = env.run_ok(&["task", "note", "list", &t1]);
+    let notes = v["data"]["notes"].as_array().unwrap();
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0]["content"], "fixed by bumping dep", "newest first");
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["notes"].as_array().unwrap().len(), 2);
+    assert_eq!(v["data"]["task"]["status"], "ready", "notes do not change status");
+
+    env.run_ok(&["task", "note", "delete", &note_id]);
+    let v = env.run_ok(&["task", "note", "list", &t1]);
+    assert_eq!(v["data"]["notes"].as_array().unwrap().len(), 1);
+    let v = env.run_err(&["task", "note", "delete", &note_id]);
+    assert_eq!(v["error"]["code"], "NOTE_NOT_FOUND");
+}
+
+#[test]
+fn test_task_notes_cascade_on_task_delete() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t4 = get_task_id(&loaded, "t4");
+    env.run_ok(&["task", "note", "add", &t4, "obsolete"]);
+    env.run_ok(&["task", "delete", &t4]);
+
+    let count: i64 = env
+        .db()
+        .query_row("SELECT COUNT(*) FROM task_notes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+// ─── 29. task tags ─────────────────────────────────────────────────
+
+#[test]
+fn test_task_tag_add_remove_list() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    env.run_ok(&["task", "tag", "add", &t1, "gpu"]);
+    let v = env.run_ok(&["task", "tag", "add", &t1, "needs-human"]);
+    assert_eq!(v["data"]["tags"], serde_json::json!(["gpu", "needs-human"]));
+
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["tags"], serde_json::json!(["gpu", "needs-human"]));
+
+    env.run_ok(&["task", "tag", "remove", &t1, "gpu"]);
+    let v = env.run_ok(&["task", "tag", "list", &t1]);
+    assert_eq!(v["data"]["tags"], serde_json::json!(["needs-human"]));
+
+    let v = env.run_err(&["task", "tag", "add", &t1, "Bad_Tag"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[test]
+fn test_task_list_tag_filter() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    env.run_ok(&["task", "tag", "add", &get_task_id(&loaded, "t2"), "slow"]);
+    env.run_ok(&["task", "tag", "add", &get_task_id(&loaded, "t3"), "slow"]);
+
+    let v = env.run_ok(&["task", "list", "--tag", "slow"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0]["tags"][0], "slow");
+    assert_eq!(v["data"]["filter"]["tag"], "slow");
+}
+
+// ─── 30. plan graph ────────────────────────────────────────────────
+
+#[test]
+fn test_plan_graph_dot() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    env.run_ok(&["task", "add", "Say \"hi\""]);
+
+    let output = env.cmd().args(["plan", "graph", "test-plan"]).output().unwrap();
+    assert!(output.status.success());
+    let dot = String::from_utf8_lossy(&output.stdout);
+    assert!(dot.starts_with("digraph \"test-plan\" {"));
+    assert!(dot.contains(&format!("\"{t1}\" -> \"{t2}\";")));
+    assert!(dot.contains(&format!("\"{t1}\" [label=\"First Task\\n[ready]\", fillcolor=green];")), "{dot}");
+    assert!(dot.contains("fillcolor=yellow"));
+    assert!(dot.contains("Say \\\"hi\\\""), "quotes escaped: {dot}");
+    assert_eq!(dot.matches(" -> ").count(), 4);
+}
+
+#[test]
+fn test_plan_graph_mermaid() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t3 = get_task_id(&loaded, "t3");
+
+    // No JSON envelope: the graph goes straight to stdout even with --json
+    let output = env.cmd().args(["--json", "plan", "graph", "test-plan", "--format", "mermaid"]).output().unwrap();
+    assert!(output.status.success());
+    let graph = String::from_utf8_lossy(&output.stdout);
+    assert!(graph.starts_with("graph TD\n"));
+    assert!(graph.contains(&format!("{t1} --> {t3}")));
+}
+
+#[test]
+fn test_plan_graph_declares_cross_plan_dependencies() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let shared = env.load_plan(&shared_setup_plan_json());
+    let s1 = get_task_id(&shared, "s1");
+    env.run_ok(&["task", "dep", "add", &t1, &s1, "--allow-cross-plan"]);
+
+    let output = env.cmd().args(["plan", "graph", "test-plan"]).output().unwrap();
+    let dot = String::from_utf8_lossy(&output.stdout);
+    assert!(dot.contains(&format!("\"{s1}\" [label=")), "{dot}");
+    assert!(dot.contains("dashed"), "{dot}");
+    assert!(dot.contains(&format!("\"{s1}\" -> \"{t1}\"")), "{dot}");
+
+    let output = env.cmd().args(["plan", "graph", "test-plan", "--format", "mermaid"]).output().unwrap();
+    let mermaid = String::from_utf8_lossy(&output.stdout);
+    assert!(mermaid.contains(&format!("{s1}[\"")), "{mermaid}");
+    assert!(mermaid.contains(&format!("{s1} --> {t1}")), "{mermaid}");
+}
+
+#[test]
+fn test_task_note_agent_and_show_order() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    env.run_ok(&["task", "start", &t1]);
+    env.run_ok(&["task", "fail", &t1]);
+    env.run_ok(&["task", "note", "add", &t1, "first try"]);
+    let v = env.run_ok(&["task", "note", "add", &t1, "timeout talking to db", "--agent", "claude"]);
+    assert_eq!(v["data"]["note"]["agent"], "claude");
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    let notes = v["data"]["notes"].as_array().unwrap();
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0]["content"], "timeout talking to db");
+    assert_eq!(notes[0]["agent"], "claude");
+    assert!(notes[1]["agent"].is_null());
+
+    let output = env.cmd().args(["task", "show", &t1]).output().unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("claude: timeout talking to db"));
+}
+
+#[test]
+fn test_note_agent_column_added_to_old_db() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    {
+        let db = env.db();
+        // A pre-versioning database: old task_notes shape, no schema_version
+        db.execute_batch("DROP TABLE schema_version; DROP TABLE task_notes; CREATE TABLE task_notes (
+            id TEXT PRIMARY KEY,
+            task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );").unwrap();
+    }
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(&basic_plan_json());
+    let t1 = get_task_id(&loaded, "t1");
+    let v = env.run_ok(&["task", "note", "add", &t1, "hi", "--agent", "a1"]);
+    assert_eq!(v["data"]["note"]["agent"], "a1");
+}
+
+// ─── 31. task estimate ─────────────────────────────────────────────
+
+#[test]
+fn test_task_estimate_and_remaining() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t3 = get_task_id(&loaded, "t3");
+
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["estimated_remaining_minutes"], 0);
+    assert!(v["data"]["tasks"][0]["estimated_minutes"].is_null(), "omitted when unset");
+
+    let v = env.run_ok(&["task", "estimate", &t1, "30"]);
+    assert_eq!(v["data"]["task"]["estimated_minutes"], 30);
+    env.run_ok(&["task", "estimate", &t2, "45"]);
+    env.run_ok(&["task", "estimate", &t3, "15"]);
+
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["id"], t1.as_str());
+    assert_eq!(v["data"]["task"]["estimated_minutes"], 30);
+
+    let v = env.run_ok(&["task", "show", &t2]);
+    assert_eq!(v["data"]["task"]["estimated_minutes"], 45);
+
+    env.run_ok(&["task", "start", &t1]);
+    env.run_ok(&["task", "done", &t1]);
+    env.run_ok(&["task", "skip", &t3]);
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["estimated_remaining_minutes"], 45);
+}
+
+#[test]
+fn test_task_estimate_rejects_negative() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let v = env.run_err(&["task", "estimate", &t1, "-5"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 32. task deadline ─────────────────────────────────────────────
+
+#[test]
+fn test_task_deadline_overdue_first() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.run_ok(&["plan", "create", "dl", "--title", "Deadlines"]);
+    env.run_ok(&["plan", "activate", "dl"]);
+    let urgent = env.run_ok(&["task", "add", "Urgent", "--priority", "10"])["data"]["task"]["id"]
+        .as_str().unwrap().to_string();
+    let late = env.run_ok(&["task", "add", "Late"])["data"]["task"]["id"]
+        .as_str().unwrap().to_string();
+
+    let v = env.run_ok(&["task", "deadline", &late, "2000-01-01T00:00:00Z"]);
+    assert_eq!(v["data"]["task"]["deadline"], "2000-01-01 00:00:00");
+    assert_eq!(v["data"]["task"]["overdue"], true);
+    env.run_ok(&["task", "deadline", &urgent, "2999-12-31"]);
+
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["id"], late.as_str(), "overdue beats priority");
+    assert_eq!(v["data"]["task"]["overdue"], true);
+
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["overdue_count"], 1);
+
+    let v = env.run_ok(&["task", "show", &urgent]);
+    assert_eq!(v["data"]["task"]["deadline"], "2999-12-31 23:59:59");
+    assert_eq!(v["data"]["task"]["overdue"], false);
+
+    env.run_ok(&["task", "start", &late]);
+    env.run_ok(&["task", "done", &late]);
+    let v = env.run_ok(&["task", "show", &late]);
+    assert!(v["data"]["task"]["deadline"].is_null(), "cleared on done");
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["overdue_count"], 0);
+}
+
+#[test]
+fn test_task_deadline_validation_and_clear() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_err(&["task", "deadline", &t1, "next tuesday"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+
+    env.run_ok(&["task", "deadline", &t1, "2030-06-01T12:00:00+09:00"]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["deadline"], "2030-06-01 03:00:00");
+
+    env.run_ok(&["task", "deadline", &t1, "--clear"]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert!(v["data"]["task"]["deadline"].is_null());
+}
+
+// ─── 33. task history ──────────────────────────────────────────────
+
+#[test]
+fn test_task_history_records_transitions() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+
+    env.run_ok(&["next", "--claim", "--agent", "a1"]);
+    env.run_ok(&["task", "fail", &t1]);
+    env.run_ok(&["task", "start", &t1, "--agent", "a2"]);
+    env.run_ok(&["task", "done", &t1]);
+
+    let v = env.run_ok(&["task", "history", &t1]);
+    let h = v["data"]["events"].as_array().unwrap();
+    let steps: Vec<(&str, &str)> = h
+        .iter()
+        .map(|t| (t["from_status"].as_str().unwrap(), t["to_status"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            ("ready", "in_progress"),
+            ("in_progress", "ready"),
+            ("ready", "in_progress"),
+            ("in_progress", "done"),
+        ]
+    );
+    assert_eq!(h[0]["agent"], "a1");
+    assert_eq!(h[2]["agent"], "a2");
+    assert_eq!(h[0]["event_type"], "status_change");
+    assert_eq!(h[0]["task_id"], t1.as_str());
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["event_count"], 4);
+    env.run_ok(&["task", "reset", &t1]);
+    let v = env.run_ok(&["task", "history", &t1]);
+    assert_eq!(v["data"]["events"][4]["event_type"], "reset");
+
+    // Cascaded unblock is logged too
+    let v = env.run_ok(&["task", "history", &t2]);
+    assert_eq!(v["data"]["events"][0]["from_status"], "blocked");
+    assert_eq!(v["data"]["events"][0]["to_status"], "ready");
+}
+
+#[test]
+fn test_task_history_since_filter() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "start", &t1]);
+    env.run_ok(&["task", "done", &t1]);
+
+    let v = env.run_ok(&["task", "history", &t1, "--since", "2000-01-01"]);
+    assert_eq!(v["data"]["events"].as_array().unwrap().len(), 2);
+    let v = env.run_ok(&["task", "history", &t1, "--since", "2999-01-01T00:00:00Z"]);
+    assert_eq!(v["data"]["events"].as_array().unwrap().len(), 0);
+    let v = env.run_err(&["task", "history", &t1, "--since", "yesterday"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 34. task assign / unassign ────────────────────────────────────
+
+#[test]
+fn test_task_assign_keeps_status() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+
+    let v = env.run_ok(&["task", "assign", &t2, "--agent", "worker-1"]);
+    assert_eq!(v["data"]["task"]["assigned_to"], "worker-1");
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+
+    let v = env.run_ok(&["task", "list", "--assigned-to", "worker-1"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 1);
+
+    let v = env.run_ok(&["task", "unassign", &t2]);
+    assert!(v["data"]["task"]["assigned_to"].is_null());
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+
+    env.run_ok(&["task", "start", &t1]);
+    env.run_ok(&["task", "done", &t1]);
+    let v = env.run_err(&["task", "assign", &t1, "--agent", "worker-1"]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+// ─── 35. next --claim --count ──────────────────────────────────────
+
+fn parallel_plan_json() -> String {
+    serde_json::json!({
+        "name": "wide",
+        "title": "Wide",
+        "tasks": [
+            {"id": "a", "title": "A", "priority": 3},
+            {"id": "b", "title": "B", "priority": 2},
+            {"id": "c", "title": "C", "priority": 1},
+            {"id": "d", "title": "D", "after": ["a"]}
+        ]
+    })
+    .to_string()
+}
+
+#[test]
+fn test_next_claim_count() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(&parallel_plan_json());
+
+    let v = env.run_ok(&["next", "--claim", "--count", "2", "--agent", "w"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0]["id"], get_task_id(&loaded, "a").as_str());
+    assert_eq!(tasks[1]["id"], get_task_id(&loaded, "b").as_str());
+    assert_eq!(tasks[0]["assigned_to"], "w");
+    assert!(v["data"]["task"].is_null());
+    assert_eq!(v["plan_completed"], false);
+    assert!(v["data"]["in_progress"].is_array());
+    assert_eq!(v["data"]["progress"]["in_progress"], 2);
+
+    // Only one ready task is left
+    let v = env.run_ok(&["next", "--claim", "--count", "5"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 1);
+
+    // Nothing left to claim: falls back to the usual waiting response
+    let output = env.cmd().args(["next", "--claim", "--count", "2", "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(v["data"]["reason"], "BLOCKED_REMAINING");
+}
+
+#[test]
+fn test_next_claim_count_concurrent_no_duplicates() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&parallel_plan_json());
+
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            let mut cmd = env.cmd();
+            cmd.args(["next", "--claim", "--count", "2", "--json"]);
+            std::thread::spawn(move || cmd.output().unwrap())
+        })
+        .collect();
+    let mut ids = Vec::new();
+    for h in handles {
+        let output = h.join().unwrap();
+        let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+        for t in v["data"]["tasks"].as_array().into_iter().flatten() {
+            ids.push(t["id"].as_str().unwrap().to_string());
+        }
+    }
+    let total = ids.len();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), total, "a task was handed out twice");
+    assert_eq!(total, 3);
+}
+
+#[test]
+fn test_next_count_previews_without_claiming() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(&parallel_plan_json());
+
+    let v = env.run_ok(&["next", "--count", "5"]);
+    let ids: Vec<&str> = v["data"]["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_str().unwrap())
+        .collect();
+    let expected: Vec<String> = ["a", "b", "c"].iter().map(|k| get_task_id(&loaded, k)).collect();
+    assert_eq!(ids, expected, "ready tasks by priority, d is blocked");
+    assert!(v["data"]["tasks"].as_array().unwrap().iter().all(|t| t["status"] == "ready"));
+    assert_eq!(v["data"]["progress"]["in_progress"], 0);
+
+    // --count 1 keeps the single-task shape
+    let v = env.run_ok(&["next", "--count", "1"]);
+    assert_eq!(v["data"]["task"]["id"], expected[0].as_str());
+    assert!(v["data"]["tasks"].is_null());
+
+    let v = env.run_err(&["next", "--count", "0"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 36. task move ─────────────────────────────────────────────────
+
+#[test]
+fn test_task_move_drops_cross_plan_deps() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t4 = get_task_id(&loaded, "t4");
+    env.run_ok(&["plan", "create", "other", "--title", "Other"]);
+    env.run_ok(&["task", "--plan", "other", "add", "Existing"]);
+
+    let v = env.run_ok(&["task", "move", &t2, "--to-plan", "other"]);
+    assert_eq!(v["data"]["to_plan"]["name"], "other");
+    let dropped = v["data"]["dropped_dependencies"].as_array().unwrap();
+    assert_eq!(dropped.len(), 2);
+    assert!(dropped.iter().any(|d| d["task_id"] == t2.as_str() && d["dependency_id"] == t1.as_str()));
+    assert!(dropped.iter().any(|d| d["task_id"] == t4.as_str() && d["dependency_id"] == t2.as_str()));
+    assert_eq!(v["data"]["task"]["status"], "ready", "no deps left in the new plan");
+
+    let v = env.run_ok(&["task", "--plan", "other", "show", &t2]);
+    assert_eq!(v["data"]["task"]["sort_order"], 1, "appended at the end");
+    assert_eq!(v["data"]["documents"].as_array().unwrap().len(), 0);
+    let v = env.run_ok(&["task", "list"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 3);
+    let v = env.run_ok(&["task", "show", &t4]);
+    assert_eq!(v["data"]["dependencies"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_task_move_keeps_documents_and_unblocks_dependents() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    env.run_ok(&["plan", "create", "other", "--title", "Other"]);
+
+    let v = env.run_ok(&["task", "move", &t1, "--to-plan", "other"]);
+    let ready: Vec<&str> = v["data"]["newly_ready"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_str().unwrap())
+        .collect();
+    assert!(ready.contains(&t2.as_str()));
+
+    let v = env.run_ok(&["task", "--plan", "other", "show", &t1]);
+    assert_eq!(v["data"]["documents"].as_array().unwrap().len(), 1);
+
+    let v = env.run_err(&["task", "--plan", "other", "move", &t1, "--to-plan", "other"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 37. plan load estimates / ETA ─────────────────────────────────
+
+#[test]
+fn test_plan_load_estimates_eta() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let plan = serde_json::json!({
+        "name": "eta",
+        "title": "ETA",
+        "tasks": [
+            {"id": "a", "title": "A", "estimated_minutes": 30},
+            {"id": "b", "title": "B", "estimate_minutes": 20, "after": ["a"]},
+            {"id": "c", "title": "C", "after": ["a"]},
+            {"id": "d", "title": "D", "estimated_minutes": 50, "after": ["b", "c"]}
+        ]
+    });
+    let loaded = env.load_plan(&plan.to_string());
+    let a = get_task_id(&loaded, "a");
+
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["estimated_remaining_minutes"], 100);
+    assert_eq!(v["data"]["unestimated"], 1);
+
+    env.run_ok(&["task", "start", &a]);
+    env.run_ok(&["task", "done", &a]);
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["estimated_remaining_minutes"], 70);
+    assert_eq!(v["data"]["unestimated"], 1);
+
+    let v = env.run_ok(&["task", "add", "E", "--estimate", "5"]);
+    assert_eq!(v["data"]["task"]["estimated_minutes"], 5);
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["estimated_remaining_minutes"], 75);
+
+    let output = env.cmd().args(["plan", "export", "eta"]).output().unwrap();
+    let exported: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(exported["tasks"][1]["estimated_minutes"], 20);
+    assert!(exported["tasks"][2].get("estimated_minutes").is_none());
+}
+
+// ─── 38. bulk transitions ──────────────────────────────────────────
+
+#[test]
+fn test_bulk_cancel_by_status() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_ok(&["task", "bulk-cancel", "--status", "blocked"]);
+    let transitioned = v["data"]["transitioned"].as_array().unwrap();
+    assert_eq!(transitioned.len(), 3);
+    assert!(transitioned.iter().all(|u| u["from_status"] == "blocked" && u["to_status"] == "cancelled"));
+    assert_eq!(v["data"]["skipped"].as_array().unwrap().len(), 0);
+    assert_eq!(v["data"]["progress"]["cancelled"], 3);
+    assert_eq!(v["data"]["progress"]["ready"], 1);
+}
+
+#[test]
+fn test_bulk_done_cascades_and_reports_skipped() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t4 = get_task_id(&loaded, "t4");
+
+    let ids = format!("{t1},{t2},{t4},NOPE");
+    let v = env.run_ok(&["task", "bulk-done", "--ids", &ids]);
+    let transitioned: Vec<&str> = v["data"]["transitioned"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|u| u["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(transitioned, vec![t1.as_str(), t2.as_str()], "t1 unblocks t2 within the batch");
+    let skipped = v["data"]["skipped"].as_array().unwrap();
+    assert_eq!(skipped.len(), 2);
+    assert!(skipped.iter().any(|f| f["id"] == t4.as_str()
+        && f["code"] == "INVALID_STATUS_TRANSITION"));
+    assert!(skipped.iter().any(|f| f["id"] == "NOPE"));
+    let ready: Vec<&str> = v["data"]["newly_ready"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ready.len(), 1, "t3 only; t2 was finished in the batch: {ready:?}");
+}
+
+#[test]
+fn test_bulk_all_skipped_exits_1() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t4 = get_task_id(&loaded, "t4");
+
+    let output = env.cmd().args(["task", "bulk-done", "--ids", &t4, "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(v["data"]["transitioned"].as_array().unwrap().len(), 0);
+    assert_eq!(v["data"]["skipped"].as_array().unwrap().len(), 1);
+
+    let v = env.run_err(&["task", "bulk-skip", "--status", "bogus"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[test]
+fn test_bulk_by_tag_skips_with_reason() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t3 = get_task_id(&loaded, "t3");
+    env.run_ok(&["task", "tag", "add", &t1, "cleanup"]);
+    env.run_ok(&["task", "tag", "add", &t3, "cleanup"]);
+    env.run_ok(&["task", "done", &t1]);
+
+    // t1 is done (cannot skip), t3 is ready; t2 is untagged and left alone
+    let v = env.run_ok(&["task", "bulk-skip", "--tag", "cleanup"]);
+    let transitioned = v["data"]["transitioned"].as_array().unwrap();
+    assert_eq!(transitioned.len(), 1);
+    assert_eq!(transitioned[0]["id"], t3.as_str());
+    let skipped = v["data"]["skipped"].as_array().unwrap();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0]["id"], t1.as_str());
+    assert_eq!(skipped[0]["code"], "INVALID_STATUS_TRANSITION");
+    assert!(skipped[0]["reason"].as_str().unwrap().contains("done"));
+    assert_eq!(v["data"]["progress"]["skipped"], 1);
+
+    let v = env.run_ok(&["task", "show", &t2]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+
+    // Status and tag combine
+    let v = env.run_ok(&["task", "bulk-cancel", "--status", "ready", "--tag", "cleanup"]);
+    assert_eq!(v["data"]["transitioned"].as_array().unwrap().len(), 0);
+}
+
+// ─── 39. tags in plan load and next ────────────────────────────────
+
+#[test]
+fn test_next_tag_filter_and_plan_load_tags() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let plan = serde_json::json!({
+        "name": "tagged",
+        "title": "Tagged",
+        "tasks": [
+            {"id": "ui", "title": "UI", "priority": 5, "tags": ["frontend"]},
+            {"id": "db", "title": "DB", "priority": 1, "tags": ["infra", "backend"]},
+            {"id": "net", "title": "Net", "priority": 3, "tags": ["infra"], "after": ["db"]}
+        ]
+    });
+    let loaded = env.load_plan(&plan.to_string());
+    let db = get_task_id(&loaded, "db");
+    let net = get_task_id(&loaded, "net");
+
+    let v = env.run_ok(&["task", "show", &db]);
+    assert_eq!(v["data"]["task"]["tags"], serde_json::json!(["backend", "infra"]));
+
+    // `net` has the higher priority but is blocked
+    let v = env.run_ok(&["next", "--tag", "infra"]);
+    assert_eq!(v["data"]["task"]["id"], db.as_str());
+
+    env.run_ok(&["task", "start", &db]);
+    env.run_ok(&["task", "done", &db]);
+    let v = env.run_ok(&["next", "--claim", "--tag", "infra"]);
+    assert_eq!(v["data"]["task"]["id"], net.as_str());
+
+    let output = env.cmd().args(["next", "--tag", "infra", "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(v["data"]["reason"], "NO_TASK_WITH_TAG");
+
+    let output = env.cmd().args(["plan", "export", "tagged"]).output().unwrap();
+    let exported: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(exported["tasks"][0]["tags"], serde_json::json!(["frontend"]));
+}
+
+#[test]
+fn test_next_no_match_reason_names_the_filter_that_emptied_the_queue() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let plan = serde_json::json!({
+        "name": "combo", "title": "Combo", "tasks": [
+            {"id": "a", "title": "A", "agent": "x", "tags": ["backend"], "priority": 1},
+            {"id": "b", "title": "B", "agent": "y", "tags": ["infra"], "priority": 9}
+        ]
+    });
+    env.load_plan(&plan.to_string());
+    let reason = |args: &[&str]| {
+        let output = env.cmd().arg("next").args(args).arg("--json").output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        serde_json::from_slice::<Value>(&output.stdout).unwrap()["data"]["reason"].clone()
+    };
+
+    // Agent x has ready work, just none tagged infra
+    assert_eq!(reason(&["--for-agent", "x", "--strict", "--tag", "infra"]), "NO_TASK_WITH_TAG");
+    assert_eq!(reason(&["--for-agent", "x", "--strict", "--min-priority", "5"]), "BELOW_PRIORITY_THRESHOLD");
+    assert_eq!(reason(&["--for-agent", "z", "--strict", "--tag", "infra"]), "NO_TASK_FOR_AGENT");
+}
+
+#[test]
+fn test_plan_load_rejects_bad_tag() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let plan = serde_json::json!({
+        "name": "bad-tags",
+        "title": "Bad",
+        "tasks": [{"id": "a", "title": "A", "tags": ["Not A Slug"]}]
+    });
+    let v = env.load_plan_raw(&plan.to_string());
+    assert_eq!(v["success"], false);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 40. plan load field-path validation ───────────────────────────
+
+#[test]
+fn test_plan_load_reports_all_field_errors() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let json = serde_json::json!({
+        "name": "Bad Name",
+        "tasks": [
+            {"id": "a", "title": "A"},
+            {"id": "b", "title": 5, "after": ["zzz"]},
+            {"id": "c", "title": "C", "priority": "high", "documents": [{"title": "D"}]}
+        ]
+    })
+    .to_string();
+    let v = env.load_plan_raw(&json);
+    assert_eq!(v["success"], false);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let errors: Vec<&str> = v["error"]["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e.as_str().unwrap())
+        .collect();
+    for expected in [
+        "name must match",
+        "title is required",
+        "tasks[1].title must be a string",
+        "tasks[1].after[0] references unknown dependency 'zzz'",
+        "tasks[2].priority must be an integer",
+        "tasks[2].documents[0].content is required",
+    ] {
+        assert!(errors.iter().any(|e| e.starts_with(expected)), "missing '{expected}' in {errors:?}");
+    }
+    assert_eq!(errors.len(), 6);
+}
+
+#[test]
+fn test_plan_load_non_object_rejected() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let v = env.load_plan_raw("[1, 2]");
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    assert_eq!(v["error"]["errors"][0], "plan must be a JSON object");
+}
+
+// ─── 41. --ndjson ──────────────────────────────────────────────────
+
+fn ndjson_lines(env: &TestEnv, args: &[&str]) -> Vec<Value> {
+    let output = env.cmd().args(args).arg("--ndjson").output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap_or_else(|e| panic!("bad line {l}: {e}")))
+        .collect()
+}
+
+#[test]
+fn test_task_list_ndjson() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let lines = ndjson_lines(&env, &["task", "list"]);
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["title"], "First Task");
+    assert!(lines.iter().all(|l| l["id"].is_string() && l["status"].is_string()));
+
+    let lines = ndjson_lines(&env, &["task", "list", "--status", "blocked", "--fields", "id"]);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].as_object().unwrap().len(), 1);
+}
+
+#[test]
+fn test_status_ndjson() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let lines = ndjson_lines(&env, &["status"]);
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0]["type"], "plan");
+    assert_eq!(lines[0]["plan"]["name"], "test-plan");
+    assert_eq!(lines[0]["progress"]["total"], 4);
+    assert!(lines[1..].iter().all(|l| l["type"] == "task"));
+}
+
+#[test]
+fn test_json_and_ndjson_conflict() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    let output = env.cmd().args(["task", "list", "--json", "--ndjson"]).output().unwrap();
+    assert!(!output.status.success());
+}
+
+// ─── 42. task show --tree ──────────────────────────────────────────
+
+#[test]
+fn test_task_show_tree() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t4 = get_task_id(&loaded, "t4");
+
+    let v = env.run_ok(&["task", "show", &t4, "--tree"]);
+    let tree = &v["data"]["tree"];
+    assert_eq!(tree["id"], t4.as_str());
+    let deps = tree["dependencies"].as_array().unwrap();
+    assert_eq!(deps.len(), 2);
+    for d in deps {
+        assert_eq!(d["status"], "blocked");
+        assert_eq!(d["dependencies"][0]["id"], t1.as_str());
+        assert_eq!(d["dependencies"][0]["dependencies"].as_array().unwrap().len(), 0);
+    }
+    assert!(v["data"]["warnings"].is_null());
+
+    let v = env.run_ok(&["task", "show", &t4]);
+    assert!(v["data"]["tree"].is_null(), "tree only with --tree");
+
+    let output = env.cmd().args(["task", "show", &t4, "--tree"]).output().unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("├─ "));
+    assert!(text.contains("└─ "));
+    assert!(text.contains("│  └─ First Task"));
+}
+
+#[test]
+fn test_task_show_tree_depth_cap() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let tasks: Vec<Value> = (0..25)
+        .map(|i| {
+            if i == 0 {
+                serde_json::json!({"id": "n0", "title": "N0"})
+            } else {
+                serde_json::json!({"id": format!("n{i}"), "title": format!("N{i}"), "after": [format!("n{}", i - 1)]})
+            }
+        })
+        .collect();
+    let plan = serde_json::json!({"name": "deep", "title": "Deep", "tasks": tasks});
+    let loaded = env.load_plan(&plan.to_string());
+    let last = get_task_id(&loaded, "n24");
+
+    let v = env.run_ok(&["task", "show", &last, "--tree"]);
+    assert_eq!(v["data"]["warnings"][0], "Dependency tree truncated at depth 20");
+    let mut node = &v["data"]["tree"];
+    for _ in 0..20 {
+        node = &node["dependencies"][0];
+    }
+    assert_eq!(node["truncated"], true);
+    assert_eq!(node["dependencies"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_task_show_tree_breaks_cycles() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    // Corrupt data on purpose: t1 → t2 → t1
+    env.db()
+        .execute(
+            "INSERT INTO task_dependencies (task_id, dependency_id) VALUES (?1, ?2)",
+            rusqlite::params![t1, t2],
+        )
+        .unwrap();
+
+    let v = env.run_ok(&["task", "show", &t2, "--tree"]);
+    let back = &v["data"]["tree"]["dependencies"][0]["dependencies"][0];
+    assert_eq!(back["id"], t2.as_str());
+    assert_eq!(back["cycle"], true);
+}
+
+// ─── 43. plan update ───────────────────────────────────────────────
+
+#[test]
+fn test_plan_update_fields() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_ok(&["plan", "update", "test-plan", "--title", "Renamed Title"]);
+    assert_eq!(v["data"]["plan"]["title"], "Renamed Title");
+    assert_eq!(v["data"]["plan"]["description"], "A test plan", "untouched");
+
+    let v = env.run_ok(&["plan", "update", "test-plan", "--description", "New desc"]);
+    assert_eq!(v["data"]["plan"]["title"], "Renamed Title");
+    assert_eq!(v["data"]["plan"]["description"], "New desc");
+
+    let v = env.run_ok(&["plan", "update", "test-plan", "--description", ""]);
+    assert!(v["data"]["plan"]["description"].is_null());
+
+    let output = env.cmd().args(["plan", "update", "test-plan", "--title", "T"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Updated plan: test-plan"));
+}
+
+#[test]
+fn test_plan_update_requires_field() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    let v = env.run_err(&["plan", "update", "test-plan"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["plan", "update", "test-plan", "--title", " "]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["plan", "update", "nope", "--title", "X"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+}
+
+// ─── 44. plan clone ────────────────────────────────────────────────
+
+#[test]
+fn test_plan_clone_resets_tasks_and_keeps_source() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    env.run_ok(&["task", "start", &t1, "--agent", "a1"]);
+    env.run_ok(&["task", "done", &t1]);
+    env.run_ok(&["task", "start", &t2]);
+    env.run_ok(&["task", "tag", "add", &t2, "backend"]);
+
+    let v = env.run_ok(&["plan", "clone", "test-plan", "test-plan-2"]);
+    assert_eq!(v["data"]["plan"]["name"], "test-plan-2");
+    assert_eq!(v["data"]["plan"]["title"], "Test Plan");
+    assert_eq!(v["data"]["tasks_created"], 4);
+    let new_t1 = v["data"]["id_mapping"][&t1].as_str().unwrap().to_string();
+    let new_t2 = v["data"]["id_mapping"][&t2].as_str().unwrap().to_string();
+    assert_ne!(new_t1, t1);
+
+    // Fresh state in the clone
+    let v = env.run_ok(&["task", "--plan", "test-plan-2", "show", &new_t1]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+    assert!(v["data"]["task"]["assigned_to"].is_null());
+    assert!(v["data"]["task"]["completed_at"].is_null());
+    assert_eq!(v["data"]["documents"].as_array().unwrap().len(), 1);
+    let v = env.run_ok(&["task", "--plan", "test-plan-2", "show", &new_t2]);
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+    assert_eq!(v["data"]["task"]["tags"], serde_json::json!(["backend"]));
+    assert_eq!(v["data"]["dependencies"][0]["id"], new_t1.as_str());
+    let v = env.run_ok(&["plan", "show", "test-plan-2"]);
+    assert_eq!(v["data"]["documents"].as_array().unwrap().len(), 1);
+
+    // Source untouched
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "done");
+    let v = env.run_ok(&["task", "show", &t2]);
+    assert_eq!(v["data"]["task"]["status"], "in_progress");
+
+    let v = env.run_err(&["plan", "clone", "test-plan", "test-plan-2"]);
+    assert_eq!(v["error"]["code"], "PLAN_NAME_CONFLICT");
+}
+
+#[test]
+fn test_plan_clone_with_as_flag_is_not_activated() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let plan_id = loaded["data"]["plan"]["id"].as_str().unwrap().to_string();
+
+    let v = env.run_ok(&["plan", "clone", "test-plan", "--as", "rerun", "--title", "Rerun"]);
+    assert_eq!(v["data"]["plan"]["name"], "rerun");
+    assert_eq!(v["data"]["plan"]["title"], "Rerun");
+    assert_eq!(v["data"]["id_mapping"].as_object().unwrap().len(), 4);
+
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["plan"]["id"], plan_id.as_str());
+}
+
+// ─── 45. task heartbeat ────────────────────────────────────────────
+
+#[test]
+fn test_task_heartbeat_clears_stale_flag() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "start", &t1, "--agent", "worker"]);
+    backdate_start(&env, &t1, 60);
+
+    let v = env.run_ok(&["status"]);
+    let entry = &v["data"]["in_progress"][0];
+    assert!(entry["last_heartbeat_at"].is_null());
+    assert_eq!(entry["stale"], true);
+    let v = env.run_ok(&["status", "--stale-after", "120"]);
+    assert_eq!(v["data"]["in_progress"][0]["stale"], false);
+
+    let v = env.run_ok(&["task", "heartbeat", &t1, "--agent", "worker"]);
+    assert!(v["data"]["task"]["last_heartbeat_at"].is_string());
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["in_progress"][0]["stale"], false);
+    assert!(v["data"]["in_progress"][0]["last_heartbeat_at"].is_string());
+
+    let v = env.run_err(&["task", "heartbeat", &t1, "--agent", "someone-else"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[test]
+fn test_task_heartbeat_requires_in_progress() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_err(&["task", "heartbeat", &t1]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+    env.run_ok(&["task", "start", &t1]);
+    env.run_ok(&["task", "done", &t1]);
+    let v = env.run_err(&["task", "heartbeat", &t1]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+// ─── 46. next --min-priority ───────────────────────────────────────
+
+#[test]
+fn test_next_min_priority_boundary() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&parallel_plan_json());
+
+    // priority == n is included
+    let v = env.run_ok(&["next", "--min-priority", "3"]);
+    assert_eq!(v["data"]["task"]["title"], "A");
+    let v = env.run_ok(&["next", "--claim", "--min-priority", "2", "--count", "5"]);
+    let titles: Vec<&str> = v["data"]["tasks"].as_array().unwrap()
+        .iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["A", "B"]);
+}
+
+#[test]
+fn test_next_below_priority_threshold() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&parallel_plan_json());
+
+    let output = env.cmd().args(["next", "--min-priority", "4", "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(v["data"]["task"].is_null());
+    assert_eq!(v["data"]["reason"], "BELOW_PRIORITY_THRESHOLD");
+
+    let output = env.cmd().args(["next", "--claim", "--min-priority", "4", "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(v["data"]["reason"], "BELOW_PRIORITY_THRESHOLD");
+    assert_eq!(v["data"]["progress"]["in_progress"], 0);
+}
+
+// ─── 47. plan merge ────────────────────────────────────────────────
+
+#[test]
+fn test_plan_merge_moves_tasks_and_deletes_source() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "done", &t1]);
+    let wide = env.load_plan(&parallel_plan_json());
+    let a = get_task_id(&wide, "a");
+    let d = get_task_id(&wide, "d");
+
+    let v = env.run_ok(&["plan", "merge", "test-plan", "--into", "wide"]);
+    assert_eq!(v["data"]["tasks_moved"], 4);
+    assert_eq!(v["data"]["deps_preserved"], 4);
+    assert_eq!(v["data"]["source_deleted"]["name"], "test-plan");
+    assert_eq!(v["data"]["target_plan"]["name"], "wide");
+
+    let v = env.run_ok(&["task", "--plan", "wide", "list"]);
+    let tasks = v["data"]["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 8);
+    let merged_t1 = tasks.iter().find(|t| t["id"] == t1.as_str()).unwrap();
+    assert_eq!(merged_t1["status"], "done");
+
+    // Dependencies survive on both sides of the merge
+    let v = env.run_ok(&["task", "--plan", "wide", "show", &d]);
+    assert_eq!(v["data"]["dependencies"][0]["id"], a.as_str());
+    let v = env.run_ok(&["task", "--plan", "wide", "show", &get_task_id(&loaded, "t4")]);
+    assert_eq!(v["data"]["dependencies"].as_array().unwrap().len(), 2);
+
+    let v = env.run_err(&["plan", "show", "test-plan"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+}
+
+#[test]
+fn test_plan_merge_rejects_self_and_rolls_back() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_err(&["plan", "merge", "test-plan", "--into", "test-plan"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["plan", "merge", "test-plan", "--into", "missing"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+    let v = env.run_ok(&["task", "list"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 4);
+}
+
+// ─── 48. db backup ─────────────────────────────────────────────────
+
+#[test]
+fn test_db_backup_snapshot() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "done", &t1]);
+    let dest = env.dir.path().join("backup.db");
+    let dest_str = dest.to_str().unwrap();
+
+    let v = env.run_ok(&["db", "backup", dest_str]);
+    assert_eq!(v["data"]["destination"], dest_str);
+    assert!(v["data"]["source"].as_str().unwrap().ends_with("taskai.db"));
+    assert_eq!(v["data"]["bytes"], fs::metadata(&dest).unwrap().len());
+
+    let backup = rusqlite::Connection::open(&dest).unwrap();
+    let status: String = backup
+        .query_row("SELECT status FROM tasks WHERE id = ?1", [&t1], |r| r.get(0))
+        .unwrap();
+    assert_eq!(status, "done");
+
+    let v = env.run_err(&["db", "backup", dest_str]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    env.run_ok(&["db", "backup", dest_str, "--force"]);
+}
+
+// ─── 49. schema migrations ─────────────────────────────────────────
+
+/// Schema as shipped before `schema_version` and the later tables/columns existed.
+const V1_SCHEMA: &str = "
+    CREATE TABLE plans (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        title TEXT NOT NULL,
+        description TEXT,
+        status TEXT NOT NULL DEFAULT 'active',
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE TABLE tasks (
+        id TEXT PRIMARY KEY,
+        plan_id TEXT NOT NULL REFERENCES plans(id) ON DELETE CASCADE,
+        title TEXT NOT NULL,
+        description TEXT,
+        status TEXT NOT NULL DEFAULT 'blocked',
+        priority INTEGER NOT NULL DEFAULT 0,
+        sort_order INTEGER NOT NULL DEFAULT 0,
+        agent TEXT,
+        assigned_to TEXT,
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+        started_at TEXT,
+        completed_at TEXT
+    );
+    CREATE TABLE task_dependencies (
+        task_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        dependency_id TEXT NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+        PRIMARY KEY (task_id, dependency_id)
+    );
+    CREATE TABLE plan_documents (
+        id TEXT PRIMARY KEY, plan_id TEXT NOT NULL, title TEXT NOT NULL, content TEXT NOT NULL
+    );
+    CREATE TABLE task_documents (
+        id TEXT PRIMARY KEY, task_id TEXT NOT NULL, title TEXT NOT NULL, content TEXT NOT NULL
+    );
+    INSERT INTO plans (id, name, title) VALUES ('P1', 'legacy', 'Legacy');
+    INSERT INTO tasks (id, plan_id, title, status) VALUES ('T1', 'P1', 'Old task', 'ready');
+";
+
+#[test]
+fn test_v1_database_upgrades_cleanly_and_idempotently() {
+    let env = TestEnv::new();
+    let dir = env.dir.path().join(".worktoolai/taskai");
+    fs::create_dir_all(&dir).unwrap();
+    env.db().execute_batch(V1_SCHEMA).unwrap();
+
+    let v = env.run_ok(&["task", "--plan", "legacy", "list"]);
+    assert_eq!(v["data"]["tasks"][0]["title"], "Old task");
+
+    let columns = |table: &str| -> Vec<String> {
+        let conn = env.db();
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})")).unwrap();
+        stmt.query_map([], |r| r.get::<_, String>(1)).unwrap().map(Result::unwrap).collect()
+    };
+    let task_columns = columns("tasks");
+    for c in ["estimated_minutes", "deadline", "last_heartbeat_at", "external_id"] {
+        assert!(task_columns.iter().any(|t| t == c), "missing tasks.{c}");
+    }
+    assert!(columns("task_notes").iter().any(|c| c == "agent"));
+    assert!(columns("plan_documents").iter().any(|c| c == "updated_at"));
+    assert!(columns("task_documents").iter().any(|c| c == "updated_at"));
+    assert!(columns("task_transitions").iter().any(|c| c == "event_type"));
+
+    let versions = || -> (i64, i64) {
+        env.db()
+            .query_row("SELECT MAX(version), COUNT(*) FROM schema_version", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+    };
+    let (latest, applied) = versions();
+    assert_eq!(latest, 14);
+    assert_eq!(applied, 14);
+
+    // Re-opening applies nothing new
+    env.run_ok(&["task", "--plan", "legacy", "start", "T1"]);
+    assert_eq!(versions(), (latest, applied));
+}
+
+#[test]
+fn test_failed_migration_keeps_earlier_steps() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    // Back to version 12, with data that makes step 14's unique index impossible
+    env.db()
+        .execute_batch(
+            "DELETE FROM schema_version WHERE version > 12;
+             DROP INDEX idx_plans_idempotency;
+             UPDATE plans SET idempotency_key = 'dup';
+             INSERT INTO plans (id, name, title, idempotency_key) VALUES ('P2', 'twin', 'Twin', 'dup');",
+        )
+        .unwrap();
+
+    let v = env.run_err(&["task", "list"]);
+    assert!(v["error"]["message"].as_str().unwrap().contains("schema version 14"), "{v}");
+    let latest: i64 = env.db().query_row("SELECT MAX(version) FROM schema_version", [], |r| r.get(0)).unwrap();
+    assert_eq!(latest, 13);
+
+    env.db().execute_batch("UPDATE plans SET idempotency_key = NULL").unwrap();
+    env.run_ok(&["task", "list"]);
+    let latest: i64 = env.db().query_row("SELECT MAX(version) FROM schema_version", [], |r| r.get(0)).unwrap();
+    assert_eq!(latest, 14);
+}
+
+#[test]
+fn test_init_records_latest_schema_version() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.run_ok(&["init"]);
+    let (latest, applied): (i64, i64) = env
+        .db()
+        .query_row("SELECT MAX(version), COUNT(*) FROM schema_version", [], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap();
+    assert_eq!(latest, 14);
+    assert_eq!(applied, 14);
+}
+
+#[test]
+fn test_task_agent_column_round_trips() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let columns: Vec<String> = env
+        .db()
+        .prepare("SELECT name FROM pragma_table_info('tasks')")
+        .unwrap()
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(|c| c.unwrap())
+        .collect();
+    let agent = columns.iter().position(|c| c == "agent").expect("tasks.agent column");
+    assert_eq!(columns[agent - 1], "sort_order");
+    assert_eq!(columns[agent + 1], "assigned_to");
+
+    let loaded = env.load_plan(r#"{"name":"ag","title":"A","tasks":[{"id":"a","title":"A","agent":"coder"}]}"#);
+    let a = get_task_id(&loaded, "a");
+    let v = env.run_ok(&["task", "show", &a]);
+    assert_eq!(v["data"]["task"]["agent"], "coder");
+}
+
+// ─── 50. plan load YAML ────────────────────────────────────────────
+
+const YAML_PLAN: &str = "\
+name: yaml-plan
+title: YAML Plan
+documents:
+  - title: Notes
+    content: Written in YAML
+tasks:
+  - id: setup
+    title: Setup
+    priority: 5
+  - id: build
+    title: Build
+    after: [setup]
+  - id: ship
+    title: Ship
+    after:
+      - build
+";
+
+fn load_with_format(env: &TestEnv, content: &str, format: Option<&str>) -> Value {
+    let p = env.write_plan("_plan.yaml", content);
+    let mut args = vec!["plan", "load", "--json"];
+    if let Some(f) = format {
+        args.extend(["--format", f]);
+    }
+    let output = env.cmd().args(&args).pipe_stdin(&p).unwrap().output().unwrap();
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_plan_load_yaml_with_dependencies() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+
+    let v = load_with_format(&env, YAML_PLAN, Some("yaml"));
+    assert_eq!(v["success"], true, "{v}");
+    assert_eq!(v["data"]["tasks_created"], 3);
+    let ready: Vec<&str> = v["data"]["ready_now"].as_array().unwrap()
+        .iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(ready, vec!["Setup"]);
+
+    let setup = get_task_id(&v, "setup");
+    let build = get_task_id(&v, "build");
+    let v = env.run_ok(&["task", "done", &setup]);
+    assert_eq!(v["data"]["newly_ready"][0]["id"], build.as_str());
+    let v = env.run_ok(&["plan", "show", "yaml-plan"]);
+    assert_eq!(v["data"]["documents"][0]["title"], "Notes");
+}
+
+#[test]
+fn test_plan_load_auto_detects_yaml() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+
+    let v = load_with_format(&env, YAML_PLAN, None);
+    assert_eq!(v["data"]["tasks_created"], 3);
+
+    // Validation is shared with JSON input
+    let cyclic = "name: loop\ntitle: Loop\ntasks:\n  - {id: a, title: A, after: [b]}\n  - {id: b, title: B, after: [a]}\n";
+    let v = load_with_format(&env, cyclic, None);
+    assert_eq!(v["error"]["code"], "CYCLE_DETECTED");
+
+    // Explicit JSON does not fall back
+    let v = load_with_format(&env, YAML_PLAN, Some("json"));
+    assert!(v["error"]["message"].as_str().unwrap().starts_with("Invalid JSON"));
+    let v = load_with_format(&env, "{not json", None);
+    assert!(v["error"]["message"].as_str().unwrap().starts_with("Invalid JSON"));
+}
+
+// ─── 51. cross-plan dependencies ───────────────────────────────────
+
+fn shared_setup_plan_json() -> String {
+    serde_json::json!({
+        "name": "shared",
+        "title": "Shared setup",
+        "tasks": [{"id": "s1", "title": "Provision"}]
+    })
+    .to_string()
+}
+
+#[test]
+fn test_cross_plan_dependency_requires_opt_in() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let shared = env.load_plan(&shared_setup_plan_json());
+    let s1 = get_task_id(&shared, "s1");
+
+    let v = env.run_err(&["task", "dep", "add", &t1, &s1]);
+    assert_eq!(v["error"]["code"], "TASK_NOT_FOUND");
+
+    let v = env.run_ok(&["task", "dep", "add", &t1, &s1, "--allow-cross-plan"]);
+    assert_eq!(v["data"]["added"]["dependency_id"], s1.as_str());
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+
+    // Exported plans leave the external edge out
+    let output = env.cmd().args(["plan", "export", "test-plan"]).output().unwrap();
+    let exported: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(exported["tasks"][0].get("after").is_none());
+
+    env.run_ok(&["task", "dep", "remove", &t1, &s1, "--allow-cross-plan"]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+}
+
+#[test]
+fn test_cross_plan_unblock_cascade() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let shared = env.load_plan(&shared_setup_plan_json());
+    let s1 = get_task_id(&shared, "s1");
+    env.run_ok(&["task", "dep", "add", &t1, &s1, "--allow-cross-plan"]);
+
+    let v = env.run_ok(&["task", "--plan", "shared", "done", &s1]);
+    assert_eq!(v["data"]["newly_ready"][0]["id"], t1.as_str());
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["id"], t1.as_str());
+}
+
+#[test]
+fn test_cross_plan_cycle_detected() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t4 = get_task_id(&loaded, "t4");
+    let shared = env.load_plan(&shared_setup_plan_json());
+    let s1 = get_task_id(&shared, "s1");
+
+    env.run_ok(&["task", "--plan", "shared", "dep", "add", &s1, &t4, "--allow-cross-plan"]);
+    let v = env.run_err(&["task", "dep", "add", &t1, &s1, "--allow-cross-plan"]);
+    assert_eq!(v["error"]["code"], "CYCLE_DETECTED");
+}
+
+#[test]
+fn test_cross_plan_cycle_through_third_plan_detected() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let mut ids = Vec::new();
+    for name in ["pa", "pb", "pc"] {
+        let loaded = env.load_plan(&serde_json::json!({
+            "name": name,
+            "title": name,
+            "tasks": [{"id": "x", "title": "X"}]
+        }).to_string());
+        ids.push(get_task_id(&loaded, "x"));
+    }
+
+    env.run_ok(&["task", "--plan", "pa", "dep", "add", &ids[0], &ids[1], "--allow-cross-plan"]);
+    env.run_ok(&["task", "--plan", "pb", "dep", "add", &ids[1], &ids[2], "--allow-cross-plan"]);
+    let v = env.run_err(&["task", "--plan", "pc", "dep", "add", &ids[2], &ids[0], "--allow-cross-plan"]);
+    assert_eq!(v["error"]["code"], "CYCLE_DETECTED");
+}
+
+// ─── 52. plan load --dry-run ───────────────────────────────────────
+
+fn load_dry_run(env: &TestEnv, content: &str) -> (Option<i32>, Value) {
+    let p = env.write_plan("_plan.json", content);
+    let output = env.cmd().args(["plan", "load", "--dry-run", "--json"]).pipe_stdin(&p).unwrap().output().unwrap();
+    (output.status.code(), serde_json::from_slice(&output.stdout).unwrap())
+}
+
+#[test]
+fn test_plan_load_dry_run_writes_nothing() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+
+    let (code, v) = load_dry_run(&env, &basic_plan_json());
+    assert_eq!(code, Some(0));
+    assert_eq!(v["data"]["valid"], true);
+    assert_eq!(v["data"]["plan_name"], "test-plan");
+    assert_eq!(v["data"]["tasks_count"], 4);
+    assert_eq!(v["data"]["ready_tasks_count"], 1);
+
+    let v = env.run_ok(&["plan", "list"]);
+    assert_eq!(v["data"]["plans"].as_array().unwrap().len(), 0);
+    let v = env.run_err(&["status"]);
+    assert_eq!(v["error"]["code"], "NO_ACTIVE_PLAN");
+}
+
+#[test]
+fn test_plan_load_dry_run_reports_errors() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let (code, v) = load_dry_run(&env, &basic_plan_json());
+    assert_eq!(code, Some(1));
+    assert_eq!(v["error"]["code"], "PLAN_NAME_CONFLICT");
+
+    let cyclic = serde_json::json!({
+        "name": "loop", "title": "Loop",
+        "tasks": [{"id": "a", "title": "A", "after": ["b"]}, {"id": "b", "title": "B", "after": ["a"]}]
+    });
+    let (code, v) = load_dry_run(&env, &cyclic.to_string());
+    assert_eq!(code, Some(1));
+    assert_eq!(v["error"]["code"], "CYCLE_DETECTED");
+}
+
+// ─── 53. task show --with-dependents ───────────────────────────────
+
+#[test]
+fn test_task_show_with_dependents() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t4 = get_task_id(&loaded, "t4");
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert!(v["data"].get("dependents").is_none());
+
+    let v = env.run_ok(&["task", "show", &t1, "--with-dependents"]);
+    let dependents = v["data"]["dependents"].as_array().unwrap();
+    assert_eq!(dependents.len(), 2);
+    assert!(dependents.iter().all(|d| d["depth"] == 1 && d["status"] == "blocked"));
+
+    let v = env.run_ok(&["task", "show", &t4, "--with-dependents"]);
+    assert_eq!(v["data"]["dependents"], serde_json::json!([]));
+}
+
+#[test]
+fn test_task_show_transitive_dependents_are_deduplicated() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t4 = get_task_id(&loaded, "t4");
+
+    // t4 is reachable through both t2 and t3 but listed once
+    let v = env.run_ok(&["task", "show", &t1, "--with-dependents", "--transitive"]);
+    let dependents = v["data"]["dependents"].as_array().unwrap();
+    assert_eq!(dependents.len(), 3);
+    let last = &dependents[2];
+    assert_eq!(last["id"], t4.as_str());
+    assert_eq!(last["depth"], 2);
+
+    let output = env.cmd().args(["task", "show", &t1, "--transitive"]).output().unwrap();
+    assert!(!output.status.success(), "--transitive requires --with-dependents");
+}
+
+// ─── 54. plan load --file ──────────────────────────────────────────
+
+#[test]
+fn test_plan_load_from_file() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    fs::create_dir_all(env.dir.path().join("plans")).unwrap();
+    env.write_plan("plans/basic.json", &basic_plan_json());
+
+    let v = env.run_ok(&["plan", "load", "--file", "plans/basic.json"]);
+    assert_eq!(v["data"]["tasks_created"], 4);
+
+    env.write_plan("flow.yaml", YAML_PLAN);
+    let v = env.run_ok(&["plan", "load", "-f", "flow.yaml"]);
+    assert_eq!(v["data"]["plan"]["name"], "yaml-plan");
+}
+
+#[test]
+fn test_plan_load_missing_file() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+
+    let v = env.run_err(&["plan", "load", "--file", "nope.json"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    assert!(v["error"]["message"].as_str().unwrap().contains("nope.json"));
+}
+
+// ─── 55. plan stats ────────────────────────────────────────────────
+
+#[test]
+fn test_plan_stats_empty_plan() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.run_ok(&["plan", "create", "empty"]);
+
+    let v = env.run_ok(&["plan", "stats", "empty"]);
+    let stats = &v["data"]["stats"];
+    assert_eq!(stats["total"], 0);
+    assert_eq!(stats["completion_percentage"], 0.0);
+    assert!(stats["avg_in_progress_minutes"].is_null());
+    assert_eq!(stats["failed_at_least_once"], 0);
+    assert_eq!(stats["agent_workload"], serde_json::json!([]));
+    assert_eq!(stats["cycle_time_minutes"]["sample_size"], 0);
+    assert!(stats["cycle_time_minutes"]["p50"].is_null());
+    assert!(stats["throughput_per_hour"].is_null());
+}
+
+#[test]
+fn test_plan_stats_aggregates_history() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+    let t3 = get_task_id(&loaded, "t3");
+
+    env.run_ok(&["task", "start", &t1, "--agent", "alice"]);
+    env.run_ok(&["task", "fail", &t1]);
+    env.run_ok(&["task", "start", &t1, "--agent", "alice"]);
+    backdate_start(&env, &t1, 30);
+    env.run_ok(&["task", "done", &t1]);
+    env.run_ok(&["task", "start", &t2, "--agent", "bob"]);
+    backdate_start(&env, &t2, 10);
+    env.run_ok(&["task", "done", &t2]);
+    env.run_ok(&["task", "cancel", &t3]);
+
+    // Defaults to the active plan
+    let v = env.run_ok(&["plan", "stats"]);
+    assert_eq!(v["data"]["plan"]["name"], "test-plan");
+    let stats = &v["data"]["stats"];
+    assert_eq!(stats["total"], 4);
+    assert_eq!(stats["completion_percentage"], 50.0);
+    assert_eq!(stats["by_status"]["done"], 2);
+    assert_eq!(stats["by_status"]["cancelled"], 1);
+    let avg = stats["avg_in_progress_minutes"].as_f64().unwrap();
+    assert!((19.0..=21.0).contains(&avg), "avg was {avg}");
+    assert_eq!(stats["failed_at_least_once"], 1);
+    assert_eq!(stats["with_documents"], 1);
+    assert_eq!(stats["agent_workload"], serde_json::json!([
+        {"agent": "alice", "completed": 1},
+        {"agent": "bob", "completed": 1}
+    ]));
+}
+
+// ─── 56. plan load --merge ─────────────────────────────────────────
+
+const MERGE_BASE: &str = r#"{"name":"m","title":"M","tasks":[
+    {"id":"a","title":"A"},
+    {"id":"b","title":"B","after":["a"]}
+]}"#;
+
+#[test]
+fn test_plan_load_merge_adds_updates_and_keeps() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(MERGE_BASE);
+    let a = get_task_id(&loaded, "a");
+    let b = get_task_id(&loaded, "b");
+    env.run_ok(&["task", "start", &a]);
+    env.run_ok(&["task", "done", &a]);
+
+    env.write_plan("m2.json", r#"{"name":"m","title":"M","tasks":[
+        {"id":"a","title":"A"},
+        {"id":"b","title":"B renamed","priority":5},
+        {"id":"c","title":"C","after":["a"]}
+    ]}"#);
+    let v = env.run_ok(&["plan", "load", "--merge", "--file", "m2.json"]);
+    let data = &v["data"];
+    assert_eq!(data["tasks_added"], 1);
+    assert_eq!(data["tasks_updated"], 1);
+    assert_eq!(data["tasks_unchanged"], 1);
+    assert_eq!(data["id_mapping"]["a"], a.as_str());
+    assert_eq!(data["id_mapping"]["b"], b.as_str());
+
+    // b keeps its dep on a even though the merge input dropped it
+    let v = env.run_ok(&["task", "show", &b]);
+    assert_eq!(v["data"]["task"]["title"], "B renamed");
+    assert_eq!(v["data"]["task"]["priority"], 5);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+
+    // New task depending on a done task is ready straight away
+    let c = data["id_mapping"]["c"].as_str().unwrap();
+    let v = env.run_ok(&["task", "show", c]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+
+    // Merging the same input again changes nothing
+    let v = env.run_ok(&["plan", "load", "--merge", "--file", "m2.json"]);
+    assert_eq!(v["data"]["tasks_added"], 0);
+    assert_eq!(v["data"]["tasks_updated"], 0);
+    assert_eq!(v["data"]["tasks_unchanged"], 3);
+}
+
+#[test]
+fn test_plan_load_merge_new_dep_blocks_ready_task() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(MERGE_BASE);
+    let a = get_task_id(&loaded, "a");
+
+    env.write_plan("m2.json", r#"{"name":"m","title":"M","tasks":[
+        {"id":"a","title":"A","after":["z"]},
+        {"id":"z","title":"Z"}
+    ]}"#);
+    let v = env.run_ok(&["plan", "load", "--merge", "--file", "m2.json"]);
+    assert_eq!(v["data"]["tasks_added"], 1);
+    let v = env.run_ok(&["task", "show", &a]);
+    assert_eq!(v["data"]["task"]["status"], "blocked");
+}
+
+#[test]
+fn test_plan_load_merge_rejects_cycle_with_existing_deps() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(MERGE_BASE);
+
+    // b -> a already exists; a -> b closes the loop
+    env.write_plan("m2.json", r#"{"name":"m","title":"M","tasks":[
+        {"id":"a","title":"A","after":["b"]},
+        {"id":"b","title":"B"}
+    ]}"#);
+    let v = env.run_err(&["plan", "load", "--merge", "--file", "m2.json"]);
+    assert_eq!(v["error"]["code"], "CYCLE_DETECTED");
+}
+
+#[test]
+fn test_plan_load_merge_without_existing_plan_loads() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.write_plan("m.json", MERGE_BASE);
+    let v = env.run_ok(&["plan", "load", "--merge", "--file", "m.json"]);
+    assert_eq!(v["data"]["tasks_created"], 2);
+
+    // Without --merge the name still conflicts
+    let v = env.run_err(&["plan", "load", "--file", "m.json"]);
+    assert_eq!(v["error"]["code"], "PLAN_NAME_CONFLICT");
+}
+
+// ─── 57. plan compare ──────────────────────────────────────────────
+
+#[test]
+fn test_plan_compare_reports_added_removed_changed() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(r#"{"name":"old","title":"Old","tasks":[
+        {"id":"a","title":"Design"},
+        {"id":"b","title":"Build","priority":1},
+        {"id":"c","title":"Legacy cleanup"}
+    ]}"#);
+    env.load_plan(r#"{"name":"new","title":"New","tasks":[
+        {"id":"a","title":"Design"},
+        {"id":"b","title":"Build","priority":3,"after":["a"]},
+        {"id":"d","title":"Docs"}
+    ]}"#);
+
+    let v = env.run_ok(&["plan", "compare", "old", "new"]);
+    let data = &v["data"];
+    assert_eq!(data["added"].as_array().unwrap().len(), 1);
+    assert_eq!(data["added"][0]["title"], "Docs");
+    assert_eq!(data["removed"].as_array().unwrap().len(), 1);
+    assert_eq!(data["removed"][0]["title"], "Legacy cleanup");
+    assert_eq!(data["changed"], serde_json::json!([
+        {"title": "Build", "field": "status", "a_value": "ready", "b_value": "blocked"},
+        {"title": "Build", "field": "priority", "a_value": 1, "b_value": 3}
+    ]));
+
+    let out = env.cmd().args(["plan", "compare", "old", "new"]).output().unwrap();
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.contains("+ Docs"), "{text}");
+    assert!(text.contains("- Legacy cleanup"), "{text}");
+    assert!(text.contains("~ Build: priority 1 → 3"), "{text}");
+}
+
+#[test]
+fn test_plan_compare_identical_and_unknown() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    let v = env.run_ok(&["plan", "compare", "test-plan", "test-plan"]);
+    assert_eq!(v["data"]["added"], serde_json::json!([]));
+    assert_eq!(v["data"]["removed"], serde_json::json!([]));
+    assert_eq!(v["data"]["changed"], serde_json::json!([]));
+
+    let v = env.run_err(&["plan", "compare", "test-plan", "nope"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+}
+
+// ─── 58. task show: why blocked ────────────────────────────────────
+
+#[test]
+fn test_task_show_explains_blocked_task() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(r#"{"name":"chain","title":"Chain","tasks":[
+        {"id":"root","title":"Root"},
+        {"id":"mid","title":"Mid","after":["root"]},
+        {"id":"side","title":"Side"},
+        {"id":"top","title":"Top","after":["mid","side"]}
+    ]}"#);
+    let root = get_task_id(&loaded, "root");
+    let mid = get_task_id(&loaded, "mid");
+    let side = get_task_id(&loaded, "side");
+    let top = get_task_id(&loaded, "top");
+    env.run_ok(&["task", "start", &side]);
+    env.run_ok(&["task", "done", &side]);
+
+    let v = env.run_ok(&["task", "show", &top]);
+    let blocked_by = v["data"]["blocked_by"].as_array().unwrap();
+    assert_eq!(blocked_by.len(), 1);
+    assert_eq!(blocked_by[0]["id"], mid.as_str());
+    assert_eq!(blocked_by[0]["status"], "blocked");
+    let chain: Vec<&str> = v["data"]["blocking_chain"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(chain, vec![mid.as_str(), root.as_str()]);
+
+    let out = env.cmd().args(["task", "show", &top]).output().unwrap();
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.contains("Blocked by: Mid"), "{text}");
+
+    // Not blocked → no explanation
+    let v = env.run_ok(&["task", "show", &root]);
+    assert!(v["data"].get("blocked_by").is_none());
+    assert!(v["data"].get("blocking_chain").is_none());
+}
+
+// ─── 59. document add ──────────────────────────────────────────────
+
+#[test]
+fn test_document_add_to_plan_and_task() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t2 = get_task_id(&loaded, "t2");
+
+    let v = env.run_ok(&["document", "add", "--plan", "test-plan", "--title", "Notes", "--content", "hello"]);
+    assert_eq!(v["data"]["plan"]["name"], "test-plan");
+    assert_eq!(v["data"]["document"]["title"], "Notes");
+    assert!(v["data"]["document"]["id"].is_string());
+    let v = env.run_ok(&["plan", "show", "test-plan"]);
+    let docs = v["data"]["documents"].as_array().unwrap();
+    assert!(docs.iter().any(|d| d["title"] == "Notes" && d["content"] == "hello"), "{docs:?}");
+
+    env.write_plan("spec.md", "# Spec\nbody");
+    let v = env.run_ok(&["document", "add", "--task", &t2, "--title", "Spec", "--content-file", "spec.md"]);
+    assert_eq!(v["data"]["task"]["id"], t2.as_str());
+    let v = env.run_ok(&["task", "show", &t2]);
+    let docs = v["data"]["documents"].as_array().unwrap();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0]["content"], "# Spec\nbody");
+
+    let out = env.cmd().args(["document", "add", "--task", &t2, "--title", "More", "--content", "x"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Attached document 'More' to task"));
+}
+
+#[test]
+fn test_document_add_errors() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_err(&["document", "add", "--title", "X", "--content", "x"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["document", "add", "--task", "nope", "--title", "X", "--content", "x"]);
+    assert_eq!(v["error"]["code"], "TASK_NOT_FOUND");
+    let v = env.run_err(&["document", "add", "--plan", "test-plan", "--title", "X", "--content-file", "missing.md"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 60. custom database location ──────────────────────────────────
+
+#[test]
+fn test_db_path_env_outside_git_repo() {
+    let work = TempDir::new().unwrap();
+    let store = TempDir::new().unwrap();
+    let db = store.path().join("shared/taskai.db");
+    let run = |args: &[&str]| -> Value {
+        #[allow(deprecated)]
+        let out = Command::cargo_bin("taskai")
+            .unwrap()
+            .current_dir(work.path())
+            .env("TASKAI_DB_PATH", &db)
+            .args(args)
+            .arg("--json")
+            .output()
+            .unwrap();
+        serde_json::from_str(&String::from_utf8_lossy(&out.stdout)).unwrap()
+    };
+
+    let v = run(&["plan", "list"]);
+    assert_eq!(v["error"]["code"], "NOT_INITIALIZED");
+    assert!(v["error"]["message"].as_str().unwrap().contains(db.to_str().unwrap()));
+
+    let v = run(&["init"]);
+    assert_eq!(v["data"]["path"], db.to_str().unwrap());
+    fs::write(work.path().join("p.json"), basic_plan_json()).unwrap();
+    let v = run(&["plan", "load", "--file", "p.json"]);
+    assert_eq!(v["success"], true, "{v}");
+    assert!(store.path().join("shared/config.json").exists());
+
+    let v = run(&["next", "--claim"]);
+    let id = v["data"]["task"]["id"].as_str().unwrap().to_string();
+    let v = run(&["task", "done", &id]);
+    assert_eq!(v["success"], true, "{v}");
+    let v = run(&["status"]);
+    assert_eq!(v["data"]["progress"]["done"], 1);
+    assert!(!work.path().join(".worktoolai").exists());
+}
+
+#[test]
+fn test_db_path_flag_wins_over_env() {
+    let env = TestEnv::new();
+    let other = env.dir.path().join("elsewhere.db");
+    let flagged = env.dir.path().join("flagged.db");
+    let out = env
+        .cmd()
+        .env("TASKAI_DB_PATH", &other)
+        .args(["init", "--json", "--db-path", flagged.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert!(flagged.exists());
+    assert!(!other.exists());
+
+    // Without either, the git-root default is still uninitialized
+    let v = env.run_err(&["plan", "list"]);
+    assert_eq!(v["error"]["code"], "NOT_INITIALIZED");
+    let v = env.run_ok(&["plan", "list", "--db-path", flagged.to_str().unwrap()]);
+    assert_eq!(v["data"]["plans"], serde_json::json!([]));
+}
+
+#[test]
+fn test_db_alias_flag_and_short_env() {
+    let env = TestEnv::new();
+    let short = env.dir.path().join("short.db");
+    let long = env.dir.path().join("long.db");
+
+    // TASKAI_DB is used when TASKAI_DB_PATH is unset
+    let out = env.cmd().env("TASKAI_DB", &short).args(["init", "--json"]).output().unwrap();
+    assert!(out.status.success());
+    assert!(short.exists());
+
+    // ...and TASKAI_DB_PATH wins when both are set
+    let out = env
+        .cmd()
+        .env("TASKAI_DB", &short)
+        .env("TASKAI_DB_PATH", &long)
+        .args(["init", "--json"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert!(long.exists());
+
+    let v = env.run_ok(&["plan", "create", "via-alias", "--db", short.to_str().unwrap()]);
+    assert_eq!(v["data"]["name"], "via-alias");
+    let out = env.cmd().env("TASKAI_DB", &short).args(["plan", "list", "--json"]).output().unwrap();
+    let v: Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(v["data"]["plans"][0]["name"], "via-alias");
+}
+
+// ─── 61. document update ───────────────────────────────────────────
+
+#[test]
+fn test_document_update_plan_and_task_documents() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t2 = get_task_id(&loaded, "t2");
+
+    let v = env.run_ok(&["document", "add", "--plan", "test-plan", "--title", "Notes", "--content", "v1"]);
+    let plan_doc = v["data"]["document"]["id"].as_str().unwrap().to_string();
+    let v = env.run_ok(&["document", "update", &plan_doc, "--title", "Notes v2"]);
+    let doc = &v["data"]["document"];
+    assert_eq!(doc["title"], "Notes v2");
+    assert_eq!(doc["content"], "v1");
+    assert!(doc["plan_id"].is_string());
+    assert!(doc["updated_at"].is_string());
+
+    let v = env.run_ok(&["document", "add", "--task", &t2, "--title", "Spec", "--content", "old"]);
+    let task_doc = v["data"]["document"]["id"].as_str().unwrap().to_string();
+    env.write_plan("spec.md", "new body");
+    let v = env.run_ok(&["document", "update", &task_doc, "--content-file", "spec.md"]);
+    assert_eq!(v["data"]["document"]["task_id"], t2.as_str());
+    let v = env.run_ok(&["task", "show", &t2]);
+    let docs = v["data"]["documents"].as_array().unwrap();
+    assert_eq!(docs[0]["title"], "Spec");
+    assert_eq!(docs[0]["content"], "new body");
+}
+
+#[test]
+fn test_document_update_errors() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_err(&["document", "update", "nope", "--title", "X"]);
+    assert_eq!(v["error"]["code"], "DOCUMENT_NOT_FOUND");
+    let v = env.run_err(&["document", "update", "nope"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 62. --lock-timeout ────────────────────────────────────────────
+
+#[test]
+fn test_lock_timeout_waits_for_held_lock() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(env.dir.path().join(".worktoolai/taskai/taskai.lock"))
+        .unwrap();
+    lock.lock().unwrap();
+
+    let v = env.run_err(&["task", "start", &t1, "--lock-timeout", "100"]);
+    assert_eq!(v["error"]["code"], "LOCK_TIMEOUT");
+    let v = env.run_err(&["task", "bulk-done", "--ids", &t1, "--lock-timeout", "100"]);
+    assert_eq!(v["error"]["code"], "LOCK_TIMEOUT");
+    // Read-only commands and commands without the flag do not wait for the lock
+    env.run_ok(&["task", "show", &t1, "--lock-timeout", "100"]);
+    env.run_ok(&["next", "--lock-timeout", "100"]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+
+    lock.unlock().unwrap();
+    env.run_ok(&["task", "start", &t1, "--lock-timeout", "100"]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "in_progress");
+}
+
+#[test]
+fn test_lock_timeout_serializes_parallel_claims() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&parallel_plan_json());
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let mut cmd = env.cmd();
+            cmd.args(["next", "--claim", "--lock-timeout", "10000", "--json"]);
+            std::thread::spawn(move || cmd.output().unwrap())
+        })
+        .collect();
+    let mut ids = Vec::new();
+    for h in handles {
+        let output = h.join().unwrap();
+        let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+        if let Some(id) = v["data"]["task"]["id"].as_str() {
+            ids.push(id.to_string());
+        } else {
+            assert_eq!(v["success"], true, "{v}");
+        }
+    }
+    let total = ids.len();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), total, "a task was handed out twice");
+    assert_eq!(total, 3);
+}
+
+// ─── 63. document delete ───────────────────────────────────────────
+
+#[test]
+fn test_document_delete_task_and_plan_documents() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t2 = get_task_id(&loaded, "t2");
+
+    let v = env.run_ok(&["document", "add", "--task", &t2, "--title", "Spec", "--content", "x"]);
+    let task_doc = v["data"]["document"]["id"].as_str().unwrap().to_string();
+    let v = env.run_ok(&["document", "delete", &task_doc]);
+    assert_eq!(v["data"]["deleted"], serde_json::json!({"id": task_doc, "title": "Spec", "kind": "task"}));
+    let v = env.run_ok(&["task", "show", &t2]);
+    assert_eq!(v["data"]["documents"], serde_json::json!([]));
+
+    let v = env.run_ok(&["document", "add", "--plan", "test-plan", "--title", "Notes", "--content", "x"]);
+    let plan_doc = v["data"]["document"]["id"].as_str().unwrap().to_string();
+    let out = env.cmd().args(["document", "delete", &plan_doc]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Deleted document 'Notes'"));
+
+    let v = env.run_err(&["document", "delete", &plan_doc]);
+    assert_eq!(v["error"]["code"], "DOCUMENT_NOT_FOUND");
+}
+
+// ─── 64. status --watch ────────────────────────────────────────────
+
+fn spawn_watch(env: &TestEnv, args: &[&str]) -> std::process::Child {
+    std::process::Command::new(env!("CARGO_BIN_EXE_taskai"))
+        .current_dir(env.dir.path())
+        .env_remove("TASKAI_DB_PATH")
+        .env_remove("TASKAI_DB")
+        .args(args)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("spawn watch")
+}
+
+#[test]
+fn test_status_watch_exits_when_plan_completes() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(r#"{"name":"w","title":"W","tasks":[{"id":"a","title":"A"}]}"#);
+    let a = get_task_id(&loaded, "a");
+
+    let child = spawn_watch(&env, &["status", "--watch", "1", "--json"]);
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    env.run_ok(&["task", "start", &a]);
+    env.run_ok(&["task", "done", &a]);
+
+    let out = child.wait_with_output().unwrap();
+    assert_eq!(out.status.code(), Some(0));
+    let lines: Vec<Value> = String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert!(lines.len() >= 2, "{lines:?}");
+    assert_eq!(lines[0]["plan_completed"], false);
+    assert_eq!(lines.last().unwrap()["plan_completed"], true);
+}
+
+#[test]
+fn test_status_watch_ctrl_c_exits_zero() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let child = spawn_watch(&env, &["status", "--watch", "1"]);
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert_eq!(out.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("test-plan"));
+}
+
+#[test]
+fn test_status_watch_rejects_zero_interval() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    let v = env.run_err(&["status", "--watch", "0"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 65. document search ───────────────────────────────────────────
+
+fn search_ids(v: &Value) -> Vec<String> {
+    v["data"]["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_document_search_full_text() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t2 = get_task_id(&loaded, "t2");
+    env.run_ok(&["plan", "create", "other"]);
+
+    let v = env.run_ok(&["document", "add", "--plan", "test-plan", "--title", "Runbook",
+        "--content", "Restart the payment gateway before deploying"]);
+    let plan_doc = v["data"]["document"]["id"].as_str().unwrap().to_string();
+    let v = env.run_ok(&["document", "add", "--task", &t2, "--title", "Gateway spec",
+        "--content", "The gateway retries three times"]);
+    let task_doc = v["data"]["document"]["id"].as_str().unwrap().to_string();
+    env.run_ok(&["document", "add", "--plan", "other", "--title", "Elsewhere", "--content", "gateway too"]);
+
+    let v = env.run_ok(&["document", "search", "gateway"]);
+    assert_eq!(search_ids(&v).len(), 3);
+    let v = env.run_ok(&["document", "search", "gateway", "--plan", "test-plan"]);
+    let mut ids = search_ids(&v);
+    ids.sort();
+    let mut expected = vec![plan_doc.clone(), task_doc.clone()];
+    expected.sort();
+    assert_eq!(ids, expected);
+    let hit = v["data"]["results"].as_array().unwrap().iter().find(|r| r["id"] == task_doc.as_str()).unwrap();
+    assert_eq!(hit["kind"], "task");
+    assert_eq!(hit["task_id"], t2.as_str());
+    assert!(hit["snippet"].as_str().unwrap().contains("[gateway]"), "{hit}");
+
+    // All words must match
+    let v = env.run_ok(&["document", "search", "payment gateway"]);
+    assert_eq!(search_ids(&v), vec![plan_doc.clone()]);
+
+    // Updates and deletes are reflected
+    env.run_ok(&["document", "update", &plan_doc, "--content", "Nothing here"]);
+    env.run_ok(&["document", "delete", &task_doc]);
+    let v = env.run_ok(&["document", "search", "gateway", "--plan", "test-plan"]);
+    assert_eq!(search_ids(&v), Vec::<String>::new());
+
+    let out = env.cmd().args(["document", "search", "gateway"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Plan other:"));
+}
+
+#[test]
+fn test_document_search_without_fts_falls_back() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    env.run_ok(&["document", "add", "--plan", "test-plan", "--title", "Runbook", "--content", "restart gateway"]);
+    env.db().execute_batch("DROP TABLE document_fts").unwrap();
+
+    let v = env.run_ok(&["document", "search", "gateway"]);
+    assert_eq!(v["data"]["results"].as_array().unwrap().len(), 1);
+    assert!(v["data"]["warnings"][0].as_str().unwrap().contains("FTS5"));
+
+    let out = env.cmd().args(["document", "search", "gateway"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Warning:"));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Runbook"));
+}
+
+// ─── 66. status agents section ─────────────────────────────────────
+
+#[test]
+fn test_status_reports_agent_workload() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(&agent_plan_json());
+    let a = get_task_id(&loaded, "a");
+    let b = get_task_id(&loaded, "b");
+
+    env.run_ok(&["task", "start", &a, "--agent", "alice"]);
+    backdate_start(&env, &a, 15);
+    env.run_ok(&["task", "start", &b, "--agent", "alice"]);
+    env.run_ok(&["task", "done", &b]);
+
+    let v = env.run_ok(&["status"]);
+    let agents = &v["data"]["agents"];
+    let alice = &agents["by_assigned_to"]["alice"];
+    assert_eq!(alice["in_progress"], 1);
+    assert_eq!(alice["done"], 1);
+    assert_eq!(alice["total"], 2);
+    let elapsed = alice["in_progress_elapsed_minutes"].as_i64().unwrap();
+    assert!((14..=16).contains(&elapsed), "elapsed was {elapsed}");
+    let total = v["data"]["progress"]["total"].as_i64().unwrap();
+    assert_eq!(agents["by_assigned_to"]["unassigned"]["total"].as_i64().unwrap(), total - 2);
+
+    // Grouped by the pre-assigned agent field instead
+    assert_eq!(agents["by_agent"]["reviewer"]["in_progress"], 1);
+    assert_eq!(agents["by_agent"]["unassigned"]["done"], 1);
+    assert_eq!(agents["by_agent"]["coder"]["ready"], 1);
+
+    let out = env.cmd().args(["status"]).output().unwrap();
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.contains("Agents by assigned_to:"), "{text}");
+    assert!(text.contains("alice"), "{text}");
+}
+
+// ─── 67. task dep list ─────────────────────────────────────────────
+
+#[test]
+fn test_dep_list_directions() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(r#"{"name":"d","title":"D","tasks":[
+        {"id":"a","title":"A"},
+        {"id":"b","title":"B"},
+        {"id":"c","title":"C","after":["a","b"]},
+        {"id":"e","title":"E","after":["c"]}
+    ]}"#);
+    let a = get_task_id(&loaded, "a");
+    let c = get_task_id(&loaded, "c");
+    let e = get_task_id(&loaded, "e");
+
+    let v = env.run_ok(&["task", "dep", "list", &c]);
+    let titles: Vec<&str> = v["data"]["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles.len(), 2);
+    assert!(titles.contains(&"A") && titles.contains(&"B"));
+    assert!(v["data"].get("dependents").is_none());
+
+    let v = env.run_ok(&["task", "dep", "list", &c, "--reverse"]);
+    assert_eq!(v["data"]["dependents"][0]["id"], e.as_str());
+    assert!(v["data"].get("dependencies").is_none());
+
+    let v = env.run_ok(&["task", "dep", "list", &c, "--both"]);
+    assert_eq!(v["data"]["dependencies"].as_array().unwrap().len(), 2);
+    assert_eq!(v["data"]["dependents"].as_array().unwrap().len(), 1);
+
+    let out = env.cmd().args(["task", "dep", "list", &e]).output().unwrap();
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert!(text.contains(&format!("C ({c}) [blocked]")), "{text}");
+    let v = env.run_ok(&["task", "dep", "list", &a]);
+    assert_eq!(v["data"]["dependencies"], serde_json::json!([]));
+}
+
+// ─── 68. handing back a claim ──────────────────────────────────────
+
+#[test]
+fn test_unassign_in_progress_returns_to_queue() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&parallel_plan_json());
+
+    let v = env.run_ok(&["next", "--claim", "--agent", "alice"]);
+    let a = v["data"]["task"]["id"].as_str().unwrap().to_string();
+
+    let v = env.run_ok(&["task", "unassign", &a]);
+    assert_eq!(v["data"]["previous_assignee"], "alice");
+    assert_eq!(v["data"]["task"]["status"], "ready");
+    let v = env.run_ok(&["task", "show", &a]);
+    assert!(v["data"]["task"]["assigned_to"].is_null());
+    assert!(v["data"]["task"]["started_at"].is_null());
+
+    // Immediately eligible again, for another agent
+    let v = env.run_ok(&["next", "--claim", "--agent", "bob"]);
+    assert_eq!(v["data"]["task"]["id"], a.as_str());
+    assert_eq!(v["data"]["task"]["assigned_to"], "bob");
+
+    // Not counted as a failure
+    let v = env.run_ok(&["plan", "stats"]);
+    assert_eq!(v["data"]["stats"]["failed_at_least_once"], 0);
+}
+
+#[test]
+fn test_next_release_hands_back_before_picking() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&parallel_plan_json());
+
+    let v = env.run_ok(&["next", "--claim", "--agent", "alice"]);
+    let a = v["data"]["task"]["id"].as_str().unwrap().to_string();
+    let v = env.run_ok(&["next", "--release", &a]);
+    assert_eq!(v["data"]["released"], a.as_str());
+    assert_eq!(v["data"]["task"]["id"], a.as_str());
+
+    // Releasing a task that is not in progress fails
+    let v = env.run_err(&["next", "--release", &a]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+// ─── 69. CSV output ────────────────────────────────────────────────
+
+#[test]
+fn test_task_list_csv_quotes_and_empty_fields() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "add", "Fix \"parser\", then lexer", "--agent", "coder"]);
+    env.run_ok(&["task", "assign", &t1, "--agent", "alice"]);
+
+    let output = env.cmd().args(["task", "list", "--format", "csv"]).output().unwrap();
+    assert!(output.status.success());
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "id,title,status,priority,sort_order,agent,assigned_to");
+    assert_eq!(lines.len(), 6, "header + 5 tasks: {csv}");
+    assert!(lines[1].starts_with(&format!("{t1},First Task,ready,10,")), "{csv}");
+    assert!(lines[1].ends_with(",alice"), "no agent renders empty: {csv}");
+    assert!(csv.contains("\"Fix \"\"parser\"\", then lexer\",ready,"), "{csv}");
+    assert!(csv.contains(",coder,\n"), "{csv}");
+
+    let output = env.cmd().args(["task", "list", "--status", "blocked", "--format", "csv"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 4);
+}
+
+#[test]
+fn test_status_csv_and_json_conflict() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let output = env.cmd().args(["status", "--format", "csv"]).output().unwrap();
+    assert!(output.status.success());
+    let csv = String::from_utf8(output.stdout).unwrap();
+    assert!(csv.starts_with("id,title,status,priority,sort_order,agent,assigned_to\n"));
+    assert_eq!(csv.lines().count(), 5);
+
+    let output = env.cmd().args(["task", "list", "--format", "csv", "--json"]).output().unwrap();
+    assert!(!output.status.success());
+    let output = env.cmd().args(["status", "--json", "--format", "csv"]).output().unwrap();
+    assert!(!output.status.success());
+}
+
+// ─── 70. critical path ─────────────────────────────────────────────
+
+#[test]
+fn test_plan_critical_path_weights_estimates() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&serde_json::json!({
+        "name": "cp",
+        "title": "Critical path",
+        "tasks": [
+            {"id": "a", "title": "Design", "estimated_minutes": 30},
+            {"id": "b", "title": "Backend", "estimated_minutes": 10, "after": ["a"]},
+            {"id": "c", "title": "Frontend", "estimated_minutes": 60, "after": ["a"]},
+            {"id": "d", "title": "Docs", "estimated_minutes": 5, "after": ["b"]},
+            {"id": "e", "title": "Polish", "after": ["c", "d"]},
+            {"id": "f", "title": "Unrelated", "estimated_minutes": 45}
+        ]
+    }).to_string());
+
+    let v = env.run_ok(&["plan", "critical-path", "cp"]);
+    let titles: Vec<&str> = v["data"]["critical_path"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["Design", "Frontend", "Polish"]);
+    assert_eq!(v["data"]["total_minutes"], 91, "30 + 60 + 1 for the unestimated task");
+    assert_eq!(v["data"]["critical_path"][2]["estimated_minutes"], Value::Null);
+
+    let v = env.run_ok(&["plan", "show", "cp"]);
+    assert_eq!(v["data"]["critical_path"].as_array().unwrap().len(), 3);
+    assert_eq!(v["data"]["critical_path_minutes"], 91);
+
+    let output = env.cmd().args(["plan", "critical-path", "cp"]).output().unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Design → Frontend → Polish"), "{text}");
+}
+
+#[test]
+fn test_critical_path_ignores_cross_plan_dependency() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let shared = env.load_plan(&shared_setup_plan_json());
+    let s1 = get_task_id(&shared, "s1");
+    env.run_ok(&["task", "dep", "add", &t1, &s1, "--allow-cross-plan"]);
+
+    let v = env.run_ok(&["plan", "critical-path", "test-plan"]);
+    let path = v["data"]["critical_path"].as_array().unwrap();
+    assert_eq!(path.len(), 3, "t1 → t2/t3 → t4: {v}");
+    assert_eq!(path[0]["id"], t1.as_str());
+    assert_eq!(v["data"]["total_minutes"], 3);
+
+    let v = env.run_ok(&["plan", "show", "test-plan"]);
+    assert_eq!(v["data"]["critical_path_minutes"], 3);
+    let v = env.run_ok(&["plan", "show", "shared"]);
+    assert_eq!(v["data"]["critical_path_minutes"], 1);
+}
+
+// ─── 71. soft delete and restore ───────────────────────────────────
+
+#[test]
+fn test_plan_soft_delete_and_restore() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_ok(&["plan", "delete", "test-plan", "--soft"]);
+    assert_eq!(v["data"]["soft"], true);
+    assert_eq!(v["data"]["deleted"]["name"], "test-plan");
+
+    // Hidden from list, visible with --all; active pointer cleared
+    let v = env.run_ok(&["plan", "list"]);
+    assert_eq!(v["data"]["plans"].as_array().unwrap().len(), 0);
+    let v = env.run_ok(&["plan", "list", "--all"]);
+    assert_eq!(v["data"]["plans"][0]["status"], "deleted");
+    let v = env.run_err(&["status"]);
+    assert_eq!(v["error"]["code"], "NO_ACTIVE_PLAN");
+    let v = env.run_err(&["status", "--plan", "test-plan"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+    let v = env.run_err(&["plan", "activate", "test-plan"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["plan", "delete", "test-plan", "--soft"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+
+    let v = env.run_ok(&["plan", "restore", "test-plan"]);
+    assert_eq!(v["data"]["restored"]["name"], "test-plan");
+    let v = env.run_ok(&["task", "--plan", "test-plan", "show", &t1]);
+    assert_eq!(v["data"]["task"]["title"], "First Task");
+    let v = env.run_err(&["plan", "restore", "test-plan"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+
+    // Without --soft the plan is gone for good
+    let v = env.run_ok(&["plan", "delete", "test-plan"]);
+    assert_eq!(v["data"]["soft"], false);
+    let v = env.run_err(&["plan", "restore", "test-plan"]);
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+}
+
+#[test]
+fn test_soft_delete_on_database_with_old_status_check() {
+    let env = TestEnv::new();
+    let dir = env.dir.path().join(".worktoolai/taskai");
+    fs::create_dir_all(&dir).unwrap();
+    let old = V1_SCHEMA.replace(
+        "status TEXT NOT NULL DEFAULT 'active',",
+        "status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'completed', 'archived')),",
+    );
+    env.db().execute_batch(&old).unwrap();
+
+    env.run_ok(&["plan", "delete", "legacy", "--soft"]);
+    let v = env.run_ok(&["plan", "list", "--all"]);
+    assert_eq!(v["data"]["plans"][0]["status"], "deleted");
+    // The task survived: nothing was rebuilt or cascaded
+    let count: i64 = env.db().query_row("SELECT COUNT(*) FROM tasks", [], |r| r.get(0)).unwrap();
+    assert_eq!(count, 1);
+    // The constraint still rejects unknown values
+    assert!(env.db().execute("UPDATE plans SET status = 'bogus'", []).is_err());
+}
+
+// ─── 72. parallel waves ────────────────────────────────────────────
+
+#[test]
+fn test_plan_waves_layers_by_dependencies() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    env.load_plan(&serde_json::json!({
+        "name": "w",
+        "title": "Waves",
+        "tasks": [
+            {"id": "a", "title": "A", "estimated_minutes": 30},
+            {"id": "b", "title": "B", "estimated_minutes": 10},
+            {"id": "c", "title": "C", "after": ["a"]},
+            {"id": "d", "title": "D", "estimated_minutes": 5, "after": ["a", "c"]},
+            {"id": "e", "title": "E", "estimated_minutes": 20, "after": ["b"]}
+        ]
+    }).to_string());
+
+    let v = env.run_ok(&["plan", "waves", "w"]);
+    let waves: Vec<Vec<&str>> = v["data"]["waves"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|w| w.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect())
+        .collect();
+    assert_eq!(waves, vec![vec!["A", "B"], vec!["C", "E"], vec!["D"]]);
+    assert_eq!(v["data"]["wave_estimated_minutes"], serde_json::json!([30, 20, 5]));
+    assert_eq!(v["data"]["max_parallelism"], 2);
+
+    let v = env.run_ok(&["plan", "show", "w"]);
+    assert_eq!(v["data"]["waves"].as_array().unwrap().len(), 3);
+
+    let output = env.cmd().args(["plan", "waves", "w"]).output().unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Wave 0 (2 tasks): A, B"), "{text}");
+    assert!(text.contains("Wave 2 (1 tasks): D"), "{text}");
+}
+
+#[test]
+fn test_plan_waves_hold_back_tasks_waiting_on_another_plan() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let shared = env.load_plan(&shared_setup_plan_json());
+    let s1 = get_task_id(&shared, "s1");
+    env.run_ok(&["task", "dep", "add", &t1, &s1, "--allow-cross-plan"]);
+
+    let v = env.run_ok(&["plan", "waves", "test-plan"]);
+    let waves = v["data"]["waves"].as_array().unwrap();
+    assert_eq!(waves.len(), 4, "{v}");
+    assert!(waves[0].as_array().unwrap().is_empty(), "t1 waits on s1: {v}");
+    assert_eq!(waves[1][0]["id"], t1.as_str());
+    assert_eq!(v["data"]["external_dependencies"][0]["id"], s1.as_str());
+
+    let output = env.cmd().args(["plan", "waves", "test-plan"]).output().unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Wave 0: waiting on other plans"), "{text}");
+
+    // Once s1 is done, t1 can start right away again
+    env.run_ok(&["task", "--plan", "shared", "done", &s1]);
+    let v = env.run_ok(&["plan", "waves", "test-plan"]);
+    assert_eq!(v["data"]["waves"][0][0]["id"], t1.as_str(), "{v}");
+    assert_eq!(v["data"]["waves"].as_array().unwrap().len(), 3);
+}
+
+// ─── 73. next --explain ────────────────────────────────────────────
+
+#[test]
+fn test_next_explain_unblock_frontier() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(&serde_json::json!({
+        "name": "x",
+        "title": "Explain",
+        "tasks": [
+            {"id": "a", "title": "A"},
+            {"id": "b", "title": "B"},
+            {"id": "c", "title": "C", "after": ["a"]},
+            {"id": "d", "title": "D", "after": ["b", "c"]},
+            {"id": "e", "title": "E"},
+            {"id": "f", "title": "F", "after": ["e"]}
+        ]
+    }).to_string());
+    let id = |k: &str| get_task_id(&loaded, k);
+    env.run_ok(&["task", "start", &id("a")]);
+    env.run_ok(&["task", "start", &id("b")]);
+    env.run_ok(&["task", "cancel", &id("e")]);
+
+    let output = env.cmd().args(["next", "--explain", "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(v["data"]["reason"], "BLOCKED_REMAINING");
+    let blocked = v["data"]["blocked_tasks"].as_array().unwrap();
+    let frontier_of = |k: &str| -> Vec<String> {
+        let entry = blocked.iter().find(|b| b["id"] == id(k).as_str()).unwrap();
+        entry["unblock_frontier"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+    // D waits on B directly and on A through C
+    let mut d = frontier_of("d");
+    d.sort();
+    assert_eq!(d, vec!["A", "B"]);
+    assert_eq!(frontier_of("c"), vec!["A"]);
+    // F hangs off a cancelled task: nothing to finish
+    assert!(frontier_of("f").is_empty());
+
+    // Opt-in only
+    let output = env.cmd().args(["next", "--json"]).output().unwrap();
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(v["data"]["blocked_tasks"][0]["unblock_frontier"].is_null());
+}
+
+// ─── 74. task claim ────────────────────────────────────────────────
+
+#[test]
+fn test_task_claim_specific_task() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+
+    let v = env.run_ok(&["task", "claim", &t1, "--agent", "w1"]);
+    assert_eq!(v["data"]["task"]["id"], t1.as_str());
+    assert_eq!(v["data"]["task"]["status"], "in_progress");
+    assert_eq!(v["data"]["task"]["assigned_to"], "w1");
+    assert_eq!(v["data"]["progress"]["in_progress"], 1);
+    assert_eq!(v["data"]["in_progress"].as_array().unwrap().len(), 1);
+
+    let v = env.run_err(&["task", "claim", &t1, "--agent", "w2"]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+    let v = env.run_err(&["task", "claim", &t2]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+#[test]
+fn test_task_claim_race_has_one_winner() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let handles: Vec<_> = ["w1", "w2"]
+        .iter()
+        .map(|agent| {
+            let mut cmd = env.cmd();
+            cmd.args(["task", "claim", &t1, "--agent", agent, "--json"]);
+            std::thread::spawn(move || cmd.output().unwrap())
+        })
+        .collect();
+    let outputs: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    let winners = outputs.iter().filter(|o| o.status.success()).count();
+    assert_eq!(winners, 1);
+    let loser = outputs.iter().find(|o| !o.status.success()).unwrap();
+    let v: Value = serde_json::from_slice(&loser.stdout).unwrap();
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+// ─── 75. claim expiry ──────────────────────────────────────────────
+
+fn expire_claim(env: &TestEnv, task_id: &str) {
+    env.db()
+        .execute(
+            "UPDATE tasks SET claim_expires_at = datetime('now', '-1 minutes') WHERE id = ?1",
+            [task_id],
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_expired_claim_returns_to_queue_on_next() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_ok(&["next", "--claim", "--agent", "w1", "--timeout", "15"]);
+    assert_eq!(v["data"]["task"]["id"], t1.as_str());
+    assert!(v["data"]["task"]["claim_expires_at"].is_string());
+
+    // Not expired yet: nothing happens
+    let output = env.cmd().args(["next", "--json"]).output().unwrap();
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(v["data"]["expired"].is_null());
+
+    expire_claim(&env, &t1);
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["expired"], serde_json::json!([t1]));
+    assert_eq!(v["data"]["task"]["id"], t1.as_str(), "back at the head of the queue");
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "ready");
+    assert!(v["data"]["task"]["assigned_to"].is_null());
+    assert!(v["data"]["task"]["started_at"].is_null());
+}
+
+#[test]
+fn test_expired_claim_released_by_status_and_env_default() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let output = env
+        .cmd()
+        .env("TASKAI_CLAIM_TIMEOUT", "10")
+        .args(["next", "--claim", "--json"])
+        .output()
+        .unwrap();
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(v["data"]["task"]["claim_expires_at"].is_string());
+
+    expire_claim(&env, &t1);
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["expired"], serde_json::json!([t1]));
+    assert_eq!(v["data"]["progress"]["in_progress"], 0);
+
+    // Finishing a task drops its expiry, so it is never "released" afterwards
+    env.run_ok(&["next", "--claim", "--timeout", "5"]);
+    env.run_ok(&["task", "done", &t1]);
+    let expires: Option<String> = env
+        .db()
+        .query_row("SELECT claim_expires_at FROM tasks WHERE id = ?1", [&t1], |r| r.get(0))
+        .unwrap();
+    assert!(expires.is_none());
+
+    let v = env.run_err(&["next", "--claim", "--timeout", "0"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 76. next --order ──────────────────────────────────────────────
+
+fn order_plan_json() -> String {
+    serde_json::json!({
+        "name": "ordered",
+        "title": "Ordered",
+        "tasks": [
+            {"id": "x", "title": "X", "priority": 5},
+            {"id": "y", "title": "Y", "priority": 5},
+            {"id": "z", "title": "Z", "priority": 9}
+        ]
+    })
+    .to_string()
+}
+
+fn next_ids(env: &TestEnv, order: &str) -> Vec<String> {
+    let v = env.run_ok(&["next", "--count", "3", "--order", order]);
+    v["data"]["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_next_order_created_ties_fall_back_to_sort_order() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(&order_plan_json());
+    let (x, y, z) = (get_task_id(&loaded, "x"), get_task_id(&loaded, "y"), get_task_id(&loaded, "z"));
+
+    // Loaded in one go, so every created_at is the same second
+    let distinct: i64 = env
+        .db()
+        .query_row("SELECT COUNT(DISTINCT created_at) FROM tasks", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(distinct, 1);
+    assert_eq!(next_ids(&env, "priority"), [z.clone(), x.clone(), y.clone()]);
+    assert_eq!(next_ids(&env, "created"), [z.clone(), x.clone(), y.clone()]);
+
+    // Once y is older, it wins the priority tie under --order created only
+    env.db()
+        .execute("UPDATE tasks SET created_at = datetime('now', '-1 hours') WHERE id = ?1", [&y])
+        .unwrap();
+    assert_eq!(next_ids(&env, "created"), [z.clone(), y.clone(), x.clone()]);
+    assert_eq!(next_ids(&env, "priority"), [z, x, y]);
+}
+
+#[test]
+fn test_next_order_sort_ignores_priority() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let loaded = env.load_plan(&order_plan_json());
+    let ids: Vec<String> = ["x", "y", "z"].iter().map(|k| get_task_id(&loaded, k)).collect();
+
+    assert_eq!(next_ids(&env, "sort"), ids);
+    let v = env.run_ok(&["next", "--claim", "--order", "sort"]);
+    assert_eq!(v["data"]["task"]["id"], ids[0].as_str());
+
+    let output = env.cmd().args(["next", "--order", "newest"]).output().unwrap();
+    assert!(!output.status.success());
+}
+
+// ─── 77. plan doc / task doc ───────────────────────────────────────
+
+#[test]
+fn test_plan_doc_add_update_remove_by_title() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    env.run_ok(&["plan", "doc", "add", "test-plan", "--title", "Design notes", "--content", "v1"]);
+    env.run_ok(&["plan", "doc", "add", "test-plan", "--title", "Design review", "--content", "r1"]);
+
+    let v = env.run_err(&["plan", "doc", "update", "test-plan", "Design", "--content", "v2"]);
+    assert_eq!(v["error"]["code"], "AMBIGUOUS_REF");
+    let v = env.run_ok(&["plan", "doc", "update", "test-plan", "Design n", "--content", "v2"]);
+    assert_eq!(v["data"]["document"]["title"], "Design notes");
+    assert_eq!(v["data"]["document"]["content"], "v2");
+
+    // Content from stdin
+    let p = env.write_plan("body.md", "from stdin");
+    let output = env
+        .cmd()
+        .args(["plan", "doc", "update", "test-plan", "Design r", "--content", "-", "--json"])
+        .pipe_stdin(&p)
+        .unwrap()
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    let v = env.run_ok(&["plan", "show", "test-plan"]);
+    let docs = v["data"]["documents"].as_array().unwrap();
+    assert!(docs.iter().any(|d| d["title"] == "Design review" && d["content"] == "from stdin"), "{docs:?}");
+
+    let v = env.run_ok(&["plan", "doc", "remove", "test-plan", "Design review"]);
+    assert_eq!(v["data"]["deleted"]["kind"], "plan");
+    let v = env.run_ok(&["plan", "show", "test-plan"]);
+    // The loaded "Design Doc" and "Design notes" remain
+    assert_eq!(v["data"]["documents"].as_array().unwrap().len(), 2);
+    let v = env.run_err(&["plan", "doc", "remove", "test-plan", "Design review"]);
+    assert_eq!(v["error"]["code"], "DOCUMENT_NOT_FOUND");
+}
+
+#[test]
+fn test_task_doc_scoped_to_task_and_rejects_empty() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+
+    let v = env.run_ok(&["task", "doc", "add", &t1, "--title", "Spec", "--content", "a"]);
+    let doc_id = v["data"]["document"]["id"].as_str().unwrap().to_string();
+    env.run_ok(&["task", "doc", "add", &t2, "--title", "Spec", "--content", "b"]);
+
+    // Resolution only looks at the owner's documents
+    let v = env.run_ok(&["task", "doc", "update", &t1, "Spec", "--title", "Spec v2"]);
+    assert_eq!(v["data"]["document"]["id"], doc_id.as_str());
+    let v = env.run_ok(&["task", "show", &t2]);
+    assert_eq!(v["data"]["documents"][0]["title"], "Spec");
+    let v = env.run_err(&["task", "doc", "remove", &t2, &doc_id]);
+    assert_eq!(v["error"]["code"], "DOCUMENT_NOT_FOUND");
+
+    let v = env.run_err(&["task", "doc", "update", &t1, &doc_id, "--content", "  "]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["task", "doc", "add", &t1, "--title", "Empty", "--content", ""]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_err(&["task", "doc", "update", &t1, &doc_id]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+
+    env.run_ok(&["task", "doc", "remove", &t1, &doc_id[..10]]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    let docs = v["data"]["documents"].as_array().unwrap();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0]["title"], "Task Doc");
+}
+
+// ─── 78. heartbeat claim renewal ───────────────────────────────────
+
+#[test]
+fn test_heartbeat_extend_renews_claim() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_ok(&["next", "--claim", "--agent", "w1", "--timeout", "1"]);
+    let first = v["data"]["task"]["claim_expires_at"].as_str().unwrap().to_string();
+
+    let v = env.run_ok(&["task", "heartbeat", &t1, "--agent", "w1", "--extend", "60"]);
+    let renewed = v["data"]["task"]["claim_expires_at"].as_str().unwrap().to_string();
+    assert!(renewed > first, "{renewed} should be later than {first}");
+    assert!(v["data"]["task"]["last_heartbeat_at"].is_string());
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["claim_expires_at"], renewed.as_str());
+    assert_eq!(v["data"]["task"]["expired"], false);
+
+    let v = env.run_err(&["task", "heartbeat", &t1, "--extend", "0"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+#[test]
+fn test_heartbeat_rejects_expired_claim() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    env.run_ok(&["next", "--claim", "--timeout", "5"]);
+    expire_claim(&env, &t1);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["expired"], true);
+
+    let v = env.run_err(&["task", "heartbeat", &t1, "--extend", "30"]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+    // Without --extend the expired claim is not silently kept alive either
+    let v = env.run_err(&["task", "heartbeat", &t1]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+
+    // Not in_progress at all
+    let t2 = get_task_id(&loaded, "t2");
+    let v = env.run_err(&["task", "heartbeat", &t2, "--extend", "30"]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+}
+
+#[test]
+fn test_heartbeat_renews_by_env_default() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_ok(&["next", "--claim", "--timeout", "1"]);
+    let first = v["data"]["task"]["claim_expires_at"].as_str().unwrap().to_string();
+    let output = env
+        .cmd()
+        .env("TASKAI_CLAIM_TIMEOUT", "45")
+        .args(["task", "heartbeat", &t1, "--json"])
+        .output()
+        .unwrap();
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(v["data"]["task"]["claim_expires_at"].as_str().unwrap() > first.as_str());
+}
+
+// ─── 79. task fail --reason ────────────────────────────────────────
+
+#[test]
+fn test_task_fail_reason_shown_until_restart() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    env.run_ok(&["task", "start", &t1, "--agent", "a1"]);
+    env.run_ok(&["task", "fail", &t1, "--reason", "tests time out"]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["failure_reason"], "tests time out");
+    assert_eq!(v["data"]["task"]["fail_count"], 1);
+    let v = env.run_ok(&["task", "history", &t1]);
+    assert_eq!(v["data"]["events"][1]["event_type"], "fail");
+
+    // The next agent sees what went wrong, even when claiming
+    let v = env.run_ok(&["next"]);
+    assert_eq!(v["data"]["task"]["failure_reason"], "tests time out");
+    let v = env.run_ok(&["next", "--claim", "--agent", "a2"]);
+    assert_eq!(v["data"]["task"]["failure_reason"], "tests time out");
+    assert_eq!(v["data"]["task"]["fail_count"], 1);
+
+    // ...but starting clears it; the count stays
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert!(v["data"]["task"]["failure_reason"].is_null());
+    env.run_ok(&["task", "fail", &t1]);
+    let v = env.run_ok(&["task", "list"]);
+    let t = v["data"]["tasks"].as_array().unwrap().iter().find(|t| t["id"] == t1.as_str()).unwrap().clone();
+    assert_eq!(t["fail_count"], 2);
+    assert!(t["failure_reason"].is_null());
+}
+
+#[test]
+fn test_task_fail_reason_requires_in_progress() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    let v = env.run_err(&["task", "fail", &t1, "--reason", "nope"]);
+    assert_eq!(v["error"]["code"], "INVALID_STATUS_TRANSITION");
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["fail_count"], 0);
+    assert!(v["data"]["task"]["failure_reason"].is_null());
+}
+
+// ─── 80. task deps / blocking ──────────────────────────────────────
+
+#[test]
+fn test_task_deps_and_blocking_ids() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let (t1, t2, t3, t4) = (
+        get_task_id(&loaded, "t1"),
+        get_task_id(&loaded, "t2"),
+        get_task_id(&loaded, "t3"),
+        get_task_id(&loaded, "t4"),
+    );
+
+    let v = env.run_ok(&["task", "deps", &t4]);
+    assert_eq!(v["data"], serde_json::json!({ "dependencies": [t2, t3] }));
+    let v = env.run_ok(&["task", "blocking", &t1]);
+    assert_eq!(v["data"], serde_json::json!({ "dependents": [t2, t3] }));
+    let v = env.run_ok(&["task", "deps", &t1]);
+    assert_eq!(v["data"]["dependencies"], serde_json::json!([]));
+
+    env.run_ok(&["task", "start", &t1]);
+    env.run_ok(&["task", "done", &t1]);
+    env.run_ok(&["task", "start", &t2]);
+    env.run_ok(&["task", "done", &t2]);
+    let v = env.run_ok(&["task", "deps", &t4, "--status-filter", "done"]);
+    assert_eq!(v["data"]["dependencies"], serde_json::json!([t2]));
+    let v = env.run_ok(&["task", "blocking", &t1, "--status-filter", "ready"]);
+    assert_eq!(v["data"]["dependents"], serde_json::json!([t3]));
+
+    // Text mode: bare IDs, one per line
+    let out = env.cmd().args(["task", "deps", &t4]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&out.stdout), format!("{t2}\n{t3}\n"));
+
+    let v = env.run_err(&["task", "deps", &t4, "--status-filter", "finished"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+}
+
+// ─── 81. task done --note ──────────────────────────────────────────
+
+#[test]
+fn test_task_done_note_shown_listed_and_exported() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    env.run_ok(&["task", "start", &t1]);
+    env.run_ok(&["task", "done", &t1, "--note", "Added 12 tests, all green"]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["completion_note"], "Added 12 tests, all green");
+
+    let out = env.cmd().args(["task", "list"]).output().unwrap();
+    let text = String::from_utf8_lossy(&out.stdout);
+    let line = text.lines().find(|l| l.contains("First Task")).unwrap();
+    assert!(line.ends_with("(has note)"), "{line}");
+    assert!(!text.lines().any(|l| l.contains("Second Task") && l.contains("(has note)")));
+
+    let out = env.cmd().args(["plan", "export", "test-plan"]).output().unwrap();
+    let export: Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(export["tasks"][0]["completion_note"], "Added 12 tests, all green");
+    assert!(export["tasks"][1].get("completion_note").is_none());
+
+    // Reset drops the note along with the completion
+    env.run_ok(&["task", "reset", &t1]);
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert!(v["data"]["task"]["completion_note"].is_null());
+}
+
+// ─── 82. config ────────────────────────────────────────────────────
+
+#[test]
+fn test_config_set_get_list_reset() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_ok(&["config", "set", "default_priority", "7"]);
+    assert_eq!(v["data"], serde_json::json!({ "key": "default_priority", "value": "7" }));
+    env.run_ok(&["config", "set", "default_agent", "builder"]);
+    let v = env.run_ok(&["config", "get", "default_agent"]);
+    assert_eq!(v["data"]["value"], "builder");
+    let v = env.run_ok(&["config", "get", "claim_expiry_minutes"]);
+    assert!(v["data"]["value"].is_null());
+
+    let v = env.run_ok(&["config", "list"]);
+    assert_eq!(v["data"]["config"]["default_priority"], "7");
+    assert!(v["data"]["config"]["output_format"].is_null());
+
+    // Setting keys must not lose the active plan
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["plan"]["name"], "test-plan");
+
+    for (key, value) in [("colour", "blue"), ("default_priority", "high"), ("claim_expiry_minutes", "0"), ("output_format", "xml")] {
+        let v = env.run_err(&["config", "set", key, value]);
+        assert_eq!(v["error"]["code"], "VALIDATION_ERROR", "{key}={value}");
+    }
+    let v = env.run_err(&["config", "get", "colour"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+
+    let v = env.run_ok(&["config", "reset", "default_agent"]);
+    assert_eq!(v["data"]["reset"], serde_json::json!(["default_agent"]));
+    let v = env.run_ok(&["config", "list"]);
+    assert!(v["data"]["config"]["default_agent"].is_null());
+    assert_eq!(v["data"]["config"]["default_priority"], "7");
+    env.run_ok(&["config", "reset"]);
+    let v = env.run_ok(&["config", "list"]);
+    assert!(v["data"]["config"]["default_priority"].is_null());
+    env.run_ok(&["status"]);
+}
+
+#[test]
+fn test_config_defaults_apply() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    env.run_ok(&["config", "set", "default_priority", "42"]);
+    env.run_ok(&["config", "set", "default_agent", "builder"]);
+    env.run_ok(&["config", "set", "claim_expiry_minutes", "20"]);
+
+    let v = env.run_ok(&["task", "add", "Configured"]);
+    assert_eq!(v["data"]["task"]["priority"], 42);
+    let v = env.run_ok(&["task", "add", "Explicit", "--priority", "1"]);
+    assert_eq!(v["data"]["task"]["priority"], 1);
+
+    let v = env.run_ok(&["next", "--claim"]);
+    let claimed = &v["data"]["task"];
+    assert_ne!(claimed["id"], t1.as_str(), "the p42 task goes first");
+    assert_eq!(claimed["assigned_to"], "builder");
+    assert!(claimed["claim_expires_at"].is_string());
+
+    // output_format json makes JSON the default
+    env.run_ok(&["config", "set", "output_format", "json"]);
+    let out = env.cmd().args(["config", "get", "output_format"]).output().unwrap();
+    let v: Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(v["data"]["value"], "json");
+}
+
+#[test]
+fn test_config_output_format_follows_db_path_flag() {
+    let env = TestEnv::new();
+    let db = env.dir.path().join("elsewhere").join("taskai.db");
+    let db = db.to_str().unwrap();
+    assert!(env.cmd().args(["--db-path", db, "init"]).output().unwrap().status.success());
+    assert!(env.cmd().args(["--db-path", db, "config", "set", "output_format", "json"]).output().unwrap().status.success());
+
+    let out = env.cmd().args(["--db-path", db, "plan", "list"]).output().unwrap();
+    let v: Value = serde_json::from_slice(&out.stdout).expect("config next to --db-path selects JSON");
+    assert_eq!(v["data"]["plans"], serde_json::json!([]));
+}
+
+// ─── 83. priority bounds and load warnings ─────────────────────────
+
+#[test]
+fn test_plan_load_rejects_extreme_priority() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let plan = serde_json::json!({
+        "name": "extreme",
+        "title": "Extreme",
+        "tasks": [
+            {"id": "a", "title": "A", "priority": 5},
+            {"id": "b", "title": "B", "priority": 99999},
+            {"id": "c", "title": "C", "priority": -5000}
+        ]
+    })
+    .to_string();
+
+    let v = env.load_plan_raw(&plan);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let message = v["error"]["message"].as_str().unwrap();
+    assert!(message.contains("'b'") && message.contains("99999"), "{message}");
+    assert!(message.contains("'c'"), "{message}");
+
+    let p = env.write_plan("extreme.json", &plan);
+    let output = env
+        .cmd()
+        .args(["plan", "load", "--allow-extreme-priority", "--json"])
+        .pipe_stdin(&p)
+        .unwrap()
+        .output()
+        .unwrap();
+    let v: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(v["success"], true, "{v}");
+    assert_eq!(v["data"]["tasks_created"], 3);
+}
+
+#[test]
+fn test_plan_load_warns_on_negative_priority_next_to_default() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+
+    let v = env.load_plan(&basic_plan_json());
+    assert_eq!(v["data"]["warnings"], serde_json::json!([]));
+
+    let v = env.load_plan(
+        &serde_json::json!({
+            "name": "mixed",
+            "title": "Mixed",
+            "tasks": [
+                {"id": "cleanup", "title": "Cleanup", "priority": -1},
+                {"id": "build", "title": "Build"}
+            ]
+        })
+        .to_string(),
+    );
+    let warnings = v["data"]["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].as_str().unwrap().contains("cleanup"), "{warnings:?}");
+}
+
+#[test]
+fn test_task_add_rejects_extreme_priority() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let v = env.run_err(&["task", "add", "Huge", "--priority", "5000"]);
+    assert_eq!(v["error"]["code"], "VALIDATION_ERROR");
+    let v = env.run_ok(&["task", "add", "Huge", "--priority", "5000", "--allow-extreme-priority"]);
+    assert_eq!(v["data"]["task"]["priority"], 5000);
+    let v = env.run_ok(&["task", "add", "Low", "--priority", "-1000"]);
+    assert_eq!(v["data"]["task"]["priority"], -1000);
+}
+
+// ─── 84. plan stats cycle time / throughput ────────────────────────
+
+fn set_timestamps(env: &TestEnv, task_id: &str, started: Option<&str>, completed: Option<&str>) {
+    env.db()
+        .execute(
+            "UPDATE tasks SET status = 'done', created_at = '2026-01-01 10:00:00', \
+             started_at = ?1, completed_at = ?2 WHERE id = ?3",
+            rusqlite::params![started, completed, task_id],
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_plan_stats_cycle_time_and_throughput() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let ids: Vec<String> = ["t1", "t2", "t3", "t4"].iter().map(|k| get_task_id(&loaded, k)).collect();
+
+    set_timestamps(&env, &ids[0], Some("2026-01-01 10:10:00"), Some("2026-01-01 10:40:00"));
+    set_timestamps(&env, &ids[1], Some("2026-01-01 10:40:00"), Some("2026-01-01 11:00:00"));
+    set_timestamps(&env, &ids[2], Some("2026-01-01 10:40:00"), Some("2026-01-01 11:40:00"));
+    // Done without a start time: excluded from every duration
+    set_timestamps(&env, &ids[3], None, Some("2026-01-01 11:00:00"));
+
+    let v = env.run_ok(&["plan", "stats"]);
+    let stats = &v["data"]["stats"];
+    assert_eq!(stats["cycle_time_minutes"], serde_json::json!({
+        "sample_size": 3, "avg": 36.7, "p50": 30.0, "p90": 60.0
+    }));
+    assert_eq!(stats["wait_time_minutes"], serde_json::json!({"sample_size": 3, "avg": 30.0}));
+    // 3 tasks between 10:10 and 11:40
+    assert_eq!(stats["throughput_per_hour"], 2.0);
+
+    let out = env.cmd().args(["plan", "stats"]).output().unwrap();
+    let text = String::from_utf8(out.stdout).unwrap();
+    assert!(text.contains("avg=36.7min p50=30.0min p90=60.0min (n=3)"), "{text}");
+    assert!(text.contains("2.0/h"), "{text}");
+}
+
+// ─── 85. TASKAI_PLAN ───────────────────────────────────────────────
+
+#[test]
+fn test_taskai_plan_env_overrides_active_plan() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    env.run_ok(&["plan", "create", "other"]);
+    env.run_ok(&["plan", "activate", "test-plan"]);
+
+    let v = env.run_ok(&["status"]);
+    assert_eq!(v["data"]["plan"]["name"], "test-plan");
+    assert_eq!(v["data"]["plan_source"], "config");
+
+    let status = |plan_flag: Option<&str>| {
+        let mut cmd = env.cmd();
+        cmd.env("TASKAI_PLAN", "other").args(["--json", "status"]);
+        if let Some(p) = plan_flag {
+            cmd.args(["--plan", p]);
+        }
+        let out = cmd.output().unwrap();
+        serde_json::from_slice::<serde_json::Value>(&out.stdout).unwrap()
+    };
+    let v = status(None);
+    assert_eq!(v["data"]["plan"]["name"], "other");
+    assert_eq!(v["data"]["plan_source"], "env");
+
+    // --plan still wins
+    let v = status(Some("test-plan"));
+    assert_eq!(v["data"]["plan"]["name"], "test-plan");
+    assert_eq!(v["data"]["plan_source"], "flag");
+
+    // Task commands follow the variable too
+    let out = env.cmd().env("TASKAI_PLAN", "other").args(["--json", "task", "list"]).output().unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(v["data"]["tasks"], serde_json::json!([]), "{v}");
+}
+
+#[test]
+fn test_taskai_plan_env_unknown_plan_errors() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let out = env.cmd().env("TASKAI_PLAN", "missing").args(["--json", "next"]).output().unwrap();
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(v["error"]["code"], "PLAN_NOT_FOUND");
+}
+
+// ─── 86. --verbose ─────────────────────────────────────────────────
+
+#[test]
+fn test_verbose_traces_to_stderr_only() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let t2 = get_task_id(&loaded, "t2");
+
+    let out = env.cmd().args(["--json", "-v", "task", "done", &t1]).output().unwrap();
+    assert!(out.status.success());
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(v["success"], true);
+    let log = String::from_utf8(out.stderr).unwrap();
+    assert!(log.contains("plan: test-plan"), "{log}");
+    assert!(log.contains("sql: BEGIN IMMEDIATE"), "{log}");
+    assert!(log.contains("sql: COMMIT"), "{log}");
+    assert!(log.contains(&format!("task {t1}: ready -> done")), "{log}");
+    assert!(log.contains(&format!("cascade: unblocking {t2}")), "{log}");
+    assert!(log.lines().all(|l| l.starts_with("[taskai] ")), "{log}");
+
+    let out = env.cmd().args(["--json", "task", "list"]).output().unwrap();
+    assert!(out.stderr.is_empty());
+}
+
+// ─── 87. doctor ────────────────────────────────────────────────────
+
+fn doctor(env: &TestEnv) -> (i32, Value) {
+    let out = env.cmd().args(["--json", "doctor"]).output().unwrap();
+    (out.status.code().unwrap(), serde_json::from_slice(&out.stdout).unwrap())
+}
+
+fn check_severity(v: &Value, name: &str) -> String {
+    v["data"]["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == name)
+        .unwrap_or_else(|| panic!("no check {name}: {v}"))["severity"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_doctor_healthy() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+
+    let (code, v) = doctor(&env);
+    assert_eq!(code, 0, "{v}");
+    let checks = v["data"]["checks"].as_array().unwrap();
+    assert_eq!(checks.len(), 7);
+    assert!(checks.iter().all(|c| c["severity"] == "ok"), "{v}");
+
+    env.cmd().arg("doctor").assert().success().stdout(predicate::str::contains("✓ schema_version"));
+}
+
+#[test]
+fn test_doctor_reports_problems() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    // Warning only: in_progress without started_at
+    env.db()
+        .execute("UPDATE tasks SET status = 'in_progress', started_at = NULL WHERE id = ?1", [&t1])
+        .unwrap();
+    let (code, v) = doctor(&env);
+    assert_eq!(code, 2, "{v}");
+    assert_eq!(check_severity(&v, "in_progress_started"), "warn");
+    assert!(v["data"]["checks"].to_string().contains(&t1));
+
+    // Error: a dependency on a task that does not exist
+    let db = env.db();
+    db.execute_batch("PRAGMA foreign_keys=OFF").unwrap();
+    db.execute("INSERT INTO task_dependencies (task_id, dependency_id) VALUES (?1, 'gone')", [&t1])
+        .unwrap();
+    let (code, v) = doctor(&env);
+    assert_eq!(code, 1, "{v}");
+    assert_eq!(check_severity(&v, "orphaned_dependencies"), "error");
+    env.cmd().arg("doctor").assert().code(1).stdout(predicate::str::contains("✗ orphaned_dependencies"));
+}
+
+#[test]
+fn test_doctor_uninitialized() {
+    let env = TestEnv::new();
+    let (code, v) = doctor(&env);
+    assert_eq!(code, 1);
+    assert_eq!(check_severity(&v, "git_repository"), "ok");
+    assert_eq!(check_severity(&v, "database"), "error");
+}
+
+#[test]
+fn test_doctor_warns_on_pre_versioning_database() {
+    let env = TestEnv::new();
+    fs::create_dir_all(env.dir.path().join(".worktoolai/taskai")).unwrap();
+    env.db().execute_batch(V1_SCHEMA).unwrap();
+
+    let (_, v) = doctor(&env);
+    assert_eq!(check_severity(&v, "schema_version"), "warn", "{v}");
+    let check = v["data"]["checks"].as_array().unwrap().iter().find(|c| c["name"] == "schema_version").unwrap();
+    assert_eq!(check["message"], "version 0, expected 14; the next write command migrates it");
+}
+
+// ─── 88. task fail --max-attempts ──────────────────────────────────
+
+#[test]
+fn test_fail_max_attempts_retries_then_cancels() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    for attempt in 1..=2 {
+        env.run_ok(&["task", "start", &t1]);
+        let v = env.run_ok(&["task", "fail", &t1, "--reason", "flaky", "--max-attempts", "2"]);
+        assert_eq!(v["data"]["completed_task"]["status"], "ready");
+        assert_eq!(v["data"]["completed_task"]["fail_count"], attempt);
+    }
+
+    env.run_ok(&["task", "start", &t1]);
+    let v = env.run_ok(&["task", "fail", &t1, "--reason", "still flaky", "--max-attempts", "2"]);
+    assert_eq!(v["data"]["completed_task"]["status"], "cancelled");
+    assert_eq!(v["data"]["completed_task"]["fail_count"], 3);
+
+    let v = env.run_ok(&["task", "show", &t1]);
+    assert_eq!(v["data"]["task"]["status"], "cancelled");
+    assert_eq!(v["data"]["task"]["failure_reason"], "still flaky");
+    assert_eq!(v["data"]["task"]["fail_count"], 3);
+}
+
+#[test]
+fn test_fail_max_attempts_zero_cancels_first_failure() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+
+    env.run_ok(&["task", "start", &t1]);
+    env.cmd()
+        .args(["task", "fail", &t1, "--max-attempts", "0"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("→ cancelled"))
+        .stdout(predicate::str::contains("Failed 1x"));
+
+    env.run_ok(&["task", "reset", &t1]);
+    env.run_ok(&["task", "start", &t1]);
+    // Without the cap a fail always goes back to the queue
+    let v = env.run_ok(&["task", "fail", &t1]);
+    assert_eq!(v["data"]["completed_task"]["status"], "ready");
+}
+
+// ─── 89. idempotency keys ──────────────────────────────────────────
+
+#[test]
+fn test_task_add_idempotency_key_replays() {
+    let env = TestEnv::new();
+    setup_with_plan(&env);
+    let count = || -> i64 {
+        env.db().query_row("SELECT COUNT(*) FROM tasks WHERE title = 'Retry me'", [], |r| r.get(0)).unwrap()
+    };
+
+    let first = env.run_ok(&["task", "add", "Retry me", "--idempotency-key", "k1"]);
+    assert_eq!(first["data"]["idempotent_replay"], false);
+    let second = env.run_ok(&["task", "add", "Retry me", "--idempotency-key", "k1"]);
+    assert_eq!(second["data"]["idempotent_replay"], true);
+    assert_eq!(second["data"]["task"]["id"], first["data"]["task"]["id"]);
+    assert_eq!(count(), 1);
+
+    // Keys are per plan
+    env.run_ok(&["plan", "create", "other"]);
+    let v = env.run_ok(&["task", "add", "Retry me", "--idempotency-key", "k1", "--plan", "other"]);
+    assert_eq!(v["data"]["idempotent_replay"], false);
+    assert_eq!(count(), 2);
+
+    // Without a key nothing changes
+    let v = env.run_ok(&["task", "add", "Retry me"]);
+    assert!(v["data"].get("idempotent_replay").is_none());
+    assert_eq!(count(), 3);
+}
+
+#[test]
+fn test_plan_load_idempotency_key_replays() {
+    let env = TestEnv::new();
+    env.run_ok(&["init"]);
+    let mut doc: Value = serde_json::from_str(&basic_plan_json()).unwrap();
+    doc["idempotency_key"] = serde_json::json!("load-1");
+
+    let first = env.load_plan(&doc.to_string());
+    assert_eq!(first["data"]["idempotent_replay"], false);
+    let second = env.load_plan(&doc.to_string());
+    assert_eq!(second["data"]["idempotent_replay"], true);
+    assert_eq!(second["data"]["plan"], first["data"]["plan"]);
+    assert_eq!(second["data"]["id_mapping"], first["data"]["id_mapping"]);
+    let plans: i64 = env.db().query_row("SELECT COUNT(*) FROM plans", [], |r| r.get(0)).unwrap();
+    assert_eq!(plans, 1);
+
+    // --idempotency-key wins over the document; a new key hits the name conflict
+    let path = env.write_plan("keyed.json", &doc.to_string());
+    let v = env.run_err(&["plan", "load", "--file", path.to_str().unwrap(), "--idempotency-key", "load-2"]);
+    assert_eq!(v["success"], false);
+    let v = env.run_ok(&["plan", "load", "--file", path.to_str().unwrap(), "--idempotency-key", "load-1"]);
+    assert_eq!(v["data"]["idempotent_replay"], true);
+}
+
+// ─── 90. completions ───────────────────────────────────────────────
+
+#[test]
+fn test_completions_scripts() {
+    let env = TestEnv::new();
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let out = env.cmd().args(["completions", shell]).output().unwrap();
+        assert!(out.status.success(), "{shell}");
+        let script = String::from_utf8(out.stdout).unwrap();
+        for word in ["activate", "load", "cancel", "json", "plan", "db-path"] {
+            assert!(script.contains(word), "{shell} script lacks {word}");
+        }
+    }
+    let bash = env.cmd().args(["completions", "bash"]).output().unwrap();
+    assert!(String::from_utf8(bash.stdout).unwrap().contains("complete -F _taskai_with_plans"));
+
+    env.cmd().args(["completions", "tcsh"]).assert().failure();
+}
+
+#[test]
+fn test_completions_out_dir_and_plan_names() {
+    let env = TestEnv::new();
+    let dir = env.dir.path().join("completions");
+    let v = env.run_ok(&["completions", "fish", "--out-dir", dir.to_str().unwrap()]);
+    let path = PathBuf::from(v["data"]["path"].as_str().unwrap());
+    assert_eq!(path, dir.join("taskai.fish"));
+    assert!(fs::read_to_string(&path).unwrap().contains("__plan-names"));
+
+    // No database yet: no names, no error
+    env.cmd().arg("__plan-names").assert().success().stdout("");
+    setup_with_plan(&env);
+    env.run_ok(&["plan", "create", "other"]);
+    env.cmd()
+        .arg("__plan-names")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("test-plan\n").and(predicate::str::contains("other\n")));
+}
+
+// ─── 91. version ───────────────────────────────────────────────────
+
+#[test]
+fn test_version_command() {
+    let env = TestEnv::new();
+    let v = env.run_ok(&["version"]);
+    assert!(!v["data"]["cli_version"].as_str().unwrap().is_empty());
+    assert!(!v["data"]["git_commit"].as_str().unwrap().is_empty());
+    assert!(v["data"]["build_time"].is_string());
+    assert!(v["data"]["db_schema_version"].is_null());
+    assert!(v["data"]["db_path"].as_str().unwrap().ends_with("taskai.db"));
+
+    env.run_ok(&["init"]);
+    let v = env.run_ok(&["version"]);
+    assert_eq!(v["data"]["db_schema_version"], v["data"]["expected_schema_version"]);
+
+    env.cmd().arg("version").assert().success().stdout(predicate::str::contains("Schema version"));
+}
+
+#[test]
+fn test_version_reports_pre_versioning_database_as_version_0() {
+    let env = TestEnv::new();
+    fs::create_dir_all(env.dir.path().join(".worktoolai/taskai")).unwrap();
+    env.db().execute_batch(V1_SCHEMA).unwrap();
+
+    let v = env.run_ok(&["version"]);
+    assert_eq!(v["data"]["db_schema_version"], 0, "{v}");
+    env.cmd().arg("version").assert().success().stdout(predicate::str::contains("the next command migrates it"));
+}
+
+// ─── 92. library API ───────────────────────────────────────────────
+
+#[test]
+fn test_api_load_next_transition_in_memory() {
+    use taskai::api::{self, TransitionAction};
+
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    api::migrate(&conn).unwrap();
+
+    let loaded = api::load_plan(
+        &conn,
+        r#"{"name":"lib","title":"Lib","tasks":[{"id":"a","title":"A"},{"id":"b","title":"B","after":["a"]}]}"#,
+    )
+    .unwrap();
+    assert_eq!(loaded.plan.name, "lib");
+    assert!(!loaded.idempotent_replay);
+    assert_eq!(loaded.ready_now.len(), 1);
+    let (a, b) = (&loaded.id_mapping["a"], &loaded.id_mapping["b"]);
+
+    let next = api::next_task(&conn, &loaded.plan.id, true, Some("bot")).unwrap();
+    let claimed = next.task.unwrap();
+    assert_eq!(&claimed.id, a);
+    assert_eq!(claimed.assigned_to.as_deref(), Some("bot"));
+    assert_eq!(next.progress.in_progress, 1);
+
+    let done = api::transition(&conn, a, TransitionAction::Done { note: None }).unwrap();
+    assert_eq!(done.newly_ready.len(), 1);
+    assert_eq!(&done.newly_ready[0].id, b);
+    assert!(!done.plan_completed);
+
+    let err = api::transition(&conn, a, TransitionAction::Skip).unwrap_err();
+    assert_eq!(err.code.as_str(), "INVALID_STATUS_TRANSITION");
+
+    let done = api::transition(&conn, b, TransitionAction::Done { note: Some("shipped") }).unwrap();
+    assert!(done.plan_completed);
+    let next = api::next_task(&conn, &loaded.plan.id, false, None).unwrap();
+    assert!(next.task.is_none() && next.plan_completed);
+}
+
+#[test]
+fn test_api_concurrent_transitions_check_the_current_status() {
+    use std::sync::{Arc, Barrier};
+    use taskai::api::{self, TransitionAction};
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("taskai.db");
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    api::migrate(&conn).unwrap();
+    let loaded = api::load_plan(&conn, r#"{"name":"race","title":"Race","tasks":[{"id":"a","title":"A"}]}"#).unwrap();
+    let task_id = loaded.id_mapping["a"].clone();
+
+    let barrier = Arc::new(Barrier::new(8));
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let (path, task_id, barrier) = (path.clone(), task_id.clone(), barrier.clone());
+            std::thread::spawn(move || {
+                let conn = rusqlite::Connection::open(path).unwrap();
+                conn.execute_batch("PRAGMA busy_timeout=5000;").unwrap();
+                barrier.wait();
+                api::transition(&conn, &task_id, TransitionAction::Start { agent: None }).is_ok()
+            })
+        })
+        .collect();
+    let started = handles.into_iter().map(|h| h.join().unwrap()).filter(|ok| *ok).count();
+    assert_eq!(started, 1);
+}
+
+// ─── 93. in-memory database ────────────────────────────────────────
+
+#[test]
+fn test_memory_db_path_needs_no_init_and_leaves_no_files() {
+    let env = TestEnv::new();
+    let run = |args: &[&str]| {
+        let output = env.cmd().env("TASKAI_DB_PATH", ":memory:").args(args).arg("--json").output().unwrap();
+        serde_json::from_slice::<Value>(&output.stdout).unwrap()
+    };
+
+    let v = run(&["init"]);
+    assert_eq!(v["data"]["path"], ":memory:");
+
+    let plan = env.write_plan("plan.json", &basic_plan_json());
+    let v = run(&["plan", "load", "--file", plan.to_str().unwrap()]);
+    assert_eq!(v["success"], true, "{v}");
+    assert_eq!(v["data"]["tasks_created"], 4);
+
+    // Nothing persists: the next process starts with an empty database
+    let v = run(&["plan", "list"]);
+    assert_eq!(v["data"]["plans"].as_array().unwrap().len(), 0, "{v}");
+    assert!(!env.dir.path().join(".worktoolai").exists());
+    assert!(!env.dir.path().join("config.json").exists());
+}
+
+// ─── 94. table output ──────────────────────────────────────────────
+
+#[test]
+fn test_task_list_table_aligns_columns() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    let long_title = "A very long task title that keeps going well past the table column width";
+    env.run_ok(&["task", "add", long_title, "--agent", "coder"]);
+
+    let output = env.cmd().args(["task", "list", "--format", "table"]).output().unwrap();
+    assert!(output.status.success());
+    let table = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(), ["ID", "STATUS", "PRIORITY", "AGENT", "TITLE"]);
+    assert_eq!(lines.len(), 6, "header + 5 tasks: {table}");
+    assert!(lines[1].starts_with(&t1[..8]) && lines[1].contains("  ready"), "{table}");
+    // Tasks loaded together share their ULID timestamp: the ID column still tells them apart
+    let ids: Vec<&str> = lines[1..].iter().map(|l| l.split_whitespace().next().unwrap()).collect();
+    assert_eq!(ids.iter().collect::<std::collections::HashSet<_>>().len(), ids.len(), "{table}");
+    for id in ids {
+        env.run_ok(&["task", "show", id]);
+    }
+    let status_col = lines[0].find("STATUS").unwrap();
+    let title_col = lines[0].find("TITLE").unwrap();
+    for line in &lines[1..] {
+        assert!(line[status_col..].starts_with(|c: char| c.is_ascii_lowercase()), "{table}");
+        assert!(line[..title_col].ends_with(' ') && !line[title_col..].starts_with(' '), "{table}");
+    }
+    assert!(table.contains("coder"), "{table}");
+    assert!(!table.contains(long_title) && table.contains("A very long task title"), "truncated: {table}");
+
+    // --json wins over --format table
+    let v = env.run_ok(&["task", "list", "--format", "table"]);
+    assert_eq!(v["data"]["tasks"].as_array().unwrap().len(), 5);
+}
+
+#[test]
+fn test_plan_list_table_shows_done_percentage() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    env.run_ok(&["task", "done", &get_task_id(&loaded, "t1")]);
+    env.run_ok(&["plan", "create", "empty"]);
+
+    let output = env.cmd().args(["plan", "list", "--format", "table"]).output().unwrap();
+    assert!(output.status.success());
+    let table = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<Vec<&str>> = table.lines().map(|l| l.split_whitespace().collect()).collect();
+    assert_eq!(rows[0], ["NAME", "STATUS", "TASKS", "DONE%"]);
+    assert!(rows.contains(&vec!["test-plan", "active", "4", "25%"]), "{table}");
+    assert!(rows.contains(&vec!["empty", "active", "0", "0%"]), "{table}");
+}
+
+// ─── 95. markdown report ───────────────────────────────────────────
+
+#[test]
+fn test_plan_show_and_status_markdown() {
+    let env = TestEnv::new();
+    let loaded = setup_with_plan(&env);
+    let t1 = get_task_id(&loaded, "t1");
+    env.run_ok(&["task", "done", &t1]);
+
+    for args in [&["plan", "show", "test-plan", "--format", "markdown"][..], &["status", "--format", "markdown"][..]] {
+        let output = env.cmd().args(args).output().unwrap();
+        assert!(output.status.success(), "{args:?}");
+        let md = String::from_utf8(output.stdout).unwrap();
+        assert!(md.starts_with("# Test Plan\n"), "{md}");
+        assert!(md.contains("| id | title | status |"), "{md}");
+        assert!(md.contains("(1/4 done)"), "{md}");
+        assert_eq!(md.matches("✅ done").count(), 1, "{md}");
+        // Short ids stay unique, and dependents list theirs under `after`
+        let rows: Vec<&str> = md.lines().filter(|l| l.starts_with("| `")).collect();
+        assert_eq!(rows.len(), 4, "{md}");
+        let t1_short = rows[0].split('`').nth(1).unwrap();
+        assert!(t1.starts_with(t1_short), "{md}");
+        assert!(rows[1].ends_with(&format!("| {t1_short} |")), "{md}");
+    }
+
+    env.cmd().args(["plan", "show", "test-plan", "--format", "markdown", "--json"]).assert().failure();
+    env.cmd().args(["status", "--format", "markdown", "--json"]).assert().failure();
+}